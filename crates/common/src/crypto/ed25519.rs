@@ -15,6 +15,12 @@ pub struct Ed25519Signer {
     verifying_key: VerifyingKey,
 }
 
+impl Default for Ed25519Signer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Ed25519Signer {
     pub fn new() -> Self {
         let signing_key = SigningKey::generate(&mut OsRng);
@@ -0,0 +1,43 @@
+//! Rebuilds the embedded signature database from a local dump.
+//!
+//! ```text
+//! cargo run -p web3ium-evm --example build_signatures -- signatures.txt [crates/evm/src/abis/data/signatures.gz]
+//! ```
+//!
+//! See [`SignatureDb::build_from_dump`] for the accepted line formats.
+
+use std::{env, fs, process};
+
+use web3ium_evm::abis::signatures::SignatureDb;
+
+const DEFAULT_OUTPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/abis/data/signatures.gz");
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(input) = args.next() else {
+        eprintln!("usage: build_signatures <dump> [output]");
+        process::exit(1);
+    };
+    let output = args.next().unwrap_or_else(|| DEFAULT_OUTPUT.to_string());
+
+    let dump = fs::read_to_string(&input).unwrap_or_else(|e| {
+        eprintln!("failed to read {}: {}", input, e);
+        process::exit(1);
+    });
+    let (db, stats) = SignatureDb::build_from_dump(&dump);
+    let compressed = db.to_compressed().expect("compress signatures");
+    fs::write(&output, &compressed).unwrap_or_else(|e| {
+        eprintln!("failed to write {}: {}", output, e);
+        process::exit(1);
+    });
+
+    println!(
+        "{} functions, {} events ({} duplicates, {} skipped) -> {} ({} bytes)",
+        stats.functions,
+        stats.events,
+        stats.duplicates,
+        stats.skipped,
+        output,
+        compressed.len()
+    );
+}
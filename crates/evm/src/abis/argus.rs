@@ -1,29 +1,31 @@
+#![allow(clippy::too_many_arguments)]
+
 use alloy_sol_types::sol;
 
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     ICoboArgus,
     "src/abis/protocols/argus/cobo_argus.json"
 );
 
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     ISafe,
     "src/abis/protocols/argus/safe.json"
 );
 
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     IRoleManager,
     "src/abis/protocols/argus/roleManager.json"
 );
 
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     IAuthorizer,
     "src/abis/protocols/argus/authorizer.json"
 );
@@ -0,0 +1,78 @@
+#![allow(clippy::too_many_arguments)]
+
+use alloy_sol_types::sol;
+
+// https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/interfaces/contracts/vault/IVault.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IBalancerVault {
+        #[derive(Debug)]
+        struct SingleSwap {
+            bytes32 poolId;
+            uint8 kind;
+            address assetIn;
+            address assetOut;
+            uint256 amount;
+            bytes userData;
+        }
+
+        #[derive(Debug)]
+        struct BatchSwapStep {
+            bytes32 poolId;
+            uint256 assetInIndex;
+            uint256 assetOutIndex;
+            uint256 amount;
+            bytes userData;
+        }
+
+        #[derive(Debug)]
+        struct FundManagement {
+            address sender;
+            bool fromInternalBalance;
+            address recipient;
+            bool toInternalBalance;
+        }
+
+        function swap(SingleSwap singleSwap, FundManagement funds, uint256 limit, uint256 deadline) external payable returns (uint256 amountCalculated);
+        function batchSwap(uint8 kind, BatchSwapStep[] swaps, address[] assets, FundManagement funds, int256[] limits, uint256 deadline) external payable returns (int256[] assetDeltas);
+        function queryBatchSwap(uint8 kind, BatchSwapStep[] swaps, address[] assets, FundManagement funds) external returns (int256[] assetDeltas);
+        function flashLoan(address recipient, address[] tokens, uint256[] amounts, bytes userData) external;
+        function getPool(bytes32 poolId) external view returns (address, uint8);
+        function getPoolTokens(bytes32 poolId) external view returns (address[] tokens, uint256[] balances, uint256 lastChangeBlock);
+        function getProtocolFeesCollector() external view returns (address);
+    }
+}
+
+// https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/pool-weighted/contracts/WeightedPool.sol
+// https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/pool-stable/contracts/ComposableStablePool.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IBalancerPool {
+        function getPoolId() external view returns (bytes32);
+        function getSwapFeePercentage() external view returns (uint256);
+        function getScalingFactors() external view returns (uint256[]);
+        function getNormalizedWeights() external view returns (uint256[]);
+        function getAmplificationParameter() external view returns (uint256 value, bool isUpdating, uint256 precision);
+        function getBptIndex() external view returns (uint256);
+    }
+}
+
+// https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/vault/contracts/ProtocolFeesCollector.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IProtocolFeesCollector {
+        function getFlashLoanFeePercentage() external view returns (uint256);
+    }
+}
+
+// https://github.com/balancer/balancer-v2-monorepo/blob/master/pkg/interfaces/contracts/vault/IFlashLoanRecipient.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IFlashLoanRecipient {
+        function receiveFlashLoan(address[] tokens, uint256[] amounts, uint256[] feeAmounts, bytes userData) external;
+    }
+}
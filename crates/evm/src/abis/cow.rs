@@ -0,0 +1,51 @@
+use alloy_sol_types::sol;
+use serde::Serialize;
+
+// https://github.com/cowprotocol/contracts/blob/main/src/contracts/GPv2Settlement.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IGPv2Settlement {
+        function domainSeparator() external view returns (bytes32);
+        function filledAmount(bytes calldata orderUid) external view returns (uint256);
+        function preSignature(bytes calldata orderUid) external view returns (uint256);
+        function setPreSignature(bytes calldata orderUid, bool signed) external;
+        function invalidateOrder(bytes calldata orderUid) external;
+    }
+}
+
+// https://github.com/cowprotocol/contracts/blob/main/src/contracts/libraries/GPv2Order.sol
+// Kind and balances are hashed as strings, e.g. `keccak256("sell")`.
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct Order {
+        address sellToken;
+        address buyToken;
+        address receiver;
+        uint256 sellAmount;
+        uint256 buyAmount;
+        uint32 validTo;
+        bytes32 appData;
+        uint256 feeAmount;
+        string kind;
+        bool partiallyFillable;
+        string sellTokenBalance;
+        string buyTokenBalance;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq, Serialize)]
+    struct OrderCancellations {
+        bytes[] orderUids;
+    }
+}
+
+// https://eips.ethereum.org/EIPS/eip-1271
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes memory signature) external view returns (bytes4 magicValue);
+    }
+}
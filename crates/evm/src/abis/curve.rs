@@ -0,0 +1,61 @@
+use alloy_sol_types::sol;
+
+// https://github.com/curvefi/curve-contract/blob/master/contracts/pool-templates/base/SwapTemplateBase.vy
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface ICurveStableSwap {
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function A() external view returns (uint256);
+        function A_precise() external view returns (uint256);
+        function fee() external view returns (uint256);
+        function get_virtual_price() external view returns (uint256);
+        function stored_rates() external view returns (uint256[]);
+        function get_dy(int128 i, int128 j, uint256 dx) external view returns (uint256);
+        function get_dy_underlying(int128 i, int128 j, uint256 dx) external view returns (uint256);
+        function exchange(int128 i, int128 j, uint256 dx, uint256 min_dy) external payable returns (uint256);
+        function exchange_underlying(int128 i, int128 j, uint256 dx, uint256 min_dy) external payable returns (uint256);
+        function totalSupply() external view returns (uint256);
+    }
+}
+
+// https://github.com/curvefi/tricrypto-ng/blob/main/contracts/main/CurveTricryptoOptimizedWETH.vy
+// https://github.com/curvefi/twocrypto-ng/blob/main/contracts/main/CurveTwocryptoOptimized.vy
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface ICurveCryptoSwap {
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function A() external view returns (uint256);
+        function gamma() external view returns (uint256);
+        function D() external view returns (uint256);
+        function mid_fee() external view returns (uint256);
+        function out_fee() external view returns (uint256);
+        function fee_gamma() external view returns (uint256);
+        function price_scale() external view returns (uint256);
+        function price_scale(uint256 k) external view returns (uint256);
+        function get_dy(uint256 i, uint256 j, uint256 dx) external view returns (uint256);
+        function exchange(uint256 i, uint256 j, uint256 dx, uint256 min_dy) external payable returns (uint256);
+        function exchange_underlying(uint256 i, uint256 j, uint256 dx, uint256 min_dy) external payable returns (uint256);
+    }
+}
+
+// https://github.com/curvefi/metaregistry/blob/main/contracts/mainnet/MetaRegistry.vy
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface ICurveMetaRegistry {
+        function find_pools_for_coins(address _from, address _to) external view returns (address[]);
+        function get_coin_indices(address _pool, address _from, address _to) external view returns (int128, int128, bool);
+        function get_n_coins(address _pool) external view returns (uint256);
+        function get_coins(address _pool) external view returns (address[8]);
+        function get_underlying_coins(address _pool) external view returns (address[8]);
+        function get_decimals(address _pool) external view returns (uint256[8]);
+        function get_underlying_decimals(address _pool) external view returns (uint256[8]);
+        function get_lp_token(address _pool) external view returns (address);
+        function get_base_pool(address _pool) external view returns (address);
+        function is_meta(address _pool) external view returns (bool);
+    }
+}
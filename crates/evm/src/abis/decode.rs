@@ -0,0 +1,229 @@
+use alloy_dyn_abi::DynSolValue;
+use alloy_json_abi::Param;
+use alloy_primitives::{hex, Selector, B256};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::abis::registry::AbiRegistry;
+
+/// Calldata decoded against one of the registered ABIs.
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedCall {
+    pub contract: String,
+    pub function: String,
+    pub signature: String,
+    pub selector: Selector,
+    pub params: Vec<DecodedParam>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub value: Value,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub indexed: bool,
+    /// Calls found inside `bytes`/`bytes[]` values.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nested: Vec<DecodedCall>,
+}
+
+/// An event log decoded against one of the registered ABIs.
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedLog {
+    pub contract: String,
+    pub event: String,
+    pub signature: String,
+    pub topic0: B256,
+    pub params: Vec<DecodedParam>,
+}
+
+/// Decodes calldata against the [`crate::abis::known_abis`], returns `None` when no ABI matches.
+pub fn decode_calldata(input: &[u8]) -> Option<DecodedCall> {
+    AbiRegistry::known().decode_call(input)
+}
+
+/// Converts an ABI value to JSON: integers as decimal strings (they rarely fit a JS number),
+/// addresses checksummed, bytes as `0x` hex, and tuples as objects when all components are named.
+pub fn value_to_json(value: &DynSolValue, components: &[Param]) -> Value {
+    match value {
+        DynSolValue::Bool(b) => Value::Bool(*b),
+        DynSolValue::Int(i, _) => Value::String(i.to_string()),
+        DynSolValue::Uint(u, _) => Value::String(u.to_string()),
+        DynSolValue::FixedBytes(word, size) => Value::String(hex::encode_prefixed(&word[..*size])),
+        DynSolValue::Address(address) => Value::String(address.to_checksum(None)),
+        DynSolValue::Function(function) => Value::String(hex::encode_prefixed(function.as_slice())),
+        DynSolValue::Bytes(bytes) => Value::String(hex::encode_prefixed(bytes)),
+        DynSolValue::String(s) => Value::String(s.clone()),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| value_to_json(value, components))
+                .collect(),
+        ),
+        DynSolValue::CustomStruct { prop_names, tuple, .. } => Value::Object(
+            prop_names
+                .iter()
+                .zip(tuple)
+                .enumerate()
+                .map(|(i, (name, value))| {
+                    let components = components.get(i).map(|c| c.components.as_slice());
+                    (name.clone(), value_to_json(value, components.unwrap_or_default()))
+                })
+                .collect(),
+        ),
+        DynSolValue::Tuple(values) => {
+            let named = components.len() == values.len()
+                && components.iter().all(|component| !component.name.is_empty());
+            if named {
+                Value::Object(
+                    components
+                        .iter()
+                        .zip(values)
+                        .map(|(component, value)| {
+                            (component.name.clone(), value_to_json(value, &component.components))
+                        })
+                        .collect(),
+                )
+            } else {
+                Value::Array(
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| {
+                            let components = components.get(i).map(|c| c.components.as_slice());
+                            value_to_json(value, components.unwrap_or_default())
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abis::argus::ISafe;
+    use crate::abis::erc::IERC20;
+    use crate::abis::uniswap::{ISwapRouter, IUniswapV2Router, IUniswapV3Router};
+    use alloy_primitives::{address, aliases::U24, Address, Bytes, U160, U256};
+    use alloy_sol_types::SolCall;
+
+    #[test]
+    fn test_decode_erc20_approve() {
+        let spender = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+        let input = IERC20::approveCall { spender, amount: U256::from(10).pow(U256::from(18)) }.abi_encode();
+
+        let call = decode_calldata(&input).unwrap();
+        assert_eq!(call.contract, "ERC20");
+        assert_eq!(call.signature, "approve(address,uint256)");
+        assert_eq!(call.params[0].name, "spender");
+        assert_eq!(call.params[0].value, Value::String(spender.to_checksum(None)));
+        assert_eq!(call.params[1].value, Value::String("1000000000000000000".into()));
+    }
+
+    #[test]
+    fn test_decode_uniswap_v2_swap() {
+        let path = vec![Address::repeat_byte(1), Address::repeat_byte(2)];
+        let input = IUniswapV2Router::swapExactTokensForTokensCall {
+            amountIn: U256::from(1000),
+            amountOutMin: U256::from(990),
+            path: path.clone(),
+            to: Address::repeat_byte(3),
+            deadline: U256::from(1_700_000_000u64),
+        }
+        .abi_encode();
+
+        let call = decode_calldata(&input).unwrap();
+        assert_eq!(call.contract, "UniswapV2Router");
+        assert_eq!(call.function, "swapExactTokensForTokens");
+        assert_eq!(call.params[2].ty, "address[]");
+        assert_eq!(
+            call.params[2].value,
+            serde_json::json!([path[0].to_checksum(None), path[1].to_checksum(None)])
+        );
+    }
+
+    #[test]
+    fn test_decode_tuple_and_multicall() {
+        let params = ISwapRouter::ExactInputSingleParams {
+            tokenIn: Address::repeat_byte(1),
+            tokenOut: Address::repeat_byte(2),
+            fee: U24::from(3000),
+            recipient: Address::repeat_byte(3),
+            deadline: U256::from(1),
+            amountIn: U256::from(100),
+            amountOutMinimum: U256::ZERO,
+            sqrtPriceLimitX96: U160::ZERO,
+        };
+        let swap = IUniswapV3Router::exactInputSingleCall { params }.abi_encode();
+        let input = IUniswapV3Router::multicallCall { data: vec![Bytes::from(swap)] }.abi_encode();
+
+        let call = decode_calldata(&input).unwrap();
+        assert_eq!(call.function, "multicall");
+        let nested = &call.params[0].nested;
+        assert_eq!(nested.len(), 1);
+        assert_eq!(nested[0].function, "exactInputSingle");
+        assert_eq!(nested[0].params[0].value["fee"], Value::String("3000".into()));
+        assert_eq!(nested[0].params[0].value["amountIn"], Value::String("100".into()));
+    }
+
+    #[test]
+    fn test_decode_safe_exec_transaction() {
+        let transfer = IERC20::transferCall { to: Address::repeat_byte(9), amount: U256::from(5) }.abi_encode();
+        let input = ISafe::execTransactionCall {
+            to: Address::repeat_byte(7),
+            value: U256::ZERO,
+            data: transfer.into(),
+            operation: 0,
+            safeTxGas: U256::ZERO,
+            baseGas: U256::ZERO,
+            gasPrice: U256::ZERO,
+            gasToken: Address::ZERO,
+            refundReceiver: Address::ZERO,
+            signatures: Bytes::new(),
+        }
+        .abi_encode();
+
+        let call = decode_calldata(&input).unwrap();
+        assert_eq!(call.contract, "Safe");
+        let data = call.params.iter().find(|p| p.name == "data").unwrap();
+        assert_eq!(data.nested[0].signature, "transfer(address,uint256)");
+    }
+
+    #[test]
+    fn test_custom_struct_keeps_nested_names() {
+        let param: Param = serde_json::from_value(serde_json::json!({
+            "name": "order",
+            "type": "tuple",
+            "components": [
+                { "name": "maker", "type": "address" },
+                {
+                    "name": "amounts",
+                    "type": "tuple",
+                    "components": [{ "name": "sell", "type": "uint256" }, { "name": "buy", "type": "uint256" }]
+                }
+            ]
+        }))
+        .unwrap();
+        let value = DynSolValue::CustomStruct {
+            name: "Order".into(),
+            prop_names: vec!["maker".into(), "amounts".into()],
+            tuple: vec![
+                DynSolValue::Address(Address::repeat_byte(1)),
+                DynSolValue::Tuple(vec![DynSolValue::from(U256::from(5)), DynSolValue::from(U256::from(7))]),
+            ],
+        };
+
+        let json = value_to_json(&value, &param.components);
+        assert_eq!(json["amounts"], serde_json::json!({ "sell": "5", "buy": "7" }));
+    }
+
+    #[test]
+    fn test_unknown_calldata() {
+        assert!(decode_calldata(&[0xde, 0xad, 0xbe, 0xef, 0x00]).is_none());
+        assert!(decode_calldata(&[0x01]).is_none());
+    }
+}
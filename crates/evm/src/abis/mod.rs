@@ -1,2 +1,40 @@
 pub mod argus;
-pub mod uniswap;
\ No newline at end of file
+pub mod balancer;
+pub mod cow;
+pub mod curve;
+pub mod decode;
+pub mod erc;
+pub mod multicall;
+pub mod registry;
+pub mod signatures;
+pub mod uniswap;
+
+use std::sync::OnceLock;
+
+use alloy_json_abi::JsonAbi;
+
+/// JSON ABIs of the contracts bound in this module, keyed by a short contract name.
+///
+/// Protocol ABIs come first so that selectors shared with the ERC standards
+/// (e.g. `transferFrom`) resolve to the more specific contract.
+pub fn known_abis() -> &'static [(&'static str, JsonAbi)] {
+    static ABIS: OnceLock<Vec<(&'static str, JsonAbi)>> = OnceLock::new();
+    ABIS.get_or_init(|| {
+        vec![
+            ("UniswapV2Router", uniswap::IUniswapV2Router::abi::contract()),
+            ("UniswapV2Factory", uniswap::IUniswapV2Factory::abi::contract()),
+            ("UniswapV3Router", uniswap::IUniswapV3Router::abi::contract()),
+            ("UniswapV3Factory", uniswap::IUniswapV3Factory::abi::contract()),
+            ("BalancerVault", balancer::IBalancerVault::abi::contract()),
+            ("GPv2Settlement", cow::IGPv2Settlement::abi::contract()),
+            ("Safe", argus::ISafe::abi::contract()),
+            ("CoboArgus", argus::ICoboArgus::abi::contract()),
+            ("ArgusRoleManager", argus::IRoleManager::abi::contract()),
+            ("ArgusAuthorizer", argus::IAuthorizer::abi::contract()),
+            ("Multicall3", multicall::IMulticall3::abi::contract()),
+            ("ERC20", erc::IERC20::abi::contract()),
+            ("ERC721", erc::IERC721::abi::contract()),
+            ("ERC1155", erc::IERC1155::abi::contract()),
+        ]
+    })
+}
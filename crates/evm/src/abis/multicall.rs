@@ -0,0 +1,38 @@
+use alloy_primitives::{address, Address};
+use alloy_sol_types::sol;
+
+/// Multicall3 is deployed at the same address on almost every EVM chain.
+// https://www.multicall3.com/deployments
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+// https://github.com/mds1/multicall/blob/main/src/Multicall3.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    #[derive(Debug, PartialEq, Eq)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Call3Value {
+            address target;
+            bool allowFailure;
+            uint256 value;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+        function aggregate3Value(Call3Value[] calldata calls) external payable returns (Result[] memory returnData);
+        function getBlockNumber() external view returns (uint256 blockNumber);
+        function getCurrentBlockTimestamp() external view returns (uint256 timestamp);
+        function getEthBalance(address addr) external view returns (uint256 balance);
+    }
+}
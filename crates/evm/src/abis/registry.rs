@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use alloy_dyn_abi::{DynSolType, DynSolValue, EventExt, FunctionExt, JsonAbiExt};
+use alloy_json_abi::{Error as AbiError, Event, Function, JsonAbi};
+use alloy_primitives::{Bytes, LogData, Selector, B256};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::abis::decode::{value_to_json, DecodedCall, DecodedLog, DecodedParam};
+use crate::abis::known_abis;
+
+/// How deep `bytes` parameters (Safe `execTransaction`, V3 `multicall`, ...) are decoded.
+const MAX_NESTED_DEPTH: usize = 4;
+
+#[derive(Error, Debug)]
+pub enum AbiRegistryError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Unknown contract: {0}")]
+    UnknownContract(String),
+    #[error("Unknown function: {0}")]
+    UnknownFunction(String),
+    #[error("Encode error: {0}")]
+    EncodeError(String),
+    #[error("Decode error: {0}")]
+    DecodeError(String),
+}
+
+type Result<T> = std::result::Result<T, AbiRegistryError>;
+
+/// ABIs loaded at runtime, indexed by function selector, event topic and error selector.
+///
+/// Contracts are looked up in registration order, so when two ABIs share a selector
+/// the one added first wins.
+#[derive(Debug, Clone, Default)]
+pub struct AbiRegistry {
+    contracts: Vec<(String, JsonAbi)>,
+    functions: HashMap<Selector, Vec<(usize, Function)>>,
+    events: HashMap<B256, Vec<(usize, Event)>>,
+    errors: HashMap<Selector, Vec<(usize, AbiError)>>,
+}
+
+impl AbiRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry preloaded with the ABIs bound at compile time in [`crate::abis`].
+    pub fn with_known_abis() -> Self {
+        let mut registry = Self::new();
+        for (name, abi) in known_abis() {
+            registry.add_abi(name, abi.clone());
+        }
+        registry
+    }
+
+    /// Shared instance of [`AbiRegistry::with_known_abis`].
+    pub fn known() -> &'static Self {
+        static KNOWN: OnceLock<AbiRegistry> = OnceLock::new();
+        KNOWN.get_or_init(Self::with_known_abis)
+    }
+
+    /// Registers an ABI, merging it into an existing contract with the same name.
+    pub fn add_abi(&mut self, name: &str, abi: JsonAbi) {
+        let index = match self.contracts.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.contracts.push((name.to_string(), JsonAbi::new()));
+                self.contracts.len() - 1
+            }
+        };
+
+        // one entry per contract and selector, re-adding an ABI doesn't duplicate it
+        for function in abi.functions() {
+            let entries = self.functions.entry(function.selector()).or_default();
+            if !entries.iter().any(|(i, _)| *i == index) {
+                entries.push((index, function.clone()));
+            }
+        }
+        for event in abi.events().filter(|event| !event.anonymous) {
+            let entries = self.events.entry(event.selector()).or_default();
+            if !entries.iter().any(|(i, _)| *i == index) {
+                entries.push((index, event.clone()));
+            }
+        }
+        for error in abi.errors() {
+            let entries = self.errors.entry(error.selector()).or_default();
+            if !entries.iter().any(|(i, _)| *i == index) {
+                entries.push((index, error.clone()));
+            }
+        }
+
+        let contract = &mut self.contracts[index].1;
+        for (name, functions) in abi.functions {
+            merge(contract.functions.entry(name).or_default(), functions);
+        }
+        for (name, events) in abi.events {
+            merge(contract.events.entry(name).or_default(), events);
+        }
+        for (name, errors) in abi.errors {
+            merge(contract.errors.entry(name).or_default(), errors);
+        }
+        if contract.constructor.is_none() {
+            contract.constructor = abi.constructor;
+        }
+    }
+
+    /// Registers a JSON ABI, either a bare array or a build artifact with an `abi` field.
+    pub fn add_json(&mut self, name: &str, json: &str) -> Result<()> {
+        let value: Value = serde_json::from_str(json)?;
+        let abi_value = match value {
+            Value::Object(mut artifact) if artifact.contains_key("abi") => artifact.remove("abi").unwrap_or_default(),
+            other => other,
+        };
+        let abi: JsonAbi = serde_json::from_value(abi_value)?;
+        self.add_abi(name, abi);
+        Ok(())
+    }
+
+    /// Registers a JSON ABI file under its file stem (`erc20.json` -> `erc20`).
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let json = fs::read_to_string(path)?;
+        self.add_json(&name, &json)
+    }
+
+    /// Registers every `.json` file in a directory, returns how many were loaded.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let mut paths = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        for path in &paths {
+            self.load_file(path)?;
+        }
+        Ok(paths.len())
+    }
+
+    /// Registers human-readable signatures such as
+    /// `function transfer(address to, uint256 amount) returns (bool)` or
+    /// `event Transfer(address indexed from, address indexed to, uint256 value)`.
+    /// The `function` keyword may be omitted.
+    pub fn add_signatures<'a>(&mut self, name: &str, signatures: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let signatures = signatures
+            .into_iter()
+            .map(|signature| {
+                let signature = signature.trim();
+                let is_item = ["function ", "event ", "error ", "constructor", "fallback", "receive"]
+                    .iter()
+                    .any(|keyword| signature.starts_with(keyword));
+                if is_item {
+                    signature.to_string()
+                } else {
+                    format!("function {}", signature)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let abi = JsonAbi::parse(signatures.iter().map(String::as_str))
+            .map_err(|e| AbiRegistryError::InvalidSignature(e.to_string()))?;
+        self.add_abi(name, abi);
+        Ok(())
+    }
+
+    pub fn contract_names(&self) -> impl Iterator<Item = &str> {
+        self.contracts.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn abi(&self, name: &str) -> Option<&JsonAbi> {
+        self.contracts
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, abi)| abi)
+    }
+
+    /// Functions matching a selector, as `(contract, function)` pairs.
+    pub fn functions_by_selector(&self, selector: Selector) -> Vec<(&str, &Function)> {
+        self.functions
+            .get(&selector)
+            .map(|functions| {
+                functions
+                    .iter()
+                    .map(|(index, function)| (self.contracts[*index].0.as_str(), function))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Events matching a topic0, as `(contract, event)` pairs.
+    pub fn events_by_topic(&self, topic0: B256) -> Vec<(&str, &Event)> {
+        self.events
+            .get(&topic0)
+            .map(|events| {
+                events
+                    .iter()
+                    .map(|(index, event)| (self.contracts[*index].0.as_str(), event))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Finds a function by name (`transfer`) or full signature (`transfer(address,uint256)`).
+    pub fn functions(&self, contract: &str, function: &str) -> Result<&[Function]> {
+        let abi = self
+            .abi(contract)
+            .ok_or_else(|| AbiRegistryError::UnknownContract(contract.to_string()))?;
+
+        let name = function.split('(').next().unwrap_or_default();
+        let functions = abi
+            .function(name)
+            .ok_or_else(|| AbiRegistryError::UnknownFunction(function.to_string()))?;
+
+        if !function.contains('(') {
+            return Ok(functions);
+        }
+        functions
+            .iter()
+            .position(|f| f.signature() == function)
+            .map(|i| &functions[i..=i])
+            .ok_or_else(|| AbiRegistryError::UnknownFunction(function.to_string()))
+    }
+
+    /// ABI-encodes a call, with selector, picking the first overload the arguments fit.
+    pub fn encode_call(&self, contract: &str, function: &str, args: &[DynSolValue]) -> Result<Bytes> {
+        let mut last_error = None;
+        for candidate in self.functions(contract, function)? {
+            match candidate.abi_encode_input(args) {
+                Ok(encoded) => return Ok(encoded.into()),
+                Err(e) => last_error = Some(e.to_string()),
+            }
+        }
+        Err(AbiRegistryError::EncodeError(last_error.unwrap_or_default()))
+    }
+
+    /// Same as [`AbiRegistry::encode_call`] with arguments given as strings
+    /// (`"0x..."`, `"1000"`, `"true"`, `"[1,2]"`), coerced to the parameter types.
+    pub fn encode_call_str(&self, contract: &str, function: &str, args: &[&str]) -> Result<Bytes> {
+        let mut last_error = None;
+        for candidate in self.functions(contract, function)? {
+            if candidate.inputs.len() != args.len() {
+                continue;
+            }
+            match coerce_args(candidate, args).and_then(|values| {
+                candidate
+                    .abi_encode_input(&values)
+                    .map_err(|e| AbiRegistryError::EncodeError(e.to_string()))
+            }) {
+                Ok(encoded) => return Ok(encoded.into()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            AbiRegistryError::EncodeError(format!("no overload of {} takes {} arguments", function, args.len()))
+        }))
+    }
+
+    /// Decodes the return data of a call, the function is identified by the selector of its
+    /// calldata so overloads sharing a name decode with their own outputs.
+    pub fn decode_output(&self, contract: &str, selector: Selector, data: &[u8]) -> Result<Vec<DynSolValue>> {
+        let index = self
+            .contracts
+            .iter()
+            .position(|(name, _)| name == contract)
+            .ok_or_else(|| AbiRegistryError::UnknownContract(contract.to_string()))?;
+        let (_, function) = self
+            .functions
+            .get(&selector)
+            .and_then(|functions| functions.iter().find(|(i, _)| *i == index))
+            .ok_or_else(|| AbiRegistryError::UnknownFunction(selector.to_string()))?;
+        function
+            .abi_decode_output(data, true)
+            .map_err(|e| AbiRegistryError::DecodeError(e.to_string()))
+    }
+
+    /// Decodes calldata against every registered ABI, returns `None` when no ABI matches.
+    pub fn decode_call(&self, input: &[u8]) -> Option<DecodedCall> {
+        self.decode_call_with_depth(input, 0)
+    }
+
+    fn decode_call_with_depth(&self, input: &[u8], depth: usize) -> Option<DecodedCall> {
+        if input.len() < 4 {
+            return None;
+        }
+        let selector = Selector::from_slice(&input[..4]);
+
+        self.functions_by_selector(selector)
+            .into_iter()
+            .find_map(|(contract, function)| self.decode_function(contract, function, &input[4..], depth))
+    }
+
+    fn decode_function(&self, contract: &str, function: &Function, data: &[u8], depth: usize) -> Option<DecodedCall> {
+        let values = function.abi_decode_input(data, true).ok()?;
+        let params = function
+            .inputs
+            .iter()
+            .zip(values.iter())
+            .map(|(param, value)| DecodedParam {
+                name: param.name.clone(),
+                ty: param.selector_type().into_owned(),
+                value: value_to_json(value, &param.components),
+                indexed: false,
+                nested: self.nested_calls(value, depth),
+            })
+            .collect();
+
+        Some(DecodedCall {
+            contract: contract.to_string(),
+            function: function.name.clone(),
+            signature: function.signature(),
+            selector: function.selector(),
+            params,
+        })
+    }
+
+    fn nested_calls(&self, value: &DynSolValue, depth: usize) -> Vec<DecodedCall> {
+        if depth >= MAX_NESTED_DEPTH {
+            return vec![];
+        }
+        match value {
+            DynSolValue::Bytes(bytes) => self.decode_call_with_depth(bytes, depth + 1).into_iter().collect(),
+            DynSolValue::Array(values) => values
+                .iter()
+                .filter_map(|value| match value {
+                    DynSolValue::Bytes(bytes) => self.decode_call_with_depth(bytes, depth + 1),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Decodes an event log, returns `None` for unknown or anonymous events.
+    pub fn decode_log(&self, log: &LogData) -> Option<DecodedLog> {
+        let topic0 = *log.topics().first()?;
+
+        self.events_by_topic(topic0).into_iter().find_map(|(contract, event)| {
+            let decoded = event.decode_log(log, true).ok()?;
+            let mut indexed = decoded.indexed.iter();
+            let mut body = decoded.body.iter();
+
+            let params = event
+                .inputs
+                .iter()
+                .map(|param| {
+                    let value = if param.indexed { indexed.next() } else { body.next() }?;
+                    Some(DecodedParam {
+                        name: param.name.clone(),
+                        ty: param.selector_type().into_owned(),
+                        value: value_to_json(value, &param.components),
+                        indexed: param.indexed,
+                        nested: vec![],
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(DecodedLog {
+                contract: contract.to_string(),
+                event: event.name.clone(),
+                signature: event.signature(),
+                topic0,
+                params,
+            })
+        })
+    }
+
+    /// Decodes revert data of a custom error, `Error(string)` or `Panic(uint256)`.
+    pub fn decode_error(&self, data: &[u8]) -> Option<DecodedCall> {
+        if data.len() < 4 {
+            return None;
+        }
+        let selector = Selector::from_slice(&data[..4]);
+
+        let builtin = match selector.0 {
+            [0x08, 0xc3, 0x79, 0xa0] => Some(("Error", DynSolType::String)),
+            [0x4e, 0x48, 0x7b, 0x71] => Some(("Panic", DynSolType::Uint(256))),
+            _ => None,
+        };
+        if let Some((name, ty)) = builtin {
+            let value = ty.abi_decode(&data[4..]).ok()?;
+            return Some(DecodedCall {
+                contract: String::new(),
+                function: name.to_string(),
+                signature: format!("{}({})", name, ty),
+                selector,
+                params: vec![DecodedParam {
+                    name: String::new(),
+                    ty: ty.to_string(),
+                    value: value_to_json(&value, &[]),
+                    indexed: false,
+                    nested: vec![],
+                }],
+            });
+        }
+
+        self.errors.get(&selector)?.iter().find_map(|(index, error)| {
+            let values = error.abi_decode_input(&data[4..], true).ok()?;
+            Some(DecodedCall {
+                contract: self.contracts[*index].0.clone(),
+                function: error.name.clone(),
+                signature: error.signature(),
+                selector,
+                params: error
+                    .inputs
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(param, value)| DecodedParam {
+                        name: param.name.clone(),
+                        ty: param.selector_type().into_owned(),
+                        value: value_to_json(value, &param.components),
+                        indexed: false,
+                        nested: vec![],
+                    })
+                    .collect(),
+            })
+        })
+    }
+}
+
+fn coerce_args(function: &Function, args: &[&str]) -> Result<Vec<DynSolValue>> {
+    function
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(param, arg)| {
+            let ty = DynSolType::parse(&param.selector_type())
+                .map_err(|e| AbiRegistryError::EncodeError(e.to_string()))?;
+            ty.coerce_str(arg)
+                .map_err(|e| AbiRegistryError::EncodeError(format!("{}: {}", param.name, e)))
+        })
+        .collect()
+}
+
+/// Appends the items of `new` missing from `items`, keeping overloads apart.
+fn merge<T: PartialEq>(items: &mut Vec<T>, new: Vec<T>) {
+    for item in new {
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abis::erc::IERC20;
+    use alloy_primitives::{address, Address, U256};
+    use alloy_sol_types::{SolCall, SolEvent};
+
+    const ERC20_SIGNATURES: [&str; 4] = [
+        "function balanceOf(address owner) view returns (uint256)",
+        "transfer(address to, uint256 amount) returns (bool)",
+        "event Transfer(address indexed from, address indexed to, uint256 value)",
+        "error InsufficientBalance(uint256 available, uint256 required)",
+    ];
+
+    #[test]
+    fn test_human_readable_signatures() {
+        let mut registry = AbiRegistry::new();
+        registry.add_signatures("token", ERC20_SIGNATURES).unwrap();
+
+        let to = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+        let encoded = registry
+            .encode_call("token", "transfer", &[DynSolValue::Address(to), DynSolValue::Uint(U256::from(7), 256)])
+            .unwrap();
+        assert_eq!(encoded, Bytes::from(IERC20::transferCall { to, amount: U256::from(7) }.abi_encode()));
+
+        let encoded_str = registry
+            .encode_call_str("token", "transfer(address,uint256)", &["0x163a5ec5e9c32238d075e2d829fe9fa87451e3b7", "7"])
+            .unwrap();
+        assert_eq!(encoded, encoded_str);
+
+        let call = registry.decode_call(&encoded).unwrap();
+        assert_eq!(call.contract, "token");
+        assert_eq!(call.params[0].name, "to");
+        assert_eq!(call.params[1].value, Value::String("7".into()));
+    }
+
+    #[test]
+    fn test_decode_output_and_error() {
+        let mut registry = AbiRegistry::new();
+        registry.add_signatures("token", ERC20_SIGNATURES).unwrap();
+
+        let output = U256::from(42).to_be_bytes::<32>();
+        let decoded = registry
+            .decode_output("token", IERC20::balanceOfCall::SELECTOR.into(), &output)
+            .unwrap();
+        assert_eq!(decoded, vec![DynSolValue::Uint(U256::from(42), 256)]);
+
+        let error = registry.abi("token").unwrap().error("InsufficientBalance").unwrap()[0].clone();
+        let revert = error
+            .abi_encode_input(&[DynSolValue::Uint(U256::from(1), 256), DynSolValue::Uint(U256::from(2), 256)])
+            .unwrap();
+        let decoded = registry.decode_error(&revert).unwrap();
+        assert_eq!(decoded.function, "InsufficientBalance");
+        assert_eq!(decoded.params[1].value, Value::String("2".into()));
+
+        let revert = DynSolValue::String("too low".into()).abi_encode_params();
+        let decoded = registry.decode_error(&[&[0x08, 0xc3, 0x79, 0xa0][..], &revert].concat()).unwrap();
+        assert_eq!(decoded.signature, "Error(string)");
+        assert_eq!(decoded.params[0].value, Value::String("too low".into()));
+    }
+
+    #[test]
+    fn test_overloads_and_duplicates() {
+        let mut registry = AbiRegistry::new();
+        let overloads = [
+            "function get(uint256 id) view returns (uint256)",
+            "function get(address owner) view returns (address)",
+        ];
+        registry.add_signatures("store", overloads).unwrap();
+        registry.add_signatures("store", overloads).unwrap();
+
+        let by_address = registry.functions("store", "get(address)").unwrap()[0].selector();
+        assert_eq!(registry.functions_by_selector(by_address).len(), 1);
+        assert_eq!(registry.abi("store").unwrap().function("get").unwrap().len(), 2);
+
+        let owner = Address::repeat_byte(7);
+        let output = DynSolValue::Address(owner).abi_encode();
+        let decoded = registry.decode_output("store", by_address, &output).unwrap();
+        assert_eq!(decoded, vec![DynSolValue::Address(owner)]);
+        assert!(matches!(
+            registry.decode_output("store", Selector::ZERO, &output),
+            Err(AbiRegistryError::UnknownFunction(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_log() {
+        let registry = AbiRegistry::with_known_abis();
+        let event = IERC20::Transfer {
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            value: U256::from(1000),
+        };
+        let log = event.encode_log_data();
+
+        let decoded = registry.decode_log(&log).unwrap();
+        assert_eq!(decoded.contract, "ERC20");
+        assert_eq!(decoded.signature, "Transfer(address,address,uint256)");
+        assert!(decoded.params[0].indexed);
+        assert_eq!(decoded.params[1].value, Value::String(Address::repeat_byte(2).to_checksum(None)));
+        assert!(!decoded.params[2].indexed);
+        assert_eq!(decoded.params[2].value, Value::String("1000".into()));
+    }
+
+    #[test]
+    fn test_load_json_files() {
+        let dir = std::env::temp_dir().join(format!("web3ium-abis-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("counter.json"),
+            r#"{"abi":[{"type":"function","name":"increment","inputs":[{"name":"by","type":"uint256"}],"outputs":[],"stateMutability":"nonpayable"}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("vault.json"),
+            r#"[{"type":"function","name":"deposit","inputs":[{"name":"assets","type":"uint256"},{"name":"receiver","type":"address"}],"outputs":[{"name":"shares","type":"uint256"}],"stateMutability":"nonpayable"}]"#,
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not an abi").unwrap();
+
+        let mut registry = AbiRegistry::new();
+        assert_eq!(registry.load_dir(&dir).unwrap(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(registry.contract_names().collect::<Vec<_>>(), vec!["counter", "vault"]);
+        let calldata = registry.encode_call_str("counter", "increment", &["5"]).unwrap();
+        assert_eq!(registry.decode_call(&calldata).unwrap().function, "increment");
+        assert!(matches!(
+            registry.encode_call_str("vault", "withdraw", &[]),
+            Err(AbiRegistryError::UnknownFunction(_))
+        ));
+        assert!(matches!(
+            registry.encode_call_str("pool", "swap", &[]),
+            Err(AbiRegistryError::UnknownContract(_))
+        ));
+    }
+}
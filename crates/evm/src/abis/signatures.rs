@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::OnceLock;
+
+use alloy_dyn_abi::{DynSolValue, EventExt, JsonAbiExt};
+use alloy_json_abi::{Event, Function};
+use alloy_primitives::{hex, LogData, Selector, B256};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use thiserror::Error;
+
+use crate::abis::decode::{value_to_json, DecodedCall, DecodedLog, DecodedParam};
+
+/// Gzipped signature list embedded in the crate, rebuilt with `examples/build_signatures.rs`.
+const EMBEDDED_SIGNATURES: &[u8] = include_bytes!("data/signatures.gz");
+
+/// Events with more parameters than this are only tried with their leading parameters indexed.
+const MAX_EVENT_PARAMS: usize = 12;
+
+#[derive(Error, Debug)]
+pub enum SignatureDbError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("Selector mismatch: {0} does not hash to {1}")]
+    SelectorMismatch(String, String),
+}
+
+type Result<T> = std::result::Result<T, SignatureDbError>;
+
+/// Offline database of text signatures, indexed by function selector and event topic.
+///
+/// Unlike [`crate::abis::registry::AbiRegistry`] entries carry no parameter names and
+/// selectors may collide, so lookups return every candidate and decoding ranks them.
+/// Signatures keep their insertion order, which the dump is expected to sort by popularity.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureDb {
+    functions: HashMap<Selector, Vec<Function>>,
+    events: HashMap<B256, Vec<Event>>,
+}
+
+/// Outcome of [`SignatureDb::build_from_dump`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DumpStats {
+    pub functions: usize,
+    pub events: usize,
+    pub duplicates: usize,
+    /// Lines that failed to parse or whose signature does not hash to the given selector.
+    pub skipped: usize,
+}
+
+impl SignatureDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared instance of the database embedded in the crate.
+    pub fn embedded() -> &'static Self {
+        static EMBEDDED: OnceLock<SignatureDb> = OnceLock::new();
+        EMBEDDED.get_or_init(|| {
+            Self::from_compressed(EMBEDDED_SIGNATURES).expect("embedded signature database is valid")
+        })
+    }
+
+    /// Loads a database written by [`SignatureDb::to_compressed`].
+    pub fn from_compressed(bytes: &[u8]) -> Result<Self> {
+        let mut text = String::new();
+        GzDecoder::new(bytes).read_to_string(&mut text)?;
+
+        let mut db = Self::new();
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("f", signature)) => db.add_function(signature)?,
+                Some(("e", signature)) => db.add_event(signature)?,
+                _ => return Err(SignatureDbError::InvalidSignature(line.to_string())),
+            };
+        }
+        Ok(db)
+    }
+
+    /// Serializes the database as a gzipped list of `f <signature>` / `e <signature>` lines.
+    pub fn to_compressed(&self) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        for line in self.lines() {
+            writeln!(encoder, "{}", line)?;
+        }
+        Ok(encoder.finish()?)
+    }
+
+    /// Builds a database from a signature dump, one entry per line:
+    ///
+    /// - `transfer(address,uint256)` or `event Transfer(address,address,uint256)`
+    /// - `0xa9059cbb,transfer(address,uint256)`, the separator may also be a tab or a space,
+    ///   a 32-byte hash marks the entry as an event
+    ///
+    /// Empty lines and `#` comments are ignored, entries with a wrong selector are skipped.
+    pub fn build_from_dump(dump: &str) -> (Self, DumpStats) {
+        let mut db = Self::new();
+        let mut stats = DumpStats::default();
+
+        for line in dump.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match db.add_dump_line(line) {
+                Ok(true) => {}
+                Ok(false) => stats.duplicates += 1,
+                Err(_) => stats.skipped += 1,
+            }
+        }
+
+        stats.functions = db.function_count();
+        stats.events = db.event_count();
+        (db, stats)
+    }
+
+    fn add_dump_line(&mut self, line: &str) -> Result<bool> {
+        let (hash, signature) = match line.split_once([',', '\t', ' ']) {
+            Some((hash, signature)) if hash.trim_start_matches("0x").len() >= 8 && hex::decode(hash).is_ok() => {
+                (Some(hash), signature.trim())
+            }
+            _ => (None, line),
+        };
+        let (is_event, signature) = match signature.strip_prefix("event ") {
+            Some(signature) => (true, signature.trim()),
+            None => (
+                hash.is_some_and(|hash| hash.trim_start_matches("0x").len() == 64),
+                signature.strip_prefix("function ").unwrap_or(signature).trim(),
+            ),
+        };
+
+        if is_event {
+            let event = parse_event(signature)?;
+            check_hash(hash, event.selector().as_slice(), signature)?;
+            Ok(self.insert_event(event))
+        } else {
+            let function = parse_function(signature)?;
+            check_hash(hash, function.selector().as_slice(), signature)?;
+            Ok(self.insert_function(function))
+        }
+    }
+
+    /// Adds a function signature such as `transfer(address,uint256)`.
+    pub fn add_function(&mut self, signature: &str) -> Result<()> {
+        self.insert_function(parse_function(signature)?);
+        Ok(())
+    }
+
+    /// Adds an event signature such as `Transfer(address,address,uint256)`.
+    pub fn add_event(&mut self, signature: &str) -> Result<()> {
+        self.insert_event(parse_event(signature)?);
+        Ok(())
+    }
+
+    fn insert_function(&mut self, function: Function) -> bool {
+        let candidates = self.functions.entry(function.selector()).or_default();
+        if candidates.iter().any(|f| f.signature() == function.signature()) {
+            return false;
+        }
+        candidates.push(function);
+        true
+    }
+
+    fn insert_event(&mut self, event: Event) -> bool {
+        let candidates = self.events.entry(event.selector()).or_default();
+        if candidates.iter().any(|e| e.signature() == event.signature()) {
+            return false;
+        }
+        candidates.push(event);
+        true
+    }
+
+    pub fn function_count(&self) -> usize {
+        self.functions.values().map(Vec::len).sum()
+    }
+
+    pub fn event_count(&self) -> usize {
+        self.events.values().map(Vec::len).sum()
+    }
+
+    /// Text signatures matching a function selector, in database order.
+    pub fn function_signatures(&self, selector: Selector) -> Vec<String> {
+        self.functions
+            .get(&selector)
+            .map(|functions| functions.iter().map(Function::signature).collect())
+            .unwrap_or_default()
+    }
+
+    /// Text signatures matching an event topic0, in database order.
+    pub fn event_signatures(&self, topic0: B256) -> Vec<String> {
+        self.events
+            .get(&topic0)
+            .map(|events| events.iter().map(Event::signature).collect())
+            .unwrap_or_default()
+    }
+
+    /// Decodes calldata against every signature sharing its selector, most plausible first.
+    ///
+    /// Candidates that don't decode are dropped. The remaining ones are ranked by whether
+    /// re-encoding the decoded values gives back the exact calldata, then by how well the values
+    /// fit their types (see [`value_penalty`]), then by how natural the function name looks
+    /// (collision hunters produce names like `func_2093253501`), then by database order.
+    pub fn guess_call(&self, input: &[u8]) -> Vec<DecodedCall> {
+        if input.len() < 4 {
+            return vec![];
+        }
+        let selector = Selector::from_slice(&input[..4]);
+        let Some(functions) = self.functions.get(&selector) else {
+            return vec![];
+        };
+
+        let mut candidates = functions
+            .iter()
+            .enumerate()
+            .filter_map(|(order, function)| {
+                let values = function.abi_decode_input(&input[4..], true).ok()?;
+                let exact = function
+                    .abi_encode_input(&values)
+                    .is_ok_and(|encoded| encoded == input);
+                let rank = (!exact, value_penalty(&values), name_penalty(&function.name), order);
+                Some((rank, decoded_call(function, &values)))
+            })
+            .collect::<Vec<_>>();
+
+        candidates.sort_by_key(|(rank, _)| *rank);
+        candidates.into_iter().map(|(_, call)| call).collect()
+    }
+
+    /// Decodes an event log against every signature sharing its topic0.
+    ///
+    /// Text signatures don't say which parameters are indexed, so every placement matching
+    /// the number of topics is tried and the one whose values best fit their types wins,
+    /// leading parameters first on ties.
+    pub fn guess_log(&self, log: &LogData) -> Vec<DecodedLog> {
+        let Some((topic0, topics)) = log.topics().split_first() else {
+            return vec![];
+        };
+        let Some(events) = self.events.get(topic0) else {
+            return vec![];
+        };
+
+        events
+            .iter()
+            .filter_map(|event| {
+                indexed_layouts(event.inputs.len(), topics.len())
+                    .into_iter()
+                    .filter_map(|layout| decode_log_with_layout(event, &layout, log))
+                    .min_by_key(|(penalty, _)| *penalty)
+                    .map(|(_, decoded)| decoded)
+            })
+            .collect()
+    }
+
+    /// Entries sorted by selector so the output is deterministic, candidates sharing a
+    /// selector keep their order.
+    fn lines(&self) -> Vec<String> {
+        let mut functions = self.functions.iter().collect::<Vec<_>>();
+        functions.sort_by_key(|(selector, _)| **selector);
+        let mut events = self.events.iter().collect::<Vec<_>>();
+        events.sort_by_key(|(topic0, _)| **topic0);
+
+        functions
+            .into_iter()
+            .flat_map(|(_, functions)| functions.iter().map(|f| format!("f {}", f.signature())))
+            .chain(
+                events
+                    .into_iter()
+                    .flat_map(|(_, events)| events.iter().map(|e| format!("e {}", e.signature()))),
+            )
+            .collect()
+    }
+}
+
+fn parse_function(signature: &str) -> Result<Function> {
+    Function::parse(signature).map_err(|e| SignatureDbError::InvalidSignature(format!("{}: {}", signature, e)))
+}
+
+fn parse_event(signature: &str) -> Result<Event> {
+    Event::parse(signature).map_err(|e| SignatureDbError::InvalidSignature(format!("{}: {}", signature, e)))
+}
+
+fn check_hash(hash: Option<&str>, expected: &[u8], signature: &str) -> Result<()> {
+    match hash {
+        Some(hash) if hex::decode(hash).ok().as_deref() != Some(expected) => Err(
+            SignatureDbError::SelectorMismatch(signature.to_string(), hash.to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// How unlikely a function name is to be hand written.
+fn name_penalty(name: &str) -> u32 {
+    let digits = name.chars().filter(char::is_ascii_digit).count();
+    let underscores = name.matches('_').count();
+
+    let mut penalty = 0;
+    if digits >= 4 {
+        penalty += 2;
+    }
+    if underscores >= 2 {
+        penalty += 1;
+    }
+    if name.len() > 32 {
+        penalty += 1;
+    }
+    penalty
+}
+
+/// Counts values that don't look like what their type usually holds: small numbers
+/// decoded as addresses, and integers shaped like an address (20 significant bytes).
+fn value_penalty(values: &[DynSolValue]) -> u32 {
+    values
+        .iter()
+        .map(|value| match value {
+            DynSolValue::Address(address) => {
+                let significant = 20 - address.iter().take_while(|b| **b == 0).count();
+                u32::from(significant > 0 && significant <= 8)
+            }
+            DynSolValue::Uint(value, _) => u32::from((141..=160).contains(&value.bit_len())),
+            DynSolValue::Array(values) | DynSolValue::FixedArray(values) | DynSolValue::Tuple(values) => {
+                value_penalty(values)
+            }
+            _ => 0,
+        })
+        .sum()
+}
+
+fn decoded_call(function: &Function, values: &[DynSolValue]) -> DecodedCall {
+    DecodedCall {
+        contract: String::new(),
+        function: function.name.clone(),
+        signature: function.signature(),
+        selector: function.selector(),
+        params: function
+            .inputs
+            .iter()
+            .zip(values)
+            .map(|(param, value)| DecodedParam {
+                name: param.name.clone(),
+                ty: param.selector_type().into_owned(),
+                value: value_to_json(value, &param.components),
+                indexed: false,
+                nested: vec![],
+            })
+            .collect(),
+    }
+}
+
+/// Every way of choosing `indexed` out of `params` positions, in lexicographic order.
+fn indexed_layouts(params: usize, indexed: usize) -> Vec<Vec<bool>> {
+    if indexed > params {
+        return vec![];
+    }
+    if params > MAX_EVENT_PARAMS {
+        return vec![(0..params).map(|i| i < indexed).collect()];
+    }
+
+    let mut layouts = Vec::new();
+    let mut layout = vec![false; params];
+    fn choose(start: usize, left: usize, layout: &mut Vec<bool>, layouts: &mut Vec<Vec<bool>>) {
+        if left == 0 {
+            layouts.push(layout.clone());
+            return;
+        }
+        for i in start..=layout.len() - left {
+            layout[i] = true;
+            choose(i + 1, left - 1, layout, layouts);
+            layout[i] = false;
+        }
+    }
+    choose(0, indexed, &mut layout, &mut layouts);
+    layouts
+}
+
+fn decode_log_with_layout(event: &Event, layout: &[bool], log: &LogData) -> Option<(u32, DecodedLog)> {
+    let mut event = event.clone();
+    for (param, indexed) in event.inputs.iter_mut().zip(layout) {
+        param.indexed = *indexed;
+    }
+
+    let decoded = event.decode_log(log, true).ok()?;
+    let body = DynSolValue::Tuple(decoded.body.clone()).abi_encode_params();
+    if body != log.data {
+        return None;
+    }
+
+    let mut indexed = decoded.indexed.iter();
+    let mut body = decoded.body.iter();
+    let params = event
+        .inputs
+        .iter()
+        .map(|param| {
+            let value = if param.indexed { indexed.next() } else { body.next() }?;
+            Some(DecodedParam {
+                name: param.name.clone(),
+                ty: param.selector_type().into_owned(),
+                value: value_to_json(value, &param.components),
+                indexed: param.indexed,
+                nested: vec![],
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let penalty = value_penalty(&decoded.indexed) + value_penalty(&decoded.body);
+    let decoded = DecodedLog {
+        contract: String::new(),
+        event: event.name.clone(),
+        signature: event.signature(),
+        topic0: event.selector(),
+        params,
+    };
+    Some((penalty, decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abis::erc::IERC20;
+    use alloy_primitives::{Address, U256};
+    use alloy_sol_types::{sol, SolCall, SolEvent};
+
+    sol! {
+        event Swap(
+            address indexed sender,
+            uint256 amount0In,
+            uint256 amount1In,
+            uint256 amount0Out,
+            uint256 amount1Out,
+            address indexed to
+        );
+    }
+
+    #[test]
+    fn test_embedded_blob_loads() {
+        let db = SignatureDb::from_compressed(EMBEDDED_SIGNATURES).unwrap();
+        assert_eq!(db.function_count(), SignatureDb::embedded().function_count());
+        assert_eq!(db.event_count(), SignatureDb::embedded().event_count());
+    }
+
+    #[test]
+    fn test_embedded_db() {
+        let db = SignatureDb::embedded();
+        assert!(db.function_count() > 300);
+        assert!(db.event_count() > 50);
+
+        let signatures = db.function_signatures(IERC20::transferCall::SELECTOR.into());
+        assert!(signatures.contains(&"transfer(address,uint256)".to_string()));
+        assert!(signatures.len() > 1);
+
+        let roundtrip = SignatureDb::from_compressed(&db.to_compressed().unwrap()).unwrap();
+        assert_eq!(roundtrip.function_count(), db.function_count());
+        assert_eq!(roundtrip.function_signatures(IERC20::transferCall::SELECTOR.into()), signatures);
+    }
+
+    #[test]
+    fn test_guess_call_ranking() {
+        let input = IERC20::transferCall { to: Address::repeat_byte(0x11), amount: U256::from(5) }.abi_encode();
+
+        let candidates = SignatureDb::embedded().guess_call(&input);
+        assert_eq!(candidates[0].signature, "transfer(address,uint256)");
+        assert_eq!(candidates[0].params[1].value, serde_json::json!("5"));
+        // `workMyDirefulOwner(uint256,uint256)` decodes too but its first argument looks like an address,
+        // `func_2093253501(bytes)` can't decode the calldata at all
+        assert_eq!(candidates[1].signature, "workMyDirefulOwner(uint256,uint256)");
+        assert!(candidates.iter().all(|c| c.function != "func_2093253501"));
+
+        assert!(SignatureDb::embedded().guess_call(&[0xde, 0xad, 0xbe, 0xef]).is_empty());
+    }
+
+    #[test]
+    fn test_guess_log() {
+        let db = SignatureDb::embedded();
+
+        let transfer = IERC20::Transfer { from: Address::repeat_byte(1), to: Address::repeat_byte(2), value: U256::from(9) };
+        let logs = db.guess_log(&transfer.encode_log_data());
+        assert_eq!(logs[0].signature, "Transfer(address,address,uint256)");
+        assert!(logs[0].params[0].indexed && logs[0].params[1].indexed && !logs[0].params[2].indexed);
+
+        // V2 `Swap` indexes its first and last parameters
+        let swap = Swap {
+            sender: Address::repeat_byte(3),
+            amount0In: U256::from(1),
+            amount1In: U256::ZERO,
+            amount0Out: U256::ZERO,
+            amount1Out: U256::from(2),
+            to: Address::repeat_byte(4),
+        };
+        let logs = db.guess_log(&swap.encode_log_data());
+        assert_eq!(logs.len(), 1);
+        let indexed = logs[0].params.iter().map(|p| p.indexed).collect::<Vec<_>>();
+        assert_eq!(indexed, vec![true, false, false, false, false, true]);
+        assert_eq!(logs[0].params[5].value, serde_json::json!(Address::repeat_byte(4).to_checksum(None)));
+    }
+
+    #[test]
+    fn test_build_from_dump() {
+        let dump = "\
+            # comment\n\
+            0xa9059cbb,transfer(address,uint256)\n\
+            0x095ea7b3\tapprove(address,uint256)\n\
+            0xdeadbeef,approve(address,uint256)\n\
+            transfer(address,uint256)\n\
+            not a signature\n\
+            0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef Transfer(address,address,uint256)\n\
+            event Approval(address,address,uint256)\n";
+
+        let (db, stats) = SignatureDb::build_from_dump(dump);
+        assert_eq!(
+            stats,
+            DumpStats { functions: 2, events: 2, duplicates: 1, skipped: 2 }
+        );
+        assert_eq!(db.event_signatures(IERC20::Approval::SIGNATURE_HASH), vec!["Approval(address,address,uint256)"]);
+        assert_eq!(db.function_signatures(IERC20::approveCall::SELECTOR.into()), vec!["approve(address,uint256)"]);
+    }
+}
@@ -1,31 +1,263 @@
+#![allow(clippy::too_many_arguments)]
+
 use alloy_sol_types::sol;
+use serde::Serialize;
 
 // https://docs.uniswap.org/contracts/v2/reference/smart-contracts/v2-deployments
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     IUniswapV2Factory,
     "src/abis/protocols/uniswapV2/factory.json"
 );
 
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     IUniswapV2Router,
     "src/abis/protocols/uniswapV2/router.json"
 );
 
+// https://github.com/Uniswap/v2-core/blob/master/contracts/UniswapV2Pair.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IUniswapV2Pair {
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+        function kLast() external view returns (uint256);
+        function totalSupply() external view returns (uint256);
+        function balanceOf(address owner) external view returns (uint256);
+        function nonces(address owner) external view returns (uint256);
+        function name() external view returns (string);
+        function DOMAIN_SEPARATOR() external view returns (bytes32);
+    }
+}
+
 // https://docs.uniswap.org/contracts/v3/reference/deployments/ethereum-deployments
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     IUniswapV3Factory,
     "src/abis/protocols/uniswapV3/factory.json"
 );
 
 sol!(
     #[allow(missing_docs)]
-    #[sol(rpc)]
+    #[sol(rpc, abi)]
     IUniswapV3Router,
     "src/abis/protocols/uniswapV3/router.json"
 );
+
+// https://github.com/Uniswap/v3-core/blob/main/contracts/interfaces/IUniswapV3Pool.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IUniswapV3Pool {
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function fee() external view returns (uint24);
+        function tickSpacing() external view returns (int24);
+        function liquidity() external view returns (uint128);
+        function slot0() external view returns (
+            uint160 sqrtPriceX96,
+            int24 tick,
+            uint16 observationIndex,
+            uint16 observationCardinality,
+            uint16 observationCardinalityNext,
+            uint8 feeProtocol,
+            bool unlocked
+        );
+        function feeGrowthGlobal0X128() external view returns (uint256);
+        function feeGrowthGlobal1X128() external view returns (uint256);
+        function tickBitmap(int16 wordPosition) external view returns (uint256);
+        function ticks(int24 tick) external view returns (
+            uint128 liquidityGross,
+            int128 liquidityNet,
+            uint256 feeGrowthOutside0X128,
+            uint256 feeGrowthOutside1X128,
+            int56 tickCumulativeOutside,
+            uint160 secondsPerLiquidityOutsideX128,
+            uint32 secondsOutside,
+            bool initialized
+        );
+    }
+}
+
+// https://github.com/Uniswap/v3-periphery/blob/main/contracts/interfaces/INonfungiblePositionManager.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    #[derive(Debug, PartialEq, Eq)]
+    interface INonfungiblePositionManager {
+        struct MintParams {
+            address token0;
+            address token1;
+            uint24 fee;
+            int24 tickLower;
+            int24 tickUpper;
+            uint256 amount0Desired;
+            uint256 amount1Desired;
+            uint256 amount0Min;
+            uint256 amount1Min;
+            address recipient;
+            uint256 deadline;
+        }
+
+        struct IncreaseLiquidityParams {
+            uint256 tokenId;
+            uint256 amount0Desired;
+            uint256 amount1Desired;
+            uint256 amount0Min;
+            uint256 amount1Min;
+            uint256 deadline;
+        }
+
+        struct DecreaseLiquidityParams {
+            uint256 tokenId;
+            uint128 liquidity;
+            uint256 amount0Min;
+            uint256 amount1Min;
+            uint256 deadline;
+        }
+
+        struct CollectParams {
+            uint256 tokenId;
+            address recipient;
+            uint128 amount0Max;
+            uint128 amount1Max;
+        }
+
+        function positions(uint256 tokenId) external view returns (
+            uint96 nonce,
+            address operator,
+            address token0,
+            address token1,
+            uint24 fee,
+            int24 tickLower,
+            int24 tickUpper,
+            uint128 liquidity,
+            uint256 feeGrowthInside0LastX128,
+            uint256 feeGrowthInside1LastX128,
+            uint128 tokensOwed0,
+            uint128 tokensOwed1
+        );
+        function balanceOf(address owner) external view returns (uint256);
+        function tokenOfOwnerByIndex(address owner, uint256 index) external view returns (uint256);
+        function mint(MintParams calldata params) external payable returns (
+            uint256 tokenId,
+            uint128 liquidity,
+            uint256 amount0,
+            uint256 amount1
+        );
+        function increaseLiquidity(IncreaseLiquidityParams calldata params) external payable returns (
+            uint128 liquidity,
+            uint256 amount0,
+            uint256 amount1
+        );
+        function decreaseLiquidity(DecreaseLiquidityParams calldata params) external payable returns (
+            uint256 amount0,
+            uint256 amount1
+        );
+        function collect(CollectParams calldata params) external payable returns (uint256 amount0, uint256 amount1);
+        function burn(uint256 tokenId) external payable;
+        function multicall(bytes[] calldata data) external payable returns (bytes[] memory results);
+        function refundETH() external payable;
+        function unwrapWETH9(uint256 amountMinimum, address recipient) external payable;
+        function sweepToken(address token, uint256 amountMinimum, address recipient) external payable;
+    }
+}
+
+// https://github.com/Uniswap/universal-router/blob/main/contracts/interfaces/IUniversalRouter.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IUniversalRouter {
+        function execute(bytes calldata commands, bytes[] calldata inputs, uint256 deadline) external payable;
+        function execute(bytes calldata commands, bytes[] calldata inputs) external payable;
+    }
+}
+
+// https://github.com/Uniswap/permit2/blob/main/src/interfaces/IAllowanceTransfer.sol
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc, abi)]
+    interface IAllowanceTransfer {
+        #[derive(Debug, PartialEq, Eq, Serialize)]
+        struct PermitDetails {
+            address token;
+            uint160 amount;
+            uint48 expiration;
+            uint48 nonce;
+        }
+
+        #[derive(Debug, PartialEq, Eq, Serialize)]
+        struct PermitSingle {
+            PermitDetails details;
+            address spender;
+            uint256 sigDeadline;
+        }
+
+        function allowance(address user, address token, address spender) external view returns (
+            uint160 amount,
+            uint48 expiration,
+            uint48 nonce
+        );
+        function approve(address token, address spender, uint160 amount, uint48 expiration) external;
+        function permit(address owner, PermitSingle memory permitSingle, bytes calldata signature) external;
+    }
+}
+
+// https://github.com/Uniswap/v4-periphery/blob/main/src/interfaces/IV4Router.sol
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    interface IV4Router {
+        struct PoolKey {
+            address currency0;
+            address currency1;
+            uint24 fee;
+            int24 tickSpacing;
+            address hooks;
+        }
+
+        struct PathKey {
+            address intermediateCurrency;
+            uint24 fee;
+            int24 tickSpacing;
+            address hooks;
+            bytes hookData;
+        }
+
+        struct ExactInputSingleParams {
+            PoolKey poolKey;
+            bool zeroForOne;
+            uint128 amountIn;
+            uint128 amountOutMinimum;
+            bytes hookData;
+        }
+
+        struct ExactInputParams {
+            address currencyIn;
+            PathKey[] path;
+            uint128 amountIn;
+            uint128 amountOutMinimum;
+        }
+
+        struct ExactOutputSingleParams {
+            PoolKey poolKey;
+            bool zeroForOne;
+            uint128 amountOut;
+            uint128 amountInMaximum;
+            bytes hookData;
+        }
+
+        struct ExactOutputParams {
+            address currencyOut;
+            PathKey[] path;
+            uint128 amountOut;
+            uint128 amountInMaximum;
+        }
+    }
+}
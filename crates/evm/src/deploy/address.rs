@@ -0,0 +1,137 @@
+use alloy_primitives::{address, b256, keccak256, Address, B256};
+
+/// Arachnid's deterministic deployment proxy, available at the same address on most chains.
+/// Calldata is `salt || init_code`, see [`crate::deploy::builder::create2_deployer_calldata`].
+pub const CREATE2_DEPLOYER: Address = address!("4e59b44847b379578588920cA78FbF26c0B4956C");
+
+/// Init code of the minimal proxy used by the solmate/Solady CREATE3 libraries.
+pub const CREATE3_PROXY_INITCODE: [u8; 16] = [
+    0x67, 0x36, 0x3d, 0x3d, 0x37, 0x36, 0x3d, 0x34, 0xf0, 0x3d, 0x52, 0x60, 0x08, 0x60, 0x18, 0xf3,
+];
+
+/// `keccak256(CREATE3_PROXY_INITCODE)`
+pub const CREATE3_PROXY_INITCODE_HASH: B256 =
+    b256!("21c35dbe1b344a2488cf3321d6ce542f8e9f305544ff09e4993a62319a497c1f");
+
+/// How a CREATE3 factory turns the user supplied salt into the CREATE2 salt of the proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Create3Salt {
+    /// The salt is used as-is (Solady/solmate `CREATE3` called directly by the factory).
+    Raw,
+    /// The salt is `keccak256(abi.encodePacked(deployer, salt))`, as in the widely deployed
+    /// `CREATE3Factory` (0x9fBB3DF7C40Da2e5A0dE984fFE2CCB7C47cd0ABf), so every deployer has its
+    /// own namespace.
+    DeployerScoped,
+}
+
+/// Address of a contract created with CREATE by `sender` at `nonce`.
+pub fn create_address(sender: Address, nonce: u64) -> Address {
+    sender.create(nonce)
+}
+
+/// Address of a contract created with CREATE2 by `factory`.
+pub fn create2_address(factory: Address, salt: B256, init_code_hash: B256) -> Address {
+    factory.create2(salt, init_code_hash)
+}
+
+/// Same as [`create2_address`] but hashes the init code (creation bytecode + constructor args).
+pub fn create2_address_from_code(factory: Address, salt: B256, init_code: &[u8]) -> Address {
+    factory.create2_from_code(salt, init_code)
+}
+
+/// Address of a contract deployed through a CREATE3 `factory`.
+///
+/// The factory deploys a proxy with CREATE2, the proxy then deploys the contract with CREATE
+/// at nonce 1, so the address does not depend on the init code.
+pub fn create3_address(factory: Address, deployer: Address, salt: B256, scheme: Create3Salt) -> Address {
+    let salt = match scheme {
+        Create3Salt::Raw => salt,
+        Create3Salt::DeployerScoped => {
+            let mut packed = [0u8; 52];
+            packed[..20].copy_from_slice(deployer.as_slice());
+            packed[20..].copy_from_slice(salt.as_slice());
+            keccak256(packed)
+        }
+    };
+
+    let proxy = factory.create2(salt, CREATE3_PROXY_INITCODE_HASH);
+    proxy.create(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    #[test]
+    fn test_create_address() {
+        let sender = address!("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        assert_eq!(
+            create_address(sender, 0),
+            address!("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d")
+        );
+        assert_eq!(
+            create_address(sender, 1),
+            address!("343c43a37d37dff08ae8c4a11544c718abb4fcf8")
+        );
+    }
+
+    #[test]
+    fn test_create2_address() {
+        // EIP-1014 examples
+        let cases = [
+            (
+                Address::ZERO,
+                B256::ZERO,
+                hex!("00").to_vec(),
+                address!("4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38"),
+            ),
+            (
+                address!("deadbeef00000000000000000000000000000000"),
+                B256::ZERO,
+                hex!("00").to_vec(),
+                address!("B928f69Bb1D91Cd65274e3c79d8986362984fDA3"),
+            ),
+            (
+                address!("00000000000000000000000000000000deadbeef"),
+                b256!("00000000000000000000000000000000000000000000000000000000cafebabe"),
+                hex!("deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef").to_vec(),
+                address!("1d8bfDC5D46DC4f61D6b6115972536eBE6A8854C"),
+            ),
+        ];
+
+        for (factory, salt, init_code, expected) in cases {
+            assert_eq!(create2_address_from_code(factory, salt, &init_code), expected);
+            assert_eq!(create2_address(factory, salt, keccak256(&init_code)), expected);
+        }
+    }
+
+    #[test]
+    fn test_create3_address() {
+        assert_eq!(keccak256(CREATE3_PROXY_INITCODE), CREATE3_PROXY_INITCODE_HASH);
+
+        let factory = address!("9fBB3DF7C40Da2e5A0dE984fFE2CCB7C47cd0ABf");
+        let deployer = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+        let salt = keccak256("web3ium");
+
+        let raw = create3_address(factory, deployer, salt, Create3Salt::Raw);
+        let proxy = create2_address(factory, salt, CREATE3_PROXY_INITCODE_HASH);
+        assert_eq!(raw, create_address(proxy, 1));
+
+        // spelled out the way the Solidity libraries do it, without the alloy helpers:
+        // proxy = keccak256(0xff ++ factory ++ salt ++ proxy hash), then keccak256(rlp([proxy, 1]))
+        let proxy = keccak256(
+            [&[0xff], factory.as_slice(), salt.as_slice(), CREATE3_PROXY_INITCODE_HASH.as_slice()].concat(),
+        );
+        let deployed = keccak256([&[0xd6, 0x94], &proxy[12..], &[0x01]].concat());
+        assert_eq!(raw, Address::from_slice(&deployed[12..]));
+
+        // the deployer only matters for scoped salts
+        let other = address!("742d35Cc6634C0532925a3b844Bc454e4438f44e");
+        assert_eq!(raw, create3_address(factory, other, salt, Create3Salt::Raw));
+        assert_ne!(
+            create3_address(factory, deployer, salt, Create3Salt::DeployerScoped),
+            create3_address(factory, other, salt, Create3Salt::DeployerScoped)
+        );
+    }
+}
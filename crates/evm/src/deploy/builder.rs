@@ -0,0 +1,62 @@
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_sol_types::SolConstructor;
+
+use crate::deploy::address::{create2_address_from_code, CREATE2_DEPLOYER};
+
+/// Creation bytecode followed by the ABI encoded constructor arguments.
+pub fn init_code(bytecode: &[u8], constructor_args: &[u8]) -> Bytes {
+    [bytecode, constructor_args].concat().into()
+}
+
+/// Same as [`init_code`] for constructors generated by `sol!`.
+pub fn init_code_with<C: SolConstructor>(bytecode: &[u8], constructor: &C) -> Bytes {
+    init_code(bytecode, &constructor.abi_encode())
+}
+
+/// Calldata for [`CREATE2_DEPLOYER`]: `salt || init_code`.
+pub fn create2_deployer_calldata(salt: B256, init_code: &[u8]) -> Bytes {
+    [salt.as_slice(), init_code].concat().into()
+}
+
+/// Calldata and predicted address of a deployment through [`CREATE2_DEPLOYER`].
+///
+/// Send the calldata in a regular call to [`CREATE2_DEPLOYER`], not a creation transaction.
+pub fn create2_deployment(salt: B256, init_code: &[u8]) -> (Bytes, Address) {
+    (
+        create2_deployer_calldata(salt, init_code),
+        create2_address_from_code(CREATE2_DEPLOYER, salt, init_code),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{hex, U256};
+    use alloy_sol_types::sol;
+
+    sol! {
+        constructor(address owner, uint256 supply);
+    }
+
+    #[test]
+    fn test_init_code() {
+        let bytecode = hex!("6080604052");
+        let owner = Address::repeat_byte(0x11);
+        let code = init_code_with(&bytecode, &constructorCall { owner, supply: U256::from(1000) });
+
+        assert_eq!(code.len(), bytecode.len() + 64);
+        assert_eq!(&code[..5], &bytecode);
+        assert_eq!(&code[5 + 12..5 + 32], owner.as_slice());
+    }
+
+    #[test]
+    fn test_create2_deployment() {
+        let salt = B256::with_last_byte(1);
+        let code = hex!("6080604052");
+        let (calldata, address) = create2_deployment(salt, &code);
+
+        assert_eq!(&calldata[..32], salt.as_slice());
+        assert_eq!(&calldata[32..], &code);
+        assert_eq!(address, CREATE2_DEPLOYER.create2_from_code(salt, code));
+    }
+}
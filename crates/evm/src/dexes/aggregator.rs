@@ -0,0 +1,420 @@
+//! A common interface over the DEX aggregator APIs, and a meta-aggregator picking the best of them.
+
+use std::time::Duration;
+
+use alloy_primitives::{address, Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+use async_trait::async_trait;
+use futures_util::future::join_all;
+
+use crate::dexes::kyber::KyberSwapError;
+use crate::dexes::odos::OdosError;
+use crate::dexes::oneinch::OneInchError;
+use crate::dexes::paraswap::ParaSwapError;
+use crate::dexes::zeroex::ZeroExError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AggregatorError {
+    #[error("KyberSwap error: {0}")]
+    KyberSwap(#[from] KyberSwapError),
+    #[error("Odos error: {0}")]
+    Odos(#[from] OdosError),
+    #[error("1inch error: {0}")]
+    OneInch(#[from] OneInchError),
+    #[error("0x error: {0}")]
+    ZeroEx(#[from] ZeroExError),
+    #[error("ParaSwap error: {0}")]
+    ParaSwap(#[from] ParaSwapError),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Invalid response from {provider}: {message}")]
+    InvalidResponse { provider: &'static str, message: String },
+    #[error("{0} timed out")]
+    Timeout(&'static str),
+    #[error("Unknown provider: {0}")]
+    UnknownProvider(String),
+    #[error("No quote: {0}")]
+    NoQuote(String),
+}
+
+type Result<T> = std::result::Result<T, AggregatorError>;
+
+/// Placeholder address the aggregators use for the native token.
+pub const NATIVE: Address = address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+/// A swap of an exact amount in, from and to `sender`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapRequest {
+    pub chain_id: u64,
+    /// [`NATIVE`] for the native token.
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub sender: Address,
+    /// 50 is 0.5%.
+    pub slippage_bps: u32,
+    /// In wei, used to weigh gas costs against output.
+    pub gas_price: U256,
+    /// Amount of `token_out` one native token (1e18 wei) is worth, if known.
+    pub native_price: Option<U256>,
+}
+
+impl SwapRequest {
+    pub fn new(chain_id: u64, token_in: Address, token_out: Address, amount_in: U256, sender: Address) -> Self {
+        Self {
+            chain_id,
+            token_in,
+            token_out,
+            amount_in,
+            sender,
+            slippage_bps: 50,
+            gas_price: U256::ZERO,
+            native_price: None,
+        }
+    }
+
+    pub fn with_slippage(mut self, slippage_bps: u32) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    /// Sets the gas price and the price of the native token in `token_out`, so quotes compare net of gas.
+    pub fn with_gas(mut self, gas_price: U256, native_price: U256) -> Self {
+        self.gas_price = gas_price;
+        self.native_price = Some(native_price);
+        self
+    }
+
+    /// `amount_out` less the slippage tolerance.
+    pub fn min_amount_out(&self, amount_out: U256) -> U256 {
+        let bps = U256::from(10_000u32.saturating_sub(self.slippage_bps));
+        amount_out * bps / U256::from(10_000)
+    }
+}
+
+/// A quote from one provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapQuote {
+    pub provider: &'static str,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub gas: u64,
+    /// Provider specific data needed to build the transaction, e.g. a route or a path id.
+    pub route: serde_json::Value,
+}
+
+impl SwapQuote {
+    /// Gas cost of the swap in `token_out`, zero if the request has no native price.
+    pub fn gas_cost(&self, request: &SwapRequest) -> U256 {
+        match request.native_price {
+            Some(price) => {
+                U256::from(self.gas)
+                    .saturating_mul(request.gas_price)
+                    .saturating_mul(price)
+                    / U256::from(1_000_000_000_000_000_000u64)
+            }
+            None => U256::ZERO,
+        }
+    }
+
+    /// `amount_out` after the gas cost.
+    pub fn net_amount_out(&self, request: &SwapRequest) -> U256 {
+        self.amount_out.saturating_sub(self.gas_cost(request))
+    }
+}
+
+/// A transaction executing a quote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapTransaction {
+    pub provider: &'static str,
+    /// The router, also the spender to approve for ERC20 inputs.
+    pub to: Address,
+    pub data: Bytes,
+    pub value: U256,
+    pub gas: u64,
+    pub amount_out: U256,
+    pub min_amount_out: U256,
+}
+
+impl SwapTransaction {
+    pub fn into_request(self, from: Address) -> TransactionRequest {
+        let mut request = TransactionRequest::default()
+            .from(from)
+            .to(self.to)
+            .input(self.data.into());
+        if !self.value.is_zero() {
+            request = request.value(self.value);
+        }
+        if self.gas > 0 {
+            request = request.gas_limit(self.gas);
+        }
+        request
+    }
+}
+
+/// An aggregator API able to quote a swap and build its transaction.
+#[async_trait]
+pub trait SwapProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn quote(&self, request: &SwapRequest) -> Result<SwapQuote>;
+
+    /// Builds the transaction of a quote this provider returned for `request`.
+    async fn build(&self, request: &SwapRequest, quote: &SwapQuote) -> Result<SwapTransaction>;
+}
+
+/// Queries several providers at once and picks the best net output.
+pub struct MetaAggregator {
+    providers: Vec<(Box<dyn SwapProvider>, Duration)>,
+}
+
+impl Default for MetaAggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetaAggregator {
+    pub fn new() -> Self {
+        Self { providers: Vec::new() }
+    }
+
+    /// Adds a provider, giving up on each of its calls after `timeout`.
+    pub fn add(mut self, provider: impl SwapProvider + 'static, timeout: Duration) -> Self {
+        self.providers.push((Box::new(provider), timeout));
+        self
+    }
+
+    pub fn providers(&self) -> impl Iterator<Item = &str> {
+        self.providers.iter().map(|(provider, _)| provider.name())
+    }
+
+    fn provider(&self, name: &str) -> Result<(&dyn SwapProvider, Duration)> {
+        self.providers
+            .iter()
+            .find(|(provider, _)| provider.name() == name)
+            .map(|(provider, timeout)| (provider.as_ref(), *timeout))
+            .ok_or_else(|| AggregatorError::UnknownProvider(name.to_string()))
+    }
+
+    /// Quotes from every provider, in the order they were added.
+    pub async fn quote_all(&self, request: &SwapRequest) -> Vec<Result<SwapQuote>> {
+        join_all(self.providers.iter().map(|(provider, timeout)| async move {
+            tokio::time::timeout(*timeout, provider.quote(request))
+                .await
+                .unwrap_or(Err(AggregatorError::Timeout(provider.name())))
+        }))
+        .await
+    }
+
+    /// The quote with the highest output net of gas, ties going to the provider added first.
+    pub async fn best_quote(&self, request: &SwapRequest) -> Result<SwapQuote> {
+        let mut best: Option<SwapQuote> = None;
+        let mut errors = Vec::new();
+        for result in self.quote_all(request).await {
+            match result {
+                Ok(quote) => {
+                    if best
+                        .as_ref()
+                        .is_none_or(|b| quote.net_amount_out(request) > b.net_amount_out(request))
+                    {
+                        best = Some(quote);
+                    }
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        best.ok_or_else(|| AggregatorError::NoQuote(errors.join("; ")))
+    }
+
+    /// Builds a quote with the provider that returned it.
+    pub async fn build(&self, request: &SwapRequest, quote: &SwapQuote) -> Result<SwapTransaction> {
+        let (provider, timeout) = self.provider(quote.provider)?;
+        tokio::time::timeout(timeout, provider.build(request, quote))
+            .await
+            .unwrap_or(Err(AggregatorError::Timeout(provider.name())))
+    }
+
+    /// Quotes every provider and builds the best quote.
+    pub async fn best_swap(&self, request: &SwapRequest) -> Result<(SwapQuote, SwapTransaction)> {
+        let quote = self.best_quote(request).await?;
+        let transaction = self.build(request, &quote).await?;
+        Ok((quote, transaction))
+    }
+}
+
+/// Rejects a request for another chain than the one `provider` was created for.
+pub(crate) fn check_chain(provider: &'static str, chain_id: u64, request: &SwapRequest) -> Result<()> {
+    if request.chain_id != chain_id {
+        return Err(AggregatorError::InvalidRequest(format!(
+            "{} client is on chain {}, request is for chain {}",
+            provider, chain_id, request.chain_id
+        )));
+    }
+    Ok(())
+}
+
+/// Parses a decimal amount returned by `provider`.
+pub(crate) fn parse_amount(provider: &'static str, amount: &str) -> Result<U256> {
+    amount.parse().map_err(|_| AggregatorError::InvalidResponse {
+        provider,
+        message: format!("invalid amount {:?}", amount),
+    })
+}
+
+/// Parses an address returned by `provider`.
+pub(crate) fn parse_address(provider: &'static str, address: &str) -> Result<Address> {
+    address.parse().map_err(|_| AggregatorError::InvalidResponse {
+        provider,
+        message: format!("invalid address {:?}", address),
+    })
+}
+
+/// Parses hex calldata returned by `provider`.
+pub(crate) fn parse_data(provider: &'static str, data: &str) -> Result<Bytes> {
+    data.parse().map_err(|_| AggregatorError::InvalidResponse {
+        provider,
+        message: "invalid calldata".into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::bytes;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const SENDER: Address = address!("d46B96d15ffF9b2B17e9c788086f3159bD0e8355");
+
+    struct FixedProvider {
+        name: &'static str,
+        amount_out: u64,
+        gas: u64,
+        delay: Duration,
+        fail: bool,
+    }
+
+    fn fixed(name: &'static str, amount_out: u64, gas: u64) -> FixedProvider {
+        FixedProvider {
+            name,
+            amount_out,
+            gas,
+            delay: Duration::ZERO,
+            fail: false,
+        }
+    }
+
+    #[async_trait]
+    impl SwapProvider for FixedProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn quote(&self, request: &SwapRequest) -> Result<SwapQuote> {
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                return Err(AggregatorError::InvalidResponse {
+                    provider: self.name,
+                    message: "no route".into(),
+                });
+            }
+            Ok(SwapQuote {
+                provider: self.name,
+                amount_in: request.amount_in,
+                amount_out: U256::from(self.amount_out),
+                gas: self.gas,
+                route: serde_json::json!(self.name),
+            })
+        }
+
+        async fn build(&self, request: &SwapRequest, quote: &SwapQuote) -> Result<SwapTransaction> {
+            assert_eq!(quote.route, serde_json::json!(self.name));
+            Ok(SwapTransaction {
+                provider: self.name,
+                to: SENDER,
+                data: bytes!("12345678"),
+                value: request.amount_in,
+                gas: quote.gas,
+                amount_out: quote.amount_out,
+                min_amount_out: request.min_amount_out(quote.amount_out),
+            })
+        }
+    }
+
+    fn request() -> SwapRequest {
+        SwapRequest::new(1, NATIVE, USDC, U256::from(10).pow(U256::from(18)), SENDER)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_best_quote_net_of_gas() {
+        let aggregator = MetaAggregator::new()
+            .add(fixed("a", 3_000_000_000, 500_000), Duration::from_secs(1))
+            .add(fixed("b", 2_999_000_000, 150_000), Duration::from_secs(1));
+
+        // without gas pricing the gross output wins
+        assert_eq!(aggregator.best_quote(&request()).await.unwrap().provider, "a");
+
+        // 20 gwei at 3000 USDC per ETH: 350k gas more costs 21 USDC, more than the 1 USDC better output
+        let request = request().with_gas(U256::from(20_000_000_000u64), U256::from(3_000_000_000u64));
+        let best = aggregator.best_quote(&request).await.unwrap();
+        assert_eq!(best.provider, "b");
+        assert_eq!(best.gas_cost(&request), U256::from(9_000_000));
+        assert_eq!(best.net_amount_out(&request), U256::from(2_990_000_000u64));
+
+        let (quote, transaction) = aggregator.best_swap(&request.clone().with_slippage(100)).await.unwrap();
+        assert_eq!(quote.provider, "b");
+        assert_eq!(transaction.min_amount_out, U256::from(2_969_010_000u64));
+        let tx = transaction.into_request(SENDER);
+        assert_eq!(tx.value, Some(request.amount_in));
+        assert_eq!(tx.gas, Some(150_000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeouts_and_failures() {
+        let slow = FixedProvider {
+            delay: Duration::from_secs(5),
+            ..fixed("slow", 4_000_000_000, 0)
+        };
+        let broken = FixedProvider {
+            fail: true,
+            ..fixed("broken", 0, 0)
+        };
+        let aggregator = MetaAggregator::new()
+            .add(slow, Duration::from_secs(2))
+            .add(broken, Duration::from_secs(2))
+            .add(fixed("ok", 2_000_000_000, 0), Duration::from_secs(2));
+        assert_eq!(aggregator.providers().collect::<Vec<_>>(), ["slow", "broken", "ok"]);
+
+        let quotes = aggregator.quote_all(&request()).await;
+        assert!(matches!(quotes[0], Err(AggregatorError::Timeout("slow"))));
+        assert!(matches!(
+            quotes[1],
+            Err(AggregatorError::InvalidResponse { provider: "broken", .. })
+        ));
+        assert_eq!(aggregator.best_quote(&request()).await.unwrap().provider, "ok");
+
+        let aggregator = MetaAggregator::new().add(
+            FixedProvider {
+                fail: true,
+                ..fixed("broken", 0, 0)
+            },
+            Duration::from_secs(2),
+        );
+        assert!(matches!(
+            aggregator.best_quote(&request()).await,
+            Err(AggregatorError::NoQuote(_))
+        ));
+
+        let unknown = SwapQuote {
+            provider: "missing",
+            amount_in: U256::ZERO,
+            amount_out: U256::ZERO,
+            gas: 0,
+            route: serde_json::Value::Null,
+        };
+        assert!(matches!(
+            aggregator.build(&request(), &unknown).await,
+            Err(AggregatorError::UnknownProvider(_))
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+//! Ports of the `FixedPoint` and `LogExpMath` libraries of the Balancer V2 solidity-utils, and the
+//! scaling and fee helpers of the base pools, returning errors where the Solidity versions revert.
+
+use alloy_primitives::{uint, I256, U256};
+
+use crate::dexes::balancer::{BalancerError, Result};
+
+pub const ONE: U256 = uint!(1000000000000000000_U256);
+
+/// Relative error bound of `LogExpMath.pow`, 1e-14.
+const MAX_POW_RELATIVE_ERROR: U256 = uint!(10000_U256);
+
+fn math_error(reason: &str) -> BalancerError {
+    BalancerError::MathError(reason.to_string())
+}
+
+pub fn mul_down(a: U256, b: U256) -> Result<U256> {
+    Ok(a.checked_mul(b).ok_or_else(|| math_error("mul overflow"))? / ONE)
+}
+
+pub fn mul_up(a: U256, b: U256) -> Result<U256> {
+    let product = a.checked_mul(b).ok_or_else(|| math_error("mul overflow"))?;
+    match product.is_zero() {
+        true => Ok(U256::ZERO),
+        false => Ok((product - U256::from(1)) / ONE + U256::from(1)),
+    }
+}
+
+pub fn div_down(a: U256, b: U256) -> Result<U256> {
+    if b.is_zero() {
+        return Err(math_error("division by zero"));
+    }
+    Ok(a.checked_mul(ONE).ok_or_else(|| math_error("div overflow"))? / b)
+}
+
+pub fn div_up(a: U256, b: U256) -> Result<U256> {
+    if b.is_zero() {
+        return Err(math_error("division by zero"));
+    }
+    match a.is_zero() {
+        true => Ok(U256::ZERO),
+        false => {
+            Ok((a.checked_mul(ONE).ok_or_else(|| math_error("div overflow"))? - U256::from(1)) / b + U256::from(1))
+        }
+    }
+}
+
+/// `1 - x`, saturating at zero.
+pub fn complement(x: U256) -> U256 {
+    ONE.saturating_sub(x)
+}
+
+/// `x^y` rounded down, within the relative error of `LogExpMath.pow`.
+pub fn pow_down(x: U256, y: U256) -> Result<U256> {
+    match y {
+        y if y == ONE => Ok(x),
+        y if y == ONE * U256::from(2) => mul_down(x, x),
+        y if y == ONE * U256::from(4) => {
+            let square = mul_down(x, x)?;
+            mul_down(square, square)
+        }
+        _ => {
+            let raw = pow(x, y)?;
+            let max_error = mul_up(raw, MAX_POW_RELATIVE_ERROR)? + U256::from(1);
+            Ok(raw.saturating_sub(max_error))
+        }
+    }
+}
+
+/// `x^y` rounded up, within the relative error of `LogExpMath.pow`.
+pub fn pow_up(x: U256, y: U256) -> Result<U256> {
+    match y {
+        y if y == ONE => Ok(x),
+        y if y == ONE * U256::from(2) => mul_up(x, x),
+        y if y == ONE * U256::from(4) => {
+            let square = mul_up(x, x)?;
+            mul_up(square, square)
+        }
+        _ => {
+            let raw = pow(x, y)?;
+            Ok(raw + mul_up(raw, MAX_POW_RELATIVE_ERROR)? + U256::from(1))
+        }
+    }
+}
+
+const ONE_18: I256 = I256::from_raw(uint!(1000000000000000000_U256));
+const ONE_20: I256 = I256::from_raw(uint!(100000000000000000000_U256));
+const ONE_36: I256 = I256::from_raw(uint!(1000000000000000000000000000000000000_U256));
+
+const MAX_NATURAL_EXPONENT: I256 = I256::from_raw(uint!(130000000000000000000_U256));
+/// `-41e18`
+const MIN_NATURAL_EXPONENT: I256 = I256::from_raw(uint!(
+    0xfffffffffffffffffffffffffffffffffffffffffffffffdc702bd3a30fc0000_U256
+));
+
+/// `ln(x)` is computed with 36 decimals within `[0.9, 1.1]`.
+const LN_36_LOWER_BOUND: I256 = I256::from_raw(uint!(900000000000000000_U256));
+const LN_36_UPPER_BOUND: I256 = I256::from_raw(uint!(1100000000000000000_U256));
+
+/// `2^254 / 1e20`
+const MILD_EXPONENT_BOUND: U256 = uint!(289480223093290488558927462521719769633174961664101410098_U256);
+
+// 18 decimal exponents and their powers of e without decimals
+const X0: I256 = I256::from_raw(uint!(128000000000000000000_U256));
+const A0: I256 = I256::from_raw(uint!(38877084059945950922200000000000000000000000000000000000_U256));
+const X1: I256 = I256::from_raw(uint!(64000000000000000000_U256));
+const A1: I256 = I256::from_raw(uint!(6235149080811616882910000000_U256));
+
+// 20 decimal exponents `2^5..2^-4` and their powers of e
+const X: [I256; 10] = [
+    I256::from_raw(uint!(3200000000000000000000_U256)),
+    I256::from_raw(uint!(1600000000000000000000_U256)),
+    I256::from_raw(uint!(800000000000000000000_U256)),
+    I256::from_raw(uint!(400000000000000000000_U256)),
+    I256::from_raw(uint!(200000000000000000000_U256)),
+    I256::from_raw(uint!(100000000000000000000_U256)),
+    I256::from_raw(uint!(50000000000000000000_U256)),
+    I256::from_raw(uint!(25000000000000000000_U256)),
+    I256::from_raw(uint!(12500000000000000000_U256)),
+    I256::from_raw(uint!(6250000000000000000_U256)),
+];
+const A: [I256; 10] = [
+    I256::from_raw(uint!(7896296018268069516100000000000000_U256)),
+    I256::from_raw(uint!(888611052050787263676000000_U256)),
+    I256::from_raw(uint!(298095798704172827474000_U256)),
+    I256::from_raw(uint!(5459815003314423907810_U256)),
+    I256::from_raw(uint!(738905609893065022723_U256)),
+    I256::from_raw(uint!(271828182845904523536_U256)),
+    I256::from_raw(uint!(164872127070012814685_U256)),
+    I256::from_raw(uint!(128402541668774148407_U256)),
+    I256::from_raw(uint!(113314845306682631683_U256)),
+    I256::from_raw(uint!(106449445891785942956_U256)),
+];
+
+/// `x^y` of 18 decimal fixed point numbers, `LogExpMath.pow`.
+pub fn pow(x: U256, y: U256) -> Result<U256> {
+    if y.is_zero() {
+        return Ok(ONE);
+    }
+    if x.is_zero() {
+        return Ok(U256::ZERO);
+    }
+    if x.bit(255) {
+        return Err(math_error("pow base out of bounds"));
+    }
+    if y >= MILD_EXPONENT_BOUND {
+        return Err(math_error("pow exponent out of bounds"));
+    }
+    let (x, y) = (I256::from_raw(x), I256::from_raw(y));
+    let logx_times_y = match LN_36_LOWER_BOUND < x && x < LN_36_UPPER_BOUND {
+        true => {
+            let ln_36_x = ln_36(x);
+            // split to keep the 36 decimal precision without overflowing
+            (ln_36_x / ONE_18) * y + ((ln_36_x % ONE_18) * y) / ONE_18
+        }
+        false => ln(x)?
+            .checked_mul(y)
+            .ok_or_else(|| math_error("pow product out of bounds"))?,
+    } / ONE_18;
+    if logx_times_y < MIN_NATURAL_EXPONENT || logx_times_y > MAX_NATURAL_EXPONENT {
+        return Err(math_error("pow product out of bounds"));
+    }
+    Ok(exp(logx_times_y)?.into_raw())
+}
+
+/// `e^x` of an 18 decimal fixed point number, `LogExpMath.exp`.
+pub fn exp(x: I256) -> Result<I256> {
+    if x < MIN_NATURAL_EXPONENT || x > MAX_NATURAL_EXPONENT {
+        return Err(math_error("invalid exponent"));
+    }
+    if x.is_negative() {
+        return Ok((ONE_18 * ONE_18) / exp(-x)?);
+    }
+    let mut x = x;
+    let first_an = match x {
+        x_ if x_ >= X0 => {
+            x -= X0;
+            A0
+        }
+        x_ if x_ >= X1 => {
+            x -= X1;
+            A1
+        }
+        _ => I256::ONE,
+    };
+
+    // 20 decimals from here, the smaller exponents are left to the Taylor series
+    x *= I256::try_from(100).unwrap();
+    let mut product = ONE_20;
+    for (x_n, a_n) in X.iter().zip(A.iter()).take(8) {
+        if x >= *x_n {
+            x -= *x_n;
+            product = (product * *a_n) / ONE_20;
+        }
+    }
+    let mut series_sum = ONE_20;
+    let mut term = x;
+    series_sum += term;
+    for n in 2..=12 {
+        term = ((term * x) / ONE_20) / I256::try_from(n).unwrap();
+        series_sum += term;
+    }
+    Ok((((product * series_sum) / ONE_20) * first_an) / I256::try_from(100).unwrap())
+}
+
+/// `ln(a)` of an 18 decimal fixed point number, `LogExpMath._ln`.
+fn ln(a: I256) -> Result<I256> {
+    if !a.is_positive() {
+        return Err(math_error("ln of a non positive number"));
+    }
+    if a < ONE_18 {
+        return Ok(-ln((ONE_18 * ONE_18) / a)?);
+    }
+    let mut a = a;
+    let mut sum = I256::ZERO;
+    if a >= A0 * ONE_18 {
+        a /= A0;
+        sum += X0;
+    }
+    if a >= A1 * ONE_18 {
+        a /= A1;
+        sum += X1;
+    }
+
+    // 20 decimals from here
+    let hundred = I256::try_from(100).unwrap();
+    sum *= hundred;
+    a *= hundred;
+    for (x_n, a_n) in X.iter().zip(A.iter()) {
+        if a >= *a_n {
+            a = (a * ONE_20) / *a_n;
+            sum += *x_n;
+        }
+    }
+
+    // ln(a) = 2 * atanh(z), z = (a - 1) / (a + 1)
+    let z = ((a - ONE_20) * ONE_20) / (a + ONE_20);
+    let z_squared = (z * z) / ONE_20;
+    let mut num = z;
+    let mut series_sum = num;
+    for n in [3, 5, 7, 9, 11] {
+        num = (num * z_squared) / ONE_20;
+        series_sum += num / I256::try_from(n).unwrap();
+    }
+    series_sum *= I256::try_from(2).unwrap();
+    Ok((sum + series_sum) / hundred)
+}
+
+/// `ln(x)` with 36 decimals for `x` close to one, `LogExpMath._ln_36`.
+fn ln_36(x: I256) -> I256 {
+    let x = x * ONE_18;
+    let z = ((x - ONE_36) * ONE_36) / (x + ONE_36);
+    let z_squared = (z * z) / ONE_36;
+    let mut num = z;
+    let mut series_sum = num;
+    for n in [3, 5, 7, 9, 11, 13, 15] {
+        num = (num * z_squared) / ONE_36;
+        series_sum += num / I256::try_from(n).unwrap();
+    }
+    series_sum * I256::try_from(2).unwrap()
+}
+
+/// `amount` in 18 decimals and at the token rate, `_upscale`.
+pub fn upscale(amount: U256, scaling_factor: U256) -> Result<U256> {
+    mul_down(amount, scaling_factor)
+}
+
+/// `_downscaleDown`
+pub fn downscale_down(amount: U256, scaling_factor: U256) -> Result<U256> {
+    div_down(amount, scaling_factor)
+}
+
+/// `_downscaleUp`
+pub fn downscale_up(amount: U256, scaling_factor: U256) -> Result<U256> {
+    div_up(amount, scaling_factor)
+}
+
+/// `amount` less the swap fee taken from it, `_subtractSwapFeeAmount`.
+pub fn subtract_swap_fee(amount: U256, swap_fee: U256) -> Result<U256> {
+    Ok(amount - mul_up(amount, swap_fee)?)
+}
+
+/// The amount that is `amount` once the swap fee is taken from it, `_addSwapFeeAmount`.
+pub fn add_swap_fee(amount: U256, swap_fee: U256) -> Result<U256> {
+    div_up(amount, complement(swap_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fp(value: u128) -> U256 {
+        U256::from(value)
+    }
+
+    #[test]
+    fn test_log_exp() {
+        assert_eq!(
+            exp(ONE_18).unwrap(),
+            I256::try_from(2_718_281_828_459_045_235u64).unwrap()
+        );
+        assert_eq!(
+            exp(-ONE_18).unwrap(),
+            I256::try_from(367_879_441_171_442_321u64).unwrap()
+        );
+        assert_eq!(
+            ln(ONE_18 * I256::try_from(10).unwrap()).unwrap(),
+            I256::try_from(2_302_585_092_994_045_683u64).unwrap()
+        );
+        assert_eq!(ln(ONE_18).unwrap(), I256::ZERO);
+        // sqrt(2), a near one base using ln_36, and an exact cube
+        assert_eq!(
+            pow(fp(2_000_000_000_000_000_000), fp(500_000_000_000_000_000)).unwrap(),
+            fp(1_414_213_562_373_095_047)
+        );
+        assert_eq!(
+            pow(fp(1_001_000_000_000_000_000), fp(2_500_000_000_000_000_000)).unwrap(),
+            fp(1_002_501_875_312_460_948)
+        );
+        assert_eq!(
+            pow(fp(950_000_000_000_000_000), fp(3_000_000_000_000_000_001)).unwrap(),
+            fp(857_375_000_000_000_000)
+        );
+        assert_eq!(pow(U256::ZERO, ONE).unwrap(), U256::ZERO);
+        assert_eq!(pow(ONE, U256::ZERO).unwrap(), ONE);
+        assert!(pow(ONE * fp(1_000_000), ONE * fp(100)).is_err());
+        assert!(exp(MAX_NATURAL_EXPONENT + ONE_18).is_err());
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let third = div_down(ONE, fp(3) * ONE).unwrap();
+        assert_eq!(third, fp(333_333_333_333_333_333));
+        assert_eq!(div_up(ONE, fp(3) * ONE).unwrap(), third + U256::from(1));
+        assert_eq!(mul_up(third, fp(3)).unwrap(), U256::from(1));
+        assert_eq!(mul_down(third, fp(3)).unwrap(), U256::ZERO);
+        assert_eq!(complement(ONE * fp(2)), U256::ZERO);
+        let x = fp(2_000_000_000_000_000_000);
+        assert_eq!(pow_up(x, ONE * fp(2)).unwrap(), fp(4) * ONE);
+        assert!(pow_down(x, fp(500_000_000_000_000_000)).unwrap() < pow_up(x, fp(500_000_000_000_000_000)).unwrap());
+        // a 1% fee taken and added back
+        let fee = fp(10_000_000_000_000_000);
+        assert_eq!(subtract_swap_fee(fp(1000), fee).unwrap(), fp(990));
+        assert_eq!(add_swap_fee(fp(990), fee).unwrap(), fp(1000));
+        assert!(div_down(ONE, U256::ZERO).is_err());
+    }
+}
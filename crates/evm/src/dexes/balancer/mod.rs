@@ -0,0 +1,610 @@
+pub mod math;
+pub mod stable;
+pub mod weighted;
+
+use alloy_eips::BlockId;
+use alloy_primitives::{address, Address, Bytes, B256, I256, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+
+use crate::abis::balancer::{IBalancerVault, IProtocolFeesCollector};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+use crate::provider::transport::ProviderError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BalancerError {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Math error: {0}")]
+    MathError(String),
+    #[error("Invalid pool id: {0}")]
+    InvalidPoolId(B256),
+    #[error("Invalid token: {0}")]
+    InvalidToken(Address),
+    #[error("Invalid swap: {0}")]
+    InvalidSwap(String),
+    #[error("Insufficient liquidity")]
+    InsufficientLiquidity,
+    #[error("Amount in exceeds the max in ratio")]
+    MaxInRatio,
+    #[error("Amount out exceeds the max out ratio")]
+    MaxOutRatio,
+    #[error("Pool not found: {0}")]
+    PoolNotFound(B256),
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+type Result<T> = std::result::Result<T, BalancerError>;
+
+/// The Vault holding the tokens of every pool, at the same address on all chains.
+pub const BALANCER_VAULT: Address = address!("BA12222222228d8Ba445958a75a0704d566BF2C8");
+
+/// The Vault takes and pays native ETH for the zero address asset.
+pub const ETH: Address = Address::ZERO;
+
+const BPS: u32 = 10_000;
+
+/// How the Vault calls a pool, encoded in the pool id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolSpecialization {
+    General = 0,
+    MinimalSwapInfo = 1,
+    TwoToken = 2,
+}
+
+/// A pool id split into the pool address, its specialization and the registration nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolId {
+    pub address: Address,
+    pub specialization: PoolSpecialization,
+    pub nonce: u128,
+}
+
+impl PoolId {
+    pub fn decode(id: B256) -> Result<Self> {
+        let specialization = match u16::from_be_bytes([id[20], id[21]]) {
+            0 => PoolSpecialization::General,
+            1 => PoolSpecialization::MinimalSwapInfo,
+            2 => PoolSpecialization::TwoToken,
+            _ => return Err(BalancerError::InvalidPoolId(id)),
+        };
+        let mut nonce = [0u8; 16];
+        nonce[6..].copy_from_slice(&id[22..]);
+        Ok(Self {
+            address: Address::from_slice(&id[..20]),
+            specialization,
+            nonce: u128::from_be_bytes(nonce),
+        })
+    }
+
+    pub fn encode(&self) -> B256 {
+        let mut id = [0u8; 32];
+        id[..20].copy_from_slice(self.address.as_slice());
+        id[20..22].copy_from_slice(&(self.specialization as u16).to_be_bytes());
+        id[22..].copy_from_slice(&self.nonce.to_be_bytes()[6..]);
+        B256::from(id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapKind {
+    GivenIn = 0,
+    GivenOut = 1,
+}
+
+/// Tokens pulled from and paid to `account`'s own balances rather than its Vault internal balance.
+pub fn funds(account: Address) -> IBalancerVault::FundManagement {
+    IBalancerVault::FundManagement {
+        sender: account,
+        fromInternalBalance: false,
+        recipient: account,
+        toInternalBalance: false,
+    }
+}
+
+/// A single pool Vault `swap`.
+#[derive(Debug, Clone)]
+pub struct SwapParams {
+    pub kind: SwapKind,
+    pub pool_id: B256,
+    pub asset_in: Address,
+    pub asset_out: Address,
+    /// The exact input of `GivenIn` swaps, the exact output of `GivenOut` ones.
+    pub amount: U256,
+    /// The minimum output of `GivenIn` swaps, the maximum input of `GivenOut` ones.
+    pub limit: U256,
+    pub funds: IBalancerVault::FundManagement,
+    pub deadline: u64,
+    pub user_data: Bytes,
+}
+
+impl SwapParams {
+    pub fn exact_in(
+        pool_id: B256,
+        asset_in: Address,
+        asset_out: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+        account: Address,
+        deadline: u64,
+    ) -> Self {
+        Self::new(
+            SwapKind::GivenIn,
+            pool_id,
+            asset_in,
+            asset_out,
+            amount_in,
+            amount_out_min,
+            account,
+            deadline,
+        )
+    }
+
+    pub fn exact_out(
+        pool_id: B256,
+        asset_in: Address,
+        asset_out: Address,
+        amount_out: U256,
+        amount_in_max: U256,
+        account: Address,
+        deadline: u64,
+    ) -> Self {
+        Self::new(
+            SwapKind::GivenOut,
+            pool_id,
+            asset_in,
+            asset_out,
+            amount_out,
+            amount_in_max,
+            account,
+            deadline,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        kind: SwapKind,
+        pool_id: B256,
+        asset_in: Address,
+        asset_out: Address,
+        amount: U256,
+        limit: U256,
+        account: Address,
+        deadline: u64,
+    ) -> Self {
+        Self {
+            kind,
+            pool_id,
+            asset_in,
+            asset_out,
+            amount,
+            limit,
+            funds: funds(account),
+            deadline,
+            user_data: Bytes::new(),
+        }
+    }
+
+    pub fn with_funds(mut self, funds: IBalancerVault::FundManagement) -> Self {
+        self.funds = funds;
+        self
+    }
+}
+
+/// A Vault `batchSwap` across pools, trading `assets` by index.
+///
+/// A step with a zero amount swaps the whole output of the previous step, which is how
+/// multi-hop swaps are built.
+#[derive(Debug, Clone)]
+pub struct BatchSwap {
+    pub kind: SwapKind,
+    pub steps: Vec<IBalancerVault::BatchSwapStep>,
+    pub assets: Vec<Address>,
+    pub funds: IBalancerVault::FundManagement,
+    /// Per asset, the maximum sent to the Vault when positive and the minimum received when
+    /// negative.
+    pub limits: Vec<I256>,
+    pub deadline: u64,
+}
+
+impl BatchSwap {
+    pub fn new(kind: SwapKind, assets: Vec<Address>, funds: IBalancerVault::FundManagement, deadline: u64) -> Self {
+        Self {
+            kind,
+            steps: Vec::new(),
+            assets,
+            funds,
+            limits: Vec::new(),
+            deadline,
+        }
+    }
+
+    /// Appends a swap of `amount` from `asset_in` to `asset_out` in pool `pool_id`.
+    pub fn step(mut self, pool_id: B256, asset_in: Address, asset_out: Address, amount: U256) -> Result<Self> {
+        let index = |asset: Address| {
+            self.assets
+                .iter()
+                .position(|a| *a == asset)
+                .map(U256::from)
+                .ok_or(BalancerError::InvalidToken(asset))
+        };
+        let step = IBalancerVault::BatchSwapStep {
+            poolId: pool_id,
+            assetInIndex: index(asset_in)?,
+            assetOutIndex: index(asset_out)?,
+            amount,
+            userData: Bytes::new(),
+        };
+        self.steps.push(step);
+        Ok(self)
+    }
+
+    pub fn with_limits(mut self, limits: Vec<I256>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            return Err(BalancerError::InvalidSwap("no swap steps".into()));
+        }
+        let n_assets = U256::from(self.assets.len());
+        for step in &self.steps {
+            if step.assetInIndex >= n_assets
+                || step.assetOutIndex >= n_assets
+                || step.assetInIndex == step.assetOutIndex
+            {
+                return Err(BalancerError::InvalidSwap(format!(
+                    "step from asset {} to {}",
+                    step.assetInIndex, step.assetOutIndex
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Batch swap limits from `queryBatchSwap` deltas, allowing `slippage_bps` more in and less out.
+pub fn limits_from_deltas(deltas: &[I256], slippage_bps: u32) -> Vec<I256> {
+    let bps = I256::try_from(BPS).unwrap();
+    let slippage = I256::try_from(slippage_bps.min(BPS)).unwrap();
+    deltas
+        .iter()
+        .map(|delta| match delta.is_positive() {
+            // round the maximum in up
+            true => (*delta * (bps + slippage) + bps - I256::ONE) / bps,
+            false => *delta * (bps - slippage) / bps,
+        })
+        .collect()
+}
+
+/// The asset deltas returned by `queryBatchSwap`, positive for assets sent to the Vault.
+pub fn decode_asset_deltas(output: &[u8]) -> Result<Vec<I256>> {
+    IBalancerVault::queryBatchSwapCall::abi_decode_returns(output, true)
+        .map(|deltas| deltas.assetDeltas)
+        .map_err(|e| BalancerError::InvalidResponse(e.to_string()))
+}
+
+/// Fees owed on top of flash loans of `amounts` at the protocol `fee_percentage`, 1e18 based.
+pub fn flash_loan_fees(amounts: &[U256], fee_percentage: U256) -> Result<Vec<U256>> {
+    amounts
+        .iter()
+        .map(|amount| math::mul_up(*amount, fee_percentage))
+        .collect()
+}
+
+/// A Balancer V2 Vault deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Balancer {
+    pub vault: Address,
+}
+
+pub const BALANCER_V2: Balancer = Balancer { vault: BALANCER_VAULT };
+
+impl Balancer {
+    pub fn new(vault: Address) -> Self {
+        Self { vault }
+    }
+
+    /// Vault `swap` transaction. ETH in is sent along, the Vault refunds what it doesn't use.
+    pub fn swap(&self, params: &SwapParams) -> Result<TransactionRequest> {
+        if params.asset_in == params.asset_out {
+            return Err(BalancerError::InvalidSwap("same asset in and out".into()));
+        }
+        let input = IBalancerVault::swapCall {
+            singleSwap: IBalancerVault::SingleSwap {
+                poolId: params.pool_id,
+                kind: params.kind as u8,
+                assetIn: params.asset_in,
+                assetOut: params.asset_out,
+                amount: params.amount,
+                userData: params.user_data.clone(),
+            },
+            funds: params.funds.clone(),
+            limit: params.limit,
+            deadline: U256::from(params.deadline),
+        }
+        .abi_encode();
+        let value = match (params.asset_in == ETH, params.kind) {
+            (false, _) => U256::ZERO,
+            (true, SwapKind::GivenIn) => params.amount,
+            (true, SwapKind::GivenOut) => params.limit,
+        };
+        Ok(self.vault_request(input, value))
+    }
+
+    /// Vault `batchSwap` transaction, with one limit per asset.
+    pub fn batch_swap(&self, swap: &BatchSwap) -> Result<TransactionRequest> {
+        swap.validate()?;
+        if swap.limits.len() != swap.assets.len() {
+            return Err(BalancerError::InvalidSwap(format!(
+                "{} limits for {} assets",
+                swap.limits.len(),
+                swap.assets.len()
+            )));
+        }
+        let input = IBalancerVault::batchSwapCall {
+            kind: swap.kind as u8,
+            swaps: swap.steps.clone(),
+            assets: swap.assets.clone(),
+            funds: swap.funds.clone(),
+            limits: swap.limits.clone(),
+            deadline: U256::from(swap.deadline),
+        }
+        .abi_encode();
+        let value = swap
+            .assets
+            .iter()
+            .zip(&swap.limits)
+            .find(|(asset, limit)| **asset == ETH && limit.is_positive())
+            .map_or(U256::ZERO, |(_, limit)| limit.into_raw());
+        Ok(self.vault_request(input, value))
+    }
+
+    /// Simulates `swap` at `block`, returning the asset deltas the Vault would settle.
+    pub async fn query_batch_swap(
+        &self,
+        provider: &EvmProvider,
+        swap: &BatchSwap,
+        block: BlockId,
+    ) -> Result<Vec<I256>> {
+        swap.validate()?;
+        let input = IBalancerVault::queryBatchSwapCall {
+            kind: swap.kind as u8,
+            swaps: swap.steps.clone(),
+            assets: swap.assets.clone(),
+            funds: swap.funds.clone(),
+        }
+        .abi_encode();
+        let output = provider.call(&self.vault_request(input, U256::ZERO), block).await?;
+        decode_asset_deltas(&output)
+    }
+
+    /// Vault `flashLoan` transaction lending `loans` to `recipient`, which is called back with
+    /// `receiveFlashLoan` and must repay them plus fees before it returns.
+    pub fn flash_loan(
+        &self,
+        recipient: Address,
+        loans: &[(Address, U256)],
+        user_data: Bytes,
+    ) -> Result<TransactionRequest> {
+        if loans.is_empty() {
+            return Err(BalancerError::InvalidSwap("no flash loans".into()));
+        }
+        // the Vault wants strictly ascending tokens
+        let mut loans = loans.to_vec();
+        loans.sort_by_key(|(token, _)| *token);
+        for (i, (token, _)) in loans.iter().enumerate() {
+            if token.is_zero() || (i > 0 && loans[i - 1].0 == *token) {
+                return Err(BalancerError::InvalidToken(*token));
+            }
+        }
+        let input = IBalancerVault::flashLoanCall {
+            recipient,
+            tokens: loans.iter().map(|(token, _)| *token).collect(),
+            amounts: loans.iter().map(|(_, amount)| *amount).collect(),
+            userData: user_data,
+        }
+        .abi_encode();
+        Ok(self.vault_request(input, U256::ZERO))
+    }
+
+    /// The protocol flash loan fee percentage, 1e18 based.
+    pub async fn fetch_flash_loan_fee(&self, provider: &EvmProvider, block: BlockId) -> Result<U256> {
+        let mut multicall = Multicall::new();
+        let collector = multicall.add(self.vault, IBalancerVault::getProtocolFeesCollectorCall {}, true);
+        let results = multicall.call(provider, block).await?;
+        let invalid = |_| BalancerError::InvalidResponse(format!("no fees collector for vault {}", self.vault));
+        let collector = results.get(&collector).map_err(invalid)?._0;
+
+        let mut multicall = Multicall::new();
+        let fee = multicall.add(
+            collector,
+            IProtocolFeesCollector::getFlashLoanFeePercentageCall {},
+            true,
+        );
+        let results = multicall.call(provider, block).await?;
+        Ok(results.get(&fee).map_err(invalid)?._0)
+    }
+
+    fn vault_request(&self, input: Vec<u8>, value: U256) -> TransactionRequest {
+        let tx = TransactionRequest::default()
+            .to(self.vault)
+            .input(Bytes::from(input).into());
+        match value.is_zero() {
+            true => tx,
+            false => tx.value(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::{aggregate3, MockNode};
+    use crate::provider::test_utils::ether;
+    use alloy_primitives::{b256, hex};
+    use alloy_sol_types::SolValue;
+    use serde_json::json;
+
+    const BAL: Address = address!("ba100000625a3754423978a60c9317c58a424e3D");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const ACCOUNT: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+    const BAL_WETH: B256 = b256!("5c6ee304399dbdb9c8ef030ab642b10820db8f56000200000000000000000014");
+    const WETH_USDC: B256 = b256!("96646936b91d6b9d7d0c47c496afbf3d6ec7b6f8000200000000000000000019");
+
+    fn int(value: i64) -> I256 {
+        I256::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_pool_id() {
+        let id = PoolId::decode(BAL_WETH).unwrap();
+        assert_eq!(id.address, address!("5c6Ee304399DBdB9C8Ef030aB642B10820DB8F56"));
+        assert_eq!(id.specialization, PoolSpecialization::TwoToken);
+        assert_eq!(id.nonce, 20);
+        assert_eq!(id.encode(), BAL_WETH);
+
+        let composable = b256!("79c58f70905f734641735bc61e45c19dd9ad60bc0000000000000000000004e7");
+        let id = PoolId::decode(composable).unwrap();
+        assert_eq!((id.specialization, id.nonce), (PoolSpecialization::General, 0x4e7));
+        let invalid = b256!("5c6ee304399dbdb9c8ef030ab642b10820db8f56000300000000000000000014");
+        assert!(matches!(PoolId::decode(invalid), Err(BalancerError::InvalidPoolId(id)) if id == invalid));
+    }
+
+    #[test]
+    fn test_swap_calldata() {
+        let params = SwapParams::exact_in(
+            BAL_WETH,
+            BAL,
+            WETH,
+            ether(1000),
+            ether(1) / U256::from(2),
+            ACCOUNT,
+            1_700_000_000,
+        );
+        let tx = BALANCER_V2.swap(&params).unwrap();
+        assert_eq!(tx.to, Some(BALANCER_VAULT.into()));
+        assert_eq!(tx.value, None);
+        let input = tx.input.input().unwrap();
+        assert_eq!(input[..4], hex!("52bbbe29"));
+        let call = IBalancerVault::swapCall::abi_decode(input, true).unwrap();
+        assert_eq!(call.singleSwap.kind, SwapKind::GivenIn as u8);
+        assert_eq!((call.singleSwap.assetIn, call.singleSwap.amount), (BAL, ether(1000)));
+        assert_eq!((call.funds.sender, call.funds.recipient), (ACCOUNT, ACCOUNT));
+        assert_eq!(call.deadline, U256::from(1_700_000_000));
+
+        // exact out ETH swaps send the maximum in
+        let params = SwapParams::exact_out(BAL_WETH, ETH, BAL, ether(1000), ether(2), ACCOUNT, 0);
+        assert_eq!(BALANCER_V2.swap(&params).unwrap().value, Some(ether(2)));
+        let params = SwapParams::exact_in(BAL_WETH, BAL, BAL, ether(1), U256::ZERO, ACCOUNT, 0);
+        assert!(matches!(BALANCER_V2.swap(&params), Err(BalancerError::InvalidSwap(_))));
+    }
+
+    #[test]
+    fn test_batch_swap() {
+        // USDC -> WETH -> BAL, the second step swapping all the WETH of the first
+        let swap = BatchSwap::new(SwapKind::GivenIn, vec![USDC, WETH, BAL], funds(ACCOUNT), 0)
+            .step(WETH_USDC, USDC, WETH, U256::from(1_000_000_000))
+            .unwrap()
+            .step(BAL_WETH, WETH, BAL, U256::ZERO)
+            .unwrap();
+        assert_eq!(swap.steps[1].assetInIndex, U256::from(1));
+        assert!(matches!(
+            BALANCER_V2.batch_swap(&swap),
+            Err(BalancerError::InvalidSwap(_))
+        ));
+        assert!(matches!(
+            swap.clone().step(BAL_WETH, ETH, BAL, U256::ZERO),
+            Err(BalancerError::InvalidToken(ETH))
+        ));
+
+        let deltas = vec![int(1_000_000_000), I256::ZERO, -int(998_000_000_000_000_000)];
+        let limits = limits_from_deltas(&deltas, 50);
+        assert_eq!(
+            limits,
+            vec![int(1_005_000_000), I256::ZERO, -int(993_010_000_000_000_000)]
+        );
+        let tx = BALANCER_V2.batch_swap(&swap.with_limits(limits.clone())).unwrap();
+        assert_eq!(tx.value, None);
+        let call = IBalancerVault::batchSwapCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(call.swaps.len(), 2);
+        assert_eq!((call.assets, call.limits), (vec![USDC, WETH, BAL], limits));
+
+        let eth_in = BatchSwap::new(SwapKind::GivenIn, vec![ETH, BAL], funds(ACCOUNT), 0)
+            .step(BAL_WETH, ETH, BAL, ether(1))
+            .unwrap()
+            .with_limits(vec![ether(1).try_into().unwrap(), I256::ZERO]);
+        assert_eq!(BALANCER_V2.batch_swap(&eth_in).unwrap().value, Some(ether(1)));
+    }
+
+    #[test]
+    fn test_flash_loan() {
+        let loans = [(WETH, ether(100)), (USDC, U256::from(1_000_000_000_000u64))];
+        let tx = BALANCER_V2
+            .flash_loan(ACCOUNT, &loans, Bytes::from_static(b"arb"))
+            .unwrap();
+        let call = IBalancerVault::flashLoanCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(call.tokens, vec![USDC, WETH]);
+        assert_eq!(call.amounts, vec![U256::from(1_000_000_000_000u64), ether(100)]);
+        assert_eq!((call.recipient, call.userData.as_ref()), (ACCOUNT, b"arb".as_ref()));
+
+        assert!(matches!(
+            BALANCER_V2.flash_loan(ACCOUNT, &[(WETH, ether(1)), (WETH, ether(2))], Bytes::new()),
+            Err(BalancerError::InvalidToken(WETH))
+        ));
+        assert!(BALANCER_V2.flash_loan(ACCOUNT, &[], Bytes::new()).is_err());
+        assert_eq!(
+            flash_loan_fees(&[ether(100), U256::from(3)], U256::from(1_000_000_000_000_000u64)).unwrap(),
+            vec![ether(1) / U256::from(10), U256::from(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_batch_swap() {
+        let collector = address!("ce88686553686DA562CE7Cea497CE749Da109f9F");
+        let node = MockNode::start(move |_, params| {
+            let to: Address = params[0]["to"].as_str().unwrap().parse().unwrap();
+            if to != BALANCER_VAULT {
+                return aggregate3(params, |call| {
+                    Some(match call.target == BALANCER_VAULT {
+                        true => collector.abi_encode(),
+                        false => U256::from(0).abi_encode(),
+                    })
+                });
+            }
+            let input = hex::decode(params[0]["input"].as_str().unwrap()).unwrap();
+            let call = IBalancerVault::queryBatchSwapCall::abi_decode(&input, true).unwrap();
+            assert_eq!(call.swaps.len(), 2);
+            let output = IBalancerVault::queryBatchSwapCall::abi_encode_returns(&(vec![
+                int(1_000_000_000),
+                I256::ZERO,
+                -int(998_000_000_000_000_000),
+            ],));
+            Ok(json!(format!("0x{}", hex::encode(output))))
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let swap = BatchSwap::new(SwapKind::GivenIn, vec![USDC, WETH, BAL], funds(ACCOUNT), 0)
+            .step(WETH_USDC, USDC, WETH, U256::from(1_000_000_000))
+            .unwrap()
+            .step(BAL_WETH, WETH, BAL, U256::ZERO)
+            .unwrap();
+        let deltas = BALANCER_V2
+            .query_batch_swap(&provider, &swap, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(deltas[2], -int(998_000_000_000_000_000));
+        assert!(decode_asset_deltas(&[0u8; 3]).is_err());
+
+        let fee = BALANCER_V2
+            .fetch_flash_loan_fee(&provider, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(fee, U256::ZERO);
+    }
+}
@@ -0,0 +1,328 @@
+//! Composable stable pools, a port of `StableMath` and the regular (non BPT) swaps of
+//! `ComposableStablePool`.
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, B256, U256};
+
+use crate::abis::balancer::{IBalancerPool, IBalancerVault};
+use crate::dexes::balancer::math::{add_swap_fee, downscale_down, downscale_up, subtract_swap_fee, upscale};
+use crate::dexes::balancer::{BalancerError, PoolId, Result};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+
+/// Amplification parameters are multiplied by 1e3.
+pub const AMP_PRECISION: u64 = 1000;
+
+const MAX_ITERATIONS: usize = 255;
+
+fn div_up(a: U256, b: U256) -> U256 {
+    match a.is_zero() {
+        true => U256::ZERO,
+        false => U256::from(1) + (a - U256::from(1)) / b,
+    }
+}
+
+/// The invariant of upscaled `balances`, `StableMath._calculateInvariant`.
+pub fn calculate_invariant(amp: U256, balances: &[U256]) -> Result<U256> {
+    let n = U256::from(balances.len());
+    let amp_precision = U256::from(AMP_PRECISION);
+    let sum = balances.iter().fold(U256::ZERO, |sum, balance| sum + balance);
+    if sum.is_zero() {
+        return Ok(U256::ZERO);
+    }
+    if balances.iter().any(|balance| balance.is_zero()) {
+        return Err(BalancerError::InsufficientLiquidity);
+    }
+    let amp_times_total = amp * n;
+    if amp_times_total <= amp_precision {
+        return Err(BalancerError::MathError("amplification too low".into()));
+    }
+    let mut invariant = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = invariant;
+        for balance in balances {
+            d_p = d_p * invariant / (*balance * n);
+        }
+        let prev = invariant;
+        invariant = ((amp_times_total * sum / amp_precision + d_p * n) * invariant)
+            / ((amp_times_total - amp_precision) * invariant / amp_precision + (n + U256::from(1)) * d_p);
+        if invariant.abs_diff(prev) <= U256::from(1) {
+            return Ok(invariant);
+        }
+    }
+    Err(BalancerError::MathError("stable invariant did not converge".into()))
+}
+
+/// The balance of token `index` for which the invariant of `balances` is `invariant`,
+/// `StableMath._getTokenBalanceGivenInvariantAndAllOtherBalances`.
+pub fn token_balance_given_invariant(amp: U256, balances: &[U256], invariant: U256, index: usize) -> Result<U256> {
+    let n = U256::from(balances.len());
+    let amp_times_total = amp * n;
+    let mut sum = balances[0];
+    let mut p_d = balances[0] * n;
+    for balance in &balances[1..] {
+        p_d = p_d * balance * n / invariant;
+        sum += balance;
+    }
+    if p_d.is_zero() {
+        return Err(BalancerError::InsufficientLiquidity);
+    }
+    sum -= balances[index];
+    let invariant_squared = invariant * invariant;
+    let amp_precision = U256::from(AMP_PRECISION);
+    let c = div_up(invariant_squared, amp_times_total * p_d) * amp_precision * balances[index];
+    let b = sum + invariant / amp_times_total * amp_precision;
+
+    let mut balance = div_up(invariant_squared + c, invariant + b);
+    for _ in 0..MAX_ITERATIONS {
+        let prev = balance;
+        let denominator = (balance * U256::from(2) + b)
+            .checked_sub(invariant)
+            .filter(|denominator| !denominator.is_zero())
+            .ok_or_else(|| BalancerError::MathError("stable balance denominator".into()))?;
+        balance = div_up(balance * balance + c, denominator);
+        if balance.abs_diff(prev) <= U256::from(1) {
+            return Ok(balance);
+        }
+    }
+    Err(BalancerError::MathError("stable balance did not converge".into()))
+}
+
+/// Output of swapping `amount_in`, all upscaled and after fees, `StableMath._calcOutGivenIn`.
+pub fn calc_out_given_in(
+    amp: U256,
+    balances: &[U256],
+    index_in: usize,
+    index_out: usize,
+    amount_in: U256,
+    invariant: U256,
+) -> Result<U256> {
+    let mut balances = balances.to_vec();
+    balances[index_in] += amount_in;
+    let final_balance_out = token_balance_given_invariant(amp, &balances, invariant, index_out)?;
+    balances[index_out]
+        .checked_sub(final_balance_out)
+        .and_then(|amount| amount.checked_sub(U256::from(1)))
+        .ok_or(BalancerError::InsufficientLiquidity)
+}
+
+/// Input needed to receive `amount_out`, all upscaled and before fees, `StableMath._calcInGivenOut`.
+pub fn calc_in_given_out(
+    amp: U256,
+    balances: &[U256],
+    index_in: usize,
+    index_out: usize,
+    amount_out: U256,
+    invariant: U256,
+) -> Result<U256> {
+    let mut balances = balances.to_vec();
+    balances[index_out] = balances[index_out]
+        .checked_sub(amount_out)
+        .filter(|balance| !balance.is_zero())
+        .ok_or(BalancerError::InsufficientLiquidity)?;
+    let final_balance_in = token_balance_given_invariant(amp, &balances, invariant, index_in)?;
+    final_balance_in
+        .checked_sub(balances[index_in])
+        .map(|amount| amount + U256::from(1))
+        .ok_or_else(|| BalancerError::MathError("stable input underflow".into()))
+}
+
+/// State of a composable stable pool, enough to quote swaps between its tokens offline.
+///
+/// `tokens`, `balances` and `scaling_factors` include the pool's own BPT at `bpt_index`, as the
+/// Vault and the pool return them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposableStablePool {
+    pub id: B256,
+    pub tokens: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// `10^(18 - decimals)` as 18 decimal fixed point numbers, times the token rate.
+    pub scaling_factors: Vec<U256>,
+    pub bpt_index: usize,
+    /// `A * AMP_PRECISION`, the value of `getAmplificationParameter`.
+    pub amp: U256,
+    /// 1e18 based, 1e14 is 0.01%.
+    pub swap_fee: U256,
+}
+
+impl ComposableStablePool {
+    /// Index of `token` among the pool tokens without the BPT.
+    fn index(&self, token: Address) -> Result<usize> {
+        match self.tokens.iter().position(|t| *t == token) {
+            Some(index) if index == self.bpt_index => {
+                Err(BalancerError::InvalidSwap("BPT swaps are joins and exits".into()))
+            }
+            Some(index) if index > self.bpt_index => Ok(index - 1),
+            Some(index) => Ok(index),
+            None => Err(BalancerError::InvalidToken(token)),
+        }
+    }
+
+    fn without_bpt<T: Copy>(&self, values: &[T]) -> Vec<T> {
+        values
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.bpt_index)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+
+    /// Upscaled balances and scaling factors of the tokens other than the BPT.
+    fn scaled(&self) -> Result<(Vec<U256>, Vec<U256>)> {
+        let scaling_factors = self.without_bpt(&self.scaling_factors);
+        let balances = self
+            .without_bpt(&self.balances)
+            .into_iter()
+            .zip(&scaling_factors)
+            .map(|(balance, factor)| upscale(balance, *factor))
+            .collect::<Result<_>>()?;
+        Ok((balances, scaling_factors))
+    }
+
+    fn indices(&self, token_in: Address, token_out: Address) -> Result<(usize, usize)> {
+        let (i, o) = (self.index(token_in)?, self.index(token_out)?);
+        match i == o {
+            true => Err(BalancerError::InvalidSwap("same token in and out".into())),
+            false => Ok((i, o)),
+        }
+    }
+
+    /// The invariant of the current balances.
+    pub fn invariant(&self) -> Result<U256> {
+        calculate_invariant(self.amp, &self.scaled()?.0)
+    }
+
+    /// Output of swapping `amount_in` of `token_in`, the `GIVEN_IN` result of the Vault.
+    pub fn quote_exact_in(&self, token_in: Address, token_out: Address, amount_in: U256) -> Result<U256> {
+        let (i, o) = self.indices(token_in, token_out)?;
+        let (balances, scaling_factors) = self.scaled()?;
+        let amount_in = upscale(subtract_swap_fee(amount_in, self.swap_fee)?, scaling_factors[i])?;
+        let invariant = calculate_invariant(self.amp, &balances)?;
+        let amount_out = calc_out_given_in(self.amp, &balances, i, o, amount_in, invariant)?;
+        downscale_down(amount_out, scaling_factors[o])
+    }
+
+    /// Input needed to receive `amount_out` of `token_out`, the `GIVEN_OUT` result of the Vault.
+    pub fn quote_exact_out(&self, token_in: Address, token_out: Address, amount_out: U256) -> Result<U256> {
+        let (i, o) = self.indices(token_in, token_out)?;
+        let (balances, scaling_factors) = self.scaled()?;
+        let amount_out = upscale(amount_out, scaling_factors[o])?;
+        let invariant = calculate_invariant(self.amp, &balances)?;
+        let amount_in = calc_in_given_out(self.amp, &balances, i, o, amount_out, invariant)?;
+        add_swap_fee(downscale_up(amount_in, scaling_factors[i])?, self.swap_fee)
+    }
+
+    /// Reads the tokens, balances, rates, amplification and fee of pool `id` at `block`.
+    pub async fn fetch(provider: &EvmProvider, vault: Address, id: B256, block: BlockId) -> Result<Self> {
+        let address = PoolId::decode(id)?.address;
+        let mut multicall = Multicall::new();
+        let tokens = multicall.add(vault, IBalancerVault::getPoolTokensCall { poolId: id }, true);
+        let scaling_factors = multicall.add(address, IBalancerPool::getScalingFactorsCall {}, true);
+        let bpt_index = multicall.add(address, IBalancerPool::getBptIndexCall {}, true);
+        let amp = multicall.add(address, IBalancerPool::getAmplificationParameterCall {}, true);
+        let swap_fee = multicall.add(address, IBalancerPool::getSwapFeePercentageCall {}, true);
+        let results = multicall.call(provider, block).await?;
+
+        let not_found = |_| BalancerError::PoolNotFound(id);
+        let tokens = results.get(&tokens).map_err(not_found)?;
+        let pool = Self {
+            id,
+            tokens: tokens.tokens,
+            balances: tokens.balances,
+            scaling_factors: results.get(&scaling_factors).map_err(not_found)?._0,
+            bpt_index: usize::try_from(results.get(&bpt_index).map_err(not_found)?._0)
+                .map_err(|_| BalancerError::PoolNotFound(id))?,
+            amp: results.get(&amp).map_err(not_found)?.value,
+            swap_fee: results.get(&swap_fee).map_err(not_found)?._0,
+        };
+        let n_tokens = pool.tokens.len();
+        if pool.balances.len() != n_tokens || pool.scaling_factors.len() != n_tokens || pool.bpt_index >= n_tokens {
+            return Err(BalancerError::PoolNotFound(id));
+        }
+        Ok(pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::balancer::math::ONE;
+    use alloy_primitives::{address, uint};
+
+    const BPT: Address = address!("79c58f70905F734641735BC61e45c19dD9Ad60bC");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+
+    fn units(amount: u64, decimals: u8) -> U256 {
+        U256::from(amount) * U256::from(10).pow(U256::from(decimals))
+    }
+
+    // DAI/USDC/USDT with A = 2000 and a 0.01% fee, the BPT first like the pool registers it
+    fn usd_pool() -> ComposableStablePool {
+        let usd_factor = ONE * units(1, 12);
+        ComposableStablePool {
+            id: B256::ZERO,
+            tokens: vec![BPT, DAI, USDC, USDT],
+            balances: vec![
+                units(1, 30),
+                units(10_000_000, 18),
+                units(12_000_000, 6),
+                units(9_000_000, 6),
+            ],
+            scaling_factors: vec![ONE, ONE, usd_factor, usd_factor],
+            bpt_index: 0,
+            amp: U256::from(2000 * AMP_PRECISION),
+            swap_fee: U256::from(100_000_000_000_000u64),
+        }
+    }
+
+    #[test]
+    fn test_stable_math() {
+        let pool = usd_pool();
+        assert_eq!(pool.invariant().unwrap(), uint!(30999888254786489466020642_U256));
+        assert_eq!(
+            pool.quote_exact_in(USDC, DAI, units(1000, 6)).unwrap(),
+            U256::from(999_812_036_567_133_830_800u128)
+        );
+        assert_eq!(
+            pool.quote_exact_in(DAI, USDT, units(1000, 18)).unwrap(),
+            U256::from(999_841_332)
+        );
+        assert_eq!(
+            pool.quote_exact_out(USDC, DAI, units(1000, 18)).unwrap(),
+            U256::from(1_000_187_999)
+        );
+        assert!(matches!(
+            pool.quote_exact_in(BPT, DAI, ONE),
+            Err(BalancerError::InvalidSwap(_))
+        ));
+        assert!(matches!(
+            pool.quote_exact_out(USDC, DAI, units(10_000_000, 18)),
+            Err(BalancerError::InsufficientLiquidity)
+        ));
+    }
+
+    #[test]
+    fn test_token_rates() {
+        // wstETH at a 1.15 rate against WETH, A = 50 and a 0.04% fee, the BPT in the middle
+        let pool = ComposableStablePool {
+            id: B256::ZERO,
+            tokens: vec![
+                address!("7f39C581F595B53c5cb19bD0b3f8dA6c935E2Ca0"),
+                BPT,
+                address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            ],
+            balances: vec![units(10_000, 18), units(1, 30), units(12_000, 18)],
+            scaling_factors: vec![U256::from(1_150_000_000_000_000_000u64), ONE, ONE],
+            bpt_index: 1,
+            amp: U256::from(50 * AMP_PRECISION),
+            swap_fee: U256::from(400_000_000_000_000u64),
+        };
+        assert_eq!(pool.invariant().unwrap(), uint!(23499895657102330382344_U256));
+        assert_eq!(
+            pool.quote_exact_in(pool.tokens[0], pool.tokens[2], ONE).unwrap(),
+            U256::from(1_150_498_175_667_739_347u64)
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Weighted pools, a port of `WeightedMath` and the swap hooks of `BaseMinimalSwapInfoPool`.
+
+use alloy_eips::BlockId;
+use alloy_primitives::{uint, Address, B256, U256};
+
+use crate::abis::balancer::{IBalancerPool, IBalancerVault};
+use crate::abis::erc::IERC20;
+use crate::dexes::balancer::math::{
+    add_swap_fee, complement, div_down, div_up, downscale_down, downscale_up, mul_down, mul_up, pow_up,
+    subtract_swap_fee, upscale, ONE,
+};
+use crate::dexes::balancer::{BalancerError, PoolId, Result};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+
+/// Swaps can't take in or out more than 30% of a balance.
+const MAX_IN_RATIO: U256 = uint!(300000000000000000_U256);
+const MAX_OUT_RATIO: U256 = uint!(300000000000000000_U256);
+
+/// Output of swapping `amount_in`, all upscaled and after fees, `WeightedMath._calcOutGivenIn`.
+pub fn calc_out_given_in(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_in: U256,
+) -> Result<U256> {
+    if amount_in > mul_down(balance_in, MAX_IN_RATIO)? {
+        return Err(BalancerError::MaxInRatio);
+    }
+    let base = div_up(balance_in, balance_in + amount_in)?;
+    let exponent = div_down(weight_in, weight_out)?;
+    let power = pow_up(base, exponent)?;
+    mul_down(balance_out, complement(power))
+}
+
+/// Input needed to receive `amount_out`, all upscaled and before fees, `WeightedMath._calcInGivenOut`.
+pub fn calc_in_given_out(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_out: U256,
+) -> Result<U256> {
+    if amount_out > mul_down(balance_out, MAX_OUT_RATIO)? {
+        return Err(BalancerError::MaxOutRatio);
+    }
+    let base = div_up(balance_out, balance_out - amount_out)?;
+    let exponent = div_up(weight_out, weight_in)?;
+    let power = pow_up(base, exponent)?;
+    mul_up(balance_in, power.saturating_sub(ONE))
+}
+
+/// State of a weighted pool, enough to quote it offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightedPool {
+    pub id: B256,
+    pub tokens: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// Normalized weights, summing to 1e18.
+    pub weights: Vec<U256>,
+    /// `10^(18 - decimals)` as 18 decimal fixed point numbers, times the token rate if any.
+    pub scaling_factors: Vec<U256>,
+    /// 1e18 based, 1e16 is 1%.
+    pub swap_fee: U256,
+}
+
+impl WeightedPool {
+    fn index(&self, token: Address) -> Result<usize> {
+        self.tokens
+            .iter()
+            .position(|t| *t == token)
+            .ok_or(BalancerError::InvalidToken(token))
+    }
+
+    /// Output of swapping `amount_in` of `token_in`, the `GIVEN_IN` result of the Vault.
+    pub fn quote_exact_in(&self, token_in: Address, token_out: Address, amount_in: U256) -> Result<U256> {
+        let (i, o) = (self.index(token_in)?, self.index(token_out)?);
+        if i == o {
+            return Err(BalancerError::InvalidSwap("same token in and out".into()));
+        }
+        let amount_in = upscale(subtract_swap_fee(amount_in, self.swap_fee)?, self.scaling_factors[i])?;
+        let amount_out = calc_out_given_in(
+            upscale(self.balances[i], self.scaling_factors[i])?,
+            self.weights[i],
+            upscale(self.balances[o], self.scaling_factors[o])?,
+            self.weights[o],
+            amount_in,
+        )?;
+        downscale_down(amount_out, self.scaling_factors[o])
+    }
+
+    /// Input needed to receive `amount_out` of `token_out`, the `GIVEN_OUT` result of the Vault.
+    pub fn quote_exact_out(&self, token_in: Address, token_out: Address, amount_out: U256) -> Result<U256> {
+        let (i, o) = (self.index(token_in)?, self.index(token_out)?);
+        if i == o {
+            return Err(BalancerError::InvalidSwap("same token in and out".into()));
+        }
+        let amount_in = calc_in_given_out(
+            upscale(self.balances[i], self.scaling_factors[i])?,
+            self.weights[i],
+            upscale(self.balances[o], self.scaling_factors[o])?,
+            self.weights[o],
+            upscale(amount_out, self.scaling_factors[o])?,
+        )?;
+        add_swap_fee(downscale_up(amount_in, self.scaling_factors[i])?, self.swap_fee)
+    }
+
+    /// Reads the tokens, balances, weights and fee of pool `id` at `block`.
+    ///
+    /// Legacy `WeightedPool` and `WeightedPool2Tokens` pools have no `getScalingFactors()`,
+    /// their factors are derived from the token decimals.
+    pub async fn fetch(provider: &EvmProvider, vault: Address, id: B256, block: BlockId) -> Result<Self> {
+        let address = PoolId::decode(id)?.address;
+        let mut multicall = Multicall::new();
+        let tokens = multicall.add(vault, IBalancerVault::getPoolTokensCall { poolId: id }, true);
+        let weights = multicall.add(address, IBalancerPool::getNormalizedWeightsCall {}, true);
+        let scaling_factors = multicall.add(address, IBalancerPool::getScalingFactorsCall {}, true);
+        let swap_fee = multicall.add(address, IBalancerPool::getSwapFeePercentageCall {}, true);
+        let results = multicall.call(provider, block).await?;
+
+        let not_found = |_| BalancerError::PoolNotFound(id);
+        let tokens = results.get(&tokens).map_err(not_found)?;
+        let scaling_factors = match results.get(&scaling_factors) {
+            Ok(scaling_factors) => scaling_factors._0,
+            Err(_) => decimal_scaling_factors(provider, id, &tokens.tokens, block).await?,
+        };
+        let pool = Self {
+            id,
+            tokens: tokens.tokens,
+            balances: tokens.balances,
+            weights: results.get(&weights).map_err(not_found)?._0,
+            scaling_factors,
+            swap_fee: results.get(&swap_fee).map_err(not_found)?._0,
+        };
+        let n_tokens = pool.tokens.len();
+        if pool.balances.len() != n_tokens || pool.weights.len() != n_tokens || pool.scaling_factors.len() != n_tokens {
+            return Err(BalancerError::PoolNotFound(id));
+        }
+        Ok(pool)
+    }
+}
+
+/// `10^(18 - decimals)` of each token, as 18 decimal fixed point numbers.
+async fn decimal_scaling_factors(
+    provider: &EvmProvider,
+    id: B256,
+    tokens: &[Address],
+    block: BlockId,
+) -> Result<Vec<U256>> {
+    let mut multicall = Multicall::new();
+    let handles: Vec<_> = tokens
+        .iter()
+        .map(|token| multicall.add(*token, IERC20::decimalsCall {}, true))
+        .collect();
+    let results = multicall.call(provider, block).await?;
+    handles
+        .iter()
+        .map(|handle| {
+            let decimals = results.get(handle).map_err(|_| BalancerError::PoolNotFound(id))?._0;
+            let exponent = 18u8.checked_sub(decimals).ok_or(BalancerError::PoolNotFound(id))?;
+            Ok(ONE * U256::from(10).pow(U256::from(exponent)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::balancer::BALANCER_VAULT;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::ether;
+    use alloy_primitives::{address, b256};
+    use alloy_sol_types::{SolCall, SolValue};
+
+    const BAL: Address = address!("ba100000625a3754423978a60c9317c58a424e3D");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+
+    // 80/20 BAL/WETH with a 1% fee
+    fn bal_weth() -> WeightedPool {
+        WeightedPool {
+            id: b256!("5c6ee304399dbdb9c8ef030ab642b10820db8f56000200000000000000000014"),
+            tokens: vec![BAL, WETH],
+            balances: vec![ether(20_000_000), ether(5000)],
+            weights: vec![
+                U256::from(800_000_000_000_000_000u64),
+                U256::from(200_000_000_000_000_000u64),
+            ],
+            scaling_factors: vec![ONE, ONE],
+            swap_fee: U256::from(10_000_000_000_000_000u64),
+        }
+    }
+
+    #[test]
+    fn test_weighted_math() {
+        let pool = bal_weth();
+        assert_eq!(
+            pool.quote_exact_in(BAL, WETH, ether(1000)).unwrap(),
+            U256::from(989_877_499_627_670_000u64)
+        );
+        assert_eq!(
+            pool.quote_exact_out(WETH, BAL, ether(1000)).unwrap(),
+            U256::from(1_010_227_285_354_661_617u64)
+        );
+        assert!(matches!(
+            pool.quote_exact_in(WETH, BAL, ether(2000)),
+            Err(BalancerError::MaxInRatio)
+        ));
+        assert!(matches!(
+            pool.quote_exact_out(WETH, BAL, ether(7_000_000)),
+            Err(BalancerError::MaxOutRatio)
+        ));
+        assert!(matches!(
+            pool.quote_exact_in(USDC, BAL, ether(1)),
+            Err(BalancerError::InvalidToken(USDC))
+        ));
+    }
+
+    #[test]
+    fn test_scaling_factors() {
+        // 50/50 USDC/WETH with a 0.3% fee
+        let pool = WeightedPool {
+            id: B256::ZERO,
+            tokens: vec![USDC, WETH],
+            balances: vec![U256::from(20_000_000_000_000u64), ether(10_000)],
+            weights: vec![U256::from(500_000_000_000_000_000u64); 2],
+            scaling_factors: vec![ONE * U256::from(1_000_000_000_000u64), ONE],
+            swap_fee: U256::from(3_000_000_000_000_000u64),
+        };
+        assert_eq!(
+            pool.quote_exact_in(USDC, WETH, U256::from(1_000_000_000)).unwrap(),
+            U256::from(498_475_151_013_720_000u64)
+        );
+        assert_eq!(pool.quote_exact_in(WETH, USDC, ONE).unwrap(), U256::from(1_993_801_218));
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        // a WeightedPool2Tokens pool, which predates getScalingFactors()
+        let legacy = b256!("96646936b91d6b9d7d0c47c496afbf3d6ec7b6f8000200000000000000000019");
+        let legacy_address = PoolId::decode(legacy).unwrap().address;
+        let node = MockNode::multicall(move |call| {
+            let data = &call.callData;
+            let output = match data[..4].try_into().unwrap() {
+                IBalancerVault::getPoolTokensCall::SELECTOR => {
+                    let id = IBalancerVault::getPoolTokensCall::abi_decode(data, true).unwrap().poolId;
+                    let tokens = match id == legacy {
+                        true => vec![USDC, WETH],
+                        false => vec![BAL, WETH],
+                    };
+                    let balances = vec![U256::from(20_000_000_000_000u64), ether(10_000)];
+                    (tokens, balances, U256::from(1)).abi_encode_params()
+                }
+                IBalancerPool::getNormalizedWeightsCall::SELECTOR => {
+                    vec![U256::from(500_000_000_000_000_000u64); 2].abi_encode()
+                }
+                IBalancerPool::getScalingFactorsCall::SELECTOR if call.target != legacy_address => {
+                    vec![ONE; 2].abi_encode()
+                }
+                IBalancerPool::getSwapFeePercentageCall::SELECTOR => {
+                    U256::from(3_000_000_000_000_000u64).abi_encode()
+                }
+                IERC20::decimalsCall::SELECTOR => match call.target == USDC {
+                    true => U256::from(6).abi_encode(),
+                    false => U256::from(18).abi_encode(),
+                },
+                _ => vec![],
+            };
+            (!output.is_empty()).then_some(output)
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let pool = WeightedPool::fetch(&provider, BALANCER_VAULT, legacy, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(pool.tokens, vec![USDC, WETH]);
+        assert_eq!(pool.scaling_factors, vec![ONE * U256::from(1_000_000_000_000u64), ONE]);
+        assert_eq!(pool.quote_exact_in(WETH, USDC, ONE).unwrap(), U256::from(1_993_801_218));
+
+        let id = bal_weth().id;
+        let pool = WeightedPool::fetch(&provider, BALANCER_VAULT, id, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(pool.scaling_factors, vec![ONE; 2]);
+        // one decimals lookup, for the legacy pool
+        assert_eq!(node.methods().len(), 3);
+    }
+}
@@ -0,0 +1,57 @@
+//! CoW Protocol: GPv2 orders, their signing schemes and the orderbook API.
+
+pub mod order;
+pub mod orderbook;
+
+use alloy_primitives::{address, Address};
+
+use crate::provider::transport::ProviderError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CowError {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("HTTP request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid status code {status}: {message}")]
+    InvalidStatus {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Orderbook error {error_type}: {description}")]
+    ApiError { error_type: String, description: String },
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+    #[error("Invalid order uid: {0}")]
+    InvalidOrderUid(String),
+    #[error("Invalid order: {0}")]
+    InvalidOrder(String),
+    #[error("Unsupported chain: {0}")]
+    UnsupportedChain(u64),
+}
+
+type Result<T> = std::result::Result<T, CowError>;
+
+/// `GPv2Settlement`, at the same address on every chain.
+pub const SETTLEMENT: Address = address!("9008D19f58AAbD9eD0D60971565AA8510560ab41");
+/// `GPv2VaultRelayer`, the spender to approve for sell tokens.
+pub const VAULT_RELAYER: Address = address!("C92E8bdf79f0507f65a392b0ab4667716BFE0110");
+/// Buy token placeholder for receiving the native token.
+pub const BUY_ETH: Address = address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+/// Amounts travel as decimal strings in the orderbook API.
+pub(crate) mod decimal {
+    use alloy_primitives::U256;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,581 @@
+//! GPv2 orders, their uids and the four signing schemes of the settlement contract.
+
+use std::fmt;
+use std::str::FromStr;
+
+use alloy_dyn_abi::Eip712Domain;
+use alloy_eips::BlockId;
+use alloy_primitives::{b256, hex, keccak256, Address, Bytes, FixedBytes, PrimitiveSignature, B256, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{SolCall, SolStruct};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::abis::cow::{self as abi, IGPv2Settlement, IERC1271};
+use crate::dexes::cow::{decimal, CowError, Result, SETTLEMENT};
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::EvmSigner;
+
+/// `keccak256("{}")`, the app data of an order without metadata.
+pub const EMPTY_APP_DATA: B256 = b256!("b48d38f93eaa084033fc5970bf96e559c33c4cdc07d889ab00b4d63f9590739d");
+
+/// `isValidSignature` return value of a valid ERC-1271 signature.
+const ERC1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// App data of an order, the hash of its JSON metadata document.
+pub fn app_data_hash(document: &str) -> B256 {
+    keccak256(document)
+}
+
+/// The EIP-712 domain orders and cancellations are signed in.
+pub fn domain(chain_id: u64) -> Eip712Domain {
+    Eip712Domain::new(
+        Some("Gnosis Protocol".into()),
+        Some("v2".into()),
+        Some(U256::from(chain_id)),
+        Some(SETTLEMENT),
+        None,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderKind {
+    Sell,
+    Buy,
+}
+
+impl OrderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderKind::Sell => "sell",
+            OrderKind::Buy => "buy",
+        }
+    }
+}
+
+/// Where sell tokens are taken from and buy tokens sent to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenBalance {
+    /// Plain ERC20 allowance to the vault relayer.
+    #[default]
+    Erc20,
+    /// ERC20 allowance to the Balancer Vault, used through the relayer.
+    External,
+    /// Balancer Vault internal balance.
+    Internal,
+}
+
+impl TokenBalance {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenBalance::Erc20 => "erc20",
+            TokenBalance::External => "external",
+            TokenBalance::Internal => "internal",
+        }
+    }
+}
+
+/// Accepts either an app data hash or the JSON document it hashes, as the orderbook returns either.
+fn deserialize_app_data<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<B256, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Ok(value.parse().unwrap_or_else(|_| app_data_hash(&value)))
+}
+
+/// A GPv2 order, as signed and as the orderbook API encodes it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order {
+    #[serde(rename = "sellToken")]
+    pub sell_token: Address,
+    #[serde(rename = "buyToken")]
+    pub buy_token: Address,
+    /// None for the owner.
+    #[serde(default)]
+    pub receiver: Option<Address>,
+    #[serde(rename = "sellAmount", with = "decimal")]
+    pub sell_amount: U256,
+    #[serde(rename = "buyAmount", with = "decimal")]
+    pub buy_amount: U256,
+    #[serde(rename = "validTo")]
+    pub valid_to: u32,
+    #[serde(rename = "appData", deserialize_with = "deserialize_app_data")]
+    pub app_data: B256,
+    /// Zero for new orders, the fee is taken from the limit price.
+    #[serde(rename = "feeAmount", with = "decimal")]
+    pub fee_amount: U256,
+    pub kind: OrderKind,
+    #[serde(rename = "partiallyFillable")]
+    pub partially_fillable: bool,
+    #[serde(default)]
+    #[serde(rename = "sellTokenBalance")]
+    pub sell_token_balance: TokenBalance,
+    #[serde(default)]
+    #[serde(rename = "buyTokenBalance")]
+    pub buy_token_balance: TokenBalance,
+}
+
+impl Order {
+    /// A fill-or-kill order selling exactly `sell_amount` for at least `buy_amount`.
+    pub fn sell(sell_token: Address, buy_token: Address, sell_amount: U256, buy_amount: U256, valid_to: u32) -> Self {
+        Self {
+            sell_token,
+            buy_token,
+            receiver: None,
+            sell_amount,
+            buy_amount,
+            valid_to,
+            app_data: EMPTY_APP_DATA,
+            fee_amount: U256::ZERO,
+            kind: OrderKind::Sell,
+            partially_fillable: false,
+            sell_token_balance: TokenBalance::Erc20,
+            buy_token_balance: TokenBalance::Erc20,
+        }
+    }
+
+    /// A fill-or-kill order buying exactly `buy_amount` for at most `sell_amount`.
+    pub fn buy(sell_token: Address, buy_token: Address, sell_amount: U256, buy_amount: U256, valid_to: u32) -> Self {
+        Self {
+            kind: OrderKind::Buy,
+            ..Self::sell(sell_token, buy_token, sell_amount, buy_amount, valid_to)
+        }
+    }
+
+    pub fn with_receiver(mut self, receiver: Address) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    pub fn with_app_data(mut self, app_data: B256) -> Self {
+        self.app_data = app_data;
+        self
+    }
+
+    fn to_struct(&self) -> abi::Order {
+        abi::Order {
+            sellToken: self.sell_token,
+            buyToken: self.buy_token,
+            receiver: self.receiver.unwrap_or_default(),
+            sellAmount: self.sell_amount,
+            buyAmount: self.buy_amount,
+            validTo: self.valid_to,
+            appData: self.app_data,
+            feeAmount: self.fee_amount,
+            kind: self.kind.as_str().into(),
+            partiallyFillable: self.partially_fillable,
+            sellTokenBalance: self.sell_token_balance.as_str().into(),
+            buyTokenBalance: self.buy_token_balance.as_str().into(),
+        }
+    }
+
+    /// The EIP-712 digest the owner signs, also the first part of the order uid.
+    pub fn digest(&self, chain_id: u64) -> B256 {
+        self.to_struct().eip712_signing_hash(&domain(chain_id))
+    }
+
+    pub fn uid(&self, chain_id: u64, owner: Address) -> OrderUid {
+        OrderUid::new(self.digest(chain_id), owner, self.valid_to)
+    }
+
+    fn eip712_signature(&self, chain_id: u64, signer: &EvmSigner) -> Result<Bytes> {
+        let signature = signer
+            .sign_eip712(domain(chain_id), &self.to_struct())
+            .map_err(|e| CowError::SignatureError(e.to_string()))?;
+        decode_signature(&signature)
+    }
+
+    /// Signs the order as EIP-712 typed data.
+    pub fn sign_eip712(&self, chain_id: u64, signer: &EvmSigner) -> Result<Signature> {
+        Ok(Signature::Eip712(self.eip712_signature(chain_id, signer)?))
+    }
+
+    /// Signs the order digest as an `eth_sign` message, for wallets without EIP-712 support.
+    pub fn sign_eth_sign(&self, chain_id: u64, signer: &EvmSigner) -> Result<Signature> {
+        let signature = signer
+            .sign_eip191_bytes(self.digest(chain_id).as_slice())
+            .map_err(|e| CowError::SignatureError(e.to_string()))?;
+        Ok(Signature::EthSign(decode_signature(&signature)?))
+    }
+
+    /// Signs the order for a smart contract owner whose `isValidSignature` checks an EIP-712 signature
+    /// of one of its signers over the order digest. Other contracts need their own encoding, see
+    /// [`Signature::Eip1271`].
+    pub fn sign_eip1271(&self, chain_id: u64, signer: &EvmSigner) -> Result<Signature> {
+        Ok(Signature::Eip1271(self.eip712_signature(chain_id, signer)?))
+    }
+}
+
+fn decode_signature(signature: &str) -> Result<Bytes> {
+    hex::decode(signature)
+        .map(Bytes::from)
+        .map_err(|e| CowError::SignatureError(e.to_string()))
+}
+
+/// Identifies an order: its digest, owner and expiry, 56 bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OrderUid(pub FixedBytes<56>);
+
+impl OrderUid {
+    pub fn new(digest: B256, owner: Address, valid_to: u32) -> Self {
+        let mut uid = [0u8; 56];
+        uid[..32].copy_from_slice(digest.as_slice());
+        uid[32..52].copy_from_slice(owner.as_slice());
+        uid[52..].copy_from_slice(&valid_to.to_be_bytes());
+        Self(FixedBytes(uid))
+    }
+
+    pub fn digest(&self) -> B256 {
+        B256::from_slice(&self.0[..32])
+    }
+
+    pub fn owner(&self) -> Address {
+        Address::from_slice(&self.0[32..52])
+    }
+
+    pub fn valid_to(&self) -> u32 {
+        u32::from_be_bytes(self.0[52..].try_into().unwrap())
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::copy_from_slice(self.0.as_slice())
+    }
+}
+
+impl fmt::Display for OrderUid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for OrderUid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for OrderUid {
+    type Err = CowError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        FixedBytes::from_str(s)
+            .map(Self)
+            .map_err(|_| CowError::InvalidOrderUid(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningScheme {
+    Eip712,
+    EthSign,
+    Eip1271,
+    PreSign,
+}
+
+/// An order signature under one of the settlement contract's schemes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signature {
+    Eip712(Bytes),
+    EthSign(Bytes),
+    /// Whatever the owner contract's `isValidSignature` accepts for the order digest.
+    Eip1271(Bytes),
+    /// Signed on chain by the owner with `setPreSignature`, see [`pre_sign`].
+    PreSign,
+}
+
+impl Signature {
+    pub fn scheme(&self) -> SigningScheme {
+        match self {
+            Signature::Eip712(_) => SigningScheme::Eip712,
+            Signature::EthSign(_) => SigningScheme::EthSign,
+            Signature::Eip1271(_) => SigningScheme::Eip1271,
+            Signature::PreSign => SigningScheme::PreSign,
+        }
+    }
+
+    /// The `signature` of an order submission, the owner itself for pre-signed orders.
+    pub fn to_bytes(&self, owner: Address) -> Bytes {
+        match self {
+            Signature::Eip712(signature) | Signature::EthSign(signature) | Signature::Eip1271(signature) => {
+                signature.clone()
+            }
+            Signature::PreSign => Bytes::copy_from_slice(owner.as_slice()),
+        }
+    }
+
+    /// Recovers the signer of an ECDSA signature over `digest`, None for the other schemes.
+    pub fn recover(&self, digest: B256) -> Result<Option<Address>> {
+        let (signature, prefixed) = match self {
+            Signature::Eip712(signature) => (signature, false),
+            Signature::EthSign(signature) => (signature, true),
+            _ => return Ok(None),
+        };
+        let signature =
+            PrimitiveSignature::try_from(signature.as_ref()).map_err(|e| CowError::SignatureError(e.to_string()))?;
+        let owner = if prefixed {
+            signature.recover_address_from_msg(digest)
+        } else {
+            signature.recover_address_from_prehash(&digest)
+        };
+        owner.map(Some).map_err(|e| CowError::SignatureError(e.to_string()))
+    }
+}
+
+/// Signs the cancellation of `uids` with the orderbook, as EIP-712 typed data.
+pub fn sign_cancellations(uids: &[OrderUid], chain_id: u64, signer: &EvmSigner) -> Result<Signature> {
+    let cancellations = abi::OrderCancellations {
+        orderUids: uids.iter().map(OrderUid::to_bytes).collect(),
+    };
+    let signature = signer
+        .sign_eip712(domain(chain_id), &cancellations)
+        .map_err(|e| CowError::SignatureError(e.to_string()))?;
+    Ok(Signature::Eip712(decode_signature(&signature)?))
+}
+
+/// Transaction with which the owner pre-signs, or with `signed` false revokes, an order.
+pub fn pre_sign(uid: &OrderUid, signed: bool) -> TransactionRequest {
+    let input = IGPv2Settlement::setPreSignatureCall {
+        orderUid: uid.to_bytes(),
+        signed,
+    }
+    .abi_encode();
+    TransactionRequest::default()
+        .to(SETTLEMENT)
+        .input(Bytes::from(input).into())
+}
+
+/// Transaction with which the owner cancels an order on chain, even if the orderbook is unavailable.
+pub fn invalidate_order(uid: &OrderUid) -> TransactionRequest {
+    let input = IGPv2Settlement::invalidateOrderCall {
+        orderUid: uid.to_bytes(),
+    }
+    .abi_encode();
+    TransactionRequest::default()
+        .to(SETTLEMENT)
+        .input(Bytes::from(input).into())
+}
+
+/// Whether `uid` is pre-signed at `block`.
+pub async fn is_pre_signed(provider: &EvmProvider, uid: &OrderUid, block: BlockId) -> Result<bool> {
+    let call = IGPv2Settlement::preSignatureCall {
+        orderUid: uid.to_bytes(),
+    };
+    let tx = TransactionRequest::default()
+        .to(SETTLEMENT)
+        .input(Bytes::from(call.abi_encode()).into());
+    let output = provider.call(&tx, block).await?;
+    let signed = IGPv2Settlement::preSignatureCall::abi_decode_returns(&output, true)
+        .map_err(|e| ProviderError::ResponseError(e.to_string()))?
+        ._0;
+    // PRE_SIGNED = keccak256("GPv2Signing.Scheme.PreSign")
+    Ok(signed == U256::from_be_bytes(keccak256("GPv2Signing.Scheme.PreSign").0))
+}
+
+/// Whether `owner` accepts an ERC-1271 `signature` of `order`, a revert counting as a rejection.
+///
+/// Other node errors are returned, an unavailable node doesn't make a signature invalid.
+pub async fn verify_eip1271(
+    provider: &EvmProvider,
+    owner: Address,
+    order: &Order,
+    chain_id: u64,
+    signature: &Signature,
+    block: BlockId,
+) -> Result<bool> {
+    let Signature::Eip1271(signature) = signature else {
+        return Err(CowError::SignatureError("not an ERC-1271 signature".into()));
+    };
+    let call = IERC1271::isValidSignatureCall {
+        hash: order.digest(chain_id),
+        signature: signature.clone(),
+    };
+    let tx = TransactionRequest::default()
+        .to(owner)
+        .input(Bytes::from(call.abi_encode()).into());
+    let output = match provider.call(&tx, block).await {
+        Ok(output) => output,
+        Err(ProviderError::RpcError { code, message, .. })
+            if code == 3 || message.starts_with("execution reverted") =>
+        {
+            return Ok(false)
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(IERC1271::isValidSignatureCall::abi_decode_returns(&output, true)
+        .is_ok_and(|magic| magic.magicValue.0 == ERC1271_MAGIC_VALUE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::client::EvmProvider;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use alloy_eips::BlockNumberOrTag;
+    use alloy_primitives::address;
+    use alloy_sol_types::SolValue;
+    use serde_json::json;
+
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const COW: Address = address!("DEf1CA1fb7FBcDC777520aa7f396b4E015F497aB");
+
+    fn order() -> Order {
+        Order::sell(
+            WETH,
+            COW,
+            U256::from(10).pow(U256::from(18)),
+            U256::from(2500) * U256::from(10).pow(U256::from(18)),
+            1_750_000_000,
+        )
+    }
+
+    #[test]
+    fn test_order_hash() {
+        // GPv2Order.TYPE_HASH and the mainnet GPv2Settlement.domainSeparator()
+        assert_eq!(
+            abi::Order::eip712_type_hash(&order().to_struct()),
+            b256!("d5a25ba2e97094ad7d83dc28a6572da797d6b3e7fc6663bd93efb789fc17e489")
+        );
+        assert_eq!(
+            domain(1).separator(),
+            b256!("c078f884a2676e1345748b1feace7b0abee5d00ecadb6e574dcdd109a63e8943")
+        );
+        assert_eq!(app_data_hash("{}"), EMPTY_APP_DATA);
+
+        // the receiver defaults to the owner, encoded as the zero address
+        let order = order();
+        assert_eq!(order.digest(1), order.clone().with_receiver(Address::ZERO).digest(1));
+        assert_ne!(order.digest(1), order.clone().with_receiver(WETH).digest(1));
+        assert_ne!(order.digest(1), order.digest(100));
+
+        let owner = account().signer.address();
+        let uid = order.uid(1, owner);
+        assert_eq!(uid.digest(), order.digest(1));
+        assert_eq!(uid.owner(), owner);
+        assert_eq!(uid.valid_to(), 1_750_000_000);
+        assert_eq!(uid.to_string().len(), 2 + 112);
+        assert_eq!(uid.to_string().parse::<OrderUid>().unwrap(), uid);
+        assert!(matches!(
+            "0x1234".parse::<OrderUid>(),
+            Err(CowError::InvalidOrderUid(_))
+        ));
+    }
+
+    #[test]
+    fn test_order_json() {
+        let order = order().with_receiver(address!("d46B96d15ffF9b2B17e9c788086f3159bD0e8355"));
+        let value = serde_json::to_value(&order).unwrap();
+        assert_eq!(value["sellAmount"], "1000000000000000000");
+        assert_eq!(value["kind"], "sell");
+        assert_eq!(value["sellTokenBalance"], "erc20");
+        assert_eq!(serde_json::from_value::<Order>(value.clone()).unwrap(), order);
+
+        // the orderbook may return the app data document instead of its hash
+        let mut value = value;
+        value["appData"] = json!("{}");
+        value["receiver"] = json!(null);
+        let parsed: Order = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.app_data, EMPTY_APP_DATA);
+        assert_eq!(parsed.receiver, None);
+    }
+
+    #[test]
+    fn test_signing_schemes() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let owner = signer.address();
+        let order = order();
+        let digest = order.digest(1);
+
+        let eip712 = order.sign_eip712(1, &signer).unwrap();
+        assert_eq!(eip712.scheme(), SigningScheme::Eip712);
+        assert_eq!(eip712.recover(digest).unwrap(), Some(owner));
+
+        let eth_sign = order.sign_eth_sign(1, &signer).unwrap();
+        assert_eq!(eth_sign.scheme(), SigningScheme::EthSign);
+        assert_eq!(eth_sign.recover(digest).unwrap(), Some(owner));
+        assert_ne!(eth_sign.to_bytes(owner), eip712.to_bytes(owner));
+
+        assert_eq!(
+            Signature::PreSign.to_bytes(owner),
+            Bytes::copy_from_slice(owner.as_slice())
+        );
+        assert_eq!(Signature::PreSign.recover(digest).unwrap(), None);
+        assert_eq!(serde_json::to_value(SigningScheme::EthSign).unwrap(), "ethsign");
+        assert_eq!(serde_json::to_value(SigningScheme::PreSign).unwrap(), "presign");
+
+        let uid = order.uid(1, owner);
+        let tx = pre_sign(&uid, true);
+        assert_eq!(tx.to, Some(SETTLEMENT.into()));
+        let call = IGPv2Settlement::setPreSignatureCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(call.orderUid, uid.to_bytes());
+        assert!(call.signed);
+        let call =
+            IGPv2Settlement::invalidateOrderCall::abi_decode(invalidate_order(&uid).input.input().unwrap(), true)
+                .unwrap();
+        assert_eq!(call.orderUid, uid.to_bytes());
+
+        let cancellation = sign_cancellations(&[uid], 1, &signer).unwrap();
+        let cancellations = abi::OrderCancellations {
+            orderUids: vec![uid.to_bytes()],
+        };
+        let hash = cancellations.eip712_signing_hash(&domain(1));
+        assert_eq!(cancellation.recover(hash).unwrap(), Some(owner));
+    }
+
+    #[tokio::test]
+    async fn test_eip1271_and_pre_sign() {
+        const SAFE: Address = address!("1111111111111111111111111111111111111111");
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let order = order();
+        let signature = order.sign_eip1271(1, &signer).unwrap();
+        assert_eq!(signature.scheme(), SigningScheme::Eip1271);
+
+        // the contract accepts ECDSA signatures of its signer over the digest, like the settlement checks
+        let (owner, digest) = (signer.address(), order.digest(1));
+        let uid = order.uid(1, SAFE);
+        let node = MockNode::start(move |method, params| {
+            assert_eq!(method, "eth_call");
+            let input: Bytes = serde_json::from_value(params[0]["input"].clone()).unwrap();
+            if input[..4] == IGPv2Settlement::preSignatureCall::SELECTOR {
+                let signed = U256::from_be_bytes(keccak256("GPv2Signing.Scheme.PreSign").0);
+                return Ok(json!(Bytes::from(signed.abi_encode())));
+            }
+            let call = IERC1271::isValidSignatureCall::abi_decode(&input, true).unwrap();
+            if call.signature.is_empty() {
+                return Err((-32005, "request limit exceeded".into()));
+            }
+            let valid = call.hash == digest
+                && Signature::Eip712(call.signature).recover(call.hash).ok().flatten() == Some(owner);
+            if !valid {
+                return Err((3, "execution reverted".into()));
+            }
+            Ok(json!(Bytes::from(FixedBytes(ERC1271_MAGIC_VALUE).abi_encode())))
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let latest = BlockId::Number(BlockNumberOrTag::Latest);
+
+        assert!(verify_eip1271(&provider, SAFE, &order, 1, &signature, latest)
+            .await
+            .unwrap());
+        let tampered = Signature::Eip1271(Bytes::from(vec![0u8; 65]));
+        assert!(!verify_eip1271(&provider, SAFE, &order, 1, &tampered, latest)
+            .await
+            .unwrap());
+        // a node failure is not a rejection
+        let empty = Signature::Eip1271(Bytes::new());
+        assert!(matches!(
+            verify_eip1271(&provider, SAFE, &order, 1, &empty, latest).await,
+            Err(CowError::ProviderError(ProviderError::RpcError { code: -32005, .. }))
+        ));
+        assert!(verify_eip1271(&provider, SAFE, &order, 1, &Signature::PreSign, latest)
+            .await
+            .is_err());
+
+        assert!(is_pre_signed(&provider, &uid, latest).await.unwrap());
+    }
+}
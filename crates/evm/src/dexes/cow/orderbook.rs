@@ -0,0 +1,602 @@
+//! Client of the CoW Protocol orderbook API: quotes, order submission, status and cancellation.
+
+use std::time::Duration;
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use reqwest::{Client, ClientBuilder, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::dexes::cow::order::{app_data_hash, Order, OrderKind, OrderUid, Signature, SigningScheme, TokenBalance};
+use crate::dexes::cow::{decimal, CowError, Result};
+
+const BASE_URL: &str = "https://api.cow.fi";
+
+/// Path of the production orderbook of `chain_id`.
+fn network(chain_id: u64) -> Option<&'static str> {
+    match chain_id {
+        1 => Some("mainnet"),
+        100 => Some("xdai"),
+        8453 => Some("base"),
+        42161 => Some("arbitrum_one"),
+        11155111 => Some("sepolia"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceQuality {
+    Fast,
+    #[default]
+    Optimal,
+    /// Only quotes whose execution was simulated.
+    Verified,
+}
+
+/// The amount a quote is for, with the kind of the order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QuoteSide {
+    Sell {
+        #[serde(rename = "sellAmountBeforeFee", with = "decimal")]
+        sell_amount_before_fee: U256,
+    },
+    Buy {
+        #[serde(rename = "buyAmountAfterFee", with = "decimal")]
+        buy_amount_after_fee: U256,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderQuoteRequest {
+    #[serde(rename = "sellToken")]
+    pub sell_token: Address,
+    #[serde(rename = "buyToken")]
+    pub buy_token: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver: Option<Address>,
+    /// The owner of the order.
+    pub from: Address,
+    #[serde(flatten)]
+    pub side: QuoteSide,
+    /// JSON app data document.
+    #[serde(rename = "appData")]
+    pub app_data: String,
+    #[serde(rename = "appDataHash")]
+    pub app_data_hash: B256,
+    #[serde(rename = "sellTokenBalance")]
+    pub sell_token_balance: TokenBalance,
+    #[serde(rename = "buyTokenBalance")]
+    pub buy_token_balance: TokenBalance,
+    #[serde(rename = "priceQuality")]
+    pub price_quality: PriceQuality,
+    /// Pre-signed and ERC-1271 orders cost more gas to settle, which the fee accounts for.
+    #[serde(rename = "signingScheme")]
+    pub signing_scheme: SigningScheme,
+    #[serde(rename = "onchainOrder")]
+    pub onchain_order: bool,
+}
+
+impl OrderQuoteRequest {
+    pub fn new(sell_token: Address, buy_token: Address, from: Address, side: QuoteSide) -> Self {
+        Self {
+            sell_token,
+            buy_token,
+            receiver: None,
+            from,
+            side,
+            app_data: "{}".to_string(),
+            app_data_hash: app_data_hash("{}"),
+            sell_token_balance: TokenBalance::Erc20,
+            buy_token_balance: TokenBalance::Erc20,
+            price_quality: PriceQuality::Optimal,
+            signing_scheme: SigningScheme::Eip712,
+            onchain_order: false,
+        }
+    }
+
+    pub fn with_receiver(mut self, receiver: Address) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    pub fn with_app_data(mut self, document: String) -> Self {
+        self.app_data_hash = app_data_hash(&document);
+        self.app_data = document;
+        self
+    }
+
+    pub fn with_signing_scheme(mut self, signing_scheme: SigningScheme) -> Self {
+        self.signing_scheme = signing_scheme;
+        self
+    }
+
+    pub fn with_price_quality(mut self, price_quality: PriceQuality) -> Self {
+        self.price_quality = price_quality;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderQuoteResponse {
+    /// The quoted order, its fee still separate from the amounts.
+    pub quote: Order,
+    pub from: Address,
+    pub expiration: String,
+    /// Passed back on submission to link the order to its quote.
+    #[serde(default)]
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub verified: bool,
+}
+
+impl OrderQuoteResponse {
+    /// The order to sign for this quote: the fee is folded into the limit price, which is then moved
+    /// `slippage_bps` against the owner.
+    pub fn order(&self, slippage_bps: u32) -> Order {
+        let quote = &self.quote;
+        let sell_amount = quote.sell_amount + quote.fee_amount;
+        let bps = U256::from(10_000);
+        let (sell_amount, buy_amount) = match quote.kind {
+            OrderKind::Sell => (
+                sell_amount,
+                quote.buy_amount * U256::from(10_000u32.saturating_sub(slippage_bps)) / bps,
+            ),
+            OrderKind::Buy => (sell_amount * U256::from(10_000 + slippage_bps) / bps, quote.buy_amount),
+        };
+        Order {
+            sell_amount,
+            buy_amount,
+            fee_amount: U256::ZERO,
+            ..quote.clone()
+        }
+    }
+}
+
+/// A signed order to submit.
+#[derive(Debug, Clone)]
+pub struct OrderCreation {
+    pub order: Order,
+    pub signature: Signature,
+    /// The owner, required to tell ERC-1271 and pre-signed orders apart from their signature.
+    pub from: Address,
+    pub quote_id: Option<i64>,
+    /// JSON document hashing to the order's app data, for the orderbook to store.
+    pub app_data: Option<String>,
+}
+
+impl OrderCreation {
+    pub fn new(order: Order, signature: Signature, from: Address) -> Self {
+        Self {
+            order,
+            signature,
+            from,
+            quote_id: None,
+            app_data: None,
+        }
+    }
+
+    pub fn with_quote_id(mut self, quote_id: Option<i64>) -> Self {
+        self.quote_id = quote_id;
+        self
+    }
+
+    pub fn with_app_data(mut self, document: String) -> Self {
+        self.app_data = Some(document);
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct OrderCreationBody<'a> {
+    #[serde(flatten)]
+    order: &'a Order,
+    #[serde(rename = "signingScheme")]
+    signing_scheme: SigningScheme,
+    signature: Bytes,
+    from: Address,
+    #[serde(rename = "quoteId", skip_serializing_if = "Option::is_none")]
+    quote_id: Option<i64>,
+    #[serde(rename = "appDataHash", skip_serializing_if = "Option::is_none")]
+    app_data_hash: Option<B256>,
+}
+
+#[derive(Serialize)]
+struct OrderCancellationBody<'a> {
+    #[serde(rename = "orderUids")]
+    order_uids: &'a [OrderUid],
+    signature: Bytes,
+    #[serde(rename = "signingScheme")]
+    signing_scheme: SigningScheme,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrderStatus {
+    /// Waiting for the owner's `setPreSignature` transaction.
+    PresignaturePending,
+    Open,
+    Fulfilled,
+    Cancelled,
+    Expired,
+}
+
+/// An order as stored by the orderbook.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderInfo {
+    pub uid: OrderUid,
+    pub owner: Address,
+    #[serde(rename = "creationDate")]
+    pub creation_date: String,
+    pub status: OrderStatus,
+    #[serde(flatten)]
+    pub order: Order,
+    #[serde(rename = "signingScheme")]
+    pub signing_scheme: SigningScheme,
+    #[serde(rename = "executedSellAmount", with = "decimal")]
+    pub executed_sell_amount: U256,
+    #[serde(rename = "executedBuyAmount", with = "decimal")]
+    pub executed_buy_amount: U256,
+    /// Cancelled on chain with `invalidateOrder`.
+    #[serde(default)]
+    pub invalidated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    #[serde(rename = "errorType")]
+    error_type: String,
+    #[serde(default)]
+    description: String,
+}
+
+pub struct OrderbookClient {
+    pub http_client: Client,
+    pub base_url: String,
+    pub chain_id: u64,
+}
+
+impl OrderbookClient {
+    /// Creates a new orderbook client.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Optional orderbook URL, network included. If None, uses the production orderbook of `chain_id`
+    /// * `chain_id` - Chain the orders are signed for
+    pub fn new(base_url: Option<String>, chain_id: u64) -> Result<Self> {
+        let base_url = match base_url {
+            Some(base_url) => base_url,
+            None => format!(
+                "{}/{}",
+                BASE_URL,
+                network(chain_id).ok_or(CowError::UnsupportedChain(chain_id))?
+            ),
+        };
+        Ok(Self {
+            http_client: Self::http_client(Duration::from_secs(10)),
+            base_url,
+            chain_id,
+        })
+    }
+
+    fn http_client(timeout: Duration) -> Client {
+        ClientBuilder::new()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to create HTTP client")
+    }
+
+    /// Sets a custom timeout for the HTTP client
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http_client = Self::http_client(timeout);
+        self
+    }
+
+    async fn send<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(match serde_json::from_str::<ApiError>(&message) {
+                Ok(error) => CowError::ApiError {
+                    error_type: error.error_type,
+                    description: error.description,
+                },
+                Err(_) => CowError::InvalidStatus { status, message },
+            });
+        }
+
+        response.json::<T>().await.map_err(CowError::RequestError)
+    }
+
+    /// Quotes an order, its fee included.
+    pub async fn quote(&self, request: &OrderQuoteRequest) -> Result<OrderQuoteResponse> {
+        let url = format!("{}/api/v1/quote", self.base_url);
+        Self::send(self.http_client.post(&url).json(request)).await
+    }
+
+    /// Submits a signed order, returning its uid.
+    pub async fn submit_order(&self, creation: &OrderCreation) -> Result<OrderUid> {
+        let app_data_hash = match &creation.app_data {
+            Some(document) if app_data_hash(document) != creation.order.app_data => {
+                return Err(CowError::InvalidOrder(
+                    "app data document does not match its hash".into(),
+                ));
+            }
+            Some(_) => Some(creation.order.app_data),
+            None => None,
+        };
+        let mut body = serde_json::to_value(OrderCreationBody {
+            order: &creation.order,
+            signing_scheme: creation.signature.scheme(),
+            signature: creation.signature.to_bytes(creation.from),
+            from: creation.from,
+            quote_id: creation.quote_id,
+            app_data_hash,
+        })?;
+        // with its hash alongside, the app data is sent as the document itself
+        if let Some(document) = &creation.app_data {
+            body["appData"] = serde_json::Value::String(document.clone());
+        }
+
+        let url = format!("{}/api/v1/orders", self.base_url);
+        Self::send(self.http_client.post(&url).json(&body)).await
+    }
+
+    pub async fn get_order(&self, uid: &OrderUid) -> Result<OrderInfo> {
+        let url = format!("{}/api/v1/orders/{}", self.base_url, uid);
+        Self::send(self.http_client.get(&url)).await
+    }
+
+    /// Orders of `owner`, newest first.
+    pub async fn get_account_orders(&self, owner: Address, offset: u32, limit: u32) -> Result<Vec<OrderInfo>> {
+        let url = format!(
+            "{}/api/v1/account/{}/orders?offset={}&limit={}",
+            self.base_url, owner, offset, limit
+        );
+        Self::send(self.http_client.get(&url)).await
+    }
+
+    /// Cancels orders off chain with a signature from [`sign_cancellations`](super::order::sign_cancellations).
+    /// Solvers may still settle an order already in a batch, [`invalidate_order`](super::order::invalidate_order)
+    /// cancels on chain.
+    pub async fn cancel_orders(&self, uids: &[OrderUid], signature: &Signature) -> Result<()> {
+        let body = match signature {
+            Signature::Eip712(bytes) | Signature::EthSign(bytes) => OrderCancellationBody {
+                order_uids: uids,
+                signature: bytes.clone(),
+                signing_scheme: signature.scheme(),
+            },
+            _ => {
+                return Err(CowError::SignatureError(
+                    "cancellations are signed with EIP-712 or eth_sign".into(),
+                ))
+            }
+        };
+        let url = format!("{}/api/v1/orders", self.base_url);
+        let _: serde_json::Value = Self::send(self.http_client.delete(&url).json(&body)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::cow::order::{domain, sign_cancellations};
+    use crate::provider::mock::{MockApi, MockRequest};
+    use crate::provider::test_utils::account;
+    use crate::signer::sign::EvmSigner;
+    use alloy_primitives::address;
+    use alloy_sol_types::SolStruct;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const COW: Address = address!("DEf1CA1fb7FBcDC777520aa7f396b4E015F497aB");
+
+    fn error(error_type: &str, description: &str) -> (u16, Value) {
+        (400, json!({ "errorType": error_type, "description": description }))
+    }
+
+    /// An orderbook that checks signatures on submission and keeps the orders it accepts.
+    fn handle(request: &MockRequest, orders: &Mutex<HashMap<String, Value>>) -> (u16, Value) {
+        let body = &request.body;
+        match (request.method.as_str(), request.route()) {
+            ("POST", "/api/v1/quote") if body["sellToken"] == body["buyToken"] => {
+                error("SameBuyAndSellToken", "Buy token is the same as the sell token.")
+            }
+            ("POST", "/api/v1/quote") => (
+                200,
+                json!({
+                    "quote": {
+                        "sellToken": body["sellToken"],
+                        "buyToken": body["buyToken"],
+                        "receiver": body["receiver"],
+                        "sellAmount": "998000000000000000",
+                        "buyAmount": "2500000000000000000000",
+                        "validTo": 1_750_000_000,
+                        "appData": body["appData"],
+                        "appDataHash": body["appDataHash"],
+                        "feeAmount": "2000000000000000",
+                        "kind": body["kind"],
+                        "partiallyFillable": false,
+                        "sellTokenBalance": "erc20",
+                        "buyTokenBalance": "erc20",
+                        "signingScheme": body["signingScheme"],
+                    },
+                    "from": body["from"],
+                    "expiration": "2025-06-15T12:00:00.000000Z",
+                    "id": 42,
+                    "verified": true,
+                }),
+            ),
+            ("POST", "/api/v1/orders") => {
+                let order: Order = serde_json::from_value(body.clone()).unwrap();
+                let owner: Address = serde_json::from_value(body["from"].clone()).unwrap();
+                let signature: Bytes = serde_json::from_value(body["signature"].clone()).unwrap();
+                let recovered = Signature::Eip712(signature).recover(order.digest(1)).unwrap();
+                if body["signingScheme"] != "eip712" || recovered != Some(owner) {
+                    return error("InvalidSignature", "signature for computed order hash is invalid");
+                }
+                if order.fee_amount != U256::ZERO {
+                    return error("NonZeroFee", "Fee must be zero");
+                }
+                let uid = order.uid(1, owner).to_string();
+                let mut stored = body.clone();
+                stored["uid"] = json!(uid);
+                stored["owner"] = json!(owner);
+                stored["creationDate"] = json!("2025-06-15T11:50:00.000000Z");
+                stored["status"] = json!("open");
+                stored["executedSellAmount"] = json!("0");
+                stored["executedBuyAmount"] = json!("0");
+                orders.lock().unwrap().insert(uid.clone(), stored);
+                (201, json!(uid))
+            }
+            ("DELETE", "/api/v1/orders") => {
+                let uids: Vec<OrderUid> = serde_json::from_value(body["orderUids"].clone()).unwrap();
+                let cancellations = crate::abis::cow::OrderCancellations {
+                    orderUids: uids.iter().map(OrderUid::to_bytes).collect(),
+                };
+                let signature: Bytes = serde_json::from_value(body["signature"].clone()).unwrap();
+                let signer = Signature::Eip712(signature)
+                    .recover(cancellations.eip712_signing_hash(&domain(1)))
+                    .unwrap();
+                let mut orders = orders.lock().unwrap();
+                for uid in &uids {
+                    if Some(uid.owner()) != signer {
+                        return error("InvalidSignature", "Malformed signature");
+                    }
+                    let Some(order) = orders.get_mut(&uid.to_string()) else {
+                        return error("OrderNotFound", "order not located in database");
+                    };
+                    order["status"] = json!("cancelled");
+                }
+                (200, json!("Cancelled"))
+            }
+            ("GET", route) => match orders.lock().unwrap().get(route.trim_start_matches("/api/v1/orders/")) {
+                Some(order) => (200, order.clone()),
+                None => (
+                    404,
+                    json!({ "errorType": "NotFound", "description": "Order was not found" }),
+                ),
+            },
+            _ => (404, Value::Null),
+        }
+    }
+
+    async fn mock_orderbook() -> MockApi {
+        let orders = Arc::new(Mutex::new(HashMap::new()));
+        MockApi::start(move |request| handle(request, &orders)).await
+    }
+
+    #[tokio::test]
+    async fn test_quote_submit_and_cancel() {
+        let api = mock_orderbook().await;
+        let client = OrderbookClient::new(Some(api.url().to_string()), 1).unwrap();
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let owner = signer.address();
+        let app_data = r#"{"appCode":"web3ium","metadata":{},"version":"1.3.0"}"#.to_string();
+
+        let side = QuoteSide::Sell {
+            sell_amount_before_fee: U256::from(10).pow(U256::from(18)),
+        };
+        let request = OrderQuoteRequest::new(WETH, COW, owner, side).with_app_data(app_data.clone());
+        let quote = client.quote(&request).await.unwrap();
+        assert_eq!(quote.id, Some(42));
+        assert!(quote.verified);
+        assert_eq!(quote.quote.app_data, app_data_hash(&app_data));
+
+        // the fee goes back into the sell amount, and 1% off the buy amount
+        let order = quote.order(100);
+        assert_eq!(order.sell_amount, U256::from(10).pow(U256::from(18)));
+        assert_eq!(order.buy_amount, U256::from(2475) * U256::from(10).pow(U256::from(18)));
+        assert_eq!(order.fee_amount, U256::ZERO);
+
+        let signature = order.sign_eip712(1, &signer).unwrap();
+        let creation = OrderCreation::new(order.clone(), signature, owner)
+            .with_quote_id(quote.id)
+            .with_app_data(app_data.clone());
+        let uid = client.submit_order(&creation).await.unwrap();
+        assert_eq!(uid, order.uid(1, owner));
+
+        let info = client.get_order(&uid).await.unwrap();
+        assert_eq!(info.status, OrderStatus::Open);
+        assert_eq!(info.owner, owner);
+        assert_eq!(info.order, order);
+        assert_eq!(info.signing_scheme, SigningScheme::Eip712);
+        assert_eq!(info.executed_sell_amount, U256::ZERO);
+
+        let cancellation = sign_cancellations(&[uid], 1, &signer).unwrap();
+        client.cancel_orders(&[uid], &cancellation).await.unwrap();
+        assert_eq!(client.get_order(&uid).await.unwrap().status, OrderStatus::Cancelled);
+
+        let requests = api.requests();
+        assert_eq!(requests[0].body["kind"], "sell");
+        assert_eq!(requests[0].body["sellAmountBeforeFee"], "1000000000000000000");
+        assert_eq!(requests[0].body["appData"], app_data.as_str());
+        assert_eq!(requests[1].body["quoteId"], 42);
+        assert_eq!(requests[1].body["appData"], app_data.as_str());
+        assert_eq!(requests[1].body["appDataHash"], json!(app_data_hash(&app_data)));
+        assert_eq!(requests[3].body["signingScheme"], "eip712");
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let api = mock_orderbook().await;
+        let client = OrderbookClient::new(Some(api.url().to_string()), 1).unwrap();
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let owner = signer.address();
+
+        assert_eq!(
+            OrderbookClient::new(None, 100).unwrap().base_url,
+            "https://api.cow.fi/xdai"
+        );
+        assert!(matches!(
+            OrderbookClient::new(None, 56),
+            Err(CowError::UnsupportedChain(56))
+        ));
+
+        let side = QuoteSide::Buy {
+            buy_amount_after_fee: U256::from(1000),
+        };
+        let request = OrderQuoteRequest::new(WETH, WETH, owner, side);
+        assert_eq!(serde_json::to_value(&request).unwrap()["buyAmountAfterFee"], "1000");
+        match client.quote(&request).await {
+            Err(CowError::ApiError { error_type, .. }) => assert_eq!(error_type, "SameBuyAndSellToken"),
+            other => panic!("unexpected result: {:?}", other.map(|quote| quote.quote)),
+        }
+
+        // signed on another chain
+        let order = Order::buy(
+            WETH,
+            COW,
+            U256::from(10).pow(U256::from(18)),
+            U256::from(1000),
+            1_750_000_000,
+        );
+        let signature = order.sign_eip712(100, &signer).unwrap();
+        let result = client
+            .submit_order(&OrderCreation::new(order.clone(), signature, owner))
+            .await;
+        assert!(matches!(result, Err(CowError::ApiError { error_type, .. }) if error_type == "InvalidSignature"));
+
+        let signature = order.sign_eip712(1, &signer).unwrap();
+        let creation = OrderCreation::new(order.clone(), signature, owner).with_app_data("{\"appCode\":1}".into());
+        assert!(matches!(
+            client.submit_order(&creation).await,
+            Err(CowError::InvalidOrder(_))
+        ));
+
+        let uid = order.uid(1, owner);
+        let result = client.get_order(&uid).await;
+        assert!(matches!(result, Err(CowError::ApiError { error_type, .. }) if error_type == "NotFound"));
+        assert!(matches!(
+            client.cancel_orders(&[uid], &Signature::PreSign).await,
+            Err(CowError::SignatureError(_))
+        ));
+    }
+}
@@ -0,0 +1,475 @@
+//! CryptoSwap pools (tricrypto, twocrypto and their `-ng` versions), ports of the Vyper
+//! `newton_D`, `newton_y` and the fee of the pools.
+//!
+//! The `-ng` pools solve `y` analytically for three coins and fall back to `newton_y`, both
+//! converge to the same balance but the last few wei of a quote can differ from `get_dy`.
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+
+use crate::abis::curve::ICurveCryptoSwap;
+use crate::dexes::curve::{
+    add, check_indices, math_error, mul, mul_div, pool_request, pow10, registry_coins, CurveError, Result,
+    FEE_DENOMINATOR, MAX_ITERATIONS, PRECISION,
+};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+
+/// `A()` of crypto pools is `A * N^N * A_MULTIPLIER`.
+pub const A_MULTIPLIER: u64 = 10_000;
+
+/// Approximate geometric mean of `x`, the starting point of `newton_D`.
+pub fn geometric_mean(x: &[U256]) -> Result<U256> {
+    let n = U256::from(x.len());
+    let x = sorted_desc(x);
+    let mut d = x[0];
+    if d.is_zero() {
+        return Err(CurveError::InsufficientLiquidity);
+    }
+    for _ in 0..MAX_ITERATIONS {
+        let prev = d;
+        let tmp = x.iter().try_fold(PRECISION, |tmp, x| mul_div(tmp, *x, d))?;
+        d = mul_div(d, add((n - U256::from(1)) * PRECISION, tmp)?, n * PRECISION)?;
+        let diff = d.abs_diff(prev);
+        if diff <= U256::from(1) || mul(diff, PRECISION)? < d {
+            return Ok(d);
+        }
+    }
+    Err(CurveError::NotConverged("geometric_mean"))
+}
+
+/// The invariant `D` of price scaled balances `x`, `ann` being `A()` of the pool.
+pub fn newton_d(ann: U256, gamma: U256, x: &[U256]) -> Result<U256> {
+    if ann.is_zero() || gamma.is_zero() {
+        return Err(math_error("zero A or gamma"));
+    }
+    let n = U256::from(x.len());
+    let x = sorted_desc(x);
+    if x.iter().any(|x| x.is_zero()) {
+        return Err(CurveError::InsufficientLiquidity);
+    }
+    let sum = x.iter().try_fold(U256::ZERO, |sum, x| add(sum, *x))?;
+    let mut d = mul(n, geometric_mean(&x)?)?;
+    for _ in 0..MAX_ITERATIONS {
+        let prev = d;
+        let k0 = x.iter().try_fold(PRECISION, |k0, x| mul_div(mul(k0, *x)?, n, d))?;
+        let g1k0 = g1k0(gamma, k0);
+        let mul1 = mul_div(mul_div(PRECISION, d, gamma)?, g1k0, gamma)?;
+        let mul1 = mul_div(mul(mul1, g1k0)?, U256::from(A_MULTIPLIER), ann)?;
+        let mul2 = mul_div(U256::from(2) * PRECISION * n, k0, g1k0)?;
+        let neg_fprime = add(add(sum, mul_div(sum, mul2, PRECISION)?)?, mul_div(mul1, n, k0)?)?;
+        let neg_fprime = neg_fprime
+            .checked_sub(mul_div(mul2, d, PRECISION)?)
+            .filter(|fprime| !fprime.is_zero())
+            .ok_or_else(|| math_error("newton_D derivative"))?;
+
+        let d_plus = mul_div(d, add(neg_fprime, sum)?, neg_fprime)?;
+        let mut d_minus = mul_div(d, d, neg_fprime)?;
+        let step = mul_div(d, mul1 / neg_fprime, PRECISION)?;
+        d_minus = match PRECISION > k0 {
+            true => add(d_minus, mul_div(step, PRECISION - k0, k0)?)?,
+            false => d_minus
+                .checked_sub(mul_div(step, k0 - PRECISION, k0)?)
+                .ok_or_else(|| math_error("newton_D underflow"))?,
+        };
+        d = match d_plus > d_minus {
+            true => d_plus - d_minus,
+            false => (d_minus - d_plus) / U256::from(2),
+        };
+        if mul(d.abs_diff(prev), pow10(14))? < d.max(pow10(16)) {
+            check_fractions(&x, d)?;
+            return Ok(d);
+        }
+    }
+    Err(CurveError::NotConverged("newton_D"))
+}
+
+/// The balance of coin `i` for which the invariant of `x` is `d`.
+pub fn newton_y(ann: U256, gamma: U256, x: &[U256], d: U256, i: usize) -> Result<U256> {
+    let n_coins = x.len();
+    if i >= n_coins {
+        return Err(CurveError::InvalidIndex(i));
+    }
+    if ann.is_zero() || gamma.is_zero() || d.is_zero() {
+        return Err(math_error("zero A, gamma or D"));
+    }
+    let n = U256::from(n_coins);
+    let mut others = x.to_vec();
+    others[i] = U256::ZERO;
+    let others = sorted_desc(&others);
+    let limit = (others[0] / pow10(14)).max(d / pow10(14)).max(U256::from(100));
+
+    let mut y = d / n;
+    let mut sum_i = U256::ZERO;
+    // smallest balances first, the largest ones last
+    for x in others[..n_coins - 1].iter().rev() {
+        if x.is_zero() {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        y = y * d / (*x * n);
+        sum_i += x;
+    }
+    let k0_i = others[..n_coins - 1].iter().fold(PRECISION, |k0, x| k0 * x * n / d);
+
+    for _ in 0..MAX_ITERATIONS {
+        let prev = y;
+        let k0 = k0_i * y * n / d;
+        let sum = sum_i + y;
+        let g1k0 = g1k0(gamma, k0);
+        let mul1 = PRECISION * d / gamma * g1k0 / gamma * g1k0 * U256::from(A_MULTIPLIER) / ann;
+        let mul2 = PRECISION + U256::from(2) * PRECISION * k0 / g1k0;
+
+        let yfprime = PRECISION * y + sum * mul2 + mul1;
+        let dyfprime = d * mul2;
+        if yfprime < dyfprime {
+            y = prev / U256::from(2);
+            continue;
+        }
+        let yfprime = yfprime - dyfprime;
+        let fprime = yfprime / y;
+        if fprime.is_zero() || k0.is_zero() {
+            return Err(math_error("newton_y derivative"));
+        }
+        let mut y_minus = mul1 / fprime;
+        let y_plus = (yfprime + PRECISION * d) / fprime + y_minus * PRECISION / k0;
+        y_minus += PRECISION * sum / fprime;
+        y = match y_plus < y_minus {
+            true => prev / U256::from(2),
+            false => y_plus - y_minus,
+        };
+        if y.abs_diff(prev) < limit.max(y / pow10(14)) {
+            check_fractions(&[y], d)?;
+            return Ok(y);
+        }
+    }
+    Err(CurveError::NotConverged("newton_y"))
+}
+
+/// How balanced `x` is, 1e18 at the peg, turning the fee from `mid_fee` towards `out_fee`.
+pub fn reduction_coefficient(x: &[U256], fee_gamma: U256) -> U256 {
+    let n = U256::from(x.len());
+    let sum = x.iter().fold(U256::ZERO, |sum, x| sum + x);
+    if sum.is_zero() {
+        return U256::ZERO;
+    }
+    let k = x.iter().fold(PRECISION, |k, x| k * n * x / sum);
+    match fee_gamma.is_zero() {
+        true => k,
+        false => fee_gamma * PRECISION / (fee_gamma + PRECISION - k),
+    }
+}
+
+fn g1k0(gamma: U256, k0: U256) -> U256 {
+    let g1k0 = gamma + PRECISION;
+    match g1k0 > k0 {
+        true => g1k0 - k0 + U256::from(1),
+        false => k0 - g1k0 + U256::from(1),
+    }
+}
+
+/// The pools revert when a balance is off by more than 100x from the others.
+fn check_fractions(x: &[U256], d: U256) -> Result<()> {
+    for x in x {
+        let fraction = x * PRECISION / d;
+        if fraction <= pow10(16) - U256::from(1) || fraction >= pow10(20) + U256::from(1) {
+            return Err(math_error("unsafe balances"));
+        }
+    }
+    Ok(())
+}
+
+fn sorted_desc(x: &[U256]) -> Vec<U256> {
+    let mut x = x.to_vec();
+    x.sort_unstable_by(|a, b| b.cmp(a));
+    x
+}
+
+/// State of a CryptoSwap pool, enough to quote it offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptoSwapPool {
+    pub address: Address,
+    pub coins: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// `10^(18 - decimals)` of each coin.
+    pub precisions: Vec<U256>,
+    /// Internal price of each coin after the first in units of the first, 1e18 based.
+    pub price_scale: Vec<U256>,
+    /// `A * N^N * A_MULTIPLIER`, as returned by `A()`.
+    pub a: U256,
+    pub gamma: U256,
+    /// The invariant stored by the pool, `D()`.
+    pub d: U256,
+    /// Fees over 1e10, from `mid_fee` at the peg to `out_fee` away from it.
+    pub mid_fee: U256,
+    pub out_fee: U256,
+    pub fee_gamma: U256,
+}
+
+impl CryptoSwapPool {
+    /// Balances scaled to 18 decimals and priced in the first coin.
+    fn xp(&self, balances: &[U256]) -> Result<Vec<U256>> {
+        balances
+            .iter()
+            .zip(&self.precisions)
+            .enumerate()
+            .map(|(k, (balance, precision))| match k {
+                0 => mul(*balance, *precision),
+                _ => mul_div(mul(*balance, self.price_scale[k - 1])?, *precision, PRECISION),
+            })
+            .collect()
+    }
+
+    /// The invariant of the current balances, what `D()` holds outside of A and gamma ramps.
+    pub fn compute_d(&self) -> Result<U256> {
+        newton_d(self.a, self.gamma, &self.xp(&self.balances)?)
+    }
+
+    /// Fee over 1e10 charged on a swap leaving the pool at `xp`.
+    pub fn fee(&self, xp: &[U256]) -> U256 {
+        let f = reduction_coefficient(xp, self.fee_gamma);
+        (self.mid_fee * f + self.out_fee * (PRECISION - f)) / PRECISION
+    }
+
+    /// Output of swapping `dx` of coin `i` for coin `j`, after fees, `get_dy`.
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Result<U256> {
+        check_indices(i, j, self.coins.len())?;
+        if self.price_scale.len() + 1 != self.coins.len() {
+            return Err(CurveError::InvalidPool(format!(
+                "{} price scales",
+                self.price_scale.len()
+            )));
+        }
+        let mut balances = self.balances.clone();
+        balances[i] = add(balances[i], dx)?;
+        let mut xp = self.xp(&balances)?;
+        let y = newton_y(self.a, self.gamma, &xp, self.d, j)?;
+        let mut dy = xp[j]
+            .checked_sub(y)
+            .and_then(|dy| dy.checked_sub(U256::from(1)))
+            .ok_or(CurveError::InsufficientLiquidity)?;
+        xp[j] = y;
+        if j > 0 {
+            dy = dy * PRECISION / self.price_scale[j - 1];
+        }
+        dy /= self.precisions[j];
+        Ok(dy - self.fee(&xp) * dy / FEE_DENOMINATOR)
+    }
+
+    /// `exchange` transaction swapping `dx` of coin `i` for at least `min_dy` of coin `j`.
+    pub fn exchange(&self, i: usize, j: usize, dx: U256, min_dy: U256) -> Result<TransactionRequest> {
+        check_indices(i, j, self.coins.len())?;
+        let input = ICurveCryptoSwap::exchangeCall {
+            i: U256::from(i),
+            j: U256::from(j),
+            dx,
+            min_dy,
+        }
+        .abi_encode();
+        Ok(pool_request(self.address, input, self.coins[i], dx))
+    }
+
+    /// `exchange_underlying` transaction of pools holding WETH, which pay and are paid in ETH
+    /// instead. `dx` is sent along when coin `i` is WETH.
+    pub fn exchange_underlying(
+        &self,
+        i: usize,
+        j: usize,
+        dx: U256,
+        min_dy: U256,
+        weth: Address,
+    ) -> Result<TransactionRequest> {
+        check_indices(i, j, self.coins.len())?;
+        let input = ICurveCryptoSwap::exchange_underlyingCall {
+            i: U256::from(i),
+            j: U256::from(j),
+            dx,
+            min_dy,
+        }
+        .abi_encode();
+        let tx = pool_request(self.address, input, self.coins[i], dx);
+        Ok(match self.coins[i] == weth {
+            true => tx.value(dx),
+            false => tx,
+        })
+    }
+
+    /// Reads the state of `pool` at `block`, with its coins from `registry`.
+    pub async fn fetch(provider: &EvmProvider, registry: Address, pool: Address, block: BlockId) -> Result<Self> {
+        let (coins, decimals) = registry_coins(provider, registry, pool, block).await?;
+        let mut multicall = Multicall::new();
+        let balances: Vec<_> = (0..coins.len())
+            .map(|i| multicall.add(pool, ICurveCryptoSwap::balancesCall { i: U256::from(i) }, true))
+            .collect();
+        // two coin pools have a single price scale, without index
+        let price_scale = match coins.len() {
+            2 => vec![multicall
+                .add(pool, ICurveCryptoSwap::price_scale_0Call {}, true)
+                .index()],
+            n => (0..n - 1)
+                .map(|k| {
+                    multicall
+                        .add(pool, ICurveCryptoSwap::price_scale_1Call { k: U256::from(k) }, true)
+                        .index()
+                })
+                .collect(),
+        };
+        let a = multicall.add(pool, ICurveCryptoSwap::ACall {}, true);
+        let gamma = multicall.add(pool, ICurveCryptoSwap::gammaCall {}, true);
+        let d = multicall.add(pool, ICurveCryptoSwap::DCall {}, true);
+        let mid_fee = multicall.add(pool, ICurveCryptoSwap::mid_feeCall {}, true);
+        let out_fee = multicall.add(pool, ICurveCryptoSwap::out_feeCall {}, true);
+        let fee_gamma = multicall.add(pool, ICurveCryptoSwap::fee_gammaCall {}, true);
+        let results = multicall.call(provider, block).await?;
+
+        let not_found = |_| CurveError::PoolNotFound(pool);
+        let price_scale = price_scale
+            .into_iter()
+            .map(|index| match results.raw(index) {
+                Some((true, data)) if data.len() == 32 => Ok(U256::from_be_slice(data)),
+                _ => Err(CurveError::PoolNotFound(pool)),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            address: pool,
+            balances: balances
+                .iter()
+                .map(|balance| Ok(results.get(balance).map_err(not_found)?._0))
+                .collect::<Result<_>>()?,
+            precisions: decimals.iter().map(|decimals| pow10(18 - decimals)).collect(),
+            coins,
+            price_scale,
+            a: results.get(&a).map_err(not_found)?._0,
+            gamma: results.get(&gamma).map_err(not_found)?._0,
+            d: results.get(&d).map_err(not_found)?._0,
+            mid_fee: results.get(&mid_fee).map_err(not_found)?._0,
+            out_fee: results.get(&out_fee).map_err(not_found)?._0,
+            fee_gamma: results.get(&fee_gamma).map_err(not_found)?._0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, hex, uint};
+
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+    const WBTC: Address = address!("2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const CRV: Address = address!("D533a949740bb3306d119CC777fa900bA034cd52");
+
+    // tricrypto2 parameters at a balanced 30M USDT / 1000 WBTC / 15000 WETH state
+    fn tricrypto() -> CryptoSwapPool {
+        let mut pool = CryptoSwapPool {
+            address: address!("D51a44d3FaE010294C616388b506AcdA1bfAAE46"),
+            coins: vec![USDT, WBTC, WETH],
+            balances: vec![
+                pow10(6) * U256::from(30_000_000),
+                pow10(8) * U256::from(1000),
+                pow10(18) * U256::from(15_000),
+            ],
+            precisions: vec![pow10(12), pow10(10), U256::from(1)],
+            price_scale: vec![PRECISION * U256::from(30_000), PRECISION * U256::from(2000)],
+            a: U256::from(1_707_629),
+            gamma: U256::from(11_809_167_828_997u64),
+            d: U256::ZERO,
+            mid_fee: U256::from(3_000_000),
+            out_fee: U256::from(30_000_000),
+            fee_gamma: U256::from(500_000_000_000_000u64),
+        };
+        pool.d = pool.compute_d().unwrap();
+        pool
+    }
+
+    // ETH/CRV parameters, holding more CRV than its price scale balances
+    fn twocrypto() -> CryptoSwapPool {
+        let mut pool = CryptoSwapPool {
+            address: address!("8301AE4fc9c624d1D396cbDAa1ed877821D7C511"),
+            coins: vec![WETH, CRV],
+            balances: vec![pow10(18) * U256::from(5000), pow10(18) * U256::from(20_000_000)],
+            precisions: vec![U256::from(1), U256::from(1)],
+            price_scale: vec![U256::from(500_000_000_000_000u64)],
+            a: U256::from(400_000),
+            gamma: U256::from(145_000_000_000_000u64),
+            d: U256::ZERO,
+            mid_fee: U256::from(26_000_000),
+            out_fee: U256::from(45_000_000),
+            fee_gamma: U256::from(230_000_000_000_000u64),
+        };
+        pool.d = pool.compute_d().unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_tricrypto() {
+        let pool = tricrypto();
+        assert_eq!(pool.d, PRECISION * U256::from(90_000_000));
+        // 1000 USDT for 0.4998 ETH, 10 ETH for 0.666 WBTC
+        assert_eq!(
+            pool.get_dy(0, 2, pow10(6) * U256::from(1000)).unwrap(),
+            U256::from(499_849_709_335_779_495u64)
+        );
+        assert_eq!(
+            pool.get_dy(2, 1, PRECISION * U256::from(10)).unwrap(),
+            U256::from(66_645_683)
+        );
+        // at the peg the fee is the mid fee
+        assert_eq!(pool.fee(&pool.xp(&pool.balances).unwrap()), pool.mid_fee);
+        assert!(matches!(
+            pool.get_dy(0, 0, U256::from(1)),
+            Err(CurveError::InvalidIndex(0))
+        ));
+    }
+
+    #[test]
+    fn test_twocrypto() {
+        let pool = twocrypto();
+        assert_eq!(pool.d, uint!(14167442725213394667600_U256));
+        assert_eq!(
+            pool.get_dy(0, 1, PRECISION).unwrap(),
+            uint!(3952367700456121163532_U256)
+        );
+        assert_eq!(
+            pool.get_dy(1, 0, PRECISION * U256::from(2000)).unwrap(),
+            U256::from(501_335_029_843_714_938u64)
+        );
+        let fee = pool.fee(&pool.xp(&pool.balances).unwrap());
+        assert!(fee > pool.mid_fee && fee < pool.out_fee);
+
+        // balances more than 100x off the invariant are refused like the pool does
+        let drained = CryptoSwapPool {
+            balances: vec![PRECISION, pool.balances[1]],
+            ..pool.clone()
+        };
+        assert!(matches!(drained.compute_d(), Err(CurveError::MathError(_))));
+
+        // amounts the pool would revert on are errors, not wrapped quotes
+        assert!(matches!(pool.get_dy(0, 1, U256::MAX), Err(CurveError::MathError(_))));
+        assert!(matches!(
+            newton_d(pool.a, pool.gamma, &[U256::MAX / U256::from(2), U256::MAX / U256::from(2)]),
+            Err(CurveError::MathError(_))
+        ));
+    }
+
+    #[test]
+    fn test_exchange_calldata() {
+        let pool = tricrypto();
+        let tx = pool.exchange(0, 2, U256::from(1_000_000), U256::ZERO).unwrap();
+        let input = tx.input.input().unwrap();
+        assert_eq!(input[..4], hex!("5b41b908"));
+        assert_eq!(tx.value, None);
+
+        let tx = pool
+            .exchange_underlying(2, 0, PRECISION, U256::from(1_900_000_000), WETH)
+            .unwrap();
+        assert_eq!(tx.value, Some(PRECISION));
+        let call = ICurveCryptoSwap::exchange_underlyingCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(
+            (call.i, call.j, call.min_dy),
+            (U256::from(2), U256::ZERO, U256::from(1_900_000_000))
+        );
+        assert!(pool.exchange(3, 0, PRECISION, U256::ZERO).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+pub mod crypto;
+pub mod stable;
+
+use alloy_eips::BlockId;
+use alloy_primitives::{address, Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+
+use crate::abis::curve::ICurveMetaRegistry;
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+use crate::provider::transport::ProviderError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CurveError {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Math error: {0}")]
+    MathError(String),
+    #[error("Invalid coin index: {0}")]
+    InvalidIndex(usize),
+    #[error("Invalid pool: {0}")]
+    InvalidPool(String),
+    #[error("Insufficient liquidity")]
+    InsufficientLiquidity,
+    #[error("{0} did not converge")]
+    NotConverged(&'static str),
+    #[error("Pool not found: {0}")]
+    PoolNotFound(Address),
+}
+
+type Result<T> = std::result::Result<T, CurveError>;
+
+/// Placeholder address Curve pools use for native ETH.
+pub const ETH: Address = address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+// https://docs.curve.fi/registry/MetaRegistryAPI/
+pub const META_REGISTRY: Address = address!("F98B45FA17DE75FB1aD0e7aFD971b0ca00e379fC");
+
+/// Fees are over 1e10, 4_000_000 is 0.04%.
+pub const FEE_DENOMINATOR: U256 = U256::from_limbs([10_000_000_000, 0, 0, 0]);
+
+pub const PRECISION: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+
+/// Newton's method iteration limit of the Vyper implementations.
+const MAX_ITERATIONS: usize = 255;
+
+/// `10^exp`, the precision multipliers and rates of coins with `exp` missing decimals.
+fn pow10(exp: u8) -> U256 {
+    U256::from(10).pow(U256::from(exp))
+}
+
+fn check_indices(i: usize, j: usize, n_coins: usize) -> Result<()> {
+    match (i, j) {
+        (i, _) if i >= n_coins => Err(CurveError::InvalidIndex(i)),
+        (_, j) if j >= n_coins || j == i => Err(CurveError::InvalidIndex(j)),
+        _ => Ok(()),
+    }
+}
+
+fn math_error(reason: &str) -> CurveError {
+    CurveError::MathError(reason.to_string())
+}
+
+// checked arithmetic, an error where the Vyper pools revert instead of a wrapped result
+
+fn add(a: U256, b: U256) -> Result<U256> {
+    a.checked_add(b).ok_or_else(|| math_error("overflow"))
+}
+
+fn mul(a: U256, b: U256) -> Result<U256> {
+    a.checked_mul(b).ok_or_else(|| math_error("overflow"))
+}
+
+fn div(a: U256, b: U256) -> Result<U256> {
+    a.checked_div(b).ok_or_else(|| math_error("division by zero"))
+}
+
+/// `a * b / c`, rounded down like the Vyper code.
+fn mul_div(a: U256, b: U256, c: U256) -> Result<U256> {
+    div(mul(a, b)?, c)
+}
+
+/// Swap transaction to `pool`, sending `dx` along when the input coin is native ETH.
+fn pool_request(pool: Address, input: Vec<u8>, coin_in: Address, dx: U256) -> TransactionRequest {
+    let tx = TransactionRequest::default().to(pool).input(Bytes::from(input).into());
+    match coin_in == ETH {
+        true => tx.value(dx),
+        false => tx,
+    }
+}
+
+/// Pools listed in the Curve `registry` that trade `from` for `to`.
+pub async fn find_pools(
+    provider: &EvmProvider,
+    registry: Address,
+    from: Address,
+    to: Address,
+    block: BlockId,
+) -> Result<Vec<Address>> {
+    let mut multicall = Multicall::new();
+    let pools = multicall.add(
+        registry,
+        ICurveMetaRegistry::find_pools_for_coinsCall { _from: from, _to: to },
+        true,
+    );
+    let results = multicall.call(provider, block).await?;
+    let pools = results.get(&pools).map_err(|_| CurveError::PoolNotFound(registry))?._0;
+    Ok(pools.into_iter().filter(|pool| !pool.is_zero()).collect())
+}
+
+/// Indices of `from` and `to` in `pool`, and whether they are underlying coins to trade with
+/// `exchange_underlying`.
+pub async fn coin_indices(
+    provider: &EvmProvider,
+    registry: Address,
+    pool: Address,
+    from: Address,
+    to: Address,
+    block: BlockId,
+) -> Result<(usize, usize, bool)> {
+    let mut multicall = Multicall::new();
+    let call = ICurveMetaRegistry::get_coin_indicesCall {
+        _pool: pool,
+        _from: from,
+        _to: to,
+    };
+    let indices = multicall.add(registry, call, true);
+    let results = multicall.call(provider, block).await?;
+    let indices = results.get(&indices).map_err(|_| CurveError::PoolNotFound(pool))?;
+    let index = |index: i128| usize::try_from(index).map_err(|_| CurveError::InvalidPool(format!("index {index}")));
+    Ok((index(indices._0)?, index(indices._1)?, indices._2))
+}
+
+/// The coins of `pool` listed in `registry` and their decimals.
+async fn registry_coins(
+    provider: &EvmProvider,
+    registry: Address,
+    pool: Address,
+    block: BlockId,
+) -> Result<(Vec<Address>, Vec<u8>)> {
+    let mut multicall = Multicall::new();
+    let n_coins = multicall.add(registry, ICurveMetaRegistry::get_n_coinsCall { _pool: pool }, true);
+    let coins = multicall.add(registry, ICurveMetaRegistry::get_coinsCall { _pool: pool }, true);
+    let decimals = multicall.add(registry, ICurveMetaRegistry::get_decimalsCall { _pool: pool }, true);
+    let results = multicall.call(provider, block).await?;
+
+    let not_found = |_| CurveError::PoolNotFound(pool);
+    let n_coins = results.get(&n_coins).map_err(not_found)?._0;
+    let n_coins = match u64::try_from(n_coins) {
+        Ok(n @ 2..=8) => n as usize,
+        _ => return Err(CurveError::PoolNotFound(pool)),
+    };
+    let coins = results.get(&coins).map_err(not_found)?._0[..n_coins].to_vec();
+    let decimals = results.get(&decimals).map_err(not_found)?._0[..n_coins]
+        .iter()
+        .map(|decimals| match u8::try_from(*decimals) {
+            Ok(decimals @ 0..=18) => Ok(decimals),
+            _ => Err(CurveError::InvalidPool(format!("{decimals} decimals"))),
+        })
+        .collect::<Result<_>>()?;
+    Ok((coins, decimals))
+}
@@ -0,0 +1,698 @@
+//! StableSwap pools, ports of the Vyper `get_D`, `get_y` and `get_y_D` and the views of plain,
+//! lending and meta pools built on them.
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+
+use crate::abis::curve::{ICurveMetaRegistry, ICurveStableSwap};
+use crate::dexes::curve::{
+    add, check_indices, div, math_error, mul, mul_div, pool_request, pow10, registry_coins, CurveError, Result,
+    FEE_DENOMINATOR, MAX_ITERATIONS, PRECISION,
+};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+
+/// `A_PRECISION` of every pool newer than the first few (3pool, compound, y, busd, ...).
+pub const A_PRECISION: u64 = 100;
+
+/// The invariant `D` of normalized balances `xp`, `amp` being `A * a_precision`.
+pub fn get_d(xp: &[U256], amp: U256, a_precision: U256) -> Result<U256> {
+    let n = U256::from(xp.len());
+    let sum = xp.iter().try_fold(U256::ZERO, |sum, x| add(sum, *x))?;
+    if sum.is_zero() {
+        return Ok(U256::ZERO);
+    }
+    if xp.iter().any(|x| x.is_zero()) {
+        return Err(CurveError::InsufficientLiquidity);
+    }
+    let ann = mul(amp, n)?;
+    if ann <= a_precision {
+        return Err(math_error("amplification too low"));
+    }
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        for x in xp {
+            d_p = mul_div(d_p, d, mul(*x, n)?)?;
+        }
+        let prev = d;
+        let numerator = mul(add(mul_div(ann, sum, a_precision)?, mul(d_p, n)?)?, d)?;
+        let denominator = add(mul_div(ann - a_precision, d, a_precision)?, mul(n + U256::from(1), d_p)?)?;
+        d = div(numerator, denominator)?;
+        if d.abs_diff(prev) <= U256::from(1) {
+            return Ok(d);
+        }
+    }
+    Err(CurveError::NotConverged("get_D"))
+}
+
+/// The balance of coin `j` that keeps `D` unchanged once coin `i` is `x`, all normalized.
+pub fn get_y(i: usize, j: usize, x: U256, xp: &[U256], amp: U256, a_precision: U256) -> Result<U256> {
+    check_indices(i, j, xp.len())?;
+    let d = get_d(xp, amp, a_precision)?;
+    let others = xp.iter().enumerate().filter_map(|(k, balance)| match k {
+        k if k == i => Some(x),
+        k if k == j => None,
+        _ => Some(*balance),
+    });
+    solve_y(others, d, amp, a_precision, xp.len())
+}
+
+/// The balance of coin `i` for which the invariant of `xp` is `d`.
+pub fn get_y_d(amp: U256, a_precision: U256, i: usize, xp: &[U256], d: U256) -> Result<U256> {
+    if i >= xp.len() {
+        return Err(CurveError::InvalidIndex(i));
+    }
+    let others = xp.iter().enumerate().filter(|(k, _)| *k != i).map(|(_, x)| *x);
+    solve_y(others, d, amp, a_precision, xp.len())
+}
+
+fn solve_y(others: impl Iterator<Item = U256>, d: U256, amp: U256, a_precision: U256, n_coins: usize) -> Result<U256> {
+    let n = U256::from(n_coins);
+    let ann = mul(amp, n)?;
+    if ann.is_zero() {
+        return Err(math_error("zero amplification"));
+    }
+    let (mut c, mut sum) = (d, U256::ZERO);
+    for x in others {
+        if x.is_zero() {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        sum = add(sum, x)?;
+        c = mul_div(c, d, mul(x, n)?)?;
+    }
+    c = mul_div(mul(c, d)?, a_precision, mul(ann, n)?)?;
+    let b = add(sum, mul_div(d, a_precision, ann)?)?;
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let prev = y;
+        let denominator = add(mul(y, U256::from(2))?, b)?
+            .checked_sub(d)
+            .filter(|denominator| !denominator.is_zero())
+            .ok_or_else(|| math_error("get_y denominator"))?;
+        y = add(mul(y, y)?, c)? / denominator;
+        if y.abs_diff(prev) <= U256::from(1) {
+            return Ok(y);
+        }
+    }
+    Err(CurveError::NotConverged("get_y"))
+}
+
+/// Rates of plain coins, `10^(36 - decimals)`.
+pub fn rates_for_decimals(decimals: &[u8]) -> Vec<U256> {
+    decimals.iter().map(|decimals| pow10(36 - decimals.min(&18))).collect()
+}
+
+/// State of a StableSwap pool, enough to quote it offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StableSwapPool {
+    pub address: Address,
+    pub coins: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// `10^(36 - decimals)` for plain coins, times the exchange rate of wrapped coins and the
+    /// virtual price of the base pool LP token of meta pools.
+    pub rates: Vec<U256>,
+    /// `A * a_precision`, what `A_precise()` returns on pools that have it.
+    pub amp: U256,
+    pub a_precision: U256,
+    /// Swap fee over [`FEE_DENOMINATOR`].
+    pub fee: U256,
+    /// Supply of the pool LP token.
+    pub total_supply: U256,
+}
+
+impl StableSwapPool {
+    /// Balances normalized to 18 decimals at the pool rates.
+    pub fn xp(&self) -> Result<Vec<U256>> {
+        xp(&self.balances, &self.rates)
+    }
+
+    pub fn d(&self) -> Result<U256> {
+        get_d(&self.xp()?, self.amp, self.a_precision)
+    }
+
+    /// Value of an LP token in normalized coins, `get_virtual_price`.
+    pub fn virtual_price(&self) -> Result<U256> {
+        if self.total_supply.is_zero() {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        Ok(self.d()? * PRECISION / self.total_supply)
+    }
+
+    /// Output of swapping `dx` of coin `i` for coin `j`, after fees, `get_dy`.
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Result<U256> {
+        check_indices(i, j, self.coins.len())?;
+        let xp = self.xp()?;
+        let x = add(xp[i], mul_div(dx, self.rates[i], PRECISION)?)?;
+        let y = get_y(i, j, x, &xp, self.amp, self.a_precision)?;
+        let dy = out_amount(xp[j], y)? * PRECISION / self.rates[j];
+        Ok(dy - self.fee * dy / FEE_DENOMINATOR)
+    }
+
+    /// LP tokens minted by depositing (or burned by withdrawing) `amounts`, without fees,
+    /// `calc_token_amount`.
+    pub fn calc_token_amount(&self, amounts: &[U256], deposit: bool) -> Result<U256> {
+        if amounts.len() != self.balances.len() {
+            return Err(CurveError::InvalidPool(format!("{} amounts", amounts.len())));
+        }
+        let d0 = self.d()?;
+        let balances = self
+            .balances
+            .iter()
+            .zip(amounts)
+            .map(|(balance, amount)| match deposit {
+                true => Ok(balance + amount),
+                false => balance.checked_sub(*amount).ok_or(CurveError::InsufficientLiquidity),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let d1 = get_d(&xp(&balances, &self.rates)?, self.amp, self.a_precision)?;
+        if d0.is_zero() {
+            return Ok(d1);
+        }
+        Ok(d1.abs_diff(d0) * self.total_supply / d0)
+    }
+
+    /// Coin `i` received for burning `amount` LP tokens, after fees, `calc_withdraw_one_coin`
+    /// of plain pools.
+    pub fn calc_withdraw_one_coin(&self, amount: U256, i: usize) -> Result<U256> {
+        let n_coins = self.coins.len();
+        if i >= n_coins {
+            return Err(CurveError::InvalidIndex(i));
+        }
+        if amount > self.total_supply || self.total_supply.is_zero() {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        let xp = self.xp()?;
+        let d0 = get_d(&xp, self.amp, self.a_precision)?;
+        let d1 = d0 - amount * d0 / self.total_supply;
+        let new_y = get_y_d(self.amp, self.a_precision, i, &xp, d1)?;
+
+        // imbalanced withdrawals pay half the swap fee on the coins they skew
+        let fee = self.fee * U256::from(n_coins) / U256::from(4 * (n_coins - 1));
+        let mut reduced = xp.clone();
+        for (k, x) in xp.iter().enumerate() {
+            let expected = match k == i {
+                true => (x * d1 / d0).saturating_sub(new_y),
+                false => x - x * d1 / d0,
+            };
+            reduced[k] -= fee * expected / FEE_DENOMINATOR;
+        }
+        let dy = out_amount(reduced[i], get_y_d(self.amp, self.a_precision, i, &reduced, d1)?)?;
+        Ok(dy * PRECISION / self.rates[i])
+    }
+
+    /// `exchange` transaction swapping `dx` of coin `i` for at least `min_dy` of coin `j`.
+    pub fn exchange(&self, i: usize, j: usize, dx: U256, min_dy: U256) -> Result<TransactionRequest> {
+        check_indices(i, j, self.coins.len())?;
+        let input = ICurveStableSwap::exchangeCall {
+            i: i as i128,
+            j: j as i128,
+            dx,
+            min_dy,
+        }
+        .abi_encode();
+        Ok(pool_request(self.address, input, self.coins[i], dx))
+    }
+
+    /// Reads the state of `pool` at `block`, with its coins and LP token from `registry`.
+    ///
+    /// Rates come from `stored_rates()` where the pool has it (stableswap-ng) and from the coin
+    /// decimals otherwise, so the rates of older lending pools have to be set by the caller.
+    pub async fn fetch(provider: &EvmProvider, registry: Address, pool: Address, block: BlockId) -> Result<Self> {
+        let (coins, decimals) = registry_coins(provider, registry, pool, block).await?;
+        let mut multicall = Multicall::new();
+        let balances: Vec<_> = (0..coins.len())
+            .map(|i| multicall.add(pool, ICurveStableSwap::balancesCall { i: U256::from(i) }, true))
+            .collect();
+        let a = multicall.add(pool, ICurveStableSwap::ACall {}, true);
+        let a_precise = multicall.add(pool, ICurveStableSwap::A_preciseCall {}, true);
+        let fee = multicall.add(pool, ICurveStableSwap::feeCall {}, true);
+        let stored_rates = multicall.add(pool, ICurveStableSwap::stored_ratesCall {}, true);
+        let lp_token = multicall.add(registry, ICurveMetaRegistry::get_lp_tokenCall { _pool: pool }, true);
+        let results = multicall.call(provider, block).await?;
+
+        let not_found = |_| CurveError::PoolNotFound(pool);
+        let a = results.get(&a).map_err(not_found)?._0;
+        let (amp, a_precision) = match results.get(&a_precise) {
+            Ok(precise) if precise._0 > a => (precise._0, U256::from(A_PRECISION)),
+            _ => (a, U256::from(1)),
+        };
+        let rates = match results.get(&stored_rates) {
+            Ok(rates) if rates._0.len() == coins.len() => rates._0,
+            _ => rates_for_decimals(&decimals),
+        };
+        let lp_token = results.get(&lp_token).map_err(not_found)?._0;
+
+        let mut multicall = Multicall::new();
+        let total_supply = multicall.add(lp_token, ICurveStableSwap::totalSupplyCall {}, true);
+        let supply = multicall.call(provider, block).await?;
+        Ok(Self {
+            address: pool,
+            balances: balances
+                .iter()
+                .map(|balance| Ok(results.get(balance).map_err(not_found)?._0))
+                .collect::<Result<_>>()?,
+            coins,
+            rates,
+            amp,
+            a_precision,
+            fee: results.get(&fee).map_err(not_found)?._0,
+            total_supply: supply.get(&total_supply).map_err(not_found)?._0,
+        })
+    }
+}
+
+fn xp(balances: &[U256], rates: &[U256]) -> Result<Vec<U256>> {
+    balances
+        .iter()
+        .zip(rates)
+        .map(|(balance, rate)| mul_div(*balance, *rate, PRECISION))
+        .collect()
+}
+
+/// `balance - y - 1`, the output before fees, rounded down by a wei in favor of the pool.
+fn out_amount(balance: U256, y: U256) -> Result<U256> {
+    balance
+        .checked_sub(y)
+        .and_then(|dy| dy.checked_sub(U256::from(1)))
+        .ok_or(CurveError::InsufficientLiquidity)
+}
+
+/// A lending pool (compound, aave, y, ...) holding wrapped coins, whose `rates` include the
+/// exchange rates of the wrapped coins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LendingPool {
+    pub pool: StableSwapPool,
+    pub underlying: Vec<Address>,
+    /// `10^(18 - decimals)` of the underlying coins.
+    pub precision_mul: Vec<U256>,
+}
+
+impl LendingPool {
+    pub fn new(pool: StableSwapPool, underlying: Vec<Address>, underlying_decimals: &[u8]) -> Result<Self> {
+        if underlying.len() != pool.coins.len() || underlying_decimals.len() != pool.coins.len() {
+            return Err(CurveError::InvalidPool(format!(
+                "{} coins and {} underlying",
+                pool.coins.len(),
+                underlying.len()
+            )));
+        }
+        let precision_mul = underlying_decimals
+            .iter()
+            .map(|decimals| pow10(18 - decimals.min(&18)))
+            .collect();
+        Ok(Self {
+            pool,
+            underlying,
+            precision_mul,
+        })
+    }
+
+    /// Output of swapping `dx` of underlying coin `i` for underlying coin `j`, after fees,
+    /// `get_dy_underlying`.
+    pub fn get_dy_underlying(&self, i: usize, j: usize, dx: U256) -> Result<U256> {
+        check_indices(i, j, self.underlying.len())?;
+        let pool = &self.pool;
+        let xp = pool.xp()?;
+        let x = xp[i] + dx * self.precision_mul[i];
+        let y = get_y(i, j, x, &xp, pool.amp, pool.a_precision)?;
+        let dy = out_amount(xp[j], y)? / self.precision_mul[j];
+        Ok(dy - pool.fee * dy / FEE_DENOMINATOR)
+    }
+
+    /// `exchange_underlying` transaction, wrapping and unwrapping the coins within the pool.
+    pub fn exchange_underlying(&self, i: usize, j: usize, dx: U256, min_dy: U256) -> Result<TransactionRequest> {
+        check_indices(i, j, self.underlying.len())?;
+        Ok(exchange_underlying(
+            self.pool.address,
+            self.underlying[i],
+            i,
+            j,
+            dx,
+            min_dy,
+        ))
+    }
+}
+
+/// A meta pool pairing a coin with the LP token of a base pool, tradable against the coins of
+/// the base pool through `exchange_underlying`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaPool {
+    pub pool: StableSwapPool,
+    pub base: StableSwapPool,
+}
+
+impl MetaPool {
+    /// Prices the base LP token of `pool` at the virtual price of `base`.
+    pub fn new(mut pool: StableSwapPool, base: StableSwapPool) -> Result<Self> {
+        if pool.coins.len() != 2 || pool.rates.len() != 2 {
+            return Err(CurveError::InvalidPool(format!("{} meta pool coins", pool.coins.len())));
+        }
+        pool.rates[1] = base.virtual_price()?;
+        Ok(Self { pool, base })
+    }
+
+    /// The meta coin followed by the base pool coins, the indices of `exchange_underlying`.
+    pub fn underlying(&self) -> Vec<Address> {
+        std::iter::once(self.pool.coins[0])
+            .chain(self.base.coins.iter().copied())
+            .collect()
+    }
+
+    /// Output of swapping `dx` of underlying coin `i` for underlying coin `j`, after fees,
+    /// `get_dy_underlying`.
+    pub fn get_dy_underlying(&self, i: usize, j: usize, dx: U256) -> Result<U256> {
+        check_indices(i, j, self.base.coins.len() + 1)?;
+        let (pool, base) = (&self.pool, &self.base);
+        let (rates, xp) = (&pool.rates, pool.xp()?);
+        let x = match (i, j) {
+            (0, _) => xp[0] + dx * rates[0] / PRECISION,
+            (_, 0) => {
+                let mut amounts = vec![U256::ZERO; base.coins.len()];
+                amounts[i - 1] = dx;
+                // the base pool deposit pays about half a swap fee
+                let x = base.calc_token_amount(&amounts, true)? * rates[1] / PRECISION;
+                x - x * base.fee / (U256::from(2) * FEE_DENOMINATOR) + xp[1]
+            }
+            _ => return base.get_dy(i - 1, j - 1, dx),
+        };
+        let (meta_i, meta_j) = (i.min(1), j.min(1));
+        let y = get_y(meta_i, meta_j, x, &xp, pool.amp, pool.a_precision)?;
+        let dy = out_amount(xp[meta_j], y)?;
+        let dy = dy - pool.fee * dy / FEE_DENOMINATOR;
+        match j {
+            0 => Ok(dy * PRECISION / rates[0]),
+            _ => base.calc_withdraw_one_coin(dy * PRECISION / rates[1], j - 1),
+        }
+    }
+
+    /// `exchange_underlying` transaction, depositing into or withdrawing from the base pool as
+    /// needed.
+    pub fn exchange_underlying(&self, i: usize, j: usize, dx: U256, min_dy: U256) -> Result<TransactionRequest> {
+        let underlying = self.underlying();
+        check_indices(i, j, underlying.len())?;
+        Ok(exchange_underlying(self.pool.address, underlying[i], i, j, dx, min_dy))
+    }
+
+    /// Reads the state of meta `pool` and its base pool at `block`.
+    pub async fn fetch(provider: &EvmProvider, registry: Address, pool: Address, block: BlockId) -> Result<Self> {
+        let mut multicall = Multicall::new();
+        let base = multicall.add(registry, ICurveMetaRegistry::get_base_poolCall { _pool: pool }, true);
+        let results = multicall.call(provider, block).await?;
+        let base = results.get(&base).map_err(|_| CurveError::PoolNotFound(pool))?._0;
+        if base.is_zero() {
+            return Err(CurveError::InvalidPool(format!("{pool} is not a meta pool")));
+        }
+        let (pool, base) = tokio::try_join!(
+            StableSwapPool::fetch(provider, registry, pool, block),
+            StableSwapPool::fetch(provider, registry, base, block)
+        )?;
+        Self::new(pool, base)
+    }
+}
+
+fn exchange_underlying(
+    pool: Address,
+    coin_in: Address,
+    i: usize,
+    j: usize,
+    dx: U256,
+    min_dy: U256,
+) -> TransactionRequest {
+    let input = ICurveStableSwap::exchange_underlyingCall {
+        i: i as i128,
+        j: j as i128,
+        dx,
+        min_dy,
+    }
+    .abi_encode();
+    pool_request(pool, input, coin_in, dx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::curve::META_REGISTRY;
+    use crate::provider::mock::MockNode;
+    use alloy_primitives::{address, hex, uint};
+    use alloy_sol_types::SolValue;
+
+    const THREE_POOL: Address = address!("bEbc44782C7dB0a1A60Cb6fe97d0b483032FF1C7");
+    const THREE_CRV: Address = address!("6c3F90f043a72FA612cbac8115EE7e52BDe6E490");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+    const LUSD: Address = address!("5f98805A4E8be255a32880FDeC7F6728C6568bA0");
+
+    fn units(amount: u64, decimals: u8) -> U256 {
+        U256::from(amount) * pow10(decimals)
+    }
+
+    // 3pool with A = 2000 and a 0.01% fee, at a 100M DAI / 110M USDC / 90M USDT state
+    fn three_pool() -> StableSwapPool {
+        StableSwapPool {
+            address: THREE_POOL,
+            coins: vec![DAI, USDC, USDT],
+            balances: vec![units(100_000_000, 18), units(110_000_000, 6), units(90_000_000, 6)],
+            rates: rates_for_decimals(&[18, 6, 6]),
+            amp: U256::from(2000),
+            a_precision: U256::from(1),
+            fee: U256::from(1_000_000),
+            total_supply: units(290_000_000, 18),
+        }
+    }
+
+    #[test]
+    fn test_plain_pool() {
+        let pool = three_pool();
+        assert_eq!(pool.d().unwrap(), uint!(299999495205290737126766893_U256));
+        assert_eq!(pool.virtual_price().unwrap(), U256::from(1_034_481_017_949_278_403u64));
+        assert_eq!(pool.get_dy(0, 1, units(1000, 18)).unwrap(), U256::from(999_945_883));
+        assert_eq!(
+            pool.get_dy(2, 0, units(1_000_000, 6)).unwrap(),
+            uint!(999950423513095361860878_U256)
+        );
+        assert_eq!(
+            pool.calc_token_amount(&[units(1000, 18), U256::ZERO, U256::ZERO], true)
+                .unwrap(),
+            U256::from(966_666_665_040_123_181_142u128)
+        );
+        assert_eq!(
+            pool.calc_withdraw_one_coin(units(1000, 18), 1).unwrap(),
+            U256::from(1_034_481_090)
+        );
+
+        // a balanced pool trades at par minus the fee
+        let balanced = StableSwapPool {
+            balances: vec![units(1_000_000, 18), units(1_000_000, 6), units(1_000_000, 6)],
+            ..pool.clone()
+        };
+        assert_eq!(balanced.d().unwrap(), units(3_000_000, 18));
+        assert_eq!(balanced.get_dy(1, 2, units(1, 6)).unwrap(), U256::from(999_900));
+
+        assert!(matches!(
+            pool.get_dy(1, 1, U256::from(1)),
+            Err(CurveError::InvalidIndex(1))
+        ));
+        assert!(matches!(
+            pool.get_dy(0, 3, U256::from(1)),
+            Err(CurveError::InvalidIndex(3))
+        ));
+        assert!(pool.get_dy(1, 2, units(200_000_000, 6)).unwrap() < pool.balances[2]);
+        let drained = StableSwapPool {
+            balances: vec![units(1, 18), U256::ZERO, units(1, 6)],
+            ..pool
+        };
+        assert!(matches!(
+            drained.get_dy(0, 1, U256::from(1)),
+            Err(CurveError::InsufficientLiquidity)
+        ));
+
+        // where Vyper reverts on overflow the quote fails instead of wrapping
+        let pool = three_pool();
+        assert!(matches!(pool.get_dy(0, 1, U256::MAX), Err(CurveError::MathError(_))));
+        let huge = [U256::MAX / U256::from(2), U256::MAX / U256::from(2)];
+        assert!(matches!(get_d(&huge, pool.amp, pool.a_precision), Err(CurveError::MathError(_))));
+        assert!(matches!(
+            get_y_d(pool.amp, pool.a_precision, 0, &huge, U256::MAX),
+            Err(CurveError::MathError(_))
+        ));
+    }
+
+    // LUSD/3CRV with A = 200 and a 0.04% fee
+    fn lusd_pool() -> MetaPool {
+        let pool = StableSwapPool {
+            address: address!("Ed279fDD11cA84bEef15AF5D39BB4d4bEE23F0cA"),
+            coins: vec![LUSD, THREE_CRV],
+            balances: vec![units(50_000_000, 18), units(48_000_000, 18)],
+            rates: rates_for_decimals(&[18, 18]),
+            amp: U256::from(200 * A_PRECISION),
+            a_precision: U256::from(A_PRECISION),
+            fee: U256::from(4_000_000),
+            total_supply: units(99_000_000, 18),
+        };
+        MetaPool::new(pool, three_pool()).unwrap()
+    }
+
+    #[test]
+    fn test_meta_pool() {
+        let meta = lusd_pool();
+        assert_eq!(meta.pool.rates[1], U256::from(1_034_481_017_949_278_403u64));
+        assert_eq!(meta.underlying(), vec![LUSD, DAI, USDC, USDT]);
+        assert_eq!(
+            meta.pool.get_dy(0, 1, units(1000, 18)).unwrap(),
+            U256::from(966_248_252_119_053_948_615u128)
+        );
+        // LUSD to USDC withdraws from 3pool, DAI to LUSD deposits into it
+        assert_eq!(
+            meta.get_dy_underlying(0, 2, units(1000, 18)).unwrap(),
+            U256::from(999_565_545)
+        );
+        assert_eq!(
+            meta.get_dy_underlying(1, 0, units(1000, 18)).unwrap(),
+            U256::from(999_582_660_770_989_275_893u128)
+        );
+        // both coins in the base pool is a base pool swap
+        assert_eq!(
+            meta.get_dy_underlying(2, 3, units(1000, 6)).unwrap(),
+            U256::from(999_798_033)
+        );
+        assert_eq!(
+            meta.get_dy_underlying(2, 3, units(1000, 6)).unwrap(),
+            meta.base.get_dy(1, 2, units(1000, 6)).unwrap()
+        );
+        assert!(matches!(
+            meta.get_dy_underlying(0, 4, U256::from(1)),
+            Err(CurveError::InvalidIndex(4))
+        ));
+    }
+
+    #[test]
+    fn test_lending_pool() {
+        // compound style cDAI/cUSDC with 8 decimals cTokens
+        let pool = StableSwapPool {
+            address: address!("A2B47E3D5c44877cca798226B7B8118F9BFb7A56"),
+            coins: vec![
+                address!("5d3a536E4D6DbD6114cc1Ead35777bAB948E3643"),
+                address!("39AA39c021dfbaE8faC545936693aC917d5E7563"),
+            ],
+            balances: vec![units(2_000_000_000, 8), units(1_900_000_000, 8)],
+            rates: vec![
+                uint!(220000000000000000000000000_U256),
+                uint!(225000000000000000000000000_U256),
+            ],
+            amp: U256::from(4500),
+            a_precision: U256::from(1),
+            fee: U256::from(4_000_000),
+            total_supply: units(42_000_000, 18),
+        };
+        let lending = LendingPool::new(pool, vec![DAI, USDC], &[18, 6]).unwrap();
+        assert_eq!(lending.pool.xp().unwrap(), vec![units(44_000_000, 18), units(42_750_000, 18)]);
+        assert_eq!(
+            lending.pool.get_dy(0, 1, units(50_000, 8)).unwrap(),
+            U256::from(4_886_902_003_423u64)
+        );
+        assert_eq!(
+            lending.get_dy_underlying(0, 1, units(1000, 18)).unwrap(),
+            U256::from(999_593_592)
+        );
+        assert!(LendingPool::new(lending.pool.clone(), vec![DAI], &[18]).is_err());
+
+        // withdrawn cTokens are worth the virtual price of the burned LP tokens, minus fees
+        let lp = units(1000, 18);
+        let worth = lp * lending.pool.virtual_price().unwrap() / PRECISION;
+        for (i, rate) in lending.pool.rates.iter().enumerate() {
+            let value = lending.pool.calc_withdraw_one_coin(lp, i).unwrap() * rate / PRECISION;
+            assert!(value < worth && value > worth * U256::from(999) / U256::from(1000));
+        }
+
+        // a rate below 1e18 used to truncate to zero
+        let discounted = StableSwapPool {
+            rates: vec![U256::from(5) * pow10(17), PRECISION],
+            balances: vec![units(88_000_000, 18), units(44_000_000, 18)],
+            ..lending.pool.clone()
+        };
+        let dy = discounted.calc_withdraw_one_coin(lp, 0).unwrap();
+        assert!(dy * discounted.rates[0] / PRECISION > units(999, 18));
+    }
+
+    #[test]
+    fn test_exchange_calldata() {
+        let pool = three_pool();
+        let tx = pool.exchange(0, 1, units(1000, 18), U256::from(999_000_000)).unwrap();
+        assert_eq!(tx.to, Some(THREE_POOL.into()));
+        assert_eq!(tx.value, None);
+        let input = tx.input.input().unwrap();
+        assert_eq!(input[..4], hex!("3df02124"));
+        let call = ICurveStableSwap::exchangeCall::abi_decode(input, true).unwrap();
+        assert_eq!((call.i, call.j, call.min_dy), (0, 1, U256::from(999_000_000)));
+
+        let meta = lusd_pool();
+        let tx = meta.exchange_underlying(0, 3, units(1000, 18), U256::ZERO).unwrap();
+        let input = tx.input.input().unwrap();
+        assert_eq!(input[..4], hex!("a6417ed6"));
+        let call = ICurveStableSwap::exchange_underlyingCall::abi_decode(input, true).unwrap();
+        assert_eq!((call.i, call.j, call.dx), (0, 3, units(1000, 18)));
+        assert!(meta.exchange_underlying(4, 0, U256::from(1), U256::ZERO).is_err());
+
+        // the stETH pool takes ETH as coin 0
+        let steth = StableSwapPool {
+            coins: vec![
+                crate::dexes::curve::ETH,
+                address!("ae7ab96520DE3A18E5e111B5EaAb095312D7fE84"),
+            ],
+            ..three_pool()
+        };
+        let tx = steth.exchange(0, 1, units(1, 18), U256::ZERO).unwrap();
+        assert_eq!(tx.value, Some(units(1, 18)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let node = MockNode::multicall(|call| {
+            let data = &call.callData;
+            let selector: [u8; 4] = data[..4].try_into().unwrap();
+            let word = |value: U256| Some(value.abi_encode());
+            match selector {
+                ICurveMetaRegistry::get_n_coinsCall::SELECTOR => word(U256::from(3)),
+                ICurveMetaRegistry::get_coinsCall::SELECTOR => {
+                    let mut coins = [Address::ZERO; 8];
+                    coins[..3].copy_from_slice(&[DAI, USDC, USDT]);
+                    Some(coins.abi_encode())
+                }
+                ICurveMetaRegistry::get_decimalsCall::SELECTOR => {
+                    let mut decimals = [U256::ZERO; 8];
+                    decimals[..3].copy_from_slice(&[U256::from(18), U256::from(6), U256::from(6)]);
+                    Some(decimals.abi_encode())
+                }
+                ICurveMetaRegistry::get_lp_tokenCall::SELECTOR => Some(THREE_CRV.abi_encode()),
+                ICurveStableSwap::balancesCall::SELECTOR => {
+                    let i = ICurveStableSwap::balancesCall::abi_decode(data, true).unwrap().i;
+                    word(three_pool().balances[i.to::<usize>()])
+                }
+                ICurveStableSwap::ACall::SELECTOR => word(U256::from(2000)),
+                ICurveStableSwap::feeCall::SELECTOR => word(U256::from(1_000_000)),
+                ICurveStableSwap::totalSupplyCall::SELECTOR if call.target == THREE_CRV => {
+                    word(units(290_000_000, 18))
+                }
+                // the oldest pools have neither `A_precise` nor `stored_rates`
+                _ => None,
+            }
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let pool = StableSwapPool::fetch(&provider, META_REGISTRY, THREE_POOL, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(pool, three_pool());
+
+        let missing = address!("0000000000000000000000000000000000000001");
+        assert!(matches!(
+            MetaPool::fetch(&provider, META_REGISTRY, missing, BlockId::latest()).await,
+            Err(CurveError::PoolNotFound(pool)) if pool == missing
+        ));
+    }
+}
@@ -1,7 +1,13 @@
+use async_trait::async_trait;
 use reqwest::{Client, ClientBuilder};
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::dexes::aggregator::{
+    check_chain, parse_address, parse_amount, parse_data, AggregatorError, SwapProvider, SwapQuote, SwapRequest,
+    SwapTransaction,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum KyberSwapError {
     #[error("HTTP request error: {0}")]
@@ -19,6 +25,29 @@ type Result<T> = std::result::Result<T, KyberSwapError>;
 
 const BASE_URL: &str = "https://aggregator-api.kyberswap.com";
 
+/// Chain ids of the chain names in KyberSwap URLs.
+const CHAINS: [(&str, u64); 19] = [
+    ("ethereum", 1),
+    ("optimism", 10),
+    ("bsc", 56),
+    ("unichain", 130),
+    ("polygon", 137),
+    ("sonic", 146),
+    ("fantom", 250),
+    ("zksync", 324),
+    ("hyperevm", 999),
+    ("polygon-zkevm", 1101),
+    ("ronin", 2020),
+    ("mantle", 5000),
+    ("base", 8453),
+    ("arbitrum", 42161),
+    ("avalanche", 43114),
+    ("linea", 59144),
+    ("berachain", 80094),
+    ("blast", 81457),
+    ("scroll", 534352),
+];
+
 #[derive(Serialize, Deserialize)]
 pub struct RouteResponse {
     pub code: i64,
@@ -183,6 +212,8 @@ pub struct OutputChange {
 pub struct KyberSwapClient {
     pub http_client: Client,
     pub base_url: String,
+    /// Chain id of the chain name, `None` for names not in [`CHAINS`].
+    pub chain_id: Option<u64>,
 }
 
 impl KyberSwapClient {
@@ -206,6 +237,7 @@ impl KyberSwapClient {
         Self {
             http_client,
             base_url: format!("{}/{}", base_url, chain),
+            chain_id: CHAINS.iter().find(|(name, _)| *name == chain).map(|(_, id)| *id),
         }
     }
 
@@ -261,8 +293,8 @@ impl KyberSwapClient {
             sender: sender.to_string(),
             recipient: recipient.to_string(),
             deadline: deadline as i64,
-            slippage_tolerance: slippage_tolerance,
-            enable_gas_estimation: enable_gas_estimation,
+            slippage_tolerance,
+            enable_gas_estimation,
             ignore_capped_slippage: false,
         };
 
@@ -300,6 +332,59 @@ impl KyberSwapClient {
     }
 }
 
+#[async_trait]
+impl SwapProvider for KyberSwapClient {
+    fn name(&self) -> &'static str {
+        "kyberswap"
+    }
+
+    async fn quote(&self, request: &SwapRequest) -> std::result::Result<SwapQuote, AggregatorError> {
+        let chain_id = self
+            .chain_id
+            .ok_or_else(|| AggregatorError::InvalidRequest(format!("unknown kyberswap chain in {}", self.base_url)))?;
+        check_chain(self.name(), chain_id, request)?;
+        let response = self
+            .get_routes(
+                &request.token_in.to_string(),
+                &request.token_out.to_string(),
+                &request.amount_in.to_string(),
+            )
+            .await?;
+        let summary = response.data.route_summary;
+        Ok(SwapQuote {
+            provider: self.name(),
+            amount_in: request.amount_in,
+            amount_out: parse_amount(self.name(), &summary.amount_out)?,
+            gas: parse_amount(self.name(), &summary.gas)?.saturating_to(),
+            route: serde_json::to_value(&summary).map_err(KyberSwapError::SerializationError)?,
+        })
+    }
+
+    async fn build(
+        &self,
+        request: &SwapRequest,
+        quote: &SwapQuote,
+    ) -> std::result::Result<SwapTransaction, AggregatorError> {
+        let summary: RouteSummary =
+            serde_json::from_value(quote.route.clone()).map_err(KyberSwapError::SerializationError)?;
+        let sender = request.sender.to_string();
+        let response = self
+            .build_route(summary, &sender, &sender, request.slippage_bps.into(), false)
+            .await?;
+        let data = response.data;
+        let amount_out = parse_amount(self.name(), &data.amount_out)?;
+        Ok(SwapTransaction {
+            provider: self.name(),
+            to: parse_address(self.name(), &data.router_address)?,
+            data: parse_data(self.name(), &data.data)?,
+            value: parse_amount(self.name(), &data.transaction_value)?,
+            gas: parse_amount(self.name(), &data.gas)?.saturating_to(),
+            amount_out,
+            min_amount_out: request.min_amount_out(amount_out),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -415,4 +500,74 @@ mod tests {
         let result = client.get_routes("invalid_address", SUSDE, "1000").await;
         assert!(result.is_err(), "Expected error for invalid token address");
     }
+
+    #[tokio::test]
+    async fn test_swap_provider() {
+        use crate::dexes::aggregator::{SwapProvider, SwapRequest};
+        use crate::provider::mock::MockApi;
+        use alloy_primitives::{address, U256};
+        use serde_json::json;
+
+        let summary = json!({
+            "tokenIn": USDT, "amountIn": "1000000000", "amountInUsd": "1000.1", "tokenInMarketPriceAvailable": true,
+            "tokenOut": DAI, "amountOut": "999650000000000000000", "amountOutUsd": "999.8",
+            "tokenOutMarketPriceAvailable": true, "gas": "184000", "gasPrice": "10000000000", "gasUsd": "6.2",
+            "extraFee": { "feeAmount": "", "chargeFeeBy": "", "isInBps": false, "feeReceiver": "" },
+            "route": [[{
+                "pool": "0xbebc44782c7db0a1a60cb6fe97d0b483032ff1c7", "tokenIn": USDT, "tokenOut": DAI,
+                "limitReturnAmount": "0", "swapAmount": "1000000000", "amountOut": "999650000000000000000",
+                "exchange": "curve", "poolLength": 3, "poolType": "curve-base",
+                "poolExtra": { "tokenInIndex": 2, "tokenOutIndex": 0 }, "extra": null
+            }]],
+            "checksum": "11717426278421766925", "timestamp": 1736000000
+        });
+        let route = json!({
+            "code": 0, "message": "successfully", "requestId": "d1b1",
+            "data": { "routeSummary": summary, "routerAddress": "0x6131B5fae19EA4f9D964eAc0408E4408b66337b5" }
+        });
+        let build = json!({
+            "code": 0, "message": "successfully",
+            "data": {
+                "amountIn": "1000000000", "amountInUsd": "1000.1", "amountOut": "999650000000000000000",
+                "amountOutUsd": "999.8", "gas": "201000", "gasUsd": "6.8",
+                "outputChange": { "amount": "0", "percent": 0.0, "level": 0 },
+                "data": "0xe21fd0e9", "routerAddress": "0x6131B5fae19EA4f9D964eAc0408E4408b66337b5",
+                "transactionValue": "0"
+            }
+        });
+        let api = MockApi::start(move |request| match (request.method.as_str(), request.route()) {
+            ("GET", "/ethereum/api/v1/routes") => (200, route.clone()),
+            ("POST", "/ethereum/api/v1/route/build") => (200, build.clone()),
+            _ => (404, json!({ "message": "not found" })),
+        })
+        .await;
+
+        let client = KyberSwapClient::new(Some(api.url().to_string()), Some(CHAIN.to_string()));
+        let sender = address!("d46B96d15ffF9b2B17e9c788086f3159bD0e8355");
+        let request = SwapRequest::new(1, USDT.parse().unwrap(), DAI.parse().unwrap(), U256::from(1_000_000_000), sender)
+            .with_slippage(10);
+
+        let quote = client.quote(&request).await.unwrap();
+        assert_eq!(quote.provider, "kyberswap");
+        assert_eq!(quote.amount_out, U256::from(999_650_000_000_000_000_000u128));
+        assert_eq!(quote.gas, 184_000);
+
+        let transaction = client.build(&request, &quote).await.unwrap();
+        assert_eq!(transaction.to, address!("6131B5fae19EA4f9D964eAc0408E4408b66337b5"));
+        assert_eq!(transaction.data.to_string(), "0xe21fd0e9");
+        assert_eq!(transaction.value, U256::ZERO);
+        assert_eq!(transaction.gas, 201_000);
+        assert_eq!(transaction.min_amount_out, U256::from(998_650_350_000_000_000_000u128));
+
+        let requests = api.requests();
+        assert_eq!(requests[0].query("amountIn"), Some("1000000000"));
+        assert_eq!(requests[1].body["slippageTolerance"], 10);
+        assert_eq!(requests[1].body["routeSummary"]["checksum"], summary["checksum"]);
+        assert_eq!(requests[1].body["sender"], sender.to_string());
+
+        // the client is bound to the chain in its URL
+        let polygon = SwapRequest { chain_id: 137, ..request };
+        assert!(matches!(client.quote(&polygon).await, Err(AggregatorError::InvalidRequest(_))));
+        assert_eq!(api.requests().len(), 2);
+    }
 }
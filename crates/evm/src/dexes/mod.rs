@@ -1,4 +1,13 @@
+pub mod aggregator;
+pub mod balancer;
+pub mod cow;
+pub mod curve;
 pub mod kyber;
 pub mod odos;
+pub mod oneinch;
+pub mod paraswap;
 pub mod uni_v3;
 pub mod uni_v2;
+pub mod uni_v4;
+pub mod universal_router;
+pub mod zeroex;
@@ -1,8 +1,14 @@
-use alloy_primitives::U256;
+use alloy_primitives::{Address, U256};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use reqwest::{Client, ClientBuilder};
 use std::time::Duration;
 
+use crate::dexes::aggregator::{
+    parse_address, parse_amount, parse_data, AggregatorError, SwapProvider, SwapQuote, SwapRequest,
+    SwapTransaction, NATIVE,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum OdosError {
     #[error("HTTP request error: {0}")]
@@ -154,8 +160,9 @@ pub struct QuoteResponse {
     pub partner_fee_percent: f64,
     #[serde(rename = "pathId")]
     pub path_id: String,
+    #[serde(default)]
     #[serde(rename = "pathViz")]
-    pub path_viz: PathViz,
+    pub path_viz: Option<PathViz>,
     #[serde(rename = "blockNumber")]
     pub block_number: i64,
 }
@@ -373,6 +380,90 @@ impl OdosClient {
 }
 
 
+/// Odos takes the zero address for the native token.
+fn odos_token(token: Address) -> String {
+    if token == NATIVE { Address::ZERO } else { token }.to_string()
+}
+
+#[async_trait]
+impl SwapProvider for OdosClient {
+    fn name(&self) -> &'static str {
+        "odos"
+    }
+
+    async fn quote(&self, request: &SwapRequest) -> std::result::Result<SwapQuote, AggregatorError> {
+        let chain_id = i32::try_from(request.chain_id)
+            .map_err(|_| AggregatorError::InvalidRequest(format!("chain id {}", request.chain_id)))?;
+        let response = OdosClient::quote(
+            self,
+            &QuoteRequest {
+                chain_id,
+                input_tokens: vec![InputToken {
+                    token_address: odos_token(request.token_in),
+                    amount: request.amount_in.to_string(),
+                }],
+                output_tokens: vec![OutputToken {
+                    token_address: odos_token(request.token_out),
+                    proportion: 1.0,
+                }],
+                gas_price: request.gas_price.saturating_to::<u128>() as f64 / 1e9,
+                user_addr: request.sender.to_string(),
+                slippage_limit_percent: f64::from(request.slippage_bps) / 100.0,
+                source_blacklist: vec![],
+                source_whitelist: vec![],
+                pool_blacklist: vec![],
+                path_viz: false,
+                referral_code: 0,
+                compact: true,
+                like_asset: false,
+                disable_rfqs: false,
+                simple: false,
+            },
+        )
+        .await?;
+
+        let amount_out = response.out_amounts.first().ok_or(AggregatorError::InvalidResponse {
+            provider: self.name(),
+            message: "no output amount".into(),
+        })?;
+        Ok(SwapQuote {
+            provider: self.name(),
+            amount_in: request.amount_in,
+            amount_out: parse_amount(self.name(), amount_out)?,
+            gas: response.gas_estimate.max(0.0) as u64,
+            route: serde_json::Value::String(response.path_id),
+        })
+    }
+
+    async fn build(
+        &self,
+        request: &SwapRequest,
+        quote: &SwapQuote,
+    ) -> std::result::Result<SwapTransaction, AggregatorError> {
+        let path_id = quote.route.as_str().ok_or(AggregatorError::InvalidRequest("missing path id".into()))?;
+        let response = self.assemble(&request.sender.to_string(), path_id, false).await?;
+        let amount_out = response.output_tokens.first().ok_or(AggregatorError::InvalidResponse {
+            provider: self.name(),
+            message: "no output token".into(),
+        })?;
+        let amount_out = parse_amount(self.name(), &amount_out.amount)?;
+        let transaction = response.transaction;
+        let value = match transaction.value.as_str() {
+            "" => U256::ZERO,
+            value => parse_amount(self.name(), value)?,
+        };
+        Ok(SwapTransaction {
+            provider: self.name(),
+            to: parse_address(self.name(), &transaction.to)?,
+            data: parse_data(self.name(), &transaction.data)?,
+            value,
+            gas: transaction.gas.max(0) as u64,
+            amount_out,
+            min_amount_out: request.min_amount_out(amount_out),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -563,4 +654,66 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_swap_provider() {
+        use crate::dexes::aggregator::{SwapProvider, SwapRequest, NATIVE};
+        use crate::provider::mock::MockApi;
+        use alloy_primitives::address;
+        use serde_json::json;
+
+        let quote = json!({
+            "inTokens": ["0x0000000000000000000000000000000000000000"], "outTokens": [SUSDE],
+            "inAmounts": ["1000000000000000000"], "outAmounts": ["3102455000000000000000"],
+            "gasEstimate": 212345.0, "dataGasEstimate": 0, "gweiPerGas": 12.0, "gasEstimateValue": 7.9,
+            "inValues": [3105.2], "outValues": [3102.1], "netOutValue": 3094.2, "priceImpact": 0.01,
+            "percentDiff": 0.0, "partnerFeePercent": 0.0, "pathId": "6ace6e4f6028d0103b5df5f6d78cf7f8",
+            "pathViz": null, "blockNumber": 21500000
+        });
+        let assemble = json!({
+            "blockNumber": 21500000, "gasEstimate": 240000, "gasEstimateValue": 8.9,
+            "inputTokens": [{ "tokenAddress": "0x0000000000000000000000000000000000000000", "amount": "1000000000000000000" }],
+            "outputTokens": [{ "tokenAddress": SUSDE, "amount": "3102455000000000000000" }],
+            "netOutValue": 3093.2, "outValues": ["3102.1"],
+            "transaction": {
+                "gas": 360000, "gasPrice": 12000000000u64, "value": "1000000000000000000",
+                "to": "0xCf5540fFFCdC3d510B18bFcA6d2b9987b0772559",
+                "from": "0x163A5EC5e9C32238d075E2D829fE9fA87451e3b7",
+                "data": "0x83bd37f9", "nonce": 7, "chainId": 1
+            },
+            "simulation": null
+        });
+        let api = MockApi::start(move |request| match request.route() {
+            "/sor/quote/v2" => (200, quote.clone()),
+            "/sor/assemble" => (200, assemble.clone()),
+            _ => (404, json!({ "detail": "not found" })),
+        })
+        .await;
+
+        let client = OdosClient::new(Some(api.url().to_string()));
+        let sender = address!("163A5EC5e9C32238d075E2D829fE9fA87451e3b7");
+        let request = SwapRequest::new(1, NATIVE, SUSDE.parse().unwrap(), U256::from(10).pow(U256::from(18)), sender)
+            .with_slippage(30)
+            .with_gas(U256::from(12_000_000_000u64), U256::from(3100u64) * U256::from(10).pow(U256::from(18)));
+
+        let quote = SwapProvider::quote(&client, &request).await.unwrap();
+        assert_eq!(quote.provider, "odos");
+        assert_eq!(quote.amount_out, U256::from(3_102_455_000_000_000_000_000u128));
+        assert_eq!(quote.gas, 212_345);
+
+        let transaction = client.build(&request, &quote).await.unwrap();
+        assert_eq!(transaction.to, address!("Cf5540fFFCdC3d510B18bFcA6d2b9987b0772559"));
+        assert_eq!(transaction.value, request.amount_in);
+        assert_eq!(transaction.gas, 360_000);
+        assert_eq!(transaction.min_amount_out, U256::from(3_093_147_635_000_000_000_000u128));
+
+        let requests = api.requests();
+        assert_eq!(requests[0].header("Content-Type"), Some("application/json"));
+        let body = &requests[0].body;
+        assert_eq!(body["chainId"], 1);
+        assert_eq!(body["inputTokens"][0]["tokenAddress"], "0x0000000000000000000000000000000000000000");
+        assert_eq!(body["gasPrice"], 12.0);
+        assert_eq!(body["slippageLimitPercent"], 0.3);
+        assert_eq!(requests[1].body["pathId"], "6ace6e4f6028d0103b5df5f6d78cf7f8");
+    }
+}
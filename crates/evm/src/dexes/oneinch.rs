@@ -0,0 +1,371 @@
+use alloy_primitives::U256;
+use async_trait::async_trait;
+use reqwest::{Client, ClientBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::dexes::aggregator::{
+    check_chain, parse_address, parse_amount, parse_data, AggregatorError, SwapProvider, SwapQuote, SwapRequest, SwapTransaction,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OneInchError {
+    #[error("HTTP request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid status code {status}: {message}")]
+    InvalidStatus {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+    #[error("Invalid API key, it must be a valid header value")]
+    InvalidApiKey,
+}
+
+type Result<T> = std::result::Result<T, OneInchError>;
+
+const BASE_URL: &str = "https://api.1inch.dev";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub address: String,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteResponse {
+    #[serde(rename = "dstAmount")]
+    pub dst_amount: String,
+    #[serde(default)]
+    #[serde(rename = "srcToken")]
+    pub src_token: Option<TokenInfo>,
+    #[serde(default)]
+    #[serde(rename = "dstToken")]
+    pub dst_token: Option<TokenInfo>,
+    #[serde(default)]
+    pub gas: u64,
+}
+
+impl QuoteResponse {
+    /// `dst_amount` as a number.
+    pub fn amount_out(&self) -> Option<U256> {
+        self.dst_amount.parse().ok()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    pub from: String,
+    pub to: String,
+    pub data: String,
+    pub value: String,
+    #[serde(default)]
+    pub gas: u64,
+    #[serde(default)]
+    #[serde(rename = "gasPrice")]
+    pub gas_price: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SwapResponse {
+    #[serde(rename = "dstAmount")]
+    pub dst_amount: String,
+    #[serde(default)]
+    #[serde(rename = "srcToken")]
+    pub src_token: Option<TokenInfo>,
+    #[serde(default)]
+    #[serde(rename = "dstToken")]
+    pub dst_token: Option<TokenInfo>,
+    pub tx: Transaction,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpenderResponse {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowanceResponse {
+    pub allowance: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApproveTransaction {
+    pub data: String,
+    #[serde(rename = "gasPrice")]
+    pub gas_price: String,
+    pub to: String,
+    pub value: String,
+}
+
+/// Parameters of a swap, `slippage` is in percent.
+#[derive(Debug, Clone)]
+pub struct SwapParams<'a> {
+    pub src: &'a str,
+    pub dst: &'a str,
+    pub amount: &'a str,
+    pub from: &'a str,
+    pub slippage: f64,
+    /// Defaults to `from`.
+    pub receiver: Option<&'a str>,
+    /// Skips the balance and allowance checks, needed to build before approving.
+    pub disable_estimate: bool,
+}
+
+pub struct OneInchClient {
+    pub http_client: Client,
+    pub base_url: String,
+    pub chain_id: u64,
+    api_key: Option<String>,
+}
+
+fn http_client(api_key: Option<&str>, timeout: Duration) -> Result<Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("Accept", "application/json".parse().unwrap());
+    if let Some(api_key) = api_key {
+        let value = format!("Bearer {}", api_key).parse().map_err(|_| OneInchError::InvalidApiKey)?;
+        headers.insert("Authorization", value);
+    }
+    Ok(ClientBuilder::new()
+        .timeout(timeout)
+        .default_headers(headers)
+        .build()
+        .expect("Failed to create HTTP client"))
+}
+
+impl OneInchClient {
+    /// Creates a new 1inch Swap API client.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Optional base URL. If None, uses default BASE_URL
+    /// * `chain_id` - Chain the swaps are on
+    /// * `api_key` - Developer portal key, sent as a bearer token
+    pub fn new(base_url: Option<String>, chain_id: u64, api_key: Option<String>) -> Result<Self> {
+        let base_url = base_url.unwrap_or(BASE_URL.to_string());
+        Ok(Self {
+            http_client: http_client(api_key.as_deref(), Duration::from_secs(10))?,
+            base_url: format!("{}/swap/v6.0/{}", base_url, chain_id),
+            chain_id,
+            api_key,
+        })
+    }
+
+    /// Sets a custom timeout for the HTTP client
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.http_client = http_client(self.api_key.as_deref(), timeout)?;
+        Ok(self)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.http_client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(OneInchError::InvalidStatus {
+                status: response.status(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response.json::<T>().await.map_err(OneInchError::RequestError)
+    }
+
+    /// Quotes swapping `amount` of `src` to `dst`, with the gas estimate and token details.
+    pub async fn quote(&self, src: &str, dst: &str, amount: &str) -> Result<QuoteResponse> {
+        self.get(&format!(
+            "/quote?src={}&dst={}&amount={}&includeTokensInfo=true&includeGas=true",
+            src, dst, amount
+        ))
+        .await
+    }
+
+    /// Builds the swap transaction.
+    pub async fn swap(&self, params: &SwapParams<'_>) -> Result<SwapResponse> {
+        let mut path = format!(
+            "/swap?src={}&dst={}&amount={}&from={}&origin={}&slippage={}&includeTokensInfo=true&includeGas=true",
+            params.src, params.dst, params.amount, params.from, params.from, params.slippage
+        );
+        if let Some(receiver) = params.receiver {
+            path.push_str(&format!("&receiver={}", receiver));
+        }
+        if params.disable_estimate {
+            path.push_str("&disableEstimate=true");
+        }
+        self.get(&path).await
+    }
+
+    /// The router to approve.
+    pub async fn approve_spender(&self) -> Result<SpenderResponse> {
+        self.get("/approve/spender").await
+    }
+
+    /// Allowance of `wallet` to the router over `token`.
+    pub async fn allowance(&self, token: &str, wallet: &str) -> Result<AllowanceResponse> {
+        self.get(&format!(
+            "/approve/allowance?tokenAddress={}&walletAddress={}",
+            token, wallet
+        ))
+        .await
+    }
+
+    /// An approval of `amount` of `token` to the router, unlimited if None.
+    pub async fn approve_transaction(&self, token: &str, amount: Option<&str>) -> Result<ApproveTransaction> {
+        let mut path = format!("/approve/transaction?tokenAddress={}", token);
+        if let Some(amount) = amount {
+            path.push_str(&format!("&amount={}", amount));
+        }
+        self.get(&path).await
+    }
+}
+
+#[async_trait]
+impl SwapProvider for OneInchClient {
+    fn name(&self) -> &'static str {
+        "1inch"
+    }
+
+    async fn quote(&self, request: &SwapRequest) -> std::result::Result<SwapQuote, AggregatorError> {
+        check_chain(self.name(), self.chain_id, request)?;
+        let response = OneInchClient::quote(
+            self,
+            &request.token_in.to_string(),
+            &request.token_out.to_string(),
+            &request.amount_in.to_string(),
+        )
+        .await?;
+        Ok(SwapQuote {
+            provider: self.name(),
+            amount_in: request.amount_in,
+            amount_out: parse_amount(self.name(), &response.dst_amount)?,
+            gas: response.gas,
+            route: serde_json::Value::Null,
+        })
+    }
+
+    async fn build(
+        &self,
+        request: &SwapRequest,
+        _quote: &SwapQuote,
+    ) -> std::result::Result<SwapTransaction, AggregatorError> {
+        check_chain(self.name(), self.chain_id, request)?;
+        let (token_in, token_out) = (request.token_in.to_string(), request.token_out.to_string());
+        let (amount, from) = (request.amount_in.to_string(), request.sender.to_string());
+        let response = self
+            .swap(&SwapParams {
+                src: &token_in,
+                dst: &token_out,
+                amount: &amount,
+                from: &from,
+                slippage: f64::from(request.slippage_bps) / 100.0,
+                receiver: None,
+                disable_estimate: false,
+            })
+            .await?;
+        let amount_out = parse_amount(self.name(), &response.dst_amount)?;
+        Ok(SwapTransaction {
+            provider: self.name(),
+            to: parse_address(self.name(), &response.tx.to)?,
+            data: parse_data(self.name(), &response.tx.data)?,
+            value: parse_amount(self.name(), &response.tx.value)?,
+            gas: response.tx.gas,
+            amount_out,
+            min_amount_out: request.min_amount_out(amount_out),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::aggregator::NATIVE;
+    use crate::provider::mock::MockApi;
+    use alloy_primitives::{address, Address};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const SENDER: Address = address!("d46B96d15ffF9b2B17e9c788086f3159bD0e8355");
+    const ROUTER: Address = address!("111111125421cA6dc452d289314280a0f8842A65");
+
+    async fn mock_api() -> MockApi {
+        MockApi::start(|request| {
+            if request.header("authorization") != Some("Bearer test-key") {
+                return (
+                    401,
+                    serde_json::json!({ "statusCode": 401, "description": "Unauthorized" }),
+                );
+            }
+            let (status, fixture) = match request.route() {
+                "/swap/v6.0/1/quote" => (200, include_str!("fixtures/oneinch_quote.json")),
+                "/swap/v6.0/1/swap" => (200, include_str!("fixtures/oneinch_swap.json")),
+                "/swap/v6.0/1/approve/spender" => (200, r#"{"address":"0x111111125421ca6dc452d289314280a0f8842a65"}"#),
+                "/swap/v6.0/1/approve/allowance" => (200, r#"{"allowance":"0"}"#),
+                _ => (404, r#"{"statusCode":404,"description":"Not found"}"#),
+            };
+            (status, serde_json::from_str(fixture).unwrap())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_quote_and_swap() {
+        let api = mock_api().await;
+        let client = OneInchClient::new(Some(api.url().to_string()), 1, Some("test-key".to_string())).unwrap();
+
+        let quote = OneInchClient::quote(&client, &NATIVE.to_string(), &USDC.to_string(), "1000000000000000000")
+            .await
+            .unwrap();
+        assert_eq!(quote.amount_out(), Some(U256::from(3_312_456_789u64)));
+        assert_eq!(quote.dst_token.unwrap().decimals, 6);
+        assert_eq!(quote.gas, 182_000);
+
+        let request = SwapRequest::new(1, NATIVE, USDC, U256::from(10).pow(U256::from(18)), SENDER).with_slippage(100);
+        let quote = SwapProvider::quote(&client, &request).await.unwrap();
+        let transaction = client.build(&request, &quote).await.unwrap();
+        assert_eq!(transaction.to, ROUTER);
+        assert_eq!(transaction.value, request.amount_in);
+        assert_eq!(transaction.gas, 227_500);
+        assert_eq!(transaction.amount_out, U256::from(3_312_456_789u64));
+        assert_eq!(transaction.min_amount_out, U256::from(3_279_332_221u64));
+        assert_eq!(&transaction.data[..4], &[0x07, 0xed, 0x23, 0x79]);
+
+        let swap = &api.requests()[2];
+        assert_eq!(swap.query("slippage"), Some("1"));
+        assert_eq!(swap.query("from"), Some(SENDER.to_string().as_str()));
+        assert_eq!(swap.query("src"), Some(NATIVE.to_string().as_str()));
+
+        let spender = client.approve_spender().await.unwrap();
+        assert_eq!(spender.address.parse::<Address>().unwrap(), ROUTER);
+        let allowance = client.allowance(&USDC.to_string(), &SENDER.to_string()).await.unwrap();
+        assert_eq!(allowance.allowance, "0");
+
+        // the client is bound to the chain in its URL
+        let requests = api.requests().len();
+        let base = SwapRequest { chain_id: 8453, ..request };
+        assert!(matches!(SwapProvider::quote(&client, &base).await, Err(AggregatorError::InvalidRequest(_))));
+        assert_eq!(api.requests().len(), requests);
+    }
+
+    #[tokio::test]
+    async fn test_api_key() {
+        let api = mock_api().await;
+        let client = OneInchClient::new(Some(api.url().to_string()), 1, None)
+            .unwrap()
+            .with_timeout(Duration::from_secs(5))
+            .unwrap();
+        let result = client.approve_spender().await;
+        assert!(matches!(
+            result,
+            Err(OneInchError::InvalidStatus { status, .. }) if status == reqwest::StatusCode::UNAUTHORIZED
+        ));
+
+        // a key read from an env file with its newline
+        assert!(matches!(
+            OneInchClient::new(None, 1, Some("test-key\n".to_string())),
+            Err(OneInchError::InvalidApiKey)
+        ));
+    }
+}
@@ -0,0 +1,370 @@
+use async_trait::async_trait;
+use reqwest::{Client, ClientBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::dexes::aggregator::{
+    check_chain, parse_address, parse_amount, parse_data, AggregatorError, SwapProvider, SwapQuote, SwapRequest, SwapTransaction,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParaSwapError {
+    #[error("HTTP request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid status code {status}: {message}")]
+    InvalidStatus {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+    #[error("Invalid API key, it must be a valid header value")]
+    InvalidApiKey,
+}
+
+type Result<T> = std::result::Result<T, ParaSwapError>;
+
+const BASE_URL: &str = "https://api.paraswap.io";
+const API_VERSION: &str = "6.2";
+
+/// The best route for a swap. It must be sent back unchanged to build the transaction, so fields not
+/// typed here are kept in `other`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceRoute {
+    #[serde(rename = "blockNumber")]
+    pub block_number: u64,
+    pub network: u64,
+    #[serde(rename = "srcToken")]
+    pub src_token: String,
+    #[serde(rename = "srcDecimals")]
+    pub src_decimals: u8,
+    #[serde(rename = "srcAmount")]
+    pub src_amount: String,
+    #[serde(rename = "destToken")]
+    pub dest_token: String,
+    #[serde(rename = "destDecimals")]
+    pub dest_decimals: u8,
+    #[serde(rename = "destAmount")]
+    pub dest_amount: String,
+    #[serde(rename = "gasCost")]
+    pub gas_cost: String,
+    pub side: String,
+    pub version: String,
+    /// The Augustus router.
+    #[serde(rename = "contractAddress")]
+    pub contract_address: String,
+    /// The spender to approve, the router itself since v6.
+    #[serde(rename = "tokenTransferProxy")]
+    pub token_transfer_proxy: String,
+    #[serde(rename = "contractMethod")]
+    pub contract_method: String,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceResponse {
+    #[serde(rename = "priceRoute")]
+    pub price_route: PriceRoute,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionRequest<'a> {
+    #[serde(rename = "srcToken")]
+    pub src_token: &'a str,
+    #[serde(rename = "srcDecimals")]
+    pub src_decimals: u8,
+    #[serde(rename = "destToken")]
+    pub dest_token: &'a str,
+    #[serde(rename = "destDecimals")]
+    pub dest_decimals: u8,
+    #[serde(rename = "srcAmount")]
+    pub src_amount: &'a str,
+    #[serde(rename = "priceRoute")]
+    pub price_route: &'a PriceRoute,
+    /// In bps.
+    pub slippage: u32,
+    #[serde(rename = "userAddress")]
+    pub user_address: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partner: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionResponse {
+    pub from: String,
+    pub to: String,
+    pub value: String,
+    pub data: String,
+    #[serde(default)]
+    #[serde(rename = "gasPrice")]
+    pub gas_price: Option<String>,
+    #[serde(default)]
+    pub gas: Option<String>,
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+}
+
+pub struct ParaSwapClient {
+    pub http_client: Client,
+    pub base_url: String,
+    pub chain_id: u64,
+    /// Sent with every request, for fee sharing and analytics.
+    pub partner: Option<String>,
+    api_key: Option<String>,
+}
+
+fn http_client(api_key: Option<&str>, timeout: Duration) -> Result<Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("Accept", "application/json".parse().unwrap());
+    if let Some(api_key) = api_key {
+        headers.insert("X-API-KEY", api_key.parse().map_err(|_| ParaSwapError::InvalidApiKey)?);
+    }
+    Ok(ClientBuilder::new()
+        .timeout(timeout)
+        .default_headers(headers)
+        .build()
+        .expect("Failed to create HTTP client"))
+}
+
+impl ParaSwapClient {
+    /// Creates a new ParaSwap client.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Optional base URL. If None, uses default BASE_URL
+    /// * `chain_id` - Network the swaps are on
+    /// * `api_key` - Optional key for higher rate limits
+    pub fn new(base_url: Option<String>, chain_id: u64, api_key: Option<String>) -> Result<Self> {
+        Ok(Self {
+            http_client: http_client(api_key.as_deref(), Duration::from_secs(10))?,
+            base_url: base_url.unwrap_or(BASE_URL.to_string()),
+            chain_id,
+            partner: None,
+            api_key,
+        })
+    }
+
+    pub fn with_partner(mut self, partner: String) -> Self {
+        self.partner = Some(partner);
+        self
+    }
+
+    /// Sets a custom timeout for the HTTP client
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.http_client = http_client(self.api_key.as_deref(), timeout)?;
+        Ok(self)
+    }
+
+    async fn parse<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+        if !response.status().is_success() {
+            return Err(ParaSwapError::InvalidStatus {
+                status: response.status(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response.json::<T>().await.map_err(ParaSwapError::RequestError)
+    }
+
+    /// Best route selling `amount` of `src_token`. Decimals can be omitted for tokens ParaSwap lists.
+    pub async fn get_price(
+        &self,
+        src_token: &str,
+        dest_token: &str,
+        amount: &str,
+        user_address: &str,
+        decimals: Option<(u8, u8)>,
+    ) -> Result<PriceResponse> {
+        let mut url = format!(
+            "{}/prices?srcToken={}&destToken={}&amount={}&side=SELL&network={}&version={}&userAddress={}",
+            self.base_url, src_token, dest_token, amount, self.chain_id, API_VERSION, user_address
+        );
+        if let Some((src_decimals, dest_decimals)) = decimals {
+            url.push_str(&format!("&srcDecimals={}&destDecimals={}", src_decimals, dest_decimals));
+        }
+        if let Some(partner) = &self.partner {
+            url.push_str(&format!("&partner={}", partner));
+        }
+
+        let response = self.http_client.get(&url).send().await?;
+        Self::parse(response).await
+    }
+
+    /// Builds the transaction of a route, `slippage_bps` below its `dest_amount`.
+    pub async fn build_transaction(
+        &self,
+        price_route: &PriceRoute,
+        user_address: &str,
+        slippage_bps: u32,
+    ) -> Result<TransactionResponse> {
+        let url = format!("{}/transactions/{}?ignoreChecks=true", self.base_url, self.chain_id);
+        let request = TransactionRequest {
+            src_token: &price_route.src_token,
+            src_decimals: price_route.src_decimals,
+            dest_token: &price_route.dest_token,
+            dest_decimals: price_route.dest_decimals,
+            src_amount: &price_route.src_amount,
+            price_route,
+            slippage: slippage_bps,
+            user_address,
+            partner: self.partner.as_deref(),
+        };
+
+        let response = self.http_client.post(&url).json(&request).send().await?;
+        Self::parse(response).await
+    }
+}
+
+#[async_trait]
+impl SwapProvider for ParaSwapClient {
+    fn name(&self) -> &'static str {
+        "paraswap"
+    }
+
+    async fn quote(&self, request: &SwapRequest) -> std::result::Result<SwapQuote, AggregatorError> {
+        check_chain(self.name(), self.chain_id, request)?;
+        let response = self
+            .get_price(
+                &request.token_in.to_string(),
+                &request.token_out.to_string(),
+                &request.amount_in.to_string(),
+                &request.sender.to_string(),
+                None,
+            )
+            .await?;
+        let route = response.price_route;
+        Ok(SwapQuote {
+            provider: self.name(),
+            amount_in: request.amount_in,
+            amount_out: parse_amount(self.name(), &route.dest_amount)?,
+            gas: parse_amount(self.name(), &route.gas_cost)?.saturating_to(),
+            route: serde_json::to_value(&route).map_err(ParaSwapError::SerializationError)?,
+        })
+    }
+
+    async fn build(
+        &self,
+        request: &SwapRequest,
+        quote: &SwapQuote,
+    ) -> std::result::Result<SwapTransaction, AggregatorError> {
+        check_chain(self.name(), self.chain_id, request)?;
+        let route: PriceRoute =
+            serde_json::from_value(quote.route.clone()).map_err(ParaSwapError::SerializationError)?;
+        let response = self
+            .build_transaction(&route, &request.sender.to_string(), request.slippage_bps)
+            .await?;
+        let gas = match &response.gas {
+            Some(gas) => parse_amount(self.name(), gas)?,
+            None => parse_amount(self.name(), &route.gas_cost)?,
+        };
+        let amount_out = parse_amount(self.name(), &route.dest_amount)?;
+        Ok(SwapTransaction {
+            provider: self.name(),
+            to: parse_address(self.name(), &response.to)?,
+            data: parse_data(self.name(), &response.data)?,
+            value: parse_amount(self.name(), &response.value)?,
+            gas: gas.saturating_to(),
+            amount_out,
+            min_amount_out: request.min_amount_out(amount_out),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::aggregator::NATIVE;
+    use crate::provider::mock::MockApi;
+    use alloy_primitives::{address, Address, U256};
+
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const AUGUSTUS: Address = address!("6A000F20005980200259B80c5102003040001068");
+
+    async fn mock_api() -> MockApi {
+        MockApi::start(|request| {
+            let (status, fixture) = match (request.method.as_str(), request.route()) {
+                ("GET", "/prices")
+                    if request.query("srcToken") == Some("0x0000000000000000000000000000000000000001") =>
+                {
+                    (
+                        400,
+                        r#"{"error":"Token not found. Please pass srcDecimals & destDecimals query params"}"#,
+                    )
+                }
+                ("GET", "/prices") => (200, include_str!("fixtures/paraswap_prices.json")),
+                ("POST", "/transactions/1") => (200, include_str!("fixtures/paraswap_transaction.json")),
+                _ => (404, r#"{"error":"Not found"}"#),
+            };
+            (status, serde_json::from_str(fixture).unwrap())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_price_and_transaction() {
+        let api = mock_api().await;
+        let client = ParaSwapClient::new(Some(api.url().to_string()), 1, Some("test-key".to_string()))
+            .unwrap()
+            .with_partner("web3ium".to_string());
+        let sender = address!("d46B96d15ffF9b2B17e9c788086f3159bD0e8355");
+        let request = SwapRequest::new(1, NATIVE, DAI, U256::from(10).pow(U256::from(18)), sender).with_slippage(50);
+
+        let quote = SwapProvider::quote(&client, &request).await.unwrap();
+        assert_eq!(quote.amount_out, U256::from(3_318_202_541_176_023_040_000u128));
+        assert_eq!(quote.gas, 153_400);
+
+        let transaction = client.build(&request, &quote).await.unwrap();
+        assert_eq!(transaction.to, AUGUSTUS);
+        assert_eq!(transaction.value, request.amount_in);
+        assert_eq!(transaction.gas, 153_400);
+        assert_eq!(
+            transaction.min_amount_out,
+            U256::from(3_301_611_528_470_142_924_800u128)
+        );
+        assert_eq!(&transaction.data[..4], &[0xe3, 0xea, 0xd5, 0x9e]);
+
+        let requests = api.requests();
+        assert_eq!(requests[0].header("x-api-key"), Some("test-key"));
+        assert_eq!(requests[0].query("version"), Some("6.2"));
+        assert_eq!(requests[0].query("partner"), Some("web3ium"));
+        // the route goes back as received, untyped fields included
+        let body = &requests[1].body;
+        assert_eq!(body["slippage"], 50);
+        assert_eq!(body["partner"], "web3ium");
+        assert_eq!(body["priceRoute"]["hmac"], "4b3f2f17f4d0a6c2e5e3d3a3c9e0d4b3a7b2a1c5");
+        assert_eq!(body["priceRoute"]["bestRoute"][0]["percent"], 100);
+
+        let polygon = SwapRequest { chain_id: 137, ..request };
+        assert!(matches!(SwapProvider::quote(&client, &polygon).await, Err(AggregatorError::InvalidRequest(_))));
+        assert!(matches!(client.build(&polygon, &quote).await, Err(AggregatorError::InvalidRequest(_))));
+        assert_eq!(api.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_error() {
+        let api = mock_api().await;
+        let client = ParaSwapClient::new(Some(api.url().to_string()), 1, None).unwrap();
+        let result = client
+            .get_price(
+                "0x0000000000000000000000000000000000000001",
+                &DAI.to_string(),
+                "1000",
+                "",
+                None,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ParaSwapError::InvalidStatus { status, ref message })
+                if status == reqwest::StatusCode::BAD_REQUEST && message.contains("srcDecimals")
+        ));
+        assert_eq!(api.requests()[0].header("x-api-key"), None);
+
+        assert!(matches!(
+            ParaSwapClient::new(None, 1, Some("test-key\r\n".to_string())),
+            Err(ParaSwapError::InvalidApiKey)
+        ));
+    }
+}
@@ -0,0 +1,1514 @@
+use alloy_dyn_abi::Eip712Domain;
+use alloy_eips::BlockId;
+use alloy_primitives::{address, b256, hex, keccak256, Address, Bytes, PrimitiveSignature, B256, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{sol, SolCall};
+use serde::Serialize;
+
+use crate::abis::uniswap::{IUniswapV2Pair, IUniswapV2Router};
+use crate::deploy::address::create2_address;
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::{EvmSigner, EvmSignerError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV2Error {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Identical token addresses")]
+    IdenticalAddresses,
+    #[error("Zero token address")]
+    ZeroAddress,
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Invalid fee: {0} bps")]
+    InvalidFee(u32),
+    #[error("Insufficient input amount")]
+    InsufficientInputAmount,
+    #[error("Insufficient output amount")]
+    InsufficientOutputAmount,
+    #[error("Insufficient liquidity")]
+    InsufficientLiquidity,
+    #[error("Arithmetic overflow")]
+    Overflow,
+    #[error("Pair not found: {0}")]
+    PairNotFound(Address),
+    #[error("Invalid swap: {0}")]
+    InvalidSwap(String),
+    #[error("Invalid liquidity: {0}")]
+    InvalidLiquidity(String),
+    #[error("Signer error: {0}")]
+    SignerError(#[from] EvmSignerError),
+}
+
+type Result<T> = std::result::Result<T, UniswapV2Error>;
+
+const BPS: u32 = 10_000;
+
+/// A Uniswap V2 deployment or one of its forks, which differ in addresses, pair init code and fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniswapV2 {
+    pub factory: Address,
+    pub router: Address,
+    /// `keccak256` of the pair creation code, used to derive pair addresses.
+    pub init_code_hash: B256,
+    /// The wrapped native token, first or last in the path of ETH swaps.
+    pub weth: Address,
+    /// Swap fee in basis points, 30 (0.3%) for Uniswap.
+    pub fee_bps: u32,
+}
+
+// https://docs.uniswap.org/contracts/v2/reference/smart-contracts/v2-deployments
+pub const UNISWAP_V2: UniswapV2 = UniswapV2 {
+    factory: address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+    router: address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D"),
+    init_code_hash: b256!("96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"),
+    weth: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+    fee_bps: 30,
+};
+
+/// PancakeSwap V2 on BNB Chain, `weth` is WBNB.
+pub const PANCAKESWAP_V2: UniswapV2 = UniswapV2 {
+    factory: address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73"),
+    router: address!("10ED43C718714eb63d5aA57B78B54704E256024E"),
+    init_code_hash: b256!("00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5"),
+    weth: address!("bb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"),
+    fee_bps: 25,
+};
+
+/// Orders two tokens the way the factory does, by address.
+pub fn sort_tokens(token_a: Address, token_b: Address) -> Result<(Address, Address)> {
+    if token_a == token_b {
+        return Err(UniswapV2Error::IdenticalAddresses);
+    }
+    let (token0, token1) = match token_a < token_b {
+        true => (token_a, token_b),
+        false => (token_b, token_a),
+    };
+    if token0.is_zero() {
+        return Err(UniswapV2Error::ZeroAddress);
+    }
+    Ok((token0, token1))
+}
+
+/// Output of swapping `amount_in`, `UniswapV2Library.getAmountOut` with a fee of `fee_bps`.
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> Result<U256> {
+    if amount_in.is_zero() {
+        return Err(UniswapV2Error::InsufficientInputAmount);
+    }
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(UniswapV2Error::InsufficientLiquidity);
+    }
+    let amount_in_with_fee = amount_in
+        .checked_mul(fee_factor(fee_bps)?)
+        .ok_or(UniswapV2Error::Overflow)?;
+    let numerator = amount_in_with_fee
+        .checked_mul(reserve_out)
+        .ok_or(UniswapV2Error::Overflow)?;
+    let denominator = reserve_in
+        .checked_mul(U256::from(BPS))
+        .and_then(|reserve| reserve.checked_add(amount_in_with_fee))
+        .ok_or(UniswapV2Error::Overflow)?;
+    Ok(numerator / denominator)
+}
+
+/// Input needed to receive `amount_out`, `UniswapV2Library.getAmountIn` with a fee of `fee_bps`.
+pub fn get_amount_in(amount_out: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> Result<U256> {
+    if amount_out.is_zero() {
+        return Err(UniswapV2Error::InsufficientOutputAmount);
+    }
+    if reserve_in.is_zero() || amount_out >= reserve_out {
+        return Err(UniswapV2Error::InsufficientLiquidity);
+    }
+    let numerator = reserve_in
+        .checked_mul(amount_out)
+        .and_then(|product| product.checked_mul(U256::from(BPS)))
+        .ok_or(UniswapV2Error::Overflow)?;
+    let denominator = (reserve_out - amount_out)
+        .checked_mul(fee_factor(fee_bps)?)
+        .ok_or(UniswapV2Error::Overflow)?;
+    Ok(numerator / denominator + U256::from(1))
+}
+
+/// Amounts along a path, `reserves` holds `(reserve_in, reserve_out)` of each hop.
+pub fn get_amounts_out(amount_in: U256, reserves: &[(U256, U256)], fee_bps: u32) -> Result<Vec<U256>> {
+    let mut amounts = vec![amount_in];
+    for (reserve_in, reserve_out) in reserves {
+        let amount = get_amount_out(*amounts.last().unwrap(), *reserve_in, *reserve_out, fee_bps)?;
+        amounts.push(amount);
+    }
+    Ok(amounts)
+}
+
+/// Amounts along a path ending in `amount_out`, `reserves` holds `(reserve_in, reserve_out)` of each hop.
+pub fn get_amounts_in(amount_out: U256, reserves: &[(U256, U256)], fee_bps: u32) -> Result<Vec<U256>> {
+    let mut amounts = vec![amount_out];
+    for (reserve_in, reserve_out) in reserves.iter().rev() {
+        let amount = get_amount_in(amounts[0], *reserve_in, *reserve_out, fee_bps)?;
+        amounts.insert(0, amount);
+    }
+    Ok(amounts)
+}
+
+/// Lowest acceptable output for a quoted `amount` and a slippage tolerance in basis points.
+pub fn min_amount_out(amount: U256, slippage_bps: u32) -> U256 {
+    amount * U256::from(BPS.saturating_sub(slippage_bps)) / U256::from(BPS)
+}
+
+/// Highest acceptable input for a quoted `amount` and a slippage tolerance in basis points.
+pub fn max_amount_in(amount: U256, slippage_bps: u32) -> U256 {
+    amount.saturating_mul(U256::from(BPS + slippage_bps)) / U256::from(BPS)
+}
+
+fn fee_factor(fee_bps: u32) -> Result<U256> {
+    match fee_bps < BPS {
+        true => Ok(U256::from(BPS - fee_bps)),
+        false => Err(UniswapV2Error::InvalidFee(fee_bps)),
+    }
+}
+
+/// A pair and its reserves at some block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pair {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub reserve0: U256,
+    pub reserve1: U256,
+}
+
+impl Pair {
+    /// `(reserve_in, reserve_out)` when swapping `token_in` for the other token.
+    pub fn reserves(&self, token_in: Address) -> Result<(U256, U256)> {
+        match token_in {
+            token if token == self.token0 => Ok((self.reserve0, self.reserve1)),
+            token if token == self.token1 => Ok((self.reserve1, self.reserve0)),
+            token => Err(UniswapV2Error::InvalidPath(format!(
+                "{} is not in pair {}",
+                token, self.address
+            ))),
+        }
+    }
+}
+
+/// How much goes in and out of a swap. Fee-on-transfer variants only exist for exact input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAmount {
+    ExactIn { amount_in: U256, amount_out_min: U256 },
+    ExactOut { amount_out: U256, amount_in_max: U256 },
+}
+
+/// A router swap along `path`, see [`UniswapV2::swap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapParams {
+    pub amount: SwapAmount,
+    pub path: Vec<Address>,
+    pub to: Address,
+    /// Unix timestamp in seconds after which the swap reverts.
+    pub deadline: u64,
+    /// Pays with native ETH, the path starts with WETH.
+    pub eth_in: bool,
+    /// Receives native ETH, the path ends with WETH.
+    pub eth_out: bool,
+    /// Uses the `SupportingFeeOnTransferTokens` variants, which check the received balance
+    /// instead of the quoted amounts.
+    pub fee_on_transfer: bool,
+}
+
+impl SwapParams {
+    pub fn exact_in(amount_in: U256, amount_out_min: U256, path: Vec<Address>, to: Address, deadline: u64) -> Self {
+        Self::new(
+            SwapAmount::ExactIn {
+                amount_in,
+                amount_out_min,
+            },
+            path,
+            to,
+            deadline,
+        )
+    }
+
+    pub fn exact_out(amount_out: U256, amount_in_max: U256, path: Vec<Address>, to: Address, deadline: u64) -> Self {
+        Self::new(
+            SwapAmount::ExactOut {
+                amount_out,
+                amount_in_max,
+            },
+            path,
+            to,
+            deadline,
+        )
+    }
+
+    fn new(amount: SwapAmount, path: Vec<Address>, to: Address, deadline: u64) -> Self {
+        Self {
+            amount,
+            path,
+            to,
+            deadline,
+            eth_in: false,
+            eth_out: false,
+            fee_on_transfer: false,
+        }
+    }
+
+    pub fn eth_in(mut self) -> Self {
+        self.eth_in = true;
+        self
+    }
+
+    pub fn eth_out(mut self) -> Self {
+        self.eth_out = true;
+        self
+    }
+
+    pub fn supporting_fee_on_transfer(mut self) -> Self {
+        self.fee_on_transfer = true;
+        self
+    }
+}
+
+/// Router calldata and `msg.value` of a swap. With ETH in and an exact output, the value is
+/// `amount_in_max` and the router refunds the difference.
+pub fn swap_calldata(params: &SwapParams) -> Result<(Bytes, U256)> {
+    if params.path.len() < 2 {
+        return Err(UniswapV2Error::InvalidPath("at least two tokens are required".into()));
+    }
+    if params.eth_in && params.eth_out {
+        return Err(UniswapV2Error::InvalidSwap("ETH can't be both input and output".into()));
+    }
+    let path = params.path.clone();
+    let (to, deadline) = (params.to, U256::from(params.deadline));
+
+    let (input, value) = match (params.amount, params.eth_in, params.eth_out, params.fee_on_transfer) {
+        (SwapAmount::ExactOut { .. }, _, _, true) => {
+            return Err(UniswapV2Error::InvalidSwap(
+                "fee-on-transfer swaps need an exact input".into(),
+            ))
+        }
+        (
+            SwapAmount::ExactIn {
+                amount_in,
+                amount_out_min,
+            },
+            true,
+            _,
+            fee_on_transfer,
+        ) => {
+            let input = match fee_on_transfer {
+                true => IUniswapV2Router::swapExactETHForTokensSupportingFeeOnTransferTokensCall {
+                    amountOutMin: amount_out_min,
+                    path,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+                false => IUniswapV2Router::swapExactETHForTokensCall {
+                    amountOutMin: amount_out_min,
+                    path,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+            };
+            (input, amount_in)
+        }
+        (
+            SwapAmount::ExactIn {
+                amount_in,
+                amount_out_min,
+            },
+            false,
+            true,
+            fee_on_transfer,
+        ) => {
+            let input = match fee_on_transfer {
+                true => IUniswapV2Router::swapExactTokensForETHSupportingFeeOnTransferTokensCall {
+                    amountIn: amount_in,
+                    amountOutMin: amount_out_min,
+                    path,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+                false => IUniswapV2Router::swapExactTokensForETHCall {
+                    amountIn: amount_in,
+                    amountOutMin: amount_out_min,
+                    path,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+            };
+            (input, U256::ZERO)
+        }
+        (
+            SwapAmount::ExactIn {
+                amount_in,
+                amount_out_min,
+            },
+            false,
+            false,
+            fee_on_transfer,
+        ) => {
+            let input = match fee_on_transfer {
+                true => IUniswapV2Router::swapExactTokensForTokensSupportingFeeOnTransferTokensCall {
+                    amountIn: amount_in,
+                    amountOutMin: amount_out_min,
+                    path,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+                false => IUniswapV2Router::swapExactTokensForTokensCall {
+                    amountIn: amount_in,
+                    amountOutMin: amount_out_min,
+                    path,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+            };
+            (input, U256::ZERO)
+        }
+        (
+            SwapAmount::ExactOut {
+                amount_out,
+                amount_in_max,
+            },
+            true,
+            _,
+            false,
+        ) => {
+            let input = IUniswapV2Router::swapETHForExactTokensCall {
+                amountOut: amount_out,
+                path,
+                to,
+                deadline,
+            }
+            .abi_encode();
+            (input, amount_in_max)
+        }
+        (
+            SwapAmount::ExactOut {
+                amount_out,
+                amount_in_max,
+            },
+            false,
+            true,
+            false,
+        ) => {
+            let input = IUniswapV2Router::swapTokensForExactETHCall {
+                amountOut: amount_out,
+                amountInMax: amount_in_max,
+                path,
+                to,
+                deadline,
+            }
+            .abi_encode();
+            (input, U256::ZERO)
+        }
+        (
+            SwapAmount::ExactOut {
+                amount_out,
+                amount_in_max,
+            },
+            false,
+            false,
+            false,
+        ) => {
+            let input = IUniswapV2Router::swapTokensForExactTokensCall {
+                amountOut: amount_out,
+                amountInMax: amount_in_max,
+                path,
+                to,
+                deadline,
+            }
+            .abi_encode();
+            (input, U256::ZERO)
+        }
+    };
+    Ok((input.into(), value))
+}
+
+impl UniswapV2 {
+    /// A fork with the Uniswap fee of 0.3%, change it with [`UniswapV2::with_fee_bps`].
+    pub fn new(factory: Address, router: Address, init_code_hash: B256, weth: Address) -> Self {
+        Self {
+            factory,
+            router,
+            init_code_hash,
+            weth,
+            fee_bps: 30,
+        }
+    }
+
+    pub fn with_fee_bps(mut self, fee_bps: u32) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+
+    /// Address of the pair of two tokens, derived with CREATE2 without querying the factory.
+    pub fn pair_address(&self, token_a: Address, token_b: Address) -> Result<Address> {
+        let (token0, token1) = sort_tokens(token_a, token_b)?;
+        let salt = keccak256([token0.as_slice(), token1.as_slice()].concat());
+        Ok(create2_address(self.factory, salt, self.init_code_hash))
+    }
+
+    /// Amounts along `path` for `amount_in`, `pairs` holds the pair of each hop.
+    pub fn quote_exact_in(&self, amount_in: U256, path: &[Address], pairs: &[Pair]) -> Result<Vec<U256>> {
+        get_amounts_out(amount_in, &self.hop_reserves(path, pairs)?, self.fee_bps)
+    }
+
+    /// Amounts along `path` to receive `amount_out`, `pairs` holds the pair of each hop.
+    pub fn quote_exact_out(&self, amount_out: U256, path: &[Address], pairs: &[Pair]) -> Result<Vec<U256>> {
+        get_amounts_in(amount_out, &self.hop_reserves(path, pairs)?, self.fee_bps)
+    }
+
+    /// Reads the reserves of every pair along `path` at `block` in one `eth_call`.
+    pub async fn fetch_pairs(&self, provider: &EvmProvider, path: &[Address], block: BlockId) -> Result<Vec<Pair>> {
+        let mut multicall = Multicall::new();
+        let mut pairs = Vec::new();
+        for hop in path.windows(2) {
+            let (token0, token1) = sort_tokens(hop[0], hop[1])?;
+            let address = self.pair_address(token0, token1)?;
+            let handle = multicall.add(address, IUniswapV2Pair::getReservesCall {}, true);
+            pairs.push((address, token0, token1, handle));
+        }
+
+        let results = multicall.call(provider, block).await?;
+        pairs
+            .into_iter()
+            .map(|(address, token0, token1, handle)| {
+                // a pair that doesn't exist has no code and returns no data
+                let reserves = results
+                    .get(&handle)
+                    .map_err(|_| UniswapV2Error::PairNotFound(address))?;
+                Ok(Pair {
+                    address,
+                    token0,
+                    token1,
+                    reserve0: U256::from(reserves.reserve0),
+                    reserve1: U256::from(reserves.reserve1),
+                })
+            })
+            .collect()
+    }
+
+    /// Router swap transaction, ready to be filled and signed.
+    pub fn swap(&self, params: &SwapParams) -> Result<TransactionRequest> {
+        if params.eth_in && params.path.first() != Some(&self.weth) {
+            return Err(UniswapV2Error::InvalidPath("ETH swaps start with WETH".into()));
+        }
+        if params.eth_out && params.path.last() != Some(&self.weth) {
+            return Err(UniswapV2Error::InvalidPath("ETH swaps end with WETH".into()));
+        }
+        let (input, value) = swap_calldata(params)?;
+        Ok(self.router_request(input.into(), value))
+    }
+
+    fn hop_reserves(&self, path: &[Address], pairs: &[Pair]) -> Result<Vec<(U256, U256)>> {
+        if path.len() < 2 || pairs.len() != path.len() - 1 {
+            return Err(UniswapV2Error::InvalidPath(format!(
+                "{} tokens and {} pairs",
+                path.len(),
+                pairs.len()
+            )));
+        }
+        path.windows(2)
+            .zip(pairs)
+            .map(|(hop, pair)| {
+                pair.reserves(hop[1])?;
+                pair.reserves(hop[0])
+            })
+            .collect()
+    }
+}
+
+/// Liquidity burned to the zero address by the first mint of a pair.
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+sol! {
+    /// The `permit` message of Uniswap V2 LP tokens.
+    #[derive(Debug, Serialize)]
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+/// `amount_a` at the pool price, `UniswapV2Library.quote`.
+pub fn quote(amount_a: U256, reserve_a: U256, reserve_b: U256) -> Result<U256> {
+    if amount_a.is_zero() {
+        return Err(UniswapV2Error::InsufficientInputAmount);
+    }
+    if reserve_a.is_zero() || reserve_b.is_zero() {
+        return Err(UniswapV2Error::InsufficientLiquidity);
+    }
+    Ok(amount_a.checked_mul(reserve_b).ok_or(UniswapV2Error::Overflow)? / reserve_a)
+}
+
+/// Amounts `addLiquidity` deposits, as the router's `_addLiquidity`: the desired amount of one
+/// token and the other at the pool price. A new pair takes both desired amounts.
+pub fn optimal_liquidity_amounts(
+    desired: (U256, U256),
+    min: (U256, U256),
+    reserves: (U256, U256),
+) -> Result<(U256, U256)> {
+    if reserves.0.is_zero() && reserves.1.is_zero() {
+        return Ok(desired);
+    }
+    let amount_b = quote(desired.0, reserves.0, reserves.1)?;
+    if amount_b <= desired.1 {
+        if amount_b < min.1 {
+            return Err(UniswapV2Error::InvalidLiquidity("insufficient B amount".into()));
+        }
+        return Ok((desired.0, amount_b));
+    }
+    let amount_a = quote(desired.1, reserves.1, reserves.0)?;
+    if amount_a < min.0 {
+        return Err(UniswapV2Error::InvalidLiquidity("insufficient A amount".into()));
+    }
+    Ok((amount_a, desired.1))
+}
+
+/// LP tokens minted for depositing `amounts`, as `UniswapV2Pair.mint`. Ignores the protocol fee
+/// minted on `kLast` growth, so it can overestimate slightly when the fee is on.
+pub fn liquidity_minted(amounts: (U256, U256), reserves: (U256, U256), total_supply: U256) -> Result<U256> {
+    let liquidity = match total_supply.is_zero() {
+        true => {
+            let product = amounts.0.checked_mul(amounts.1).ok_or(UniswapV2Error::Overflow)?;
+            product.root(2).saturating_sub(U256::from(MINIMUM_LIQUIDITY))
+        }
+        false => {
+            if reserves.0.is_zero() || reserves.1.is_zero() {
+                return Err(UniswapV2Error::InsufficientLiquidity);
+            }
+            let liquidity0 = amounts.0.checked_mul(total_supply).ok_or(UniswapV2Error::Overflow)? / reserves.0;
+            let liquidity1 = amounts.1.checked_mul(total_supply).ok_or(UniswapV2Error::Overflow)? / reserves.1;
+            liquidity0.min(liquidity1)
+        }
+    };
+    match liquidity.is_zero() {
+        true => Err(UniswapV2Error::InvalidLiquidity("insufficient liquidity minted".into())),
+        false => Ok(liquidity),
+    }
+}
+
+/// Token amounts returned for burning `liquidity`, as `UniswapV2Pair.burn`.
+pub fn underlying_amounts(liquidity: U256, reserves: (U256, U256), total_supply: U256) -> Result<(U256, U256)> {
+    if total_supply.is_zero() {
+        return Err(UniswapV2Error::InsufficientLiquidity);
+    }
+    if liquidity > total_supply {
+        return Err(UniswapV2Error::InvalidLiquidity(format!(
+            "{} exceeds the total supply {}",
+            liquidity, total_supply
+        )));
+    }
+    let amount0 = liquidity.checked_mul(reserves.0).ok_or(UniswapV2Error::Overflow)? / total_supply;
+    let amount1 = liquidity.checked_mul(reserves.1).ok_or(UniswapV2Error::Overflow)? / total_supply;
+    Ok((amount0, amount1))
+}
+
+/// LP tokens held in a pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityPosition {
+    pub pair: Pair,
+    pub liquidity: U256,
+    pub total_supply: U256,
+}
+
+impl LiquidityPosition {
+    /// Share of the pool, between 0 and 1.
+    pub fn share(&self) -> f64 {
+        match self.total_supply.is_zero() {
+            true => 0.0,
+            false => f64::from(self.liquidity) / f64::from(self.total_supply),
+        }
+    }
+
+    /// Amounts of `token0` and `token1` the whole position withdraws.
+    pub fn amounts(&self) -> Result<(U256, U256)> {
+        underlying_amounts(
+            self.liquidity,
+            (self.pair.reserve0, self.pair.reserve1),
+            self.total_supply,
+        )
+    }
+}
+
+/// An `addLiquidity` deposit, see [`UniswapV2::add_liquidity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddLiquidity {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub amount_a_desired: U256,
+    pub amount_b_desired: U256,
+    pub amount_a_min: U256,
+    pub amount_b_min: U256,
+    pub to: Address,
+    /// Unix timestamp in seconds after which the deposit reverts.
+    pub deadline: u64,
+    /// Deposits the WETH side as native ETH through `addLiquidityETH`.
+    pub eth: bool,
+}
+
+impl AddLiquidity {
+    /// Deposits up to the desired amounts at the current price of `pair`, reverting if the
+    /// price moves more than `slippage_bps` before it's mined.
+    pub fn from_reserves(
+        pair: &Pair,
+        token_a: Address,
+        amount_a_desired: U256,
+        amount_b_desired: U256,
+        slippage_bps: u32,
+        to: Address,
+        deadline: u64,
+    ) -> Result<Self> {
+        let reserves = pair.reserves(token_a)?;
+        let token_b = match token_a == pair.token0 {
+            true => pair.token1,
+            false => pair.token0,
+        };
+        let (amount_a, amount_b) =
+            optimal_liquidity_amounts((amount_a_desired, amount_b_desired), (U256::ZERO, U256::ZERO), reserves)?;
+        Ok(Self {
+            token_a,
+            token_b,
+            amount_a_desired: amount_a,
+            amount_b_desired: amount_b,
+            amount_a_min: min_amount_out(amount_a, slippage_bps),
+            amount_b_min: min_amount_out(amount_b, slippage_bps),
+            to,
+            deadline,
+            eth: false,
+        })
+    }
+
+    pub fn eth(mut self) -> Self {
+        self.eth = true;
+        self
+    }
+}
+
+/// A `removeLiquidity` withdrawal, see [`UniswapV2::remove_liquidity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoveLiquidity {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub liquidity: U256,
+    pub amount_a_min: U256,
+    pub amount_b_min: U256,
+    pub to: Address,
+    /// Unix timestamp in seconds after which the withdrawal (and its permit) expires.
+    pub deadline: u64,
+    /// Withdraws the WETH side as native ETH.
+    pub eth: bool,
+    /// Uses the `SupportingFeeOnTransferTokens` variants, only available with `eth`.
+    pub fee_on_transfer: bool,
+}
+
+impl RemoveLiquidity {
+    /// Burns `liquidity` of `position`, accepting `slippage_bps` less than its current value.
+    pub fn from_position(
+        position: &LiquidityPosition,
+        token_a: Address,
+        liquidity: U256,
+        slippage_bps: u32,
+        to: Address,
+        deadline: u64,
+    ) -> Result<Self> {
+        let pair = position.pair;
+        let (amount0, amount1) = underlying_amounts(liquidity, (pair.reserve0, pair.reserve1), position.total_supply)?;
+        let (token_b, amount_a, amount_b) = match token_a {
+            token if token == pair.token0 => (pair.token1, amount0, amount1),
+            token if token == pair.token1 => (pair.token0, amount1, amount0),
+            token => {
+                return Err(UniswapV2Error::InvalidPath(format!(
+                    "{} is not in pair {}",
+                    token, pair.address
+                )))
+            }
+        };
+        Ok(Self {
+            token_a,
+            token_b,
+            liquidity,
+            amount_a_min: min_amount_out(amount_a, slippage_bps),
+            amount_b_min: min_amount_out(amount_b, slippage_bps),
+            to,
+            deadline,
+            eth: false,
+            fee_on_transfer: false,
+        })
+    }
+
+    pub fn eth(mut self) -> Self {
+        self.eth = true;
+        self
+    }
+
+    pub fn supporting_fee_on_transfer(mut self) -> Self {
+        self.fee_on_transfer = true;
+        self
+    }
+}
+
+/// What an LP token owner signs instead of approving the router, see
+/// [`UniswapV2::remove_liquidity_with_permit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LpPermit {
+    /// Name of the LP token, the EIP-712 domain name (`Uniswap V2`, `Pancake LPs`, ...).
+    pub name: String,
+    pub chain_id: u64,
+    pub nonce: U256,
+    /// Permits `U256::MAX` instead of the withdrawn liquidity.
+    pub approve_max: bool,
+}
+
+/// `v`, `r` and `s` of a signed [`Permit`].
+type PermitSignature = (u8, B256, B256);
+
+impl UniswapV2 {
+    /// Reads the reserves of the pair of two tokens at `block`.
+    pub async fn fetch_pair(
+        &self,
+        provider: &EvmProvider,
+        token_a: Address,
+        token_b: Address,
+        block: BlockId,
+    ) -> Result<Pair> {
+        Ok(self.fetch_pairs(provider, &[token_a, token_b], block).await?.remove(0))
+    }
+
+    /// Reads the LP balance of `owner`, the total supply and the reserves of a pair in one `eth_call`.
+    pub async fn fetch_position(
+        &self,
+        provider: &EvmProvider,
+        token_a: Address,
+        token_b: Address,
+        owner: Address,
+        block: BlockId,
+    ) -> Result<LiquidityPosition> {
+        let (token0, token1) = sort_tokens(token_a, token_b)?;
+        let address = self.pair_address(token0, token1)?;
+        let mut multicall = Multicall::new();
+        let reserves = multicall.add(address, IUniswapV2Pair::getReservesCall {}, true);
+        let total_supply = multicall.add(address, IUniswapV2Pair::totalSupplyCall {}, true);
+        let balance = multicall.add(address, IUniswapV2Pair::balanceOfCall { owner }, true);
+
+        let results = multicall.call(provider, block).await?;
+        let not_found = |_| UniswapV2Error::PairNotFound(address);
+        let reserves = results.get(&reserves).map_err(not_found)?;
+        Ok(LiquidityPosition {
+            pair: Pair {
+                address,
+                token0,
+                token1,
+                reserve0: U256::from(reserves.reserve0),
+                reserve1: U256::from(reserves.reserve1),
+            },
+            liquidity: results.get(&balance).map_err(not_found)?._0,
+            total_supply: results.get(&total_supply).map_err(not_found)?._0,
+        })
+    }
+
+    /// Reads the domain name and permit nonce of `owner` for the LP token of `pair`.
+    pub async fn fetch_permit(&self, provider: &EvmProvider, pair: Address, owner: Address) -> Result<LpPermit> {
+        let mut multicall = Multicall::new();
+        let name = multicall.add(pair, IUniswapV2Pair::nameCall {}, true);
+        let nonce = multicall.add(pair, IUniswapV2Pair::noncesCall { owner }, true);
+        let (chain_id, results) =
+            tokio::try_join!(provider.get_chain_id(), multicall.call(provider, BlockId::latest()))?;
+
+        let not_found = |_| UniswapV2Error::PairNotFound(pair);
+        Ok(LpPermit {
+            name: results.get(&name).map_err(not_found)?._0,
+            chain_id,
+            nonce: results.get(&nonce).map_err(not_found)?._0,
+            approve_max: false,
+        })
+    }
+
+    /// Router deposit transaction, ready to be filled and signed. Both tokens (or the token of
+    /// an ETH deposit) must be approved to the router.
+    pub fn add_liquidity(&self, params: &AddLiquidity) -> Result<TransactionRequest> {
+        let deadline = U256::from(params.deadline);
+        if !params.eth {
+            let input = IUniswapV2Router::addLiquidityCall {
+                tokenA: params.token_a,
+                tokenB: params.token_b,
+                amountADesired: params.amount_a_desired,
+                amountBDesired: params.amount_b_desired,
+                amountAMin: params.amount_a_min,
+                amountBMin: params.amount_b_min,
+                to: params.to,
+                deadline,
+            }
+            .abi_encode();
+            return Ok(self.router_request(input, U256::ZERO));
+        }
+
+        let (token, amount_token_desired, amount_token_min, amount_eth, amount_eth_min) = match self.weth {
+            weth if weth == params.token_b => (
+                params.token_a,
+                params.amount_a_desired,
+                params.amount_a_min,
+                params.amount_b_desired,
+                params.amount_b_min,
+            ),
+            weth if weth == params.token_a => (
+                params.token_b,
+                params.amount_b_desired,
+                params.amount_b_min,
+                params.amount_a_desired,
+                params.amount_a_min,
+            ),
+            _ => return Err(UniswapV2Error::InvalidLiquidity("ETH deposits need a WETH pair".into())),
+        };
+        let input = IUniswapV2Router::addLiquidityETHCall {
+            token,
+            amountTokenDesired: amount_token_desired,
+            amountTokenMin: amount_token_min,
+            amountETHMin: amount_eth_min,
+            to: params.to,
+            deadline,
+        }
+        .abi_encode();
+        Ok(self.router_request(input, amount_eth))
+    }
+
+    /// Router withdrawal transaction, the LP token must be approved to the router.
+    pub fn remove_liquidity(&self, params: &RemoveLiquidity) -> Result<TransactionRequest> {
+        let input = self.remove_liquidity_calldata(params, None)?;
+        Ok(self.router_request(input, U256::ZERO))
+    }
+
+    /// Router withdrawal transaction approving the LP token in the same call, with a permit
+    /// signed by `signer` (the LP token owner) that expires at the withdrawal deadline.
+    pub fn remove_liquidity_with_permit(
+        &self,
+        params: &RemoveLiquidity,
+        permit: &LpPermit,
+        signer: &EvmSigner,
+    ) -> Result<TransactionRequest> {
+        let pair = self.pair_address(params.token_a, params.token_b)?;
+        let message = Permit {
+            owner: signer.address(),
+            spender: self.router,
+            value: match permit.approve_max {
+                true => U256::MAX,
+                false => params.liquidity,
+            },
+            nonce: permit.nonce,
+            deadline: U256::from(params.deadline),
+        };
+        let domain = Eip712Domain::new(
+            Some(permit.name.clone().into()),
+            Some("1".into()),
+            Some(U256::from(permit.chain_id)),
+            Some(pair),
+            None,
+        );
+        let signature = signer.sign_eip712(domain, &message)?;
+        let signature = hex::decode(&signature)
+            .ok()
+            .and_then(|bytes| PrimitiveSignature::try_from(bytes.as_slice()).ok())
+            .ok_or_else(|| EvmSignerError::SignatureError(format!("invalid signature {}", signature)))?;
+        let signature = (27 + signature.v() as u8, signature.r().into(), signature.s().into());
+
+        let input = self.remove_liquidity_calldata(params, Some((permit.approve_max, signature)))?;
+        Ok(self.router_request(input, U256::ZERO))
+    }
+
+    fn remove_liquidity_calldata(
+        &self,
+        params: &RemoveLiquidity,
+        permit: Option<(bool, PermitSignature)>,
+    ) -> Result<Vec<u8>> {
+        let (liquidity, to, deadline) = (params.liquidity, params.to, U256::from(params.deadline));
+        if !params.eth {
+            if params.fee_on_transfer {
+                return Err(UniswapV2Error::InvalidLiquidity(
+                    "fee-on-transfer withdrawals are only available for ETH pairs".into(),
+                ));
+            }
+            let input = match permit {
+                None => IUniswapV2Router::removeLiquidityCall {
+                    tokenA: params.token_a,
+                    tokenB: params.token_b,
+                    liquidity,
+                    amountAMin: params.amount_a_min,
+                    amountBMin: params.amount_b_min,
+                    to,
+                    deadline,
+                }
+                .abi_encode(),
+                Some((approve_max, (v, r, s))) => IUniswapV2Router::removeLiquidityWithPermitCall {
+                    tokenA: params.token_a,
+                    tokenB: params.token_b,
+                    liquidity,
+                    amountAMin: params.amount_a_min,
+                    amountBMin: params.amount_b_min,
+                    to,
+                    deadline,
+                    approveMax: approve_max,
+                    v,
+                    r,
+                    s,
+                }
+                .abi_encode(),
+            };
+            return Ok(input);
+        }
+
+        let (token, amount_token_min, amount_eth_min) = match self.weth {
+            weth if weth == params.token_b => (params.token_a, params.amount_a_min, params.amount_b_min),
+            weth if weth == params.token_a => (params.token_b, params.amount_b_min, params.amount_a_min),
+            _ => {
+                return Err(UniswapV2Error::InvalidLiquidity(
+                    "ETH withdrawals need a WETH pair".into(),
+                ))
+            }
+        };
+        let input = match (permit, params.fee_on_transfer) {
+            (None, false) => IUniswapV2Router::removeLiquidityETHCall {
+                token,
+                liquidity,
+                amountTokenMin: amount_token_min,
+                amountETHMin: amount_eth_min,
+                to,
+                deadline,
+            }
+            .abi_encode(),
+            (None, true) => IUniswapV2Router::removeLiquidityETHSupportingFeeOnTransferTokensCall {
+                token,
+                liquidity,
+                amountTokenMin: amount_token_min,
+                amountETHMin: amount_eth_min,
+                to,
+                deadline,
+            }
+            .abi_encode(),
+            (Some((approve_max, (v, r, s))), false) => IUniswapV2Router::removeLiquidityETHWithPermitCall {
+                token,
+                liquidity,
+                amountTokenMin: amount_token_min,
+                amountETHMin: amount_eth_min,
+                to,
+                deadline,
+                approveMax: approve_max,
+                v,
+                r,
+                s,
+            }
+            .abi_encode(),
+            (Some((approve_max, (v, r, s))), true) => {
+                IUniswapV2Router::removeLiquidityETHWithPermitSupportingFeeOnTransferTokensCall {
+                    token,
+                    liquidity,
+                    amountTokenMin: amount_token_min,
+                    amountETHMin: amount_eth_min,
+                    to,
+                    deadline,
+                    approveMax: approve_max,
+                    v,
+                    r,
+                    s,
+                }
+                .abi_encode()
+            }
+        };
+        Ok(input)
+    }
+
+    fn router_request(&self, input: Vec<u8>, value: U256) -> TransactionRequest {
+        let tx = TransactionRequest::default()
+            .to(self.router)
+            .input(Bytes::from(input).into());
+        match value.is_zero() {
+            true => tx,
+            false => tx.value(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::{aggregate3, MockNode};
+    use crate::provider::test_utils::{account, ether};
+    use alloy_sol_types::{SolStruct, SolValue};
+    use serde_json::json;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const RECIPIENT: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+
+    #[test]
+    fn test_pair_address() {
+        assert_eq!(
+            UNISWAP_V2.pair_address(WETH, USDC).unwrap(),
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")
+        );
+        assert_eq!(
+            UNISWAP_V2.pair_address(USDC, WETH).unwrap(),
+            UNISWAP_V2.pair_address(WETH, USDC).unwrap()
+        );
+        // WBNB/BUSD
+        assert_eq!(
+            PANCAKESWAP_V2
+                .pair_address(
+                    PANCAKESWAP_V2.weth,
+                    address!("e9e7CEA3DedcA5984780Bafc599bD69ADd087D56")
+                )
+                .unwrap(),
+            address!("58F876857a02D6762E0101bb5C46A8c1ED44Dc16")
+        );
+        assert!(matches!(
+            sort_tokens(USDC, USDC),
+            Err(UniswapV2Error::IdenticalAddresses)
+        ));
+        assert!(matches!(
+            sort_tokens(Address::ZERO, USDC),
+            Err(UniswapV2Error::ZeroAddress)
+        ));
+    }
+
+    #[test]
+    fn test_amounts() {
+        // values from the UniswapV2Router02 `getAmountOut`/`getAmountIn`
+        assert_eq!(
+            get_amount_out(U256::from(1000), ether(1), ether(2), 30).unwrap(),
+            U256::from(1993)
+        );
+        assert_eq!(
+            get_amount_out(ether(1), ether(100), ether(200), 30).unwrap(),
+            U256::from(1974316068794122597u64)
+        );
+        assert_eq!(
+            get_amount_in(U256::from(1993), ether(1), ether(2), 30).unwrap(),
+            U256::from(1000)
+        );
+        assert_eq!(
+            get_amount_in(ether(1), ether(100), ether(200), 30).unwrap(),
+            U256::from(504024636724243082u64)
+        );
+        // PancakeSwap charges 0.25%
+        assert_eq!(
+            get_amount_out(ether(1), ether(100), ether(200), 25).unwrap(),
+            U256::from(1975296418228173964u64)
+        );
+
+        assert!(matches!(
+            get_amount_out(U256::ZERO, ether(1), ether(1), 30),
+            Err(UniswapV2Error::InsufficientInputAmount)
+        ));
+        assert!(matches!(
+            get_amount_in(ether(2), ether(1), ether(2), 30),
+            Err(UniswapV2Error::InsufficientLiquidity)
+        ));
+        assert!(matches!(
+            get_amount_out(ether(1), ether(1), ether(1), 10_000),
+            Err(UniswapV2Error::InvalidFee(_))
+        ));
+        assert!(matches!(
+            get_amount_out(U256::MAX, ether(1), ether(1), 30),
+            Err(UniswapV2Error::Overflow)
+        ));
+
+        assert_eq!(min_amount_out(U256::from(10_000), 50), U256::from(9_950));
+        assert_eq!(max_amount_in(U256::from(10_000), 50), U256::from(10_050));
+    }
+
+    #[test]
+    fn test_multi_hop_quote() {
+        let pair = |token_a, token_b, reserve_a, reserve_b| {
+            let (token0, token1) = sort_tokens(token_a, token_b).unwrap();
+            let (reserve0, reserve1) = if token0 == token_a {
+                (reserve_a, reserve_b)
+            } else {
+                (reserve_b, reserve_a)
+            };
+            Pair {
+                address: UNISWAP_V2.pair_address(token0, token1).unwrap(),
+                token0,
+                token1,
+                reserve0,
+                reserve1,
+            }
+        };
+        let path = [USDC, WETH, DAI];
+        let pairs = [
+            pair(USDC, WETH, U256::from(40_000_000_000_000u64), ether(20_000)),
+            pair(WETH, DAI, ether(10_000), ether(20_000_000)),
+        ];
+
+        let amounts = UNISWAP_V2
+            .quote_exact_in(U256::from(2_000_000_000u64), &path, &pairs)
+            .unwrap();
+        let first = get_amount_out(U256::from(2_000_000_000u64), pairs[0].reserve0, pairs[0].reserve1, 30).unwrap();
+        assert_eq!(amounts[1], first);
+        assert_eq!(
+            amounts[2],
+            get_amount_out(first, ether(10_000), ether(20_000_000), 30).unwrap()
+        );
+
+        let amounts_in = UNISWAP_V2.quote_exact_out(amounts[2], &path, &pairs).unwrap();
+        assert_eq!(amounts_in[2], amounts[2]);
+        assert!(amounts_in[0] <= U256::from(2_000_000_000u64));
+
+        assert!(matches!(
+            UNISWAP_V2.quote_exact_in(U256::from(1), &[USDC, DAI], &pairs[..1]),
+            Err(UniswapV2Error::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_swap_calldata() {
+        let amount_in = ether(1);
+        let params =
+            SwapParams::exact_in(amount_in, U256::from(1), vec![WETH, USDC], RECIPIENT, 1_700_000_000).eth_in();
+        let tx = UNISWAP_V2.swap(&params).unwrap();
+        assert_eq!((tx.to, tx.value), (Some(UNISWAP_V2.router.into()), Some(amount_in)));
+        let call = IUniswapV2Router::swapExactETHForTokensCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(
+            (call.path, call.to, call.deadline),
+            (vec![WETH, USDC], RECIPIENT, U256::from(1_700_000_000))
+        );
+
+        let (input, value) = swap_calldata(&params.clone().supporting_fee_on_transfer()).unwrap();
+        assert_eq!(value, amount_in);
+        assert_eq!(
+            input[..4],
+            IUniswapV2Router::swapExactETHForTokensSupportingFeeOnTransferTokensCall::SELECTOR
+        );
+
+        let params = SwapParams::exact_out(U256::from(100), U256::from(120), vec![USDC, WETH], RECIPIENT, 0).eth_out();
+        let tx = UNISWAP_V2.swap(&params).unwrap();
+        assert_eq!(tx.value, None);
+        let call = IUniswapV2Router::swapTokensForExactETHCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!((call.amountOut, call.amountInMax), (U256::from(100), U256::from(120)));
+
+        let selectors = [
+            (
+                SwapParams::exact_in(U256::from(1), U256::ZERO, vec![USDC, DAI], RECIPIENT, 0),
+                IUniswapV2Router::swapExactTokensForTokensCall::SELECTOR,
+            ),
+            (
+                SwapParams::exact_in(U256::from(1), U256::ZERO, vec![USDC, WETH], RECIPIENT, 0).eth_out(),
+                IUniswapV2Router::swapExactTokensForETHCall::SELECTOR,
+            ),
+            (
+                SwapParams::exact_in(U256::from(1), U256::ZERO, vec![USDC, WETH], RECIPIENT, 0)
+                    .eth_out()
+                    .supporting_fee_on_transfer(),
+                IUniswapV2Router::swapExactTokensForETHSupportingFeeOnTransferTokensCall::SELECTOR,
+            ),
+            (
+                SwapParams::exact_in(U256::from(1), U256::ZERO, vec![USDC, DAI], RECIPIENT, 0)
+                    .supporting_fee_on_transfer(),
+                IUniswapV2Router::swapExactTokensForTokensSupportingFeeOnTransferTokensCall::SELECTOR,
+            ),
+            (
+                SwapParams::exact_out(U256::from(1), U256::from(2), vec![WETH, DAI], RECIPIENT, 0).eth_in(),
+                IUniswapV2Router::swapETHForExactTokensCall::SELECTOR,
+            ),
+            (
+                SwapParams::exact_out(U256::from(1), U256::from(2), vec![USDC, DAI], RECIPIENT, 0),
+                IUniswapV2Router::swapTokensForExactTokensCall::SELECTOR,
+            ),
+        ];
+        for (params, selector) in selectors {
+            assert_eq!(UNISWAP_V2.swap(&params).unwrap().input.input().unwrap()[..4], selector);
+        }
+
+        let invalid = SwapParams::exact_out(U256::from(1), U256::from(2), vec![USDC, DAI], RECIPIENT, 0);
+        assert!(matches!(
+            swap_calldata(&invalid.supporting_fee_on_transfer()),
+            Err(UniswapV2Error::InvalidSwap(_))
+        ));
+        let invalid = SwapParams::exact_in(U256::from(1), U256::ZERO, vec![USDC, DAI], RECIPIENT, 0).eth_in();
+        assert!(matches!(UNISWAP_V2.swap(&invalid), Err(UniswapV2Error::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pairs() {
+        let weth_usdc = UNISWAP_V2.pair_address(WETH, USDC).unwrap();
+        let node = MockNode::multicall(move |call| {
+            // USDC is token0
+            Some(match call.target == weth_usdc {
+                true => (U256::from(40_000_000_000_000u64), ether(20_000), U256::from(1)).abi_encode_params(),
+                false => vec![],
+            })
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let pairs = UNISWAP_V2
+            .fetch_pairs(&provider, &[WETH, USDC], BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!((pairs[0].address, pairs[0].token0), (weth_usdc, USDC));
+        assert_eq!(
+            pairs[0].reserves(WETH).unwrap(),
+            (ether(20_000), U256::from(40_000_000_000_000u64))
+        );
+        let amounts = UNISWAP_V2.quote_exact_in(ether(1), &[WETH, USDC], &pairs).unwrap();
+        assert_eq!(amounts[1], U256::from(1_993_900_604u64));
+
+        let missing = UNISWAP_V2.pair_address(WETH, DAI).unwrap();
+        assert!(matches!(
+            UNISWAP_V2.fetch_pairs(&provider, &[USDC, WETH, DAI], BlockId::latest()).await,
+            Err(UniswapV2Error::PairNotFound(pair)) if pair == missing
+        ));
+    }
+
+    fn usdc_weth(reserve_usdc: U256, reserve_weth: U256) -> Pair {
+        Pair {
+            address: UNISWAP_V2.pair_address(USDC, WETH).unwrap(),
+            token0: USDC,
+            token1: WETH,
+            reserve0: reserve_usdc,
+            reserve1: reserve_weth,
+        }
+    }
+
+    #[test]
+    fn test_liquidity_math() {
+        assert_eq!(quote(ether(1), ether(10), ether(20)).unwrap(), ether(2));
+        assert!(matches!(
+            quote(ether(1), U256::ZERO, ether(1)),
+            Err(UniswapV2Error::InsufficientLiquidity)
+        ));
+
+        let reserves = (ether(10), ether(20));
+        let none = (U256::ZERO, U256::ZERO);
+        // B is the limiting side, then A
+        assert_eq!(
+            optimal_liquidity_amounts((ether(1), ether(3)), none, reserves).unwrap(),
+            (ether(1), ether(2))
+        );
+        assert_eq!(
+            optimal_liquidity_amounts((ether(2), ether(2)), none, reserves).unwrap(),
+            (ether(1), ether(2))
+        );
+        assert!(matches!(
+            optimal_liquidity_amounts((ether(2), ether(2)), (ether(2), U256::ZERO), reserves),
+            Err(UniswapV2Error::InvalidLiquidity(_))
+        ));
+        // a new pair takes the desired amounts
+        assert_eq!(
+            optimal_liquidity_amounts((ether(1), ether(3)), none, none).unwrap(),
+            (ether(1), ether(3))
+        );
+
+        assert_eq!(
+            liquidity_minted((ether(1), ether(4)), none, U256::ZERO).unwrap(),
+            ether(2) - U256::from(MINIMUM_LIQUIDITY)
+        );
+        assert_eq!(
+            liquidity_minted((ether(1), ether(4)), reserves, ether(100)).unwrap(),
+            ether(10)
+        );
+        assert!(liquidity_minted((U256::from(10), U256::from(10)), none, U256::ZERO).is_err());
+
+        assert_eq!(
+            underlying_amounts(ether(25), reserves, ether(100)).unwrap(),
+            (ether(10) / U256::from(4), ether(5))
+        );
+        assert!(matches!(
+            underlying_amounts(ether(101), reserves, ether(100)),
+            Err(UniswapV2Error::InvalidLiquidity(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_liquidity() {
+        // 2000 USDC per WETH
+        let pair = usdc_weth(U256::from(20_000_000_000_000u64), ether(10_000));
+        let params = AddLiquidity::from_reserves(
+            &pair,
+            WETH,
+            ether(1),
+            U256::from(5_000_000_000u64),
+            50,
+            RECIPIENT,
+            1_700_000_000,
+        )
+        .unwrap();
+        assert_eq!((params.token_a, params.token_b), (WETH, USDC));
+        assert_eq!(params.amount_b_desired, U256::from(2_000_000_000u64));
+        assert_eq!(params.amount_b_min, U256::from(1_990_000_000u64));
+
+        let tx = UNISWAP_V2.add_liquidity(&params).unwrap();
+        assert_eq!(tx.value, None);
+        let call = IUniswapV2Router::addLiquidityCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(
+            (call.tokenA, call.amountADesired, call.amountAMin),
+            (WETH, ether(1), min_amount_out(ether(1), 50))
+        );
+
+        let tx = UNISWAP_V2.add_liquidity(&params.eth()).unwrap();
+        assert_eq!((tx.to, tx.value), (Some(UNISWAP_V2.router.into()), Some(ether(1))));
+        let call = IUniswapV2Router::addLiquidityETHCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(
+            (call.token, call.amountTokenDesired),
+            (USDC, U256::from(2_000_000_000u64))
+        );
+        assert_eq!(call.amountETHMin, min_amount_out(ether(1), 50));
+
+        let mut params = params.eth();
+        params.token_a = DAI;
+        assert!(matches!(
+            UNISWAP_V2.add_liquidity(&params),
+            Err(UniswapV2Error::InvalidLiquidity(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_liquidity_with_permit() {
+        let position = LiquidityPosition {
+            pair: usdc_weth(U256::from(20_000_000_000_000u64), ether(10_000)),
+            liquidity: ether(2),
+            total_supply: ether(200),
+        };
+        assert_eq!(position.share(), 0.01);
+        assert_eq!(
+            position.amounts().unwrap(),
+            (U256::from(200_000_000_000u64), ether(100))
+        );
+
+        let params = RemoveLiquidity::from_position(&position, WETH, ether(1), 100, RECIPIENT, 1_700_000_000).unwrap();
+        assert_eq!(
+            (params.token_b, params.amount_a_min),
+            (USDC, min_amount_out(ether(50), 100))
+        );
+
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let permit = LpPermit {
+            name: "Uniswap V2".into(),
+            chain_id: 1,
+            nonce: U256::from(3),
+            approve_max: false,
+        };
+        let tx = UNISWAP_V2
+            .remove_liquidity_with_permit(&params, &permit, &signer)
+            .unwrap();
+        let call =
+            IUniswapV2Router::removeLiquidityWithPermitCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!((call.tokenA, call.liquidity, call.approveMax), (WETH, ether(1), false));
+
+        // the router calls `pair.permit(owner, router, liquidity, deadline, v, r, s)`
+        let message = Permit {
+            owner: signer.address(),
+            spender: UNISWAP_V2.router,
+            value: ether(1),
+            nonce: U256::from(3),
+            deadline: call.deadline,
+        };
+        assert_eq!(
+            message.eip712_type_hash(),
+            b256!("6e71edae12b1b97f4d1f60370fef10105fa2faae0126114a169c64845d6126c9")
+        );
+        let domain = Eip712Domain::new(
+            Some("Uniswap V2".into()),
+            Some("1".into()),
+            Some(U256::from(1)),
+            Some(position.pair.address),
+            None,
+        );
+        let signature = format!("0x{}{}{:02x}", hex::encode(call.r), hex::encode(call.s), call.v);
+        assert_eq!(
+            EvmSigner::recover_eip712_address(domain, &message, &signature).unwrap(),
+            signer.address()
+        );
+
+        let params = params.eth().supporting_fee_on_transfer();
+        let permit = LpPermit {
+            approve_max: true,
+            ..permit
+        };
+        let tx = UNISWAP_V2
+            .remove_liquidity_with_permit(&params, &permit, &signer)
+            .unwrap();
+        let call = IUniswapV2Router::removeLiquidityETHWithPermitSupportingFeeOnTransferTokensCall::abi_decode(
+            tx.input.input().unwrap(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            (call.token, call.amountETHMin, call.approveMax),
+            (USDC, params.amount_a_min, true)
+        );
+
+        let tx = UNISWAP_V2
+            .remove_liquidity(&RemoveLiquidity {
+                fee_on_transfer: false,
+                ..params
+            })
+            .unwrap();
+        assert_eq!(
+            tx.input.input().unwrap()[..4],
+            IUniswapV2Router::removeLiquidityETHCall::SELECTOR
+        );
+        assert!(matches!(
+            UNISWAP_V2.remove_liquidity(&RemoveLiquidity { eth: false, ..params }),
+            Err(UniswapV2Error::InvalidLiquidity(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_position() {
+        let node = MockNode::start(|method, params| {
+            if method == "eth_chainId" {
+                return Ok(json!("0x1"));
+            }
+            aggregate3(params, |call| {
+                Some(match call.callData[..4].try_into().unwrap() {
+                    IUniswapV2Pair::getReservesCall::SELECTOR => {
+                        (U256::from(20_000_000_000_000u64), ether(10_000), U256::from(1)).abi_encode_params()
+                    }
+                    IUniswapV2Pair::totalSupplyCall::SELECTOR => ether(200).abi_encode(),
+                    IUniswapV2Pair::balanceOfCall::SELECTOR => ether(2).abi_encode(),
+                    IUniswapV2Pair::nameCall::SELECTOR => "Uniswap V2".to_string().abi_encode(),
+                    IUniswapV2Pair::noncesCall::SELECTOR => U256::from(3).abi_encode(),
+                    _ => Vec::new(),
+                })
+            })
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let position = UNISWAP_V2
+            .fetch_position(&provider, WETH, USDC, RECIPIENT, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(
+            position.pair,
+            usdc_weth(U256::from(20_000_000_000_000u64), ether(10_000))
+        );
+        assert_eq!((position.liquidity, position.total_supply), (ether(2), ether(200)));
+
+        let permit = UNISWAP_V2
+            .fetch_permit(&provider, position.pair.address, RECIPIENT)
+            .await
+            .unwrap();
+        assert_eq!(
+            permit,
+            LpPermit {
+                name: "Uniswap V2".into(),
+                chain_id: 1,
+                nonce: U256::from(3),
+                approve_max: false,
+            }
+        );
+    }
+}
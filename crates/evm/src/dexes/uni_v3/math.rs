@@ -0,0 +1,609 @@
+//! Ports of the v3-core math libraries (`FullMath`, `TickMath`, `SqrtPriceMath`, `SwapMath`),
+//! returning errors where the Solidity versions revert.
+
+use alloy_primitives::{uint, I256, U256, U512};
+
+use crate::dexes::uni_v3::{Result, UniswapV3Error};
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = 887272;
+
+/// `get_sqrt_ratio_at_tick(MIN_TICK)`
+pub const MIN_SQRT_RATIO: U256 = uint!(4295128739_U256);
+/// `get_sqrt_ratio_at_tick(MAX_TICK)`
+pub const MAX_SQRT_RATIO: U256 = uint!(1461446703485210103287273052203988822378723970342_U256);
+
+pub const Q96: U256 = uint!(0x1000000000000000000000000_U256);
+pub const Q128: U256 = uint!(0x100000000000000000000000000000000_U256);
+
+const MAX_U160: U256 = uint!(0xffffffffffffffffffffffffffffffffffffffff_U256);
+
+/// Fees are in hundredths of a bip, 3000 is 0.3%.
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+// `sqrt(1.0001^-(2^i))` as Q128.128, for i in 0..20
+const TICK_RATIOS: [U256; 20] = [
+    uint!(0xfffcb933bd6fad37aa2d162d1a594001_U256),
+    uint!(0xfff97272373d413259a46990580e213a_U256),
+    uint!(0xfff2e50f5f656932ef12357cf3c7fdcc_U256),
+    uint!(0xffe5caca7e10e4e61c3624eaa0941cd0_U256),
+    uint!(0xffcb9843d60f6159c9db58835c926644_U256),
+    uint!(0xff973b41fa98c081472e6896dfb254c0_U256),
+    uint!(0xff2ea16466c96a3843ec78b326b52861_U256),
+    uint!(0xfe5dee046a99a2a811c461f1969c3053_U256),
+    uint!(0xfcbe86c7900a88aedcffc83b479aa3a4_U256),
+    uint!(0xf987a7253ac413176f2b074cf7815e54_U256),
+    uint!(0xf3392b0822b70005940c7a398e4b70f3_U256),
+    uint!(0xe7159475a2c29b7443b29c7fa6e889d9_U256),
+    uint!(0xd097f3bdfd2022b8845ad8f792aa5825_U256),
+    uint!(0xa9f746462d870fdf8a65dc1f90e061e5_U256),
+    uint!(0x70d869a156d2a1b890bb3df62baf32f7_U256),
+    uint!(0x31be135f97d08fd981231505542fcfa6_U256),
+    uint!(0x9aa508b5b7a84e1c677de54f3e99bc9_U256),
+    uint!(0x5d6af8dedb81196699c329225ee604_U256),
+    uint!(0x2216e584f5fa1ea926041bedfe98_U256),
+    uint!(0x48a170391f7dc42444e8fa2_U256),
+];
+
+fn math_error(reason: &str) -> UniswapV3Error {
+    UniswapV3Error::MathError(reason.to_string())
+}
+
+/// `floor(a * b / denominator)` with a 512-bit intermediate product.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    if denominator.is_zero() {
+        return Err(math_error("division by zero"));
+    }
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    U256::checked_from_limbs_slice(result.as_limbs()).ok_or_else(|| math_error("mul_div overflow"))
+}
+
+/// `ceil(a * b / denominator)` with a 512-bit intermediate product.
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    let result = mul_div(a, b, denominator)?;
+    match (U512::from(a) * U512::from(b) % U512::from(denominator)).is_zero() {
+        true => Ok(result),
+        false => result
+            .checked_add(U256::from(1))
+            .ok_or_else(|| math_error("mul_div overflow")),
+    }
+}
+
+fn div_rounding_up(a: U256, b: U256) -> U256 {
+    let quotient = a / b;
+    match (a % b).is_zero() {
+        true => quotient,
+        false => quotient + U256::from(1),
+    }
+}
+
+/// `sqrt(1.0001^tick) * 2^96`, `TickMath.getSqrtRatioAtTick`.
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(UniswapV3Error::InvalidTick(tick));
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = match abs_tick & 1 {
+        0 => Q128,
+        _ => TICK_RATIOS[0],
+    };
+    for (i, factor) in TICK_RATIOS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << i) != 0 {
+            ratio = (ratio * factor) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    // Q128.128 to Q64.96, rounding up so that the tick at the result is `tick`
+    let rounding = match (ratio & U256::from(u32::MAX)).is_zero() {
+        true => U256::ZERO,
+        false => U256::from(1),
+    };
+    Ok((ratio >> 32) + rounding)
+}
+
+/// The greatest tick whose ratio is at most `sqrt_price_x96`, `TickMath.getTickAtSqrtRatio`.
+/// Found by binary search over [`get_sqrt_ratio_at_tick`], which gives the same result.
+pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U256) -> Result<i32> {
+    if sqrt_price_x96 < MIN_SQRT_RATIO || sqrt_price_x96 >= MAX_SQRT_RATIO {
+        return Err(UniswapV3Error::InvalidSqrtPrice(sqrt_price_x96));
+    }
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        match get_sqrt_ratio_at_tick(mid)? <= sqrt_price_x96 {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+    Ok(low)
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount0RoundingUp`
+pub fn get_next_sqrt_price_from_amount0_rounding_up(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> Result<U256> {
+    if amount.is_zero() {
+        return Ok(sqrt_price_x96);
+    }
+    let numerator1 = U256::from(liquidity) << 96usize;
+    let product = amount.checked_mul(sqrt_price_x96);
+
+    if add {
+        if let Some(product) = product {
+            if let Some(denominator) = numerator1.checked_add(product) {
+                return mul_div_rounding_up(numerator1, sqrt_price_x96, denominator);
+            }
+        }
+        let denominator = (numerator1 / sqrt_price_x96)
+            .checked_add(amount)
+            .ok_or_else(|| math_error("price overflow"))?;
+        return Ok(div_rounding_up(numerator1, denominator));
+    }
+
+    match product {
+        Some(product) if numerator1 > product => {
+            let next = mul_div_rounding_up(numerator1, sqrt_price_x96, numerator1 - product)?;
+            match next <= MAX_U160 {
+                true => Ok(next),
+                false => Err(math_error("price overflow")),
+            }
+        }
+        _ => Err(math_error("not enough liquidity for the output")),
+    }
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount1RoundingDown`
+pub fn get_next_sqrt_price_from_amount1_rounding_down(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> Result<U256> {
+    let liquidity = U256::from(liquidity);
+    if add {
+        let quotient = match amount <= MAX_U160 {
+            true => (amount << 96) / liquidity,
+            false => mul_div(amount, Q96, liquidity)?,
+        };
+        return match sqrt_price_x96.checked_add(quotient) {
+            Some(next) if next <= MAX_U160 => Ok(next),
+            _ => Err(math_error("price overflow")),
+        };
+    }
+
+    let quotient = match amount <= MAX_U160 {
+        true => div_rounding_up(amount << 96, liquidity),
+        false => mul_div_rounding_up(amount, Q96, liquidity)?,
+    };
+    match sqrt_price_x96 > quotient {
+        true => Ok(sqrt_price_x96 - quotient),
+        false => Err(math_error("not enough liquidity for the output")),
+    }
+}
+
+/// Price after swapping `amount_in`, `SqrtPriceMath.getNextSqrtPriceFromInput`.
+pub fn get_next_sqrt_price_from_input(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<U256> {
+    if sqrt_price_x96.is_zero() || liquidity == 0 {
+        return Err(UniswapV3Error::InsufficientLiquidity);
+    }
+    match zero_for_one {
+        true => get_next_sqrt_price_from_amount0_rounding_up(sqrt_price_x96, liquidity, amount_in, true),
+        false => get_next_sqrt_price_from_amount1_rounding_down(sqrt_price_x96, liquidity, amount_in, true),
+    }
+}
+
+/// Price after receiving `amount_out`, `SqrtPriceMath.getNextSqrtPriceFromOutput`.
+pub fn get_next_sqrt_price_from_output(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool,
+) -> Result<U256> {
+    if sqrt_price_x96.is_zero() || liquidity == 0 {
+        return Err(UniswapV3Error::InsufficientLiquidity);
+    }
+    match zero_for_one {
+        true => get_next_sqrt_price_from_amount1_rounding_down(sqrt_price_x96, liquidity, amount_out, false),
+        false => get_next_sqrt_price_from_amount0_rounding_up(sqrt_price_x96, liquidity, amount_out, false),
+    }
+}
+
+/// Token0 between two prices for `liquidity`, `SqrtPriceMath.getAmount0Delta`.
+pub fn get_amount0_delta(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128, round_up: bool) -> Result<U256> {
+    let (sqrt_ratio_a, sqrt_ratio_b) = match sqrt_ratio_a > sqrt_ratio_b {
+        true => (sqrt_ratio_b, sqrt_ratio_a),
+        false => (sqrt_ratio_a, sqrt_ratio_b),
+    };
+    if sqrt_ratio_a.is_zero() {
+        return Err(UniswapV3Error::InvalidSqrtPrice(sqrt_ratio_a));
+    }
+    let numerator1 = U256::from(liquidity) << 96usize;
+    let numerator2 = sqrt_ratio_b - sqrt_ratio_a;
+    match round_up {
+        true => Ok(div_rounding_up(
+            mul_div_rounding_up(numerator1, numerator2, sqrt_ratio_b)?,
+            sqrt_ratio_a,
+        )),
+        false => Ok(mul_div(numerator1, numerator2, sqrt_ratio_b)? / sqrt_ratio_a),
+    }
+}
+
+/// Token1 between two prices for `liquidity`, `SqrtPriceMath.getAmount1Delta`.
+pub fn get_amount1_delta(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128, round_up: bool) -> Result<U256> {
+    let (sqrt_ratio_a, sqrt_ratio_b) = match sqrt_ratio_a > sqrt_ratio_b {
+        true => (sqrt_ratio_b, sqrt_ratio_a),
+        false => (sqrt_ratio_a, sqrt_ratio_b),
+    };
+    match round_up {
+        true => mul_div_rounding_up(U256::from(liquidity), sqrt_ratio_b - sqrt_ratio_a, Q96),
+        false => mul_div(U256::from(liquidity), sqrt_ratio_b - sqrt_ratio_a, Q96),
+    }
+}
+
+/// Signed token0 delta of adding (positive) or removing (negative) liquidity, rounded in
+/// favour of the pool.
+pub fn get_amount0_delta_signed(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: i128) -> Result<I256> {
+    let amount = get_amount0_delta(sqrt_ratio_a, sqrt_ratio_b, liquidity.unsigned_abs(), liquidity >= 0)?;
+    signed(amount, liquidity < 0)
+}
+
+/// Signed token1 delta of adding (positive) or removing (negative) liquidity, rounded in
+/// favour of the pool.
+pub fn get_amount1_delta_signed(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: i128) -> Result<I256> {
+    let amount = get_amount1_delta(sqrt_ratio_a, sqrt_ratio_b, liquidity.unsigned_abs(), liquidity >= 0)?;
+    signed(amount, liquidity < 0)
+}
+
+pub(crate) fn signed(amount: U256, negative: bool) -> Result<I256> {
+    let amount = I256::try_from(amount).map_err(|_| math_error("int256 overflow"))?;
+    Ok(match negative {
+        true => -amount,
+        false => amount,
+    })
+}
+
+/// `LiquidityMath.addDelta`
+pub fn add_delta(liquidity: u128, delta: i128) -> Result<u128> {
+    match delta < 0 {
+        true => liquidity
+            .checked_sub(delta.unsigned_abs())
+            .ok_or_else(|| math_error("liquidity underflow")),
+        false => liquidity
+            .checked_add(delta as u128)
+            .ok_or_else(|| math_error("liquidity overflow")),
+    }
+}
+
+/// One step of a swap within a single liquidity range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next_x96: U256,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+/// `SwapMath.computeSwapStep`: swaps towards `sqrt_price_target_x96`, a positive
+/// `amount_remaining` is an exact input and a negative one an exact output.
+pub fn compute_swap_step(
+    sqrt_price_current_x96: U256,
+    sqrt_price_target_x96: U256,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: u32,
+) -> Result<SwapStep> {
+    if fee_pips >= FEE_DENOMINATOR {
+        return Err(UniswapV3Error::InvalidFee(fee_pips));
+    }
+    let zero_for_one = sqrt_price_current_x96 >= sqrt_price_target_x96;
+    let exact_in = !amount_remaining.is_negative();
+    let remaining = amount_remaining.unsigned_abs();
+    let (current, target) = (sqrt_price_current_x96, sqrt_price_target_x96);
+    let fee = U256::from(fee_pips);
+    let denominator = U256::from(FEE_DENOMINATOR);
+
+    let mut amount_in = U256::ZERO;
+    let mut amount_out = U256::ZERO;
+    let sqrt_price_next = match exact_in {
+        true => {
+            let remaining_less_fee = mul_div(remaining, denominator - fee, denominator)?;
+            amount_in = match zero_for_one {
+                true => get_amount0_delta(target, current, liquidity, true)?,
+                false => get_amount1_delta(current, target, liquidity, true)?,
+            };
+            match remaining_less_fee >= amount_in {
+                true => target,
+                false => get_next_sqrt_price_from_input(current, liquidity, remaining_less_fee, zero_for_one)?,
+            }
+        }
+        false => {
+            amount_out = match zero_for_one {
+                true => get_amount1_delta(target, current, liquidity, false)?,
+                false => get_amount0_delta(current, target, liquidity, false)?,
+            };
+            match remaining >= amount_out {
+                true => target,
+                false => get_next_sqrt_price_from_output(current, liquidity, remaining, zero_for_one)?,
+            }
+        }
+    };
+
+    let max = sqrt_price_next == target;
+    match zero_for_one {
+        true => {
+            if !(max && exact_in) {
+                amount_in = get_amount0_delta(sqrt_price_next, current, liquidity, true)?;
+            }
+            if !max || exact_in {
+                amount_out = get_amount1_delta(sqrt_price_next, current, liquidity, false)?;
+            }
+        }
+        false => {
+            if !(max && exact_in) {
+                amount_in = get_amount1_delta(current, sqrt_price_next, liquidity, true)?;
+            }
+            if !max || exact_in {
+                amount_out = get_amount0_delta(current, sqrt_price_next, liquidity, false)?;
+            }
+        }
+    }
+
+    // the output can't exceed what was asked for
+    if !exact_in && amount_out > remaining {
+        amount_out = remaining;
+    }
+    let fee_amount = match exact_in && sqrt_price_next != target {
+        // the price didn't reach the target, the rest of the input is the fee
+        true => remaining - amount_in,
+        false => mul_div_rounding_up(amount_in, fee, denominator - fee)?,
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next_x96: sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::test_utils::ether;
+
+    // `encodePriceSqrt` of the v3-core tests
+    const PRICE_1_1: U256 = uint!(79228162514264337593543950336_U256);
+    const PRICE_101_100: U256 = uint!(79623317895830914510487008059_U256);
+    const PRICE_121_100: U256 = uint!(87150978765690771352898345369_U256);
+    const PRICE_1000_100: U256 = uint!(250541448375047931186501464011_U256);
+    const PRICE_10000_100: U256 = uint!(792281625142643375935439503360_U256);
+
+    #[test]
+    fn test_tick_math() {
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK).unwrap(), MAX_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(0).unwrap(), Q96);
+        assert_eq!(
+            get_sqrt_ratio_at_tick(50).unwrap(),
+            uint!(79426470787362580746886972461_U256)
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(-50).unwrap(),
+            uint!(79030349367926598376800521322_U256)
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(100000).unwrap(),
+            uint!(11755562826496067164730007768450_U256)
+        );
+        assert!(matches!(
+            get_sqrt_ratio_at_tick(MAX_TICK + 1),
+            Err(UniswapV3Error::InvalidTick(_))
+        ));
+
+        assert_eq!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO).unwrap(), MIN_TICK);
+        assert_eq!(
+            get_tick_at_sqrt_ratio(MIN_SQRT_RATIO + U256::from(1)).unwrap(),
+            MIN_TICK
+        );
+        assert_eq!(
+            get_tick_at_sqrt_ratio(MAX_SQRT_RATIO - U256::from(1)).unwrap(),
+            MAX_TICK - 1
+        );
+        assert_eq!(get_tick_at_sqrt_ratio(PRICE_1_1).unwrap(), 0);
+        assert_eq!(get_tick_at_sqrt_ratio(PRICE_121_100).unwrap(), 1906);
+        for tick in [-887271, -50000, -1, 1, 2, 60, 50000, 887271] {
+            let ratio = get_sqrt_ratio_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_ratio(ratio).unwrap(), tick);
+            assert_eq!(get_tick_at_sqrt_ratio(ratio - U256::from(1)).unwrap(), tick - 1);
+        }
+        assert!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO).is_err());
+    }
+
+    #[test]
+    fn test_full_math() {
+        assert_eq!(
+            mul_div(Q128, U256::from(50), U256::from(100)).unwrap(),
+            Q128 / U256::from(2)
+        );
+        assert_eq!(mul_div(U256::MAX, U256::MAX, U256::MAX).unwrap(), U256::MAX);
+        assert!(mul_div(Q128, Q128, U256::from(1)).is_err());
+        assert!(mul_div(U256::from(1), U256::from(1), U256::ZERO).is_err());
+        assert_eq!(
+            mul_div_rounding_up(U256::from(7), U256::from(1), U256::from(2)).unwrap(),
+            U256::from(4)
+        );
+        assert!(mul_div_rounding_up(U256::MAX, U256::MAX, U256::MAX - U256::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_sqrt_price_math() {
+        let input =
+            |amount, zero_for_one| get_next_sqrt_price_from_input(PRICE_1_1, ether(1).to(), amount, zero_for_one);
+        assert_eq!(
+            input(ether(1) / U256::from(10), false).unwrap(),
+            uint!(87150978765690771352898345369_U256)
+        );
+        assert_eq!(
+            input(ether(1) / U256::from(10), true).unwrap(),
+            uint!(72025602285694852357767227579_U256)
+        );
+        assert_eq!(input(U256::ZERO, true).unwrap(), PRICE_1_1);
+        assert_eq!(
+            get_next_sqrt_price_from_input(PRICE_1_1, 10 * 10u128.pow(18), U256::from(1) << 100, true).unwrap(),
+            uint!(624999999995069620_U256)
+        );
+        assert_eq!(
+            get_next_sqrt_price_from_input(PRICE_1_1, 1, U256::MAX / U256::from(2), true).unwrap(),
+            U256::from(1)
+        );
+        assert!(get_next_sqrt_price_from_input(PRICE_1_1, 0, U256::from(1), true).is_err());
+
+        let output =
+            |amount, zero_for_one| get_next_sqrt_price_from_output(PRICE_1_1, ether(1).to(), amount, zero_for_one);
+        assert_eq!(
+            output(ether(1) / U256::from(10), false).unwrap(),
+            uint!(88031291682515930659493278152_U256)
+        );
+        assert_eq!(
+            output(ether(1) / U256::from(10), true).unwrap(),
+            uint!(71305346262837903834189555302_U256)
+        );
+        // more than the virtual reserves
+        assert!(get_next_sqrt_price_from_output(
+            uint!(20282409603651670423947251286016_U256),
+            1024,
+            U256::from(4),
+            false
+        )
+        .is_err());
+        assert!(get_next_sqrt_price_from_output(
+            uint!(20282409603651670423947251286016_U256),
+            1024,
+            U256::from(262144),
+            true
+        )
+        .is_err());
+
+        assert_eq!(
+            get_amount0_delta(PRICE_1_1, PRICE_121_100, ether(1).to(), true).unwrap(),
+            U256::from(90909090909090910u64)
+        );
+        assert_eq!(
+            get_amount0_delta(PRICE_1_1, PRICE_121_100, ether(1).to(), false).unwrap(),
+            U256::from(90909090909090909u64)
+        );
+        assert_eq!(
+            get_amount1_delta(PRICE_1_1, PRICE_121_100, ether(1).to(), true).unwrap(),
+            U256::from(100000000000000000u64)
+        );
+        assert_eq!(
+            get_amount1_delta(PRICE_1_1, PRICE_121_100, ether(1).to(), false).unwrap(),
+            U256::from(99999999999999999u64)
+        );
+        assert_eq!(
+            get_amount0_delta(PRICE_1_1, PRICE_1_1, ether(1).to(), true).unwrap(),
+            U256::ZERO
+        );
+
+        assert_eq!(
+            get_amount0_delta_signed(PRICE_1_1, PRICE_121_100, -(10i128.pow(18))).unwrap(),
+            I256::try_from(-90909090909090909i64).unwrap()
+        );
+        assert_eq!(add_delta(1, -1).unwrap(), 0);
+        assert!(add_delta(0, -1).is_err());
+        assert!(add_delta(u128::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_compute_swap_step() {
+        let one = I256::try_from(ether(1)).unwrap();
+
+        // exact in, capped at the target price, one for zero
+        let step = compute_swap_step(PRICE_1_1, PRICE_101_100, ether(2).to(), one, 600).unwrap();
+        assert_eq!(step.sqrt_price_next_x96, PRICE_101_100);
+        assert_eq!(
+            (step.amount_in, step.fee_amount, step.amount_out),
+            (
+                U256::from(9975124224178055u64),
+                U256::from(5988667735148u64),
+                U256::from(9925619580021728u64)
+            )
+        );
+
+        // exact out, capped at the target price, one for zero
+        let step = compute_swap_step(PRICE_1_1, PRICE_101_100, ether(2).to(), -one, 600).unwrap();
+        assert_eq!(step.sqrt_price_next_x96, PRICE_101_100);
+        assert_eq!(
+            (step.amount_in, step.fee_amount, step.amount_out),
+            (
+                U256::from(9975124224178055u64),
+                U256::from(5988667735148u64),
+                U256::from(9925619580021728u64)
+            )
+        );
+
+        // exact in, fully spent, one for zero
+        let step = compute_swap_step(PRICE_1_1, PRICE_1000_100, ether(2).to(), one, 600).unwrap();
+        assert!(step.sqrt_price_next_x96 < PRICE_1000_100);
+        assert_eq!(
+            (step.amount_in, step.fee_amount, step.amount_out),
+            (
+                U256::from(999400000000000000u64),
+                U256::from(600000000000000u64),
+                U256::from(666399946655997866u64)
+            )
+        );
+
+        // exact out, fully received, one for zero
+        let step = compute_swap_step(PRICE_1_1, PRICE_10000_100, ether(2).to(), -one, 600).unwrap();
+        assert!(step.sqrt_price_next_x96 < PRICE_10000_100);
+        assert_eq!(
+            (step.amount_in, step.fee_amount, step.amount_out),
+            (
+                U256::from(2000000000000000000u64),
+                U256::from(1200720432259356u64),
+                ether(1)
+            )
+        );
+
+        // the output is capped at the desired amount
+        let step = compute_swap_step(
+            uint!(417332158212080721273783715441582_U256),
+            uint!(1452870262520218020823638996_U256),
+            159344665391607089467575320103,
+            I256::try_from(-1).unwrap(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(step.sqrt_price_next_x96, uint!(417332158212080721273783715441581_U256));
+        assert_eq!(
+            (step.amount_in, step.fee_amount, step.amount_out),
+            (U256::from(1), U256::from(1), U256::from(1))
+        );
+
+        // the entire input is taken as fee
+        let step = compute_swap_step(
+            U256::from(2413),
+            uint!(79887613182836312_U256),
+            1985041575832132834610021537970,
+            I256::try_from(10).unwrap(),
+            1872,
+        )
+        .unwrap();
+        assert_eq!(step.sqrt_price_next_x96, U256::from(2413));
+        assert_eq!(
+            (step.amount_in, step.fee_amount, step.amount_out),
+            (U256::ZERO, U256::from(10), U256::ZERO)
+        );
+    }
+}
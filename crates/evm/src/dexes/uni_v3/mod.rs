@@ -0,0 +1,390 @@
+pub mod math;
+pub mod path;
+pub mod pool;
+pub mod position;
+
+use alloy_primitives::{address, b256, keccak256, Address, Bytes, B256, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{SolCall, SolValue};
+
+use crate::abis::uniswap::{ISwapRouter, IUniswapV3Router};
+use crate::deploy::address::create2_address;
+use crate::dexes::uni_v2::SwapAmount;
+use crate::dexes::uni_v3::math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO};
+use crate::dexes::uni_v3::path::V3Path;
+use crate::provider::transport::ProviderError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV3Error {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Math error: {0}")]
+    MathError(String),
+    #[error("Invalid tick: {0}")]
+    InvalidTick(i32),
+    #[error("Invalid sqrt price: {0}")]
+    InvalidSqrtPrice(U256),
+    #[error("Invalid fee: {0}")]
+    InvalidFee(u32),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Insufficient liquidity")]
+    InsufficientLiquidity,
+    #[error("Tick data not loaded around tick {0}")]
+    MissingTickData(i32),
+    #[error("Pool not found: {0}")]
+    PoolNotFound(Address),
+    #[error("Invalid swap: {0}")]
+    InvalidSwap(String),
+    #[error("Position not found: {0}")]
+    PositionNotFound(U256),
+    #[error("Positions of {0} not found")]
+    PositionsNotFound(Address),
+    #[error("Too many positions: {0}")]
+    TooManyPositions(U256),
+    #[error("Invalid tick spacing: {0}")]
+    InvalidTickSpacing(i32),
+    #[error("Identical token addresses")]
+    IdenticalAddresses,
+}
+
+type Result<T> = std::result::Result<T, UniswapV3Error>;
+
+/// Tick spacing of the fee tiers enabled by the factory, fees are in hundredths of a bip.
+pub fn tick_spacing(fee: u32) -> Option<i32> {
+    match fee {
+        100 => Some(1),
+        500 => Some(10),
+        3000 => Some(60),
+        10000 => Some(200),
+        _ => None,
+    }
+}
+
+/// Orders two tokens the way the factory does, by address.
+pub fn sort_tokens(token_a: Address, token_b: Address) -> Result<(Address, Address)> {
+    match token_a.cmp(&token_b) {
+        std::cmp::Ordering::Less => Ok((token_a, token_b)),
+        std::cmp::Ordering::Greater => Ok((token_b, token_a)),
+        std::cmp::Ordering::Equal => Err(UniswapV3Error::IdenticalAddresses),
+    }
+}
+
+/// A Uniswap V3 deployment: the factory, its pool init code, the `SwapRouter` and the
+/// `NonfungiblePositionManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniswapV3 {
+    pub factory: Address,
+    pub router: Address,
+    pub position_manager: Address,
+    /// `keccak256` of the pool creation code, used to derive pool addresses.
+    pub init_code_hash: B256,
+    /// The wrapped native token, first or last in the path of ETH swaps.
+    pub weth: Address,
+}
+
+// https://docs.uniswap.org/contracts/v3/reference/deployments/ethereum-deployments
+pub const UNISWAP_V3: UniswapV3 = UniswapV3 {
+    factory: address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+    router: address!("E592427A0AEce92De3Edee1F18E0157C05861564"),
+    position_manager: address!("C36442b4a4522E871399CD717aBDD847Ab11FE88"),
+    init_code_hash: b256!("e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54"),
+    weth: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+};
+
+/// A `SwapRouter` swap along `path`, see [`UniswapV3::swap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V3SwapParams {
+    pub amount: SwapAmount,
+    pub path: V3Path,
+    pub recipient: Address,
+    /// Unix timestamp in seconds after which the swap reverts.
+    pub deadline: u64,
+    /// Price at which a single-hop swap stops, zero for no limit.
+    pub sqrt_price_limit_x96: U256,
+    /// Pays with native ETH, the path starts with WETH.
+    pub eth_in: bool,
+    /// Receives native ETH, the path ends with WETH.
+    pub eth_out: bool,
+}
+
+impl V3SwapParams {
+    pub fn exact_in(amount_in: U256, amount_out_min: U256, path: V3Path, recipient: Address, deadline: u64) -> Self {
+        Self::new(
+            SwapAmount::ExactIn {
+                amount_in,
+                amount_out_min,
+            },
+            path,
+            recipient,
+            deadline,
+        )
+    }
+
+    pub fn exact_out(amount_out: U256, amount_in_max: U256, path: V3Path, recipient: Address, deadline: u64) -> Self {
+        Self::new(
+            SwapAmount::ExactOut {
+                amount_out,
+                amount_in_max,
+            },
+            path,
+            recipient,
+            deadline,
+        )
+    }
+
+    fn new(amount: SwapAmount, path: V3Path, recipient: Address, deadline: u64) -> Self {
+        Self {
+            amount,
+            path,
+            recipient,
+            deadline,
+            sqrt_price_limit_x96: U256::ZERO,
+            eth_in: false,
+            eth_out: false,
+        }
+    }
+
+    pub fn with_sqrt_price_limit(mut self, sqrt_price_limit_x96: U256) -> Self {
+        self.sqrt_price_limit_x96 = sqrt_price_limit_x96;
+        self
+    }
+
+    pub fn eth_in(mut self) -> Self {
+        self.eth_in = true;
+        self
+    }
+
+    pub fn eth_out(mut self) -> Self {
+        self.eth_out = true;
+        self
+    }
+}
+
+impl UniswapV3 {
+    /// Address of the pool of two tokens and a fee tier, derived with CREATE2.
+    pub fn pool_address(&self, token_a: Address, token_b: Address, fee: u32) -> Result<Address> {
+        let (token0, token1) = sort_tokens(token_a, token_b)?;
+        let salt = keccak256((token0, token1, U256::from(fee)).abi_encode());
+        Ok(create2_address(self.factory, salt, self.init_code_hash))
+    }
+
+    /// Router swap transaction, ready to be filled and signed.
+    ///
+    /// ETH output is sent to the router and unwrapped to the recipient, and the unspent ETH of
+    /// an exact output swap is refunded, both through the router's `multicall`.
+    pub fn swap(&self, params: &V3SwapParams) -> Result<TransactionRequest> {
+        if params.eth_in && params.path.token_in() != self.weth {
+            return Err(UniswapV3Error::InvalidPath("ETH swaps start with WETH".into()));
+        }
+        if params.eth_out && params.path.token_out() != self.weth {
+            return Err(UniswapV3Error::InvalidPath("ETH swaps end with WETH".into()));
+        }
+        if params.eth_in && params.eth_out {
+            return Err(UniswapV3Error::InvalidSwap("ETH can't be both input and output".into()));
+        }
+        let single = params.path.fees().len() == 1;
+        if !single && !params.sqrt_price_limit_x96.is_zero() {
+            return Err(UniswapV3Error::InvalidSwap(
+                "price limits only apply to single-hop swaps".into(),
+            ));
+        }
+        // zero means no limit, anything else has to pass the pool's own bounds check
+        let limit = params.sqrt_price_limit_x96;
+        if !limit.is_zero() && (limit <= MIN_SQRT_RATIO || limit >= MAX_SQRT_RATIO) {
+            return Err(UniswapV3Error::InvalidSqrtPrice(limit));
+        }
+
+        // with address(0) the router keeps the output
+        let recipient = match params.eth_out {
+            true => Address::ZERO,
+            false => params.recipient,
+        };
+        let deadline = U256::from(params.deadline);
+        let (token_in, fee, token_out) = (params.path.token_in(), params.path.fees()[0], params.path.token_out());
+
+        let (swap, value, min_out) = match params.amount {
+            SwapAmount::ExactIn {
+                amount_in,
+                amount_out_min,
+            } => {
+                let swap = match single {
+                    true => IUniswapV3Router::exactInputSingleCall {
+                        params: ISwapRouter::ExactInputSingleParams {
+                            tokenIn: token_in,
+                            tokenOut: token_out,
+                            fee: fee.try_into().map_err(|_| UniswapV3Error::InvalidFee(fee))?,
+                            recipient,
+                            deadline,
+                            amountIn: amount_in,
+                            amountOutMinimum: amount_out_min,
+                            sqrtPriceLimitX96: params.sqrt_price_limit_x96.to(),
+                        },
+                    }
+                    .abi_encode(),
+                    false => IUniswapV3Router::exactInputCall {
+                        params: ISwapRouter::ExactInputParams {
+                            path: params.path.encode(),
+                            recipient,
+                            deadline,
+                            amountIn: amount_in,
+                            amountOutMinimum: amount_out_min,
+                        },
+                    }
+                    .abi_encode(),
+                };
+                (swap, amount_in, amount_out_min)
+            }
+            SwapAmount::ExactOut {
+                amount_out,
+                amount_in_max,
+            } => {
+                let swap = match single {
+                    true => IUniswapV3Router::exactOutputSingleCall {
+                        params: ISwapRouter::ExactOutputSingleParams {
+                            tokenIn: token_in,
+                            tokenOut: token_out,
+                            fee: fee.try_into().map_err(|_| UniswapV3Error::InvalidFee(fee))?,
+                            recipient,
+                            deadline,
+                            amountOut: amount_out,
+                            amountInMaximum: amount_in_max,
+                            sqrtPriceLimitX96: params.sqrt_price_limit_x96.to(),
+                        },
+                    }
+                    .abi_encode(),
+                    false => IUniswapV3Router::exactOutputCall {
+                        params: ISwapRouter::ExactOutputParams {
+                            path: params.path.encode_reversed(),
+                            recipient,
+                            deadline,
+                            amountOut: amount_out,
+                            amountInMaximum: amount_in_max,
+                        },
+                    }
+                    .abi_encode(),
+                };
+                (swap, amount_in_max, amount_out)
+            }
+        };
+
+        let mut calls: Vec<Bytes> = vec![swap.into()];
+        if params.eth_out {
+            calls.push(
+                IUniswapV3Router::unwrapWETH9Call {
+                    amountMinimum: min_out,
+                    recipient: params.recipient,
+                }
+                .abi_encode()
+                .into(),
+            );
+        }
+        let exact_out = matches!(params.amount, SwapAmount::ExactOut { .. });
+        if params.eth_in && exact_out {
+            calls.push(IUniswapV3Router::refundETHCall {}.abi_encode().into());
+        }
+        let input = match calls.len() {
+            1 => calls.remove(0),
+            _ => IUniswapV3Router::multicallCall { data: calls }.abi_encode().into(),
+        };
+
+        let tx = TransactionRequest::default().to(self.router).input(input.into());
+        Ok(match params.eth_in {
+            true => tx.value(value),
+            false => tx,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const RECIPIENT: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+
+    #[test]
+    fn test_pool_address() {
+        assert_eq!(
+            UNISWAP_V3.pool_address(WETH, USDC, 500).unwrap(),
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+        assert_eq!(
+            UNISWAP_V3.pool_address(USDC, WETH, 3000).unwrap(),
+            address!("8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8")
+        );
+        assert!(UNISWAP_V3.pool_address(USDC, USDC, 500).is_err());
+        assert_eq!(tick_spacing(3000), Some(60));
+        assert_eq!(tick_spacing(42), None);
+    }
+
+    #[test]
+    fn test_swap_calldata() {
+        let params = V3SwapParams::exact_in(
+            U256::from(1000),
+            U256::from(900),
+            V3Path::single(USDC, 500, WETH).unwrap(),
+            RECIPIENT,
+            1,
+        );
+        let tx = UNISWAP_V3.swap(&params).unwrap();
+        assert_eq!((tx.to, tx.value), (Some(UNISWAP_V3.router.into()), None));
+        let call = IUniswapV3Router::exactInputSingleCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!((call.params.tokenIn, call.params.fee.to::<u32>()), (USDC, 500));
+        assert_eq!(call.params.recipient, RECIPIENT);
+        for limit in [U256::from(1), MIN_SQRT_RATIO, MAX_SQRT_RATIO, U256::MAX] {
+            assert!(matches!(
+                UNISWAP_V3.swap(&params.clone().with_sqrt_price_limit(limit)),
+                Err(UniswapV3Error::InvalidSqrtPrice(_))
+            ));
+        }
+        let tx = UNISWAP_V3.swap(&params.with_sqrt_price_limit(MIN_SQRT_RATIO + U256::from(1))).unwrap();
+        let call = IUniswapV3Router::exactInputSingleCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(U256::from(call.params.sqrtPriceLimitX96), MIN_SQRT_RATIO + U256::from(1));
+
+        // multi-hop exact output paths run backwards
+        let path = V3Path::new(vec![USDC, WETH, DAI], vec![500, 3000]).unwrap();
+        let params = V3SwapParams::exact_out(U256::from(1000), U256::from(1100), path.clone(), RECIPIENT, 1);
+        let tx = UNISWAP_V3.swap(&params).unwrap();
+        let call = IUniswapV3Router::exactOutputCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(V3Path::decode_reversed(&call.params.path).unwrap(), path);
+        assert!(UNISWAP_V3.swap(&params.with_sqrt_price_limit(U256::from(1))).is_err());
+
+        // ETH in with an exact output refunds the rest
+        let params = V3SwapParams::exact_out(
+            U256::from(1000),
+            U256::from(1100),
+            V3Path::single(WETH, 500, USDC).unwrap(),
+            RECIPIENT,
+            1,
+        );
+        let tx = UNISWAP_V3.swap(&params.eth_in()).unwrap();
+        assert_eq!(tx.value, Some(U256::from(1100)));
+        let calls = IUniswapV3Router::multicallCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap()
+            .data;
+        assert_eq!(calls[0][..4], IUniswapV3Router::exactOutputSingleCall::SELECTOR);
+        assert_eq!(calls[1][..], IUniswapV3Router::refundETHCall {}.abi_encode());
+
+        // ETH out is unwrapped by the router
+        let path = V3Path::new(vec![DAI, USDC, WETH], vec![100, 500]).unwrap();
+        let params = V3SwapParams::exact_in(U256::from(1000), U256::from(900), path, RECIPIENT, 1).eth_out();
+        let tx = UNISWAP_V3.swap(&params).unwrap();
+        let calls = IUniswapV3Router::multicallCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap()
+            .data;
+        let swap = IUniswapV3Router::exactInputCall::abi_decode(&calls[0], true).unwrap();
+        assert_eq!(swap.params.recipient, Address::ZERO);
+        let unwrap = IUniswapV3Router::unwrapWETH9Call::abi_decode(&calls[1], true).unwrap();
+        assert_eq!((unwrap.amountMinimum, unwrap.recipient), (U256::from(900), RECIPIENT));
+
+        let path = V3Path::single(USDC, 500, DAI).unwrap();
+        let params = V3SwapParams::exact_in(U256::from(1), U256::ZERO, path, RECIPIENT, 1);
+        assert!(matches!(
+            UNISWAP_V3.swap(&params.eth_in()),
+            Err(UniswapV3Error::InvalidPath(_))
+        ));
+    }
+}
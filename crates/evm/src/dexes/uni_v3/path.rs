@@ -0,0 +1,140 @@
+use alloy_primitives::{Address, Bytes};
+
+use crate::dexes::uni_v3::{Result, UniswapV3Error};
+
+const ADDRESS_SIZE: usize = 20;
+const FEE_SIZE: usize = 3;
+
+/// A multi-hop swap route, tokens from input to output with the fee tier of each hop.
+///
+/// Always at least one hop, with one fee per hop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V3Path {
+    tokens: Vec<Address>,
+    fees: Vec<u32>,
+}
+
+impl V3Path {
+    pub fn new(tokens: Vec<Address>, fees: Vec<u32>) -> Result<Self> {
+        if tokens.len() < 2 || fees.len() != tokens.len() - 1 {
+            return Err(UniswapV3Error::InvalidPath(format!(
+                "{} tokens and {} fees",
+                tokens.len(),
+                fees.len()
+            )));
+        }
+        if let Some(fee) = fees.iter().find(|fee| **fee >= 1 << 24) {
+            return Err(UniswapV3Error::InvalidFee(*fee));
+        }
+        Ok(Self { tokens, fees })
+    }
+
+    pub fn single(token_in: Address, fee: u32, token_out: Address) -> Result<Self> {
+        Self::new(vec![token_in, token_out], vec![fee])
+    }
+
+    pub fn tokens(&self) -> &[Address] {
+        &self.tokens
+    }
+
+    pub fn fees(&self) -> &[u32] {
+        &self.fees
+    }
+
+    pub fn token_in(&self) -> Address {
+        self.tokens[0]
+    }
+
+    pub fn token_out(&self) -> Address {
+        self.tokens[self.tokens.len() - 1]
+    }
+
+    /// `(token_in, fee, token_out)` of each hop, in swap order.
+    pub fn hops(&self) -> impl Iterator<Item = (Address, u32, Address)> + '_ {
+        self.tokens
+            .windows(2)
+            .zip(&self.fees)
+            .map(|(tokens, fee)| (tokens[0], *fee, tokens[1]))
+    }
+
+    /// `token (20 bytes) || fee (3 bytes) || token ...`, the path of `exactInput`.
+    pub fn encode(&self) -> Bytes {
+        encode(self.tokens.iter(), self.fees.iter())
+    }
+
+    /// The path from output to input, as `exactOutput` expects it.
+    pub fn encode_reversed(&self) -> Bytes {
+        encode(self.tokens.iter().rev(), self.fees.iter().rev())
+    }
+
+    /// Decodes a path in input to output order.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let hop = ADDRESS_SIZE + FEE_SIZE;
+        if data.len() < ADDRESS_SIZE + hop || !(data.len() - ADDRESS_SIZE).is_multiple_of(hop) {
+            return Err(UniswapV3Error::InvalidPath(format!("invalid length {}", data.len())));
+        }
+        let mut tokens = vec![Address::from_slice(&data[..ADDRESS_SIZE])];
+        let mut fees = Vec::new();
+        for chunk in data[ADDRESS_SIZE..].chunks(hop) {
+            fees.push(u32::from_be_bytes([0, chunk[0], chunk[1], chunk[2]]));
+            tokens.push(Address::from_slice(&chunk[FEE_SIZE..]));
+        }
+        Ok(Self { tokens, fees })
+    }
+
+    /// Decodes the path of an `exactOutput` call, which runs from output to input.
+    pub fn decode_reversed(data: &[u8]) -> Result<Self> {
+        let mut path = Self::decode(data)?;
+        path.tokens.reverse();
+        path.fees.reverse();
+        Ok(path)
+    }
+}
+
+fn encode<'a>(mut tokens: impl Iterator<Item = &'a Address>, fees: impl Iterator<Item = &'a u32>) -> Bytes {
+    let mut data = Vec::new();
+    data.extend_from_slice(tokens.next().unwrap().as_slice());
+    for (fee, token) in fees.zip(tokens) {
+        data.extend_from_slice(&fee.to_be_bytes()[1..]);
+        data.extend_from_slice(token.as_slice());
+    }
+    data.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, hex};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+    #[test]
+    fn test_path_encoding() {
+        let path = V3Path::new(vec![USDC, WETH, DAI], vec![500, 3000]).unwrap();
+        let encoded = path.encode();
+        assert_eq!(
+            hex::encode(&encoded),
+            "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb480001f4c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2000bb86b175474e89094c44da98b954eedeac495271d0f"
+        );
+        assert_eq!(V3Path::decode(&encoded).unwrap(), path);
+
+        let reversed = path.encode_reversed();
+        assert_eq!(&reversed[..20], DAI.as_slice());
+        assert_eq!(&reversed[20..23], &hex!("000bb8"));
+        assert_eq!(V3Path::decode_reversed(&reversed).unwrap(), path);
+        assert_eq!(
+            path.hops().collect::<Vec<_>>(),
+            vec![(USDC, 500, WETH), (WETH, 3000, DAI)]
+        );
+
+        assert!(V3Path::new(vec![USDC], vec![]).is_err());
+        assert!(V3Path::new(vec![], vec![]).is_err());
+        assert!(V3Path::new(vec![USDC, WETH], vec![]).is_err());
+        assert!(V3Path::single(USDC, 1 << 24, WETH).is_err());
+        assert!(V3Path::new(vec![USDC, WETH], vec![1 << 24]).is_err());
+        assert!(V3Path::decode(&encoded[..42]).is_err());
+        assert!(V3Path::decode(USDC.as_slice()).is_err());
+    }
+}
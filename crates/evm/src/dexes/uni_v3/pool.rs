@@ -0,0 +1,536 @@
+use std::collections::BTreeMap;
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, I256, U256};
+
+use crate::abis::uniswap::IUniswapV3Pool;
+use crate::dexes::uni_v3::math::{
+    add_delta, compute_swap_step, get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK,
+    MIN_SQRT_RATIO, MIN_TICK,
+};
+use crate::dexes::uni_v3::path::V3Path;
+use crate::dexes::uni_v3::{tick_spacing, Result, UniswapV3Error};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+
+/// Words of the tick bitmap loaded on each side of the current tick by [`Pool::fetch`].
+pub const DEFAULT_TICK_WORDS: i32 = 2;
+
+/// The state of a pool needed to simulate swaps offline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pool {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    /// Fee in hundredths of a bip.
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    /// Liquidity in range at the current tick.
+    pub liquidity: u128,
+    /// `liquidityNet` of each initialized tick.
+    pub ticks: BTreeMap<i32, i128>,
+    /// Ticks whose initialization is known, `None` when the whole tick range is.
+    pub tick_range: Option<(i32, i32)>,
+}
+
+/// Outcome of a swap, amounts are positive when paid to the pool and negative when received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapResult {
+    pub amount0: I256,
+    pub amount1: I256,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+impl Pool {
+    /// An empty pool at `sqrt_price_x96`, liquidity is added with [`Pool::add_liquidity`].
+    pub fn new(address: Address, token0: Address, token1: Address, fee: u32, sqrt_price_x96: U256) -> Result<Self> {
+        let tick_spacing = tick_spacing(fee).ok_or(UniswapV3Error::InvalidFee(fee))?;
+        Ok(Self {
+            address,
+            token0,
+            token1,
+            fee,
+            tick_spacing,
+            sqrt_price_x96,
+            tick: get_tick_at_sqrt_ratio(sqrt_price_x96)?,
+            liquidity: 0,
+            ticks: BTreeMap::new(),
+            tick_range: None,
+        })
+    }
+
+    /// Loads the pool state and the initialized ticks within `words` bitmap words of the current tick.
+    pub async fn fetch(provider: &EvmProvider, address: Address, words: i32, block: BlockId) -> Result<Self> {
+        let mut multicall = Multicall::new();
+        let token0 = multicall.add(address, IUniswapV3Pool::token0Call {}, true);
+        let token1 = multicall.add(address, IUniswapV3Pool::token1Call {}, true);
+        let fee = multicall.add(address, IUniswapV3Pool::feeCall {}, true);
+        let spacing = multicall.add(address, IUniswapV3Pool::tickSpacingCall {}, true);
+        let liquidity = multicall.add(address, IUniswapV3Pool::liquidityCall {}, true);
+        let slot0 = multicall.add(address, IUniswapV3Pool::slot0Call {}, true);
+        let results = multicall.call(provider, block).await?;
+
+        // a pool that doesn't exist has no code and returns no data
+        let not_found = |_| UniswapV3Error::PoolNotFound(address);
+        let slot0 = results.get(&slot0).map_err(not_found)?;
+        let tick_spacing = i32::try_from(results.get(&spacing).map_err(not_found)?._0)
+            .map_err(|e| UniswapV3Error::MathError(e.to_string()))?;
+        if tick_spacing <= 0 {
+            return Err(UniswapV3Error::PoolNotFound(address));
+        }
+        let mut pool = Self {
+            address,
+            token0: results.get(&token0).map_err(not_found)?._0,
+            token1: results.get(&token1).map_err(not_found)?._0,
+            fee: u32::try_from(results.get(&fee).map_err(not_found)?._0)
+                .map_err(|e| UniswapV3Error::MathError(e.to_string()))?,
+            tick_spacing,
+            sqrt_price_x96: U256::from(slot0.sqrtPriceX96),
+            tick: i32::try_from(slot0.tick).map_err(|e| UniswapV3Error::MathError(e.to_string()))?,
+            liquidity: results.get(&liquidity).map_err(not_found)?._0,
+            ticks: BTreeMap::new(),
+            tick_range: None,
+        };
+
+        let word = pool.tick.div_euclid(tick_spacing) >> 8;
+        let min_word = MIN_TICK.div_euclid(tick_spacing) >> 8;
+        let max_word = MAX_TICK.div_euclid(tick_spacing) >> 8;
+        let (first, last) = ((word - words).max(min_word), (word + words).min(max_word));
+        let mut multicall = Multicall::new();
+        let bitmaps: Vec<_> = (first..=last)
+            .map(|word| {
+                let call = IUniswapV3Pool::tickBitmapCall {
+                    wordPosition: word as i16,
+                };
+                (word, multicall.add(address, call, false))
+            })
+            .collect();
+        let results = multicall.call(provider, block).await?;
+
+        let mut initialized = Vec::new();
+        for (word, handle) in bitmaps {
+            let bitmap = results.get(&handle).map_err(not_found)?._0;
+            for bit in 0..256 {
+                if bitmap.bit(bit) {
+                    initialized.push(((word << 8) + bit as i32) * tick_spacing);
+                }
+            }
+        }
+
+        let mut multicall = Multicall::new();
+        let handles: Vec<_> = initialized
+            .iter()
+            .map(|tick| {
+                let call = IUniswapV3Pool::ticksCall {
+                    tick: (*tick).try_into().map_err(|_| UniswapV3Error::InvalidTick(*tick))?,
+                };
+                Ok((*tick, multicall.add(address, call, false)))
+            })
+            .collect::<Result<_>>()?;
+        if !multicall.is_empty() {
+            let results = multicall.call(provider, block).await?;
+            for (tick, handle) in handles {
+                pool.ticks
+                    .insert(tick, results.get(&handle).map_err(not_found)?.liquidityNet);
+            }
+        }
+        pool.tick_range = Some(((first << 8) * tick_spacing, ((last << 8) + 255) * tick_spacing));
+        Ok(pool)
+    }
+
+    /// Adds a position between two initializable ticks, like a `mint` would.
+    pub fn add_liquidity(&mut self, tick_lower: i32, tick_upper: i32, liquidity: u128) -> Result<()> {
+        for tick in [tick_lower, tick_upper] {
+            if !(MIN_TICK..=MAX_TICK).contains(&tick) || tick % self.tick_spacing != 0 {
+                return Err(UniswapV3Error::InvalidTick(tick));
+            }
+        }
+        if tick_lower >= tick_upper {
+            return Err(UniswapV3Error::InvalidTick(tick_upper));
+        }
+        let delta = i128::try_from(liquidity).map_err(|e| UniswapV3Error::MathError(e.to_string()))?;
+        for (tick, delta) in [(tick_lower, delta), (tick_upper, -delta)] {
+            let net = self.ticks.get(&tick).copied().unwrap_or_default();
+            let net = net
+                .checked_add(delta)
+                .ok_or_else(|| UniswapV3Error::MathError("liquidity net overflow".into()))?;
+            self.ticks.insert(tick, net);
+        }
+        if (tick_lower..tick_upper).contains(&self.tick) {
+            self.liquidity = add_delta(self.liquidity, delta)?;
+        }
+        Ok(())
+    }
+
+    /// `TickBitmap.nextInitializedTickWithinOneWord` over the known ticks.
+    pub fn next_initialized_tick_within_one_word(&self, tick: i32, lte: bool) -> Result<(i32, bool)> {
+        let spacing = self.tick_spacing;
+        let compressed = tick.div_euclid(spacing);
+        let (first, last) = match lte {
+            true => (compressed - compressed.rem_euclid(256), compressed),
+            false => (compressed + 1, compressed + 1 + 255 - (compressed + 1).rem_euclid(256)),
+        };
+        let (first, last) = (first * spacing, last * spacing);
+        if let Some((lower, upper)) = self.tick_range {
+            if first < lower || last > upper {
+                return Err(UniswapV3Error::MissingTickData(tick));
+            }
+        }
+
+        let mut range = self.ticks.range(first..=last).filter(|(_, net)| **net != 0);
+        let next = match lte {
+            true => range.next_back(),
+            false => range.next(),
+        };
+        Ok(match (next, lte) {
+            (Some((tick, _)), _) => (*tick, true),
+            (None, true) => (first, false),
+            (None, false) => (last, false),
+        })
+    }
+
+    /// Simulates `UniswapV3Pool.swap`, a positive `amount_specified` is an exact input and a
+    /// negative one an exact output. Protocol fees are not taken out of the pool state.
+    pub fn swap(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: Option<U256>,
+    ) -> Result<SwapResult> {
+        if amount_specified.is_zero() {
+            return Err(UniswapV3Error::InvalidSwap("zero amount".into()));
+        }
+        let limit = sqrt_price_limit_x96.unwrap_or(match zero_for_one {
+            true => MIN_SQRT_RATIO + U256::from(1),
+            false => MAX_SQRT_RATIO - U256::from(1),
+        });
+        let valid_limit = match zero_for_one {
+            true => limit < self.sqrt_price_x96 && limit > MIN_SQRT_RATIO,
+            false => limit > self.sqrt_price_x96 && limit < MAX_SQRT_RATIO,
+        };
+        if !valid_limit {
+            return Err(UniswapV3Error::InvalidSqrtPrice(limit));
+        }
+
+        let exact_input = amount_specified.is_positive();
+        let mut remaining = amount_specified;
+        let mut calculated = I256::ZERO;
+        let mut sqrt_price = self.sqrt_price_x96;
+        let mut tick = self.tick;
+        let mut liquidity = self.liquidity;
+        let overflow = || UniswapV3Error::MathError("amount overflow".into());
+
+        while !remaining.is_zero() && sqrt_price != limit {
+            let start = sqrt_price;
+            let (tick_next, initialized) = self.next_initialized_tick_within_one_word(tick, zero_for_one)?;
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next = get_sqrt_ratio_at_tick(tick_next)?;
+            let target = match zero_for_one {
+                true => sqrt_price_next.max(limit),
+                false => sqrt_price_next.min(limit),
+            };
+
+            let step = compute_swap_step(sqrt_price, target, liquidity, remaining, self.fee)?;
+            sqrt_price = step.sqrt_price_next_x96;
+            let amount_in = I256::try_from(step.amount_in + step.fee_amount).map_err(|_| overflow())?;
+            let amount_out = I256::try_from(step.amount_out).map_err(|_| overflow())?;
+            match exact_input {
+                true => {
+                    remaining = remaining.checked_sub(amount_in).ok_or_else(overflow)?;
+                    calculated = calculated.checked_sub(amount_out).ok_or_else(overflow)?;
+                }
+                false => {
+                    remaining = remaining.checked_add(amount_out).ok_or_else(overflow)?;
+                    calculated = calculated.checked_add(amount_in).ok_or_else(overflow)?;
+                }
+            }
+
+            if sqrt_price == sqrt_price_next {
+                if initialized {
+                    let net = self.ticks.get(&tick_next).copied().unwrap_or_default();
+                    liquidity = add_delta(liquidity, if zero_for_one { -net } else { net })?;
+                }
+                tick = if zero_for_one { tick_next - 1 } else { tick_next };
+            } else if sqrt_price != start {
+                tick = get_tick_at_sqrt_ratio(sqrt_price)?;
+            }
+        }
+
+        let specified = amount_specified - remaining;
+        let (amount0, amount1) = match zero_for_one == exact_input {
+            true => (specified, calculated),
+            false => (calculated, specified),
+        };
+        Ok(SwapResult {
+            amount0,
+            amount1,
+            sqrt_price_x96: sqrt_price,
+            tick,
+            liquidity,
+        })
+    }
+
+    /// Moves the pool to the state after `result`.
+    pub fn apply_swap(&mut self, result: &SwapResult) {
+        self.sqrt_price_x96 = result.sqrt_price_x96;
+        self.tick = result.tick;
+        self.liquidity = result.liquidity;
+    }
+
+    /// Output of selling exactly `amount_in` of `token_in`.
+    pub fn quote_exact_in(&self, token_in: Address, amount_in: U256) -> Result<U256> {
+        let zero_for_one = self.zero_for_one(token_in)?;
+        let amount = I256::try_from(amount_in).map_err(|e| UniswapV3Error::MathError(e.to_string()))?;
+        let result = self.swap(zero_for_one, amount, None)?;
+        let (paid, received) = match zero_for_one {
+            true => (result.amount0, result.amount1),
+            false => (result.amount1, result.amount0),
+        };
+        if paid != amount {
+            return Err(UniswapV3Error::InsufficientLiquidity);
+        }
+        Ok(received.unsigned_abs())
+    }
+
+    /// Input needed to buy exactly `amount_out` of `token_out`.
+    pub fn quote_exact_out(&self, token_out: Address, amount_out: U256) -> Result<U256> {
+        let zero_for_one = !self.zero_for_one(token_out)?;
+        let amount = I256::try_from(amount_out).map_err(|e| UniswapV3Error::MathError(e.to_string()))?;
+        let result = self.swap(zero_for_one, -amount, None)?;
+        let (paid, received) = match zero_for_one {
+            true => (result.amount0, result.amount1),
+            false => (result.amount1, result.amount0),
+        };
+        if received != -amount {
+            return Err(UniswapV3Error::InsufficientLiquidity);
+        }
+        Ok(paid.unsigned_abs())
+    }
+
+    fn zero_for_one(&self, token_in: Address) -> Result<bool> {
+        match token_in {
+            token if token == self.token0 => Ok(true),
+            token if token == self.token1 => Ok(false),
+            token => Err(UniswapV3Error::InvalidPath(format!(
+                "{} is not in pool {}",
+                token, self.address
+            ))),
+        }
+    }
+}
+
+/// Amounts of each token of an `exactInput` swap along `path`, `pools` being the pool of each hop.
+pub fn quote_exact_input(path: &V3Path, pools: &[Pool], amount_in: U256) -> Result<Vec<U256>> {
+    let pools = hop_pools(path, pools)?;
+    let mut amounts = vec![amount_in];
+    for ((token_in, _, _), pool) in path.hops().zip(pools) {
+        amounts.push(pool.quote_exact_in(token_in, amounts[amounts.len() - 1])?);
+    }
+    Ok(amounts)
+}
+
+/// Amounts of each token of an `exactOutput` swap along `path`, from input to output.
+pub fn quote_exact_output(path: &V3Path, pools: &[Pool], amount_out: U256) -> Result<Vec<U256>> {
+    let pools = hop_pools(path, pools)?;
+    let hops: Vec<_> = path.hops().zip(pools).collect();
+    let mut amounts = vec![amount_out];
+    for ((_, _, token_out), pool) in hops.into_iter().rev() {
+        amounts.push(pool.quote_exact_out(token_out, amounts[amounts.len() - 1])?);
+    }
+    amounts.reverse();
+    Ok(amounts)
+}
+
+fn hop_pools<'a>(path: &V3Path, pools: &'a [Pool]) -> Result<&'a [Pool]> {
+    if pools.len() != path.fees().len() {
+        return Err(UniswapV3Error::InvalidPath(format!(
+            "{} hops and {} pools",
+            path.fees().len(),
+            pools.len()
+        )));
+    }
+    for ((token_in, fee, token_out), pool) in path.hops().zip(pools) {
+        let tokens = [pool.token0, pool.token1];
+        if pool.fee != fee || !tokens.contains(&token_in) || !tokens.contains(&token_out) {
+            return Err(UniswapV3Error::InvalidPath(format!(
+                "pool {} doesn't match hop {} -> {}",
+                pool.address, token_in, token_out
+            )));
+        }
+    }
+    Ok(pools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::uni_v3::UNISWAP_V3;
+    use crate::dexes::uni_v3::math::{compute_swap_step, Q96};
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::ether;
+    use alloy_primitives::address;
+    use alloy_sol_types::{SolCall, SolValue};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+    fn pool(token_a: Address, token_b: Address, fee: u32) -> Pool {
+        let (token0, token1) = crate::dexes::uni_v3::sort_tokens(token_a, token_b).unwrap();
+        let address = UNISWAP_V3.pool_address(token0, token1, fee).unwrap();
+        Pool::new(address, token0, token1, fee, Q96).unwrap()
+    }
+
+    #[test]
+    fn test_swap_within_range() {
+        let mut pool = pool(DAI, USDC, 3000);
+        pool.add_liquidity(-600, 600, 10u128.pow(21)).unwrap();
+        assert_eq!((pool.tick, pool.liquidity), (0, 10u128.pow(21)));
+
+        // a swap that stays within the range is a single swap step
+        let amount = I256::try_from(ether(1)).unwrap();
+        let target = get_sqrt_ratio_at_tick(-600).unwrap();
+        let step = compute_swap_step(Q96, target, pool.liquidity, amount, 3000).unwrap();
+        let result = pool.swap(true, amount, None).unwrap();
+        assert_eq!(result.amount0, amount);
+        assert_eq!(result.amount1, -I256::try_from(step.amount_out).unwrap());
+        assert_eq!(result.sqrt_price_x96, step.sqrt_price_next_x96);
+        assert_eq!(result.tick, get_tick_at_sqrt_ratio(step.sqrt_price_next_x96).unwrap());
+        assert_eq!(pool.quote_exact_in(pool.token0, ether(1)).unwrap(), step.amount_out);
+
+        // buying that output back costs at most the input
+        let amount_in = pool.quote_exact_out(pool.token1, step.amount_out).unwrap();
+        assert!(amount_in <= ether(1) && amount_in > ether(1) - U256::from(2));
+
+        pool.apply_swap(&result);
+        assert_eq!((pool.sqrt_price_x96, pool.tick), (result.sqrt_price_x96, result.tick));
+        let limit = pool.sqrt_price_x96;
+        assert!(matches!(
+            pool.swap(true, amount, Some(limit)),
+            Err(UniswapV3Error::InvalidSqrtPrice(_))
+        ));
+        assert!(pool.quote_exact_in(WETH, ether(1)).is_err());
+    }
+
+    #[test]
+    fn test_swap_across_ticks() {
+        let mut pool = pool(DAI, USDC, 500);
+        let l = 10u128.pow(20);
+        pool.add_liquidity(-100, 100, l).unwrap();
+        pool.add_liquidity(-1000, -100, 2 * l).unwrap();
+        assert_eq!(pool.next_initialized_tick_within_one_word(0, true).unwrap(), (0, false));
+        assert_eq!(
+            pool.next_initialized_tick_within_one_word(-1, true).unwrap(),
+            (-100, true)
+        );
+        assert_eq!(
+            pool.next_initialized_tick_within_one_word(-101, true).unwrap(),
+            (-1000, true)
+        );
+        assert_eq!(
+            pool.next_initialized_tick_within_one_word(100, false).unwrap(),
+            (2550, false)
+        );
+
+        // selling token0 pushes the price through tick -100 into the deeper range
+        let result = pool.swap(true, I256::try_from(ether(1)).unwrap(), None).unwrap();
+        assert!(result.tick < -100 && result.tick >= -1000);
+        assert_eq!(result.liquidity, 2 * l);
+        let to_boundary =
+            crate::dexes::uni_v3::math::get_amount0_delta(get_sqrt_ratio_at_tick(-100).unwrap(), Q96, l, true).unwrap();
+        assert!(to_boundary < ether(1));
+
+        // each hop of a multi-hop quote uses its own pool
+        let mut weth_dai = self::pool(WETH, DAI, 3000);
+        weth_dai.add_liquidity(-6000, 6000, 10u128.pow(22)).unwrap();
+        let path = V3Path::new(vec![WETH, DAI, USDC], vec![3000, 500]).unwrap();
+        let pools = [weth_dai, pool.clone()];
+        let amounts = quote_exact_input(&path, &pools, ether(1)).unwrap();
+        assert_eq!(amounts[1], pools[0].quote_exact_in(WETH, ether(1)).unwrap());
+        assert_eq!(amounts[2], pool.quote_exact_in(DAI, amounts[1]).unwrap());
+        let amounts_in = quote_exact_output(&path, &pools, amounts[2]).unwrap();
+        assert_eq!(amounts_in[2], amounts[2]);
+        assert!(amounts_in[0] <= ether(1));
+        assert!(quote_exact_input(&path, &pools[..1], ether(1)).is_err());
+
+        // draining every range can't be filled
+        assert!(matches!(
+            pool.quote_exact_in(pool.token0, ether(1_000_000)),
+            Err(UniswapV3Error::InsufficientLiquidity)
+        ));
+        assert!(matches!(
+            pool.quote_exact_out(pool.token1, ether(1_000_000)),
+            Err(UniswapV3Error::InsufficientLiquidity)
+        ));
+
+        // ticks outside the loaded bitmap words are unknown
+        pool.tick_range = Some((-2560, 2550));
+        assert!(matches!(
+            pool.swap(true, I256::try_from(ether(1_000_000)).unwrap(), None),
+            Err(UniswapV3Error::MissingTickData(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pool() {
+        let address = UNISWAP_V3.pool_address(DAI, USDC, 500).unwrap();
+        let node = MockNode::multicall(move |call| {
+            let data = &call.callData;
+            let output = match data[..4].try_into().unwrap() {
+                IUniswapV3Pool::token0Call::SELECTOR => DAI.abi_encode(),
+                IUniswapV3Pool::token1Call::SELECTOR => USDC.abi_encode(),
+                IUniswapV3Pool::feeCall::SELECTOR => U256::from(500).abi_encode(),
+                IUniswapV3Pool::tickSpacingCall::SELECTOR => U256::from(10).abi_encode(),
+                IUniswapV3Pool::liquidityCall::SELECTOR => U256::from(10u128.pow(20)).abi_encode(),
+                IUniswapV3Pool::slot0Call::SELECTOR => {
+                    let mut output = (Q96, U256::ZERO).abi_encode_params();
+                    output.extend([0u8; 32 * 5]);
+                    output
+                }
+                IUniswapV3Pool::tickBitmapCall::SELECTOR => {
+                    // ticks -100 and 100 are in words -1 and 0
+                    let word = IUniswapV3Pool::tickBitmapCall::abi_decode(data, true)
+                        .unwrap()
+                        .wordPosition;
+                    match word {
+                        -1 => (U256::from(1) << 246usize).abi_encode(),
+                        0 => (U256::from(1) << 10usize).abi_encode(),
+                        _ => U256::ZERO.abi_encode(),
+                    }
+                }
+                IUniswapV3Pool::ticksCall::SELECTOR => {
+                    let tick = IUniswapV3Pool::ticksCall::abi_decode(data, true).unwrap().tick;
+                    let net = match i32::try_from(tick).unwrap() {
+                        -100 => I256::try_from(10u128.pow(20)).unwrap(),
+                        _ => -I256::try_from(10u128.pow(20)).unwrap(),
+                    };
+                    let mut output = (U256::from(10u128.pow(20)), net).abi_encode_params();
+                    output.extend([0u8; 32 * 6]);
+                    output
+                }
+                _ => vec![],
+            };
+            (call.target == address).then_some(output)
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let fetched = Pool::fetch(&provider, address, DEFAULT_TICK_WORDS, BlockId::latest())
+            .await
+            .unwrap();
+        let mut expected = pool(DAI, USDC, 500);
+        expected.add_liquidity(-100, 100, 10u128.pow(20)).unwrap();
+        expected.tick_range = Some((-2 * 2560, 3 * 2560 - 10));
+        assert_eq!(fetched, expected);
+
+        let missing = UNISWAP_V3.pool_address(DAI, USDC, 100).unwrap();
+        assert!(matches!(
+            Pool::fetch(&provider, missing, DEFAULT_TICK_WORDS, BlockId::latest()).await,
+            Err(UniswapV3Error::PoolNotFound(pool)) if pool == missing
+        ));
+    }
+}
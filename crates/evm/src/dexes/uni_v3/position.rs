@@ -0,0 +1,1020 @@
+use alloy_eips::BlockId;
+use alloy_primitives::aliases::{I24, U24};
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+
+use crate::abis::uniswap::{INonfungiblePositionManager, IUniswapV3Pool};
+use crate::dexes::uni_v3::math::{get_sqrt_ratio_at_tick, mul_div, MAX_TICK, MIN_TICK, Q128, Q96};
+use crate::dexes::uni_v3::pool::Pool;
+use crate::dexes::uni_v3::{Result, UniswapV3, UniswapV3Error};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+
+/// Most position NFTs [`UniswapV3::fetch_positions`] loads for one owner.
+pub const MAX_POSITIONS: u64 = 1000;
+
+fn to_u128(value: U256) -> Result<u128> {
+    u128::try_from(value).map_err(|_| UniswapV3Error::MathError("liquidity overflow".into()))
+}
+
+fn sorted(sqrt_ratio_a: U256, sqrt_ratio_b: U256) -> (U256, U256) {
+    (sqrt_ratio_a.min(sqrt_ratio_b), sqrt_ratio_a.max(sqrt_ratio_b))
+}
+
+/// `LiquidityAmounts.getLiquidityForAmount0`: liquidity provided by `amount0` between two prices.
+pub fn get_liquidity_for_amount0(sqrt_ratio_a: U256, sqrt_ratio_b: U256, amount0: U256) -> Result<u128> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    let intermediate = mul_div(a, b, Q96)?;
+    to_u128(mul_div(amount0, intermediate, b - a)?)
+}
+
+/// `LiquidityAmounts.getLiquidityForAmount1`: liquidity provided by `amount1` between two prices.
+pub fn get_liquidity_for_amount1(sqrt_ratio_a: U256, sqrt_ratio_b: U256, amount1: U256) -> Result<u128> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    to_u128(mul_div(amount1, Q96, b - a)?)
+}
+
+/// `LiquidityAmounts.getLiquidityForAmounts`: the most liquidity both amounts can provide
+/// between two prices at the current price.
+pub fn get_liquidity_for_amounts(
+    sqrt_price_x96: U256,
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    amount0: U256,
+    amount1: U256,
+) -> Result<u128> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    if sqrt_price_x96 <= a {
+        get_liquidity_for_amount0(a, b, amount0)
+    } else if sqrt_price_x96 < b {
+        let liquidity0 = get_liquidity_for_amount0(sqrt_price_x96, b, amount0)?;
+        let liquidity1 = get_liquidity_for_amount1(a, sqrt_price_x96, amount1)?;
+        Ok(liquidity0.min(liquidity1))
+    } else {
+        get_liquidity_for_amount1(a, b, amount1)
+    }
+}
+
+/// `LiquidityAmounts.getAmount0ForLiquidity`, rounded down.
+pub fn get_amount0_for_liquidity(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128) -> Result<U256> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    if a.is_zero() {
+        return Err(UniswapV3Error::InvalidSqrtPrice(a));
+    }
+    Ok(mul_div(U256::from(liquidity) << 96usize, b - a, b)? / a)
+}
+
+/// `LiquidityAmounts.getAmount1ForLiquidity`, rounded down.
+pub fn get_amount1_for_liquidity(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128) -> Result<U256> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    mul_div(U256::from(liquidity), b - a, Q96)
+}
+
+/// `LiquidityAmounts.getAmountsForLiquidity`: token amounts of `liquidity` between two prices
+/// at the current price, rounded down like a withdrawal.
+pub fn get_amounts_for_liquidity(
+    sqrt_price_x96: U256,
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    liquidity: u128,
+) -> Result<(U256, U256)> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    if sqrt_price_x96 <= a {
+        Ok((get_amount0_for_liquidity(a, b, liquidity)?, U256::ZERO))
+    } else if sqrt_price_x96 < b {
+        Ok((
+            get_amount0_for_liquidity(sqrt_price_x96, b, liquidity)?,
+            get_amount1_for_liquidity(a, sqrt_price_x96, liquidity)?,
+        ))
+    } else {
+        Ok((U256::ZERO, get_amount1_for_liquidity(a, b, liquidity)?))
+    }
+}
+
+/// Fee growth state of an initialized tick, as returned by the pool's `ticks`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickFeeGrowth {
+    pub fee_growth_outside0_x128: U256,
+    pub fee_growth_outside1_x128: U256,
+}
+
+/// `Tick.getFeeGrowthInside`: fees per unit of liquidity earned between two ticks, as Q128.128
+/// values that wrap around like the pool's.
+pub fn get_fee_growth_inside(
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global: (U256, U256),
+    lower: TickFeeGrowth,
+    upper: TickFeeGrowth,
+) -> (U256, U256) {
+    let inside = |global: U256, lower_outside: U256, upper_outside: U256| {
+        let below = match tick_current >= tick_lower {
+            true => lower_outside,
+            false => global.wrapping_sub(lower_outside),
+        };
+        let above = match tick_current < tick_upper {
+            true => upper_outside,
+            false => global.wrapping_sub(upper_outside),
+        };
+        global.wrapping_sub(below).wrapping_sub(above)
+    };
+    (
+        inside(
+            fee_growth_global.0,
+            lower.fee_growth_outside0_x128,
+            upper.fee_growth_outside0_x128,
+        ),
+        inside(
+            fee_growth_global.1,
+            lower.fee_growth_outside1_x128,
+            upper.fee_growth_outside1_x128,
+        ),
+    )
+}
+
+/// Price of token0 in token1 at `tick`, in whole tokens.
+pub fn tick_to_price(tick: i32, decimals0: u8, decimals1: u8) -> f64 {
+    1.0001f64.powi(tick) * 10f64.powi(decimals0 as i32 - decimals1 as i32)
+}
+
+/// The greatest tick whose price of token0 in token1 is at most `price`, in whole tokens.
+pub fn price_to_tick(price: f64, decimals0: u8, decimals1: u8) -> Result<i32> {
+    if !price.is_finite() || price <= 0.0 {
+        return Err(UniswapV3Error::MathError(format!("invalid price {}", price)));
+    }
+    let raw = price * 10f64.powi(decimals1 as i32 - decimals0 as i32);
+    let tick = (raw.ln() / 1.0001f64.ln()).floor();
+    if tick < MIN_TICK as f64 || tick > MAX_TICK as f64 {
+        return Err(UniswapV3Error::MathError(format!("price {} out of range", price)));
+    }
+    // the logarithm can land one tick off near a boundary
+    let tick = tick as i32;
+    Ok(match tick {
+        tick if tick < MAX_TICK && tick_to_price(tick + 1, decimals0, decimals1) <= price => tick + 1,
+        tick if tick > MIN_TICK && tick_to_price(tick, decimals0, decimals1) > price => tick - 1,
+        tick => tick,
+    })
+}
+
+/// The initializable tick closest to `tick`, halves rounding up, within the tick range.
+pub fn nearest_usable_tick(tick: i32, tick_spacing: i32) -> Result<i32> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3Error::InvalidTickSpacing(tick_spacing));
+    }
+    let rounded = (tick + tick_spacing / 2).div_euclid(tick_spacing) * tick_spacing;
+    Ok(if rounded < MIN_TICK {
+        rounded + tick_spacing
+    } else if rounded > MAX_TICK {
+        rounded - tick_spacing
+    } else {
+        rounded
+    })
+}
+
+/// A position NFT of the `NonfungiblePositionManager`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub token_id: U256,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside0_last_x128: U256,
+    pub fee_growth_inside1_last_x128: U256,
+    /// Fees and withdrawn liquidity credited at the last update, not collected yet.
+    pub tokens_owed0: u128,
+    pub tokens_owed1: u128,
+}
+
+impl Position {
+    /// Token amounts the liquidity is worth at `sqrt_price_x96`.
+    pub fn amounts(&self, sqrt_price_x96: U256) -> Result<(U256, U256)> {
+        let sqrt_ratio_a = get_sqrt_ratio_at_tick(self.tick_lower)?;
+        let sqrt_ratio_b = get_sqrt_ratio_at_tick(self.tick_upper)?;
+        get_amounts_for_liquidity(sqrt_price_x96, sqrt_ratio_a, sqrt_ratio_b, self.liquidity)
+    }
+
+    /// Fees `collect` would pay out given the current fee growth inside the position's range.
+    pub fn uncollected_fees(&self, fee_growth_inside: (U256, U256)) -> Result<(U256, U256)> {
+        let liquidity = U256::from(self.liquidity);
+        let fees0 = mul_div(
+            fee_growth_inside.0.wrapping_sub(self.fee_growth_inside0_last_x128),
+            liquidity,
+            Q128,
+        )?;
+        let fees1 = mul_div(
+            fee_growth_inside.1.wrapping_sub(self.fee_growth_inside1_last_x128),
+            liquidity,
+            Q128,
+        )?;
+        // the manager truncates the owed amounts to uint128
+        Ok((
+            U256::from(self.tokens_owed0.wrapping_add(fees0.wrapping_to::<u128>())),
+            U256::from(self.tokens_owed1.wrapping_add(fees1.wrapping_to::<u128>())),
+        ))
+    }
+}
+
+/// A new position, see [`UniswapV3::mint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintParams {
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount0_desired: U256,
+    pub amount1_desired: U256,
+    pub amount0_min: U256,
+    pub amount1_min: U256,
+    pub recipient: Address,
+    /// Unix timestamp in seconds after which the mint reverts.
+    pub deadline: u64,
+    /// Pays the WETH side with native ETH, the unused part is refunded.
+    pub eth: bool,
+}
+
+impl MintParams {
+    pub fn new(
+        pool: &Pool,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount0_desired: U256,
+        amount1_desired: U256,
+        recipient: Address,
+        deadline: u64,
+    ) -> Result<Self> {
+        for tick in [tick_lower, tick_upper] {
+            if !(MIN_TICK..=MAX_TICK).contains(&tick) || tick % pool.tick_spacing != 0 {
+                return Err(UniswapV3Error::InvalidTick(tick));
+            }
+        }
+        if tick_lower >= tick_upper {
+            return Err(UniswapV3Error::InvalidTick(tick_upper));
+        }
+        Ok(Self {
+            token0: pool.token0,
+            token1: pool.token1,
+            fee: pool.fee,
+            tick_lower,
+            tick_upper,
+            amount0_desired,
+            amount1_desired,
+            amount0_min: U256::ZERO,
+            amount1_min: U256::ZERO,
+            recipient,
+            deadline,
+            eth: false,
+        })
+    }
+
+    pub fn with_min_amounts(mut self, amount0_min: U256, amount1_min: U256) -> Self {
+        self.amount0_min = amount0_min;
+        self.amount1_min = amount1_min;
+        self
+    }
+
+    pub fn eth(mut self) -> Self {
+        self.eth = true;
+        self
+    }
+}
+
+/// Liquidity added to an existing position, see [`UniswapV3::increase_liquidity`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncreaseLiquidityParams {
+    pub token_id: U256,
+    pub token0: Address,
+    pub token1: Address,
+    pub amount0_desired: U256,
+    pub amount1_desired: U256,
+    pub amount0_min: U256,
+    pub amount1_min: U256,
+    pub deadline: u64,
+    /// Pays the WETH side with native ETH, the unused part is refunded.
+    pub eth: bool,
+}
+
+impl IncreaseLiquidityParams {
+    pub fn new(position: &Position, amount0_desired: U256, amount1_desired: U256, deadline: u64) -> Self {
+        Self {
+            token_id: position.token_id,
+            token0: position.token0,
+            token1: position.token1,
+            amount0_desired,
+            amount1_desired,
+            amount0_min: U256::ZERO,
+            amount1_min: U256::ZERO,
+            deadline,
+            eth: false,
+        }
+    }
+
+    pub fn with_min_amounts(mut self, amount0_min: U256, amount1_min: U256) -> Self {
+        self.amount0_min = amount0_min;
+        self.amount1_min = amount1_min;
+        self
+    }
+
+    pub fn eth(mut self) -> Self {
+        self.eth = true;
+        self
+    }
+}
+
+/// Liquidity withdrawn from a position, credited to its owed tokens until collected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecreaseLiquidityParams {
+    pub token_id: U256,
+    pub liquidity: u128,
+    pub amount0_min: U256,
+    pub amount1_min: U256,
+    pub deadline: u64,
+}
+
+impl DecreaseLiquidityParams {
+    pub fn new(position: &Position, liquidity: u128, deadline: u64) -> Self {
+        Self {
+            token_id: position.token_id,
+            liquidity,
+            amount0_min: U256::ZERO,
+            amount1_min: U256::ZERO,
+            deadline,
+        }
+    }
+
+    pub fn with_min_amounts(mut self, amount0_min: U256, amount1_min: U256) -> Self {
+        self.amount0_min = amount0_min;
+        self.amount1_min = amount1_min;
+        self
+    }
+}
+
+/// Payout of the owed tokens of a position, all of them by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectParams {
+    pub token_id: U256,
+    pub token0: Address,
+    pub token1: Address,
+    pub recipient: Address,
+    pub amount0_max: u128,
+    pub amount1_max: u128,
+    /// Receives the WETH side as native ETH.
+    pub eth_out: bool,
+}
+
+impl CollectParams {
+    pub fn new(position: &Position, recipient: Address) -> Self {
+        Self {
+            token_id: position.token_id,
+            token0: position.token0,
+            token1: position.token1,
+            recipient,
+            amount0_max: u128::MAX,
+            amount1_max: u128::MAX,
+            eth_out: false,
+        }
+    }
+
+    pub fn with_max_amounts(mut self, amount0_max: u128, amount1_max: u128) -> Self {
+        self.amount0_max = amount0_max;
+        self.amount1_max = amount1_max;
+        self
+    }
+
+    pub fn eth_out(mut self) -> Self {
+        self.eth_out = true;
+        self
+    }
+}
+
+impl UniswapV3 {
+    /// Loads a position NFT.
+    pub async fn fetch_position(&self, provider: &EvmProvider, token_id: U256, block: BlockId) -> Result<Position> {
+        let mut positions = self.fetch_positions_by_id(provider, &[token_id], block).await?;
+        Ok(positions.remove(0))
+    }
+
+    /// Loads every position NFT held by `owner`.
+    pub async fn fetch_positions(
+        &self,
+        provider: &EvmProvider,
+        owner: Address,
+        block: BlockId,
+    ) -> Result<Vec<Position>> {
+        let manager = self.position_manager;
+        let mut multicall = Multicall::new();
+        let balance = multicall.add(manager, INonfungiblePositionManager::balanceOfCall { owner }, false);
+        let results = multicall.call(provider, block).await?;
+        let balance = results
+            .get(&balance)
+            .map_err(|_| UniswapV3Error::PositionsNotFound(owner))?
+            ._0;
+        let count = u64::try_from(balance)
+            .ok()
+            .filter(|count| *count <= MAX_POSITIONS)
+            .ok_or(UniswapV3Error::TooManyPositions(balance))?;
+
+        let mut multicall = Multicall::new();
+        let handles: Vec<_> = (0..count)
+            .map(|index| {
+                let call = INonfungiblePositionManager::tokenOfOwnerByIndexCall {
+                    owner,
+                    index: U256::from(index),
+                };
+                multicall.add(manager, call, false)
+            })
+            .collect();
+        if handles.is_empty() {
+            return Ok(vec![]);
+        }
+        let results = multicall.call(provider, block).await?;
+        let token_ids = handles
+            .iter()
+            .map(|handle| {
+                results
+                    .get(handle)
+                    .map(|token_id| token_id._0)
+                    .map_err(|_| UniswapV3Error::PositionsNotFound(owner))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.fetch_positions_by_id(provider, &token_ids, block).await
+    }
+
+    async fn fetch_positions_by_id(
+        &self,
+        provider: &EvmProvider,
+        token_ids: &[U256],
+        block: BlockId,
+    ) -> Result<Vec<Position>> {
+        let mut multicall = Multicall::new();
+        let handles: Vec<_> = token_ids
+            .iter()
+            .map(|token_id| {
+                let call = INonfungiblePositionManager::positionsCall { tokenId: *token_id };
+                multicall.add(self.position_manager, call, true)
+            })
+            .collect();
+        let results = multicall.call(provider, block).await?;
+        token_ids
+            .iter()
+            .zip(handles)
+            .map(|(token_id, handle)| {
+                // positions of burned or unknown tokens revert with `Invalid token ID`
+                let position = results
+                    .get(&handle)
+                    .map_err(|_| UniswapV3Error::PositionNotFound(*token_id))?;
+                let tick = |tick: I24| i32::try_from(tick).map_err(|e| UniswapV3Error::MathError(e.to_string()));
+                Ok(Position {
+                    token_id: *token_id,
+                    token0: position.token0,
+                    token1: position.token1,
+                    fee: position.fee.to(),
+                    tick_lower: tick(position.tickLower)?,
+                    tick_upper: tick(position.tickUpper)?,
+                    liquidity: position.liquidity,
+                    fee_growth_inside0_last_x128: position.feeGrowthInside0LastX128,
+                    fee_growth_inside1_last_x128: position.feeGrowthInside1LastX128,
+                    tokens_owed0: position.tokensOwed0,
+                    tokens_owed1: position.tokensOwed1,
+                })
+            })
+            .collect()
+    }
+
+    /// Fees earned by a position since its last update plus what it is already owed, read
+    /// from the fee growth of its pool.
+    pub async fn fetch_uncollected_fees(
+        &self,
+        provider: &EvmProvider,
+        position: &Position,
+        block: BlockId,
+    ) -> Result<(U256, U256)> {
+        let pool = self.pool_address(position.token0, position.token1, position.fee)?;
+        let tick = |tick: i32| I24::try_from(tick).map_err(|_| UniswapV3Error::InvalidTick(tick));
+        let mut multicall = Multicall::new();
+        let slot0 = multicall.add(pool, IUniswapV3Pool::slot0Call {}, true);
+        let global0 = multicall.add(pool, IUniswapV3Pool::feeGrowthGlobal0X128Call {}, true);
+        let global1 = multicall.add(pool, IUniswapV3Pool::feeGrowthGlobal1X128Call {}, true);
+        let lower = multicall.add(
+            pool,
+            IUniswapV3Pool::ticksCall {
+                tick: tick(position.tick_lower)?,
+            },
+            true,
+        );
+        let upper = multicall.add(
+            pool,
+            IUniswapV3Pool::ticksCall {
+                tick: tick(position.tick_upper)?,
+            },
+            true,
+        );
+        let results = multicall.call(provider, block).await?;
+
+        let not_found = |_| UniswapV3Error::PoolNotFound(pool);
+        let current = i32::try_from(results.get(&slot0).map_err(not_found)?.tick)
+            .map_err(|e| UniswapV3Error::MathError(e.to_string()))?;
+        let growth = |ticks: IUniswapV3Pool::ticksReturn| TickFeeGrowth {
+            fee_growth_outside0_x128: ticks.feeGrowthOutside0X128,
+            fee_growth_outside1_x128: ticks.feeGrowthOutside1X128,
+        };
+        let inside = get_fee_growth_inside(
+            position.tick_lower,
+            position.tick_upper,
+            current,
+            (
+                results.get(&global0).map_err(not_found)?._0,
+                results.get(&global1).map_err(not_found)?._0,
+            ),
+            growth(results.get(&lower).map_err(not_found)?),
+            growth(results.get(&upper).map_err(not_found)?),
+        );
+        position.uncollected_fees(inside)
+    }
+
+    /// `NonfungiblePositionManager.mint` transaction.
+    pub fn mint(&self, params: &MintParams) -> Result<TransactionRequest> {
+        let tick = |tick: i32| I24::try_from(tick).map_err(|_| UniswapV3Error::InvalidTick(tick));
+        let call = INonfungiblePositionManager::mintCall {
+            params: INonfungiblePositionManager::MintParams {
+                token0: params.token0,
+                token1: params.token1,
+                fee: U24::try_from(params.fee).map_err(|_| UniswapV3Error::InvalidFee(params.fee))?,
+                tickLower: tick(params.tick_lower)?,
+                tickUpper: tick(params.tick_upper)?,
+                amount0Desired: params.amount0_desired,
+                amount1Desired: params.amount1_desired,
+                amount0Min: params.amount0_min,
+                amount1Min: params.amount1_min,
+                recipient: params.recipient,
+                deadline: U256::from(params.deadline),
+            },
+        };
+        let desired = (params.amount0_desired, params.amount1_desired);
+        self.deposit_request(call.abi_encode(), (params.token0, params.token1), desired, params.eth)
+    }
+
+    /// `NonfungiblePositionManager.increaseLiquidity` transaction.
+    pub fn increase_liquidity(&self, params: &IncreaseLiquidityParams) -> Result<TransactionRequest> {
+        let call = INonfungiblePositionManager::increaseLiquidityCall {
+            params: INonfungiblePositionManager::IncreaseLiquidityParams {
+                tokenId: params.token_id,
+                amount0Desired: params.amount0_desired,
+                amount1Desired: params.amount1_desired,
+                amount0Min: params.amount0_min,
+                amount1Min: params.amount1_min,
+                deadline: U256::from(params.deadline),
+            },
+        };
+        let desired = (params.amount0_desired, params.amount1_desired);
+        self.deposit_request(call.abi_encode(), (params.token0, params.token1), desired, params.eth)
+    }
+
+    /// `NonfungiblePositionManager.decreaseLiquidity` transaction, the tokens stay owed to the
+    /// position until collected.
+    pub fn decrease_liquidity(&self, params: &DecreaseLiquidityParams) -> TransactionRequest {
+        self.position_manager_request(vec![decrease_liquidity_call(params)], U256::ZERO)
+    }
+
+    /// `NonfungiblePositionManager.collect` transaction.
+    pub fn collect(&self, params: &CollectParams) -> Result<TransactionRequest> {
+        Ok(self.position_manager_request(self.collect_calls(params)?, U256::ZERO))
+    }
+
+    /// `NonfungiblePositionManager.burn` transaction, the position must be empty and collected.
+    pub fn burn(&self, token_id: U256) -> TransactionRequest {
+        let call = INonfungiblePositionManager::burnCall { tokenId: token_id };
+        self.position_manager_request(vec![call.abi_encode().into()], U256::ZERO)
+    }
+
+    /// Decreases liquidity and collects in one transaction, burning the NFT afterwards if
+    /// `burn` is set, which only succeeds when all of the liquidity is removed.
+    pub fn remove_liquidity(
+        &self,
+        decrease: &DecreaseLiquidityParams,
+        collect: &CollectParams,
+        burn: bool,
+    ) -> Result<TransactionRequest> {
+        if decrease.token_id != collect.token_id {
+            return Err(UniswapV3Error::InvalidSwap(format!(
+                "decreasing {} but collecting {}",
+                decrease.token_id, collect.token_id
+            )));
+        }
+        let mut calls = vec![decrease_liquidity_call(decrease)];
+        calls.extend(self.collect_calls(collect)?);
+        if burn {
+            calls.push(
+                INonfungiblePositionManager::burnCall {
+                    tokenId: decrease.token_id,
+                }
+                .abi_encode()
+                .into(),
+            );
+        }
+        Ok(self.position_manager_request(calls, U256::ZERO))
+    }
+
+    fn deposit_request(
+        &self,
+        call: Vec<u8>,
+        tokens: (Address, Address),
+        desired: (U256, U256),
+        eth: bool,
+    ) -> Result<TransactionRequest> {
+        let mut calls: Vec<Bytes> = vec![call.into()];
+        let value = match eth {
+            true if tokens.0 == self.weth => desired.0,
+            true if tokens.1 == self.weth => desired.1,
+            true => return Err(UniswapV3Error::InvalidPath("ETH deposits need a WETH pool".into())),
+            false => U256::ZERO,
+        };
+        if eth {
+            calls.push(INonfungiblePositionManager::refundETHCall {}.abi_encode().into());
+        }
+        Ok(self.position_manager_request(calls, value))
+    }
+
+    // with address(0) the manager keeps the tokens, then unwraps WETH and sweeps the other token
+    fn collect_calls(&self, params: &CollectParams) -> Result<Vec<Bytes>> {
+        let other = match params.eth_out {
+            true if params.token0 == self.weth => Some(params.token1),
+            true if params.token1 == self.weth => Some(params.token0),
+            true => return Err(UniswapV3Error::InvalidPath("ETH payouts need a WETH pool".into())),
+            false => None,
+        };
+        let collect = INonfungiblePositionManager::collectCall {
+            params: INonfungiblePositionManager::CollectParams {
+                tokenId: params.token_id,
+                recipient: match other {
+                    Some(_) => Address::ZERO,
+                    None => params.recipient,
+                },
+                amount0Max: params.amount0_max,
+                amount1Max: params.amount1_max,
+            },
+        };
+        let mut calls: Vec<Bytes> = vec![collect.abi_encode().into()];
+        if let Some(token) = other {
+            let unwrap = INonfungiblePositionManager::unwrapWETH9Call {
+                amountMinimum: U256::ZERO,
+                recipient: params.recipient,
+            };
+            let sweep = INonfungiblePositionManager::sweepTokenCall {
+                token,
+                amountMinimum: U256::ZERO,
+                recipient: params.recipient,
+            };
+            calls.push(unwrap.abi_encode().into());
+            calls.push(sweep.abi_encode().into());
+        }
+        Ok(calls)
+    }
+
+    fn position_manager_request(&self, mut calls: Vec<Bytes>, value: U256) -> TransactionRequest {
+        let input = match calls.len() {
+            1 => calls.remove(0),
+            _ => INonfungiblePositionManager::multicallCall { data: calls }
+                .abi_encode()
+                .into(),
+        };
+        let tx = TransactionRequest::default()
+            .to(self.position_manager)
+            .input(input.into());
+        match value.is_zero() {
+            true => tx,
+            false => tx.value(value),
+        }
+    }
+}
+
+fn decrease_liquidity_call(params: &DecreaseLiquidityParams) -> Bytes {
+    INonfungiblePositionManager::decreaseLiquidityCall {
+        params: INonfungiblePositionManager::DecreaseLiquidityParams {
+            tokenId: params.token_id,
+            liquidity: params.liquidity,
+            amount0Min: params.amount0_min,
+            amount1Min: params.amount1_min,
+            deadline: U256::from(params.deadline),
+        },
+    }
+    .abi_encode()
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::uni_v3::UNISWAP_V3;
+    use crate::provider::mock::MockNode;
+    use alloy_primitives::{address, uint};
+    use alloy_sol_types::SolValue;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const OWNER: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+
+    // encodePriceSqrt(reserve1, reserve0) of the v3 tests
+    fn encode_price_sqrt(reserve1: u64, reserve0: u64) -> U256 {
+        (U256::from(reserve1) << 192usize)
+            .checked_div(U256::from(reserve0))
+            .unwrap()
+            .root(2)
+    }
+
+    fn usdc_weth_position() -> Position {
+        Position {
+            token_id: U256::from(7),
+            token0: USDC,
+            token1: WETH,
+            fee: 500,
+            tick_lower: 190000,
+            tick_upper: 210000,
+            liquidity: 10u128.pow(18),
+            fee_growth_inside0_last_x128: U256::ZERO,
+            fee_growth_inside1_last_x128: U256::ZERO,
+            tokens_owed0: 5,
+            tokens_owed1: 0,
+        }
+    }
+
+    #[test]
+    fn test_liquidity_amounts() {
+        let (a, b) = (encode_price_sqrt(100, 110), encode_price_sqrt(110, 100));
+        let (amount0, amount1) = (U256::from(100), U256::from(200));
+        let cases = [
+            (encode_price_sqrt(1, 1), 2148, (99, 99)),
+            (encode_price_sqrt(99, 110), 1048, (99, 0)),
+            (encode_price_sqrt(111, 100), 2097, (0, 199)),
+            (a, 1048, (99, 0)),
+            (b, 2097, (0, 199)),
+        ];
+        for (price, liquidity, amounts) in cases {
+            assert_eq!(
+                get_liquidity_for_amounts(price, a, b, amount0, amount1).unwrap(),
+                liquidity
+            );
+            assert_eq!(
+                get_amounts_for_liquidity(price, a, b, liquidity).unwrap(),
+                (U256::from(amounts.0), U256::from(amounts.1))
+            );
+        }
+    }
+
+    #[test]
+    fn test_fee_growth_inside() {
+        let global = (U256::from(15), U256::from(15));
+        let zero = TickFeeGrowth::default();
+        let growth = |fee_growth_outside0_x128: U256, fee_growth_outside1_x128: U256| TickFeeGrowth {
+            fee_growth_outside0_x128,
+            fee_growth_outside1_x128,
+        };
+        let fees = |tick, lower, upper| get_fee_growth_inside(-2, 2, tick, global, lower, upper);
+        let n = |value: u64| U256::from(value);
+
+        assert_eq!(fees(0, zero, zero), (n(15), n(15)));
+        assert_eq!(fees(4, zero, zero), (n(0), n(0)));
+        assert_eq!(fees(-4, zero, zero), (n(0), n(0)));
+        assert_eq!(fees(0, zero, growth(n(2), n(3))), (n(13), n(12)));
+        assert_eq!(fees(0, growth(n(2), n(3)), zero), (n(13), n(12)));
+        assert_eq!(fees(0, growth(n(2), n(3)), growth(n(4), n(1))), (n(9), n(11)));
+        // outside values can be above the global ones and wrap
+        let lower = growth(U256::MAX - n(3), U256::MAX - n(2));
+        assert_eq!(fees(0, lower, growth(n(3), n(5))), (n(16), n(13)));
+
+        let position = usdc_weth_position();
+        let inside = (Q128 * n(3), Q128 / n(2));
+        assert_eq!(
+            position.uncollected_fees(inside).unwrap(),
+            (n(3 * 10u64.pow(18) + 5), n(5 * 10u64.pow(17)))
+        );
+    }
+
+    #[test]
+    fn test_uncollected_fees_truncate() {
+        // owed fees past uint128 wrap like `uint128(FullMath.mulDiv(..))` in the manager
+        let position = Position {
+            liquidity: u128::MAX,
+            tokens_owed0: 1,
+            fee_growth_inside0_last_x128: U256::ZERO,
+            fee_growth_inside1_last_x128: Q128,
+            ..usdc_weth_position()
+        };
+        let inside = (Q128 * U256::from(2), Q128 + U256::from(1));
+        assert_eq!(
+            position.uncollected_fees(inside).unwrap(),
+            (U256::from(u128::MAX), U256::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_price_to_tick() {
+        assert_eq!(nearest_usable_tick(MIN_TICK, 1).unwrap(), MIN_TICK);
+        assert_eq!(nearest_usable_tick(MAX_TICK, 1).unwrap(), MAX_TICK);
+        assert_eq!(nearest_usable_tick(MIN_TICK, 60).unwrap(), -887220);
+        assert_eq!(nearest_usable_tick(MAX_TICK, 60).unwrap(), 887220);
+        assert_eq!(nearest_usable_tick(5, 10).unwrap(), 10);
+        assert_eq!(nearest_usable_tick(-5, 10).unwrap(), 0);
+        assert_eq!(nearest_usable_tick(-6, 10).unwrap(), -10);
+        assert_eq!(nearest_usable_tick(4, 10).unwrap(), 0);
+        assert!(matches!(nearest_usable_tick(4, 0), Err(UniswapV3Error::InvalidTickSpacing(0))));
+        assert!(nearest_usable_tick(4, -10).is_err());
+
+        assert_eq!(price_to_tick(1.0, 18, 18).unwrap(), 0);
+        assert_eq!(price_to_tick(1.0001, 18, 18).unwrap(), 1);
+        assert_eq!(price_to_tick(0.9999, 18, 18).unwrap(), -2);
+        // USDC/WETH at 2000 USDC per ETH
+        let tick = price_to_tick(1.0 / 2000.0, 6, 18).unwrap();
+        assert_eq!(tick, 200311);
+        assert!(tick_to_price(tick, 6, 18) <= 1.0 / 2000.0 && tick_to_price(tick + 1, 6, 18) > 1.0 / 2000.0);
+        assert_eq!(nearest_usable_tick(tick, 10).unwrap(), 200310);
+        assert!(price_to_tick(0.0, 6, 18).is_err());
+        assert!(price_to_tick(1e300, 18, 6).is_err());
+
+        let position = usdc_weth_position();
+        let (amount0, amount1) = position.amounts(get_sqrt_ratio_at_tick(200310).unwrap()).unwrap();
+        assert!(!amount0.is_zero() && !amount1.is_zero());
+        let below = position.amounts(get_sqrt_ratio_at_tick(180000).unwrap()).unwrap();
+        assert!(below.1.is_zero());
+    }
+
+    #[test]
+    fn test_position_calldata() {
+        let mut pool = Pool::new(
+            UNISWAP_V3.pool_address(USDC, WETH, 500).unwrap(),
+            USDC,
+            WETH,
+            500,
+            get_sqrt_ratio_at_tick(200311).unwrap(),
+        )
+        .unwrap();
+        assert!(MintParams::new(&pool, 190005, 210000, U256::ZERO, U256::ZERO, OWNER, 1).is_err());
+        let params = MintParams::new(&pool, 190000, 210000, U256::from(1000), U256::from(2000), OWNER, 1)
+            .unwrap()
+            .with_min_amounts(U256::from(900), U256::from(1800));
+        let tx = UNISWAP_V3.mint(&params).unwrap();
+        assert_eq!((tx.to, tx.value), (Some(UNISWAP_V3.position_manager.into()), None));
+        let call = INonfungiblePositionManager::mintCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(i32::try_from(call.params.tickLower).unwrap(), 190000);
+        assert_eq!(
+            (call.params.amount0Min, call.params.recipient),
+            (U256::from(900), OWNER)
+        );
+
+        // ETH pays the WETH side and is refunded
+        let tx = UNISWAP_V3.mint(&params.eth()).unwrap();
+        assert_eq!(tx.value, Some(U256::from(2000)));
+        let calls = INonfungiblePositionManager::multicallCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap()
+            .data;
+        assert_eq!(calls[1][..], INonfungiblePositionManager::refundETHCall {}.abi_encode());
+        pool.token1 = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+        let params = MintParams::new(&pool, -100, 100, U256::from(1), U256::from(1), OWNER, 1).unwrap();
+        assert!(UNISWAP_V3.mint(&params.eth()).is_err());
+
+        let position = usdc_weth_position();
+        let increase = IncreaseLiquidityParams::new(&position, U256::from(10), U256::from(20), 1).eth();
+        let tx = UNISWAP_V3.increase_liquidity(&increase).unwrap();
+        assert_eq!(tx.value, Some(U256::from(20)));
+
+        // removing everything: decrease, collect as ETH and burn
+        let decrease = DecreaseLiquidityParams::new(&position, position.liquidity, 1);
+        let collect = CollectParams::new(&position, OWNER).eth_out();
+        let tx = UNISWAP_V3.remove_liquidity(&decrease, &collect, true).unwrap();
+        assert_eq!(tx.value, None);
+        let calls = INonfungiblePositionManager::multicallCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap()
+            .data;
+        assert_eq!(calls.len(), 5);
+        let decreased = INonfungiblePositionManager::decreaseLiquidityCall::abi_decode(&calls[0], true).unwrap();
+        assert_eq!(decreased.params.liquidity, position.liquidity);
+        let collected = INonfungiblePositionManager::collectCall::abi_decode(&calls[1], true).unwrap();
+        assert_eq!(
+            (collected.params.recipient, collected.params.amount0Max),
+            (Address::ZERO, u128::MAX)
+        );
+        let unwrap = INonfungiblePositionManager::unwrapWETH9Call::abi_decode(&calls[2], true).unwrap();
+        assert_eq!(unwrap.recipient, OWNER);
+        let sweep = INonfungiblePositionManager::sweepTokenCall::abi_decode(&calls[3], true).unwrap();
+        assert_eq!((sweep.token, sweep.recipient), (USDC, OWNER));
+        assert_eq!(
+            calls[4][..],
+            INonfungiblePositionManager::burnCall { tokenId: U256::from(7) }.abi_encode()
+        );
+
+        let tx = UNISWAP_V3.collect(&CollectParams::new(&position, OWNER)).unwrap();
+        let collected = INonfungiblePositionManager::collectCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(collected.params.recipient, OWNER);
+        let other = Position {
+            token_id: U256::from(8),
+            ..position.clone()
+        };
+        assert!(UNISWAP_V3
+            .remove_liquidity(&decrease, &CollectParams::new(&other, OWNER), false)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_positions() {
+        let pool = UNISWAP_V3.pool_address(USDC, WETH, 500).unwrap();
+        let node = MockNode::multicall(move |call| {
+            let data = &call.callData;
+            let output = match data[..4].try_into().unwrap() {
+                INonfungiblePositionManager::balanceOfCall::SELECTOR => {
+                    match INonfungiblePositionManager::balanceOfCall::abi_decode(data, true).unwrap().owner {
+                        OWNER => U256::from(2).abi_encode(),
+                        _ => U256::MAX.abi_encode(),
+                    }
+                }
+                INonfungiblePositionManager::tokenOfOwnerByIndexCall::SELECTOR => {
+                    let call = INonfungiblePositionManager::tokenOfOwnerByIndexCall::abi_decode(data, true);
+                    (call.unwrap().index + U256::from(7)).abi_encode()
+                }
+                INonfungiblePositionManager::positionsCall::SELECTOR => {
+                    let token_id = INonfungiblePositionManager::positionsCall::abi_decode(data, true)
+                        .unwrap()
+                        .tokenId;
+                    // burned tokens revert
+                    if token_id == U256::from(99) {
+                        return None;
+                    }
+                    let ticks = (U256::from(190000), U256::from(210000));
+                    let growth = (token_id, U256::ZERO, U256::ZERO, U256::from(5));
+                    let mut output =
+                        (U256::ZERO, Address::ZERO, USDC, WETH, U256::from(500)).abi_encode_params();
+                    output.extend(ticks.abi_encode_params());
+                    output.extend((U256::from(10u128.pow(18)),).abi_encode_params());
+                    output.extend(growth.abi_encode_params());
+                    output
+                }
+                IUniswapV3Pool::slot0Call::SELECTOR => {
+                    let mut output = (Q96, U256::from(200311)).abi_encode_params();
+                    output.extend([0u8; 32 * 5]);
+                    output
+                }
+                IUniswapV3Pool::feeGrowthGlobal0X128Call::SELECTOR => (Q128 * U256::from(10)).abi_encode(),
+                IUniswapV3Pool::feeGrowthGlobal1X128Call::SELECTOR => Q128.abi_encode(),
+                IUniswapV3Pool::ticksCall::SELECTOR => {
+                    let tick = IUniswapV3Pool::ticksCall::abi_decode(data, true).unwrap().tick;
+                    // fees below the range were earned before the position
+                    let outside = match i32::try_from(tick).unwrap() {
+                        190000 => Q128 * U256::from(2),
+                        _ => U256::ZERO,
+                    };
+                    let mut output = (U256::from(1), U256::ZERO, outside, U256::ZERO).abi_encode_params();
+                    output.extend([0u8; 32 * 4]);
+                    output
+                }
+                _ => vec![],
+            };
+            let known = call.target == pool || call.target == UNISWAP_V3.position_manager;
+            (!output.is_empty() && known).then_some(output)
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let positions = UNISWAP_V3
+            .fetch_positions(&provider, OWNER, BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(positions.len(), 2);
+        let expected = Position {
+            fee_growth_inside0_last_x128: U256::from(7),
+            tokens_owed0: 0,
+            tokens_owed1: 5,
+            ..usdc_weth_position()
+        };
+        assert_eq!(positions[0], expected);
+        assert_eq!(positions[1].token_id, U256::from(8));
+        let position = UNISWAP_V3
+            .fetch_position(&provider, U256::from(7), BlockId::latest())
+            .await
+            .unwrap();
+        assert_eq!(position, expected);
+        assert!(matches!(
+            UNISWAP_V3.fetch_position(&provider, U256::from(99), BlockId::latest()).await,
+            Err(UniswapV3Error::PositionNotFound(id)) if id == U256::from(99)
+        ));
+        assert!(matches!(
+            UNISWAP_V3.fetch_positions(&provider, Address::repeat_byte(9), BlockId::latest()).await,
+            Err(UniswapV3Error::TooManyPositions(balance)) if balance == U256::MAX
+        ));
+
+        // 10 - 2 per liquidity on token0 less what was already counted, all of the global
+        // growth plus the owed amount on token1
+        let fees = UNISWAP_V3
+            .fetch_uncollected_fees(&provider, &position, BlockId::latest())
+            .await
+            .unwrap();
+        let liquidity = U256::from(10u128.pow(18));
+        assert_eq!(
+            fees,
+            (uint!(8_U256) * liquidity - U256::from(1), liquidity + U256::from(5))
+        );
+    }
+}
@@ -0,0 +1,468 @@
+use alloy_primitives::aliases::{I24, U24};
+use alloy_primitives::{address, keccak256, Address, Bytes, B256, U256};
+use alloy_sol_types::SolValue;
+
+use crate::abis::uniswap::IV4Router;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV4Error {
+    #[error("Invalid pool key: {0}")]
+    InvalidPoolKey(String),
+    #[error("Invalid hooks {0} for the pool fee")]
+    InvalidHooks(Address),
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+    #[error("Amount doesn't fit in uint128: {0}")]
+    Overflow(U256),
+}
+
+type Result<T> = std::result::Result<T, UniswapV4Error>;
+
+// https://docs.uniswap.org/contracts/v4/deployments
+pub const POOL_MANAGER: Address = address!("000000000004444c5dc75cB358380D2e3dE08A90");
+
+/// Native ETH as a V4 currency.
+pub const NATIVE: Address = Address::ZERO;
+
+/// Fee of pools whose LP fee is set by their hooks.
+pub const DYNAMIC_FEE_FLAG: u32 = 0x800000;
+/// Highest static LP fee, 100% in hundredths of a bip.
+pub const MAX_LP_FEE: u32 = 1_000_000;
+pub const MIN_TICK_SPACING: i32 = 1;
+pub const MAX_TICK_SPACING: i32 = 32767;
+
+/// `Actions` of the V4 router, the steps of a `V4_SWAP` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Action {
+    SwapExactInSingle = 0x06,
+    SwapExactIn = 0x07,
+    SwapExactOutSingle = 0x08,
+    SwapExactOut = 0x09,
+    Settle = 0x0b,
+    SettleAll = 0x0c,
+    SettlePair = 0x0d,
+    Take = 0x0e,
+    TakeAll = 0x0f,
+    TakePortion = 0x10,
+    TakePair = 0x11,
+    CloseCurrency = 0x12,
+    Sweep = 0x14,
+}
+
+/// Callbacks a hook contract implements, encoded in the low 14 bits of its address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HookPermissions {
+    pub before_initialize: bool,
+    pub after_initialize: bool,
+    pub before_add_liquidity: bool,
+    pub after_add_liquidity: bool,
+    pub before_remove_liquidity: bool,
+    pub after_remove_liquidity: bool,
+    pub before_swap: bool,
+    pub after_swap: bool,
+    pub before_donate: bool,
+    pub after_donate: bool,
+    pub before_swap_returns_delta: bool,
+    pub after_swap_returns_delta: bool,
+    pub after_add_liquidity_returns_delta: bool,
+    pub after_remove_liquidity_returns_delta: bool,
+}
+
+impl HookPermissions {
+    pub fn from_address(hooks: Address) -> Self {
+        let flags = u16::from_be_bytes([hooks[18], hooks[19]]);
+        let has = |bit: u16| flags & (1 << bit) != 0;
+        Self {
+            before_initialize: has(13),
+            after_initialize: has(12),
+            before_add_liquidity: has(11),
+            after_add_liquidity: has(10),
+            before_remove_liquidity: has(9),
+            after_remove_liquidity: has(8),
+            before_swap: has(7),
+            after_swap: has(6),
+            before_donate: has(5),
+            after_donate: has(4),
+            before_swap_returns_delta: has(3),
+            after_swap_returns_delta: has(2),
+            after_add_liquidity_returns_delta: has(1),
+            after_remove_liquidity_returns_delta: has(0),
+        }
+    }
+
+    /// Whether any callback is enabled.
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Identifies a V4 pool, which lives in the singleton `PoolManager` rather than its own contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    /// The lower currency, [`NATIVE`] for ETH.
+    pub currency0: Address,
+    pub currency1: Address,
+    /// Fee in hundredths of a bip, or [`DYNAMIC_FEE_FLAG`].
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub hooks: Address,
+}
+
+impl PoolKey {
+    /// Sorts the currencies and checks the key the way `PoolManager.initialize` does.
+    pub fn new(currency_a: Address, currency_b: Address, fee: u32, tick_spacing: i32, hooks: Address) -> Result<Self> {
+        if currency_a == currency_b {
+            return Err(UniswapV4Error::InvalidPoolKey("identical currencies".into()));
+        }
+        if !(MIN_TICK_SPACING..=MAX_TICK_SPACING).contains(&tick_spacing) {
+            return Err(UniswapV4Error::InvalidPoolKey(format!("tick spacing {}", tick_spacing)));
+        }
+        if fee != DYNAMIC_FEE_FLAG && fee > MAX_LP_FEE {
+            return Err(UniswapV4Error::InvalidPoolKey(format!("fee {}", fee)));
+        }
+        let key = Self {
+            currency0: currency_a.min(currency_b),
+            currency1: currency_a.max(currency_b),
+            fee,
+            tick_spacing,
+            hooks,
+        };
+        if !key.has_valid_hooks() {
+            return Err(UniswapV4Error::InvalidHooks(hooks));
+        }
+        Ok(key)
+    }
+
+    /// `keccak256(abi.encode(key))`, the pool id used by the `PoolManager` and its events.
+    pub fn id(&self) -> Result<B256> {
+        Ok(keccak256(self.to_sol()?.abi_encode()))
+    }
+
+    pub fn is_dynamic_fee(&self) -> bool {
+        self.fee == DYNAMIC_FEE_FLAG
+    }
+
+    pub fn hook_permissions(&self) -> HookPermissions {
+        HookPermissions::from_address(self.hooks)
+    }
+
+    /// `Hooks.isValidHookAddress`: delta-returning flags need their callback, pools without
+    /// hooks can't have dynamic fees and hooks must implement something.
+    pub fn has_valid_hooks(&self) -> bool {
+        let permissions = self.hook_permissions();
+        if (permissions.before_swap_returns_delta && !permissions.before_swap)
+            || (permissions.after_swap_returns_delta && !permissions.after_swap)
+            || (permissions.after_add_liquidity_returns_delta && !permissions.after_add_liquidity)
+            || (permissions.after_remove_liquidity_returns_delta && !permissions.after_remove_liquidity)
+        {
+            return false;
+        }
+        match self.hooks.is_zero() {
+            true => !self.is_dynamic_fee(),
+            false => permissions.any() || self.is_dynamic_fee(),
+        }
+    }
+
+    /// Whether swapping `currency_in` moves the price down, token0 for token1.
+    pub fn zero_for_one(&self, currency_in: Address) -> Result<bool> {
+        match currency_in {
+            currency if currency == self.currency0 => Ok(true),
+            currency if currency == self.currency1 => Ok(false),
+            currency => Err(UniswapV4Error::InvalidPath(format!(
+                "{} is not in pool {}/{}",
+                currency, self.currency0, self.currency1
+            ))),
+        }
+    }
+
+    /// The key as the router encodes it, failing on a fee or tick spacing that doesn't fit in
+    /// 24 bits instead of encoding a different pool.
+    pub(crate) fn to_sol(self) -> Result<IV4Router::PoolKey> {
+        Ok(IV4Router::PoolKey {
+            currency0: self.currency0,
+            currency1: self.currency1,
+            fee: U24::try_from(self.fee)
+                .map_err(|_| UniswapV4Error::InvalidPoolKey(format!("fee {}", self.fee)))?,
+            tickSpacing: I24::try_from(self.tick_spacing)
+                .map_err(|_| UniswapV4Error::InvalidPoolKey(format!("tick spacing {}", self.tick_spacing)))?,
+            hooks: self.hooks,
+        })
+    }
+}
+
+/// `PathKey`s of a multi-hop swap from `currency_in` through `pools`, each hop entering the
+/// next pool with the currency it received.
+pub fn path_keys(currency_in: Address, pools: &[PoolKey]) -> Result<(Vec<IV4Router::PathKey>, Address)> {
+    if pools.is_empty() {
+        return Err(UniswapV4Error::InvalidPath("no pools".into()));
+    }
+    let mut currency = currency_in;
+    let mut path = Vec::with_capacity(pools.len());
+    for pool in pools {
+        currency = match pool.zero_for_one(currency)? {
+            true => pool.currency1,
+            false => pool.currency0,
+        };
+        let key = pool.to_sol()?;
+        path.push(IV4Router::PathKey {
+            intermediateCurrency: currency,
+            fee: key.fee,
+            tickSpacing: key.tickSpacing,
+            hooks: key.hooks,
+            hookData: Bytes::new(),
+        });
+    }
+    Ok((path, currency))
+}
+
+fn to_u128(amount: U256) -> Result<u128> {
+    u128::try_from(amount).map_err(|_| UniswapV4Error::Overflow(amount))
+}
+
+/// The `actions` and `params` of a V4 router call, input of the `V4_SWAP` command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct V4Actions {
+    actions: Vec<u8>,
+    params: Vec<Bytes>,
+    native_value: U256,
+}
+
+impl V4Actions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, action: Action, params: Vec<u8>) -> &mut Self {
+        self.actions.push(action as u8);
+        self.params.push(params.into());
+        self
+    }
+
+    pub fn swap_exact_in_single(
+        &mut self,
+        pool: &PoolKey,
+        currency_in: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+    ) -> Result<&mut Self> {
+        let params = IV4Router::ExactInputSingleParams {
+            poolKey: pool.to_sol()?,
+            zeroForOne: pool.zero_for_one(currency_in)?,
+            amountIn: to_u128(amount_in)?,
+            amountOutMinimum: to_u128(amount_out_min)?,
+            hookData: Bytes::new(),
+        };
+        Ok(self.add(Action::SwapExactInSingle, params.abi_encode()))
+    }
+
+    pub fn swap_exact_out_single(
+        &mut self,
+        pool: &PoolKey,
+        currency_in: Address,
+        amount_out: U256,
+        amount_in_max: U256,
+    ) -> Result<&mut Self> {
+        let params = IV4Router::ExactOutputSingleParams {
+            poolKey: pool.to_sol()?,
+            zeroForOne: pool.zero_for_one(currency_in)?,
+            amountOut: to_u128(amount_out)?,
+            amountInMaximum: to_u128(amount_in_max)?,
+            hookData: Bytes::new(),
+        };
+        Ok(self.add(Action::SwapExactOutSingle, params.abi_encode()))
+    }
+
+    /// A multi-hop exact input swap through `pools` in order.
+    pub fn swap_exact_in(
+        &mut self,
+        currency_in: Address,
+        pools: &[PoolKey],
+        amount_in: U256,
+        amount_out_min: U256,
+    ) -> Result<&mut Self> {
+        let (path, _) = path_keys(currency_in, pools)?;
+        let params = IV4Router::ExactInputParams {
+            currencyIn: currency_in,
+            path,
+            amountIn: to_u128(amount_in)?,
+            amountOutMinimum: to_u128(amount_out_min)?,
+        };
+        Ok(self.add(Action::SwapExactIn, params.abi_encode()))
+    }
+
+    /// A multi-hop exact output swap through `pools`, listed from input to output.
+    pub fn swap_exact_out(
+        &mut self,
+        currency_in: Address,
+        pools: &[PoolKey],
+        amount_out: U256,
+        amount_in_max: U256,
+    ) -> Result<&mut Self> {
+        // the router walks exact output paths backwards, each key holding the hop's input
+        let (_, currency_out) = path_keys(currency_in, pools)?;
+        let mut currency = currency_out;
+        let mut path = Vec::with_capacity(pools.len());
+        for pool in pools.iter().rev() {
+            currency = match pool.zero_for_one(currency)? {
+                true => pool.currency1,
+                false => pool.currency0,
+            };
+            let key = pool.to_sol()?;
+            path.push(IV4Router::PathKey {
+                intermediateCurrency: currency,
+                fee: key.fee,
+                tickSpacing: key.tickSpacing,
+                hooks: key.hooks,
+                hookData: Bytes::new(),
+            });
+        }
+        path.reverse();
+        let params = IV4Router::ExactOutputParams {
+            currencyOut: currency_out,
+            path,
+            amountOut: to_u128(amount_out)?,
+            amountInMaximum: to_u128(amount_in_max)?,
+        };
+        Ok(self.add(Action::SwapExactOut, params.abi_encode()))
+    }
+
+    /// Pays what the swaps owe in `currency`, up to `max_amount`, from the caller.
+    pub fn settle_all(&mut self, currency: Address, max_amount: U256) -> &mut Self {
+        if currency == NATIVE {
+            self.native_value += max_amount;
+        }
+        self.add(Action::SettleAll, (currency, max_amount).abi_encode_params())
+    }
+
+    /// Pays `amount` of `currency`, from the caller or from the router's balance.
+    pub fn settle(&mut self, currency: Address, amount: U256, payer_is_user: bool) -> &mut Self {
+        if currency == NATIVE && payer_is_user {
+            self.native_value += amount;
+        }
+        self.add(Action::Settle, (currency, amount, payer_is_user).abi_encode_params())
+    }
+
+    /// Sends the caller everything owed in `currency`, at least `min_amount`.
+    pub fn take_all(&mut self, currency: Address, min_amount: U256) -> &mut Self {
+        self.add(Action::TakeAll, (currency, min_amount).abi_encode_params())
+    }
+
+    /// Sends `amount` of `currency` to `recipient`, zero taking all that is owed.
+    pub fn take(&mut self, currency: Address, recipient: Address, amount: U256) -> &mut Self {
+        self.add(Action::Take, (currency, recipient, amount).abi_encode_params())
+    }
+
+    /// ETH the caller has to send along for native settlements.
+    pub fn native_value(&self) -> U256 {
+        self.native_value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// `abi.encode(bytes actions, bytes[] params)`.
+    pub fn encode(&self) -> Bytes {
+        (Bytes::from(self.actions.clone()), self.params.clone())
+            .abi_encode_params()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::b256;
+    use alloy_sol_types::{sol_data, SolType};
+
+    type ActionsInput = (sol_data::Bytes, sol_data::Array<sol_data::Bytes>);
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+    #[test]
+    fn test_pool_key() {
+        let key = PoolKey::new(USDC, NATIVE, 500, 10, Address::ZERO).unwrap();
+        assert_eq!((key.currency0, key.currency1), (NATIVE, USDC));
+        assert_eq!(
+            key.id().unwrap(),
+            b256!("21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27")
+        );
+        assert!(key.zero_for_one(NATIVE).unwrap());
+        assert!(key.zero_for_one(DAI).is_err());
+
+        // public fields can bypass `new`, the encoding must not wrap them into another pool
+        let wide = PoolKey { tick_spacing: 1 << 23, ..key };
+        assert!(matches!(wide.id(), Err(UniswapV4Error::InvalidPoolKey(_))));
+        let wide = PoolKey { fee: 1 << 24, ..key };
+        assert!(matches!(wide.to_sol(), Err(UniswapV4Error::InvalidPoolKey(_))));
+
+        assert!(PoolKey::new(USDC, USDC, 500, 10, Address::ZERO).is_err());
+        assert!(PoolKey::new(USDC, WETH, 500, 0, Address::ZERO).is_err());
+        assert!(PoolKey::new(USDC, WETH, MAX_LP_FEE + 1, 10, Address::ZERO).is_err());
+        assert!(matches!(
+            PoolKey::new(USDC, WETH, DYNAMIC_FEE_FLAG, 10, Address::ZERO),
+            Err(UniswapV4Error::InvalidHooks(_))
+        ));
+
+        // before and after swap with the before swap delta
+        let hooks = address!("00000000000000000000000000000000000000c8");
+        let permissions = HookPermissions::from_address(hooks);
+        assert!(permissions.before_swap && permissions.after_swap && permissions.before_swap_returns_delta);
+        assert!(!permissions.before_initialize && !permissions.after_swap_returns_delta);
+        assert!(PoolKey::new(USDC, WETH, 3000, 60, hooks).is_ok());
+        // a delta flag without its callback
+        let hooks = address!("0000000000000000000000000000000000000004");
+        assert!(PoolKey::new(USDC, WETH, 3000, 60, hooks).is_err());
+        // hooks without callbacks only make sense for dynamic fees
+        let hooks = address!("0000000000000000000000000000000000004000");
+        assert!(PoolKey::new(USDC, WETH, 3000, 60, hooks).is_err());
+        assert!(PoolKey::new(USDC, WETH, DYNAMIC_FEE_FLAG, 60, hooks)
+            .unwrap()
+            .is_dynamic_fee());
+    }
+
+    #[test]
+    fn test_v4_actions() {
+        let eth_usdc = PoolKey::new(NATIVE, USDC, 500, 10, Address::ZERO).unwrap();
+        let usdc_dai = PoolKey::new(USDC, DAI, 100, 1, Address::ZERO).unwrap();
+
+        let mut actions = V4Actions::new();
+        actions
+            .swap_exact_in_single(&eth_usdc, NATIVE, U256::from(1000), U256::from(900))
+            .unwrap()
+            .settle_all(NATIVE, U256::from(1000))
+            .take_all(USDC, U256::from(900));
+        assert_eq!(actions.native_value(), U256::from(1000));
+        let (encoded_actions, params) = ActionsInput::abi_decode_params(&actions.encode(), true).unwrap();
+        assert_eq!(encoded_actions[..], [0x06, 0x0c, 0x0f]);
+        // structs with dynamic fields are encoded behind an offset, like `abi.encode(params)`
+        let swap = <IV4Router::ExactInputSingleParams as SolType>::abi_decode(&params[0], true).unwrap();
+        assert!(swap.zeroForOne);
+        assert_eq!((swap.poolKey, swap.amountIn), (eth_usdc.to_sol().unwrap(), 1000));
+
+        let mut actions = V4Actions::new();
+        actions
+            .swap_exact_in(NATIVE, &[eth_usdc, usdc_dai], U256::from(1000), U256::from(900))
+            .unwrap()
+            .swap_exact_out(NATIVE, &[eth_usdc, usdc_dai], U256::from(900), U256::from(1000))
+            .unwrap();
+        let (_, params) = ActionsInput::abi_decode_params(&actions.encode(), true).unwrap();
+        let swap = <IV4Router::ExactInputParams as SolType>::abi_decode(&params[0], true).unwrap();
+        let hops: Vec<_> = swap.path.iter().map(|key| key.intermediateCurrency).collect();
+        assert_eq!((swap.currencyIn, hops), (NATIVE, vec![USDC, DAI]));
+        let swap = <IV4Router::ExactOutputParams as SolType>::abi_decode(&params[1], true).unwrap();
+        let hops: Vec<_> = swap.path.iter().map(|key| key.intermediateCurrency).collect();
+        assert_eq!((swap.currencyOut, hops), (DAI, vec![NATIVE, USDC]));
+
+        assert!(V4Actions::new()
+            .swap_exact_in(WETH, &[eth_usdc], U256::from(1), U256::ZERO)
+            .is_err());
+        assert!(matches!(
+            V4Actions::new().swap_exact_in_single(&eth_usdc, NATIVE, U256::MAX, U256::ZERO),
+            Err(UniswapV4Error::Overflow(_))
+        ));
+    }
+}
@@ -0,0 +1,664 @@
+use alloy_dyn_abi::Eip712Domain;
+use alloy_eips::BlockId;
+use alloy_primitives::{address, hex, uint, Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{SolCall, SolValue};
+
+use crate::abis::uniswap::{IAllowanceTransfer, IUniversalRouter};
+use crate::dexes::kyber::RouteSummary;
+use crate::dexes::uni_v2::{UniswapV2, UniswapV2Error, UNISWAP_V2};
+use crate::dexes::uni_v3::path::V3Path;
+use crate::dexes::uni_v3::{UniswapV3, UniswapV3Error, UNISWAP_V3};
+use crate::dexes::uni_v4::{UniswapV4Error, V4Actions};
+use crate::provider::client::EvmProvider;
+use crate::provider::multicall::Multicall;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::{EvmSigner, EvmSignerError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniversalRouterError {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Uniswap V2 error: {0}")]
+    UniswapV2Error(#[from] UniswapV2Error),
+    #[error("Uniswap V3 error: {0}")]
+    UniswapV3Error(#[from] UniswapV3Error),
+    #[error("Uniswap V4 error: {0}")]
+    UniswapV4Error(#[from] UniswapV4Error),
+    #[error("Signer error: {0}")]
+    SignerError(#[from] EvmSignerError),
+    #[error("Invalid route: {0}")]
+    InvalidRoute(String),
+    #[error("Unsupported pool {pool} on {exchange}")]
+    UnsupportedPool { pool: String, exchange: String },
+}
+
+type Result<T> = std::result::Result<T, UniversalRouterError>;
+
+// https://docs.uniswap.org/contracts/v4/deployments
+pub const UNIVERSAL_ROUTER: Address = address!("66a9893cC07D91D95644AEDD05D03f95e1dBA8Af");
+pub const PERMIT2: Address = address!("000000000022D473030F116dDEE9F6B43aC78BA3");
+
+/// Recipient resolved by the router to the caller.
+pub const MSG_SENDER: Address = address!("0000000000000000000000000000000000000001");
+/// Recipient resolved by the router to itself, to chain commands.
+pub const ADDRESS_THIS: Address = address!("0000000000000000000000000000000000000002");
+/// Amount resolved by the router to its whole balance of the token.
+pub const CONTRACT_BALANCE: U256 = uint!(0x8000000000000000000000000000000000000000000000000000000000000000_U256);
+/// Lets the command revert without reverting the whole execution.
+pub const FLAG_ALLOW_REVERT: u8 = 0x80;
+
+/// Placeholder aggregators use for the native token.
+const NATIVE_TOKEN: Address = address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+/// Universal Router commands, the first byte of each command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Command {
+    V3SwapExactIn = 0x00,
+    V3SwapExactOut = 0x01,
+    Permit2TransferFrom = 0x02,
+    Permit2PermitBatch = 0x03,
+    Sweep = 0x04,
+    Transfer = 0x05,
+    PayPortion = 0x06,
+    V2SwapExactIn = 0x08,
+    V2SwapExactOut = 0x09,
+    Permit2Permit = 0x0a,
+    WrapEth = 0x0b,
+    UnwrapWeth = 0x0c,
+    Permit2TransferFromBatch = 0x0d,
+    BalanceCheckErc20 = 0x0e,
+    V4Swap = 0x10,
+    V3PositionManagerPermit = 0x11,
+    V3PositionManagerCall = 0x12,
+    V4InitializePool = 0x13,
+    V4PositionManagerCall = 0x14,
+}
+
+/// The commands and inputs of an `execute` call, built in execution order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouterPlan {
+    commands: Vec<u8>,
+    inputs: Vec<Bytes>,
+    value: U256,
+}
+
+impl RouterPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, command: Command, input: Vec<u8>, allow_revert: bool) -> &mut Self {
+        let flag = if allow_revert { FLAG_ALLOW_REVERT } else { 0 };
+        self.commands.push(command as u8 | flag);
+        self.inputs.push(input.into());
+        self
+    }
+
+    pub fn v2_swap_exact_in(
+        &mut self,
+        recipient: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: &[Address],
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (recipient, amount_in, amount_out_min, path.to_vec(), payer_is_user).abi_encode_params();
+        self.add(Command::V2SwapExactIn, input, false)
+    }
+
+    pub fn v2_swap_exact_out(
+        &mut self,
+        recipient: Address,
+        amount_out: U256,
+        amount_in_max: U256,
+        path: &[Address],
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (recipient, amount_out, amount_in_max, path.to_vec(), payer_is_user).abi_encode_params();
+        self.add(Command::V2SwapExactOut, input, false)
+    }
+
+    pub fn v3_swap_exact_in(
+        &mut self,
+        recipient: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: &V3Path,
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (recipient, amount_in, amount_out_min, path.encode(), payer_is_user).abi_encode_params();
+        self.add(Command::V3SwapExactIn, input, false)
+    }
+
+    pub fn v3_swap_exact_out(
+        &mut self,
+        recipient: Address,
+        amount_out: U256,
+        amount_in_max: U256,
+        path: &V3Path,
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (
+            recipient,
+            amount_out,
+            amount_in_max,
+            path.encode_reversed(),
+            payer_is_user,
+        )
+            .abi_encode_params();
+        self.add(Command::V3SwapExactOut, input, false)
+    }
+
+    /// Runs V4 router actions, sending along the ETH they settle from the caller.
+    pub fn v4_swap(&mut self, actions: &V4Actions) -> &mut Self {
+        self.value += actions.native_value();
+        self.add(Command::V4Swap, actions.encode().to_vec(), false)
+    }
+
+    /// Wraps `amount` of the ETH sent along into WETH.
+    pub fn wrap_eth(&mut self, recipient: Address, amount: U256) -> &mut Self {
+        if amount != CONTRACT_BALANCE {
+            self.value += amount;
+        }
+        self.add(Command::WrapEth, (recipient, amount).abi_encode_params(), false)
+    }
+
+    /// Unwraps the router's WETH, at least `amount_min`, to `recipient`.
+    pub fn unwrap_weth(&mut self, recipient: Address, amount_min: U256) -> &mut Self {
+        self.add(Command::UnwrapWeth, (recipient, amount_min).abi_encode_params(), false)
+    }
+
+    /// Sends the router's whole balance of `token`, at least `amount_min`, to `recipient`.
+    pub fn sweep(&mut self, token: Address, recipient: Address, amount_min: U256) -> &mut Self {
+        self.add(
+            Command::Sweep,
+            (token, recipient, amount_min).abi_encode_params(),
+            false,
+        )
+    }
+
+    pub fn transfer(&mut self, token: Address, recipient: Address, value: U256) -> &mut Self {
+        self.add(Command::Transfer, (token, recipient, value).abi_encode_params(), false)
+    }
+
+    /// Sends `bips` of the router's balance of `token` to `recipient`, e.g. as an interface fee.
+    pub fn pay_portion(&mut self, token: Address, recipient: Address, bips: u32) -> &mut Self {
+        let input = (token, recipient, U256::from(bips)).abi_encode_params();
+        self.add(Command::PayPortion, input, false)
+    }
+
+    /// Sets the router's Permit2 allowance from a signed `PermitSingle`, see [`sign_permit2`].
+    pub fn permit2_permit(&mut self, permit: &IAllowanceTransfer::PermitSingle, signature: Bytes) -> &mut Self {
+        let input = (permit.clone(), signature).abi_encode_params();
+        self.add(Command::Permit2Permit, input, false)
+    }
+
+    pub fn commands(&self) -> &[u8] {
+        &self.commands
+    }
+
+    pub fn inputs(&self) -> &[Bytes] {
+        &self.inputs
+    }
+
+    /// ETH the transaction has to send.
+    pub fn value(&self) -> U256 {
+        self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// The EIP-712 domain of Permit2 messages, which has no version.
+pub fn permit2_domain(chain_id: u64) -> Eip712Domain {
+    Eip712Domain::new(
+        Some("Permit2".into()),
+        None,
+        Some(U256::from(chain_id)),
+        Some(PERMIT2),
+        None,
+    )
+}
+
+/// Signs a Permit2 `PermitSingle` for the `PERMIT2_PERMIT` command.
+pub fn sign_permit2(signer: &EvmSigner, chain_id: u64, permit: &IAllowanceTransfer::PermitSingle) -> Result<Bytes> {
+    let signature = signer.sign_eip712(permit2_domain(chain_id), permit)?;
+    let signature = hex::decode(&signature)
+        .map_err(|_| EvmSignerError::SignatureError(format!("invalid signature {}", signature)))?;
+    Ok(signature.into())
+}
+
+/// A Universal Router deployment with the Uniswap deployments its commands reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniversalRouter {
+    pub address: Address,
+    pub weth: Address,
+    pub v2: UniswapV2,
+    pub v3: UniswapV3,
+}
+
+pub const UNISWAP_UNIVERSAL_ROUTER: UniversalRouter = UniversalRouter {
+    address: UNIVERSAL_ROUTER,
+    weth: UNISWAP_V3.weth,
+    v2: UNISWAP_V2,
+    v3: UNISWAP_V3,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    V2,
+    V3(u32),
+}
+
+impl UniversalRouter {
+    /// `execute` transaction of `plan`, reverting after `deadline` if one is given.
+    pub fn execute(&self, plan: &RouterPlan, deadline: Option<u64>) -> TransactionRequest {
+        let commands = Bytes::from(plan.commands.clone());
+        let inputs = plan.inputs.clone();
+        let input = match deadline {
+            Some(deadline) => IUniversalRouter::execute_0Call {
+                commands,
+                inputs,
+                deadline: U256::from(deadline),
+            }
+            .abi_encode(),
+            None => IUniversalRouter::execute_1Call { commands, inputs }.abi_encode(),
+        };
+        let tx = TransactionRequest::default()
+            .to(self.address)
+            .input(Bytes::from(input).into());
+        match plan.value.is_zero() {
+            true => tx,
+            false => tx.value(plan.value),
+        }
+    }
+
+    /// Replays an aggregator route through the router, so that a quote made of Uniswap V2 and
+    /// V3 pools can be executed without the aggregator's contract.
+    ///
+    /// Every split is swapped into the router and the output is swept, or unwrapped for the
+    /// native token, to `recipient` only if it reaches `amount_out_min`. Tokens are pulled from
+    /// the caller through Permit2, which must allow the router.
+    pub fn plan_route_summary(
+        &self,
+        summary: &RouteSummary,
+        recipient: Address,
+        amount_out_min: U256,
+    ) -> Result<RouterPlan> {
+        let token_in = parse_address(&summary.token_in)?;
+        let token_out = parse_address(&summary.token_out)?;
+        let native_in = token_in == NATIVE_TOKEN;
+        let native_out = token_out == NATIVE_TOKEN;
+        let wrapped = |token: Address| if token == NATIVE_TOKEN { self.weth } else { token };
+        let (token_in, token_out) = (wrapped(token_in), wrapped(token_out));
+        if summary.route.is_empty() {
+            return Err(UniversalRouterError::InvalidRoute("no splits".into()));
+        }
+
+        let mut plan = RouterPlan::new();
+        if native_in {
+            plan.wrap_eth(ADDRESS_THIS, parse_amount(&summary.amount_in)?);
+        }
+        for split in &summary.route {
+            let first = split
+                .first()
+                .ok_or_else(|| UniversalRouterError::InvalidRoute("empty split".into()))?;
+            let amount_in = parse_amount(&first.swap_amount)?;
+
+            // consecutive hops on the same protocol are swapped as one path
+            let mut segments: Vec<(Protocol, Vec<Address>, Vec<u32>)> = Vec::new();
+            let mut token = token_in;
+            for hop in split {
+                let (hop_in, hop_out) = (
+                    wrapped(parse_address(&hop.token_in)?),
+                    wrapped(parse_address(&hop.token_out)?),
+                );
+                if hop_in != token {
+                    return Err(UniversalRouterError::InvalidRoute(format!(
+                        "hop through {} starts with {} instead of {}",
+                        hop.pool, hop_in, token
+                    )));
+                }
+                let protocol = self.protocol(&hop.pool, &hop.exchange, hop_in, hop_out)?;
+                match segments.last_mut() {
+                    Some((last, tokens, fees)) if same_protocol(*last, protocol) => {
+                        tokens.push(hop_out);
+                        if let Protocol::V3(fee) = protocol {
+                            fees.push(fee);
+                        }
+                    }
+                    _ => {
+                        let fees = match protocol {
+                            Protocol::V3(fee) => vec![fee],
+                            Protocol::V2 => vec![],
+                        };
+                        segments.push((protocol, vec![hop_in, hop_out], fees));
+                    }
+                }
+                token = hop_out;
+            }
+            if token != token_out {
+                return Err(UniversalRouterError::InvalidRoute(format!(
+                    "split ends with {} instead of {}",
+                    token, token_out
+                )));
+            }
+
+            // later segments spend what the previous one left in the router
+            for (i, (protocol, tokens, fees)) in segments.into_iter().enumerate() {
+                let amount = if i == 0 { amount_in } else { CONTRACT_BALANCE };
+                let payer_is_user = i == 0 && !native_in;
+                match protocol {
+                    Protocol::V2 => plan.v2_swap_exact_in(ADDRESS_THIS, amount, U256::ZERO, &tokens, payer_is_user),
+                    Protocol::V3(_) => {
+                        let path = V3Path::new(tokens, fees)?;
+                        plan.v3_swap_exact_in(ADDRESS_THIS, amount, U256::ZERO, &path, payer_is_user)
+                    }
+                };
+            }
+        }
+        match native_out {
+            true => plan.unwrap_weth(recipient, amount_out_min),
+            false => plan.sweep(token_out, recipient, amount_out_min),
+        };
+        Ok(plan)
+    }
+
+    /// Permit2 allowance of the router over `owner`'s `token`: amount, expiration and nonce.
+    pub async fn fetch_permit2_allowance(
+        &self,
+        provider: &EvmProvider,
+        owner: Address,
+        token: Address,
+        block: BlockId,
+    ) -> Result<IAllowanceTransfer::allowanceReturn> {
+        let mut multicall = Multicall::new();
+        let call = IAllowanceTransfer::allowanceCall {
+            user: owner,
+            token,
+            spender: self.address,
+        };
+        let handle = multicall.add(PERMIT2, call, false);
+        let results = multicall.call(provider, block).await?;
+        results
+            .get(&handle)
+            .map_err(|e| ProviderError::ResponseError(format!("Permit2 allowance: {:?}", e)).into())
+    }
+
+    // the pool address tells the protocol and the V3 fee tier apart
+    fn protocol(&self, pool: &str, exchange: &str, token_in: Address, token_out: Address) -> Result<Protocol> {
+        let unsupported = || UniversalRouterError::UnsupportedPool {
+            pool: pool.to_string(),
+            exchange: exchange.to_string(),
+        };
+        let address = pool.parse::<Address>().map_err(|_| unsupported())?;
+        if self.v2.pair_address(token_in, token_out)? == address {
+            return Ok(Protocol::V2);
+        }
+        for fee in [100, 500, 3000, 10000] {
+            if self.v3.pool_address(token_in, token_out, fee)? == address {
+                return Ok(Protocol::V3(fee));
+            }
+        }
+        Err(unsupported())
+    }
+}
+
+fn same_protocol(a: Protocol, b: Protocol) -> bool {
+    matches!(
+        (a, b),
+        (Protocol::V2, Protocol::V2) | (Protocol::V3(_), Protocol::V3(_))
+    )
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    address
+        .parse()
+        .map_err(|_| UniversalRouterError::InvalidRoute(format!("invalid address {}", address)))
+}
+
+fn parse_amount(amount: &str) -> Result<U256> {
+    amount
+        .parse()
+        .map_err(|_| UniversalRouterError::InvalidRoute(format!("invalid amount {}", amount)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::uni_v4::{PoolKey, NATIVE};
+    use crate::provider::test_utils::account;
+    use alloy_primitives::aliases::U48;
+    use alloy_primitives::U160;
+    use alloy_sol_types::{sol_data, SolType};
+    use serde_json::json;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const RECIPIENT: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+
+    type SwapInput<P> = (
+        sol_data::Address,
+        sol_data::Uint<256>,
+        sol_data::Uint<256>,
+        P,
+        sol_data::Bool,
+    );
+
+    fn execute_call(tx: &TransactionRequest) -> IUniversalRouter::execute_0Call {
+        IUniversalRouter::execute_0Call::abi_decode(tx.input.input().unwrap(), true).unwrap()
+    }
+
+    #[test]
+    fn test_router_plan() {
+        let eth_usdc = PoolKey::new(NATIVE, USDC, 500, 10, Address::ZERO).unwrap();
+        let mut actions = V4Actions::new();
+        actions
+            .swap_exact_in_single(&eth_usdc, NATIVE, U256::from(1000), U256::from(900))
+            .unwrap()
+            .settle_all(NATIVE, U256::from(1000))
+            .take_all(USDC, U256::from(900));
+
+        let mut plan = RouterPlan::new();
+        plan.wrap_eth(ADDRESS_THIS, U256::from(500))
+            .v3_swap_exact_in(
+                ADDRESS_THIS,
+                CONTRACT_BALANCE,
+                U256::ZERO,
+                &V3Path::single(WETH, 500, USDC).unwrap(),
+                false,
+            )
+            .v2_swap_exact_out(RECIPIENT, U256::from(10), U256::from(20), &[USDC, DAI], true)
+            .v4_swap(&actions)
+            .pay_portion(USDC, RECIPIENT, 25)
+            .add(Command::Sweep, (USDC, RECIPIENT, U256::ZERO).abi_encode_params(), true);
+        assert_eq!(plan.commands(), [0x0b, 0x00, 0x09, 0x10, 0x06, 0x84]);
+        assert_eq!(plan.value(), U256::from(1500));
+
+        let tx = UNISWAP_UNIVERSAL_ROUTER.execute(&plan, Some(1_700_000_000));
+        assert_eq!(
+            (tx.to, tx.value),
+            (Some(UNIVERSAL_ROUTER.into()), Some(U256::from(1500)))
+        );
+        let call = execute_call(&tx);
+        assert_eq!(call.deadline, U256::from(1_700_000_000));
+        assert_eq!(call.inputs[3], actions.encode());
+        let (recipient, amount, _, path, payer_is_user) =
+            SwapInput::<sol_data::Bytes>::abi_decode_params(&call.inputs[1], true).unwrap();
+        assert_eq!(
+            (recipient, amount, payer_is_user),
+            (ADDRESS_THIS, CONTRACT_BALANCE, false)
+        );
+        assert_eq!(V3Path::decode(&path).unwrap(), V3Path::single(WETH, 500, USDC).unwrap());
+        let (_, _, _, path, _) =
+            SwapInput::<sol_data::Array<sol_data::Address>>::abi_decode_params(&call.inputs[2], true).unwrap();
+        assert_eq!(path, vec![USDC, DAI]);
+
+        let tx = UNISWAP_UNIVERSAL_ROUTER.execute(&RouterPlan::new().sweep(USDC, RECIPIENT, U256::ZERO).clone(), None);
+        assert_eq!(tx.value, None);
+        assert!(IUniversalRouter::execute_1Call::abi_decode(tx.input.input().unwrap(), true).is_ok());
+    }
+
+    #[test]
+    fn test_permit2_permit() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let permit = IAllowanceTransfer::PermitSingle {
+            details: IAllowanceTransfer::PermitDetails {
+                token: USDC,
+                amount: U160::MAX,
+                expiration: U48::from(1_800_000_000u64),
+                nonce: U48::ZERO,
+            },
+            spender: UNIVERSAL_ROUTER,
+            sigDeadline: U256::from(1_700_000_000),
+        };
+        let signature = sign_permit2(&signer, 1, &permit).unwrap();
+        assert_eq!(signature.len(), 65);
+        let recovered =
+            EvmSigner::recover_eip712_address(permit2_domain(1), &permit, &format!("0x{}", hex::encode(&signature)))
+                .unwrap();
+        assert_eq!(recovered, signer.address());
+
+        let mut plan = RouterPlan::new();
+        plan.permit2_permit(&permit, signature.clone());
+        // the permit is inlined, followed by the signature offset
+        let input = &plan.inputs()[0];
+        assert_eq!(input[6 * 32..7 * 32], U256::from(7 * 32).to_be_bytes::<32>());
+        let (decoded, decoded_signature) =
+            <(IAllowanceTransfer::PermitSingle, sol_data::Bytes)>::abi_decode_params(input, true).unwrap();
+        assert_eq!((decoded, decoded_signature), (permit, signature));
+    }
+
+    fn route_summary(token_in: &str, token_out: &str, route: serde_json::Value) -> RouteSummary {
+        serde_json::from_value(json!({
+            "tokenIn": token_in,
+            "amountIn": "3000000000000000000",
+            "amountInUsd": "6000",
+            "tokenInMarketPriceAvailable": true,
+            "tokenOut": token_out,
+            "amountOut": "6000000000",
+            "amountOutUsd": "6000",
+            "tokenOutMarketPriceAvailable": true,
+            "gas": "200000",
+            "gasPrice": "1000000000",
+            "gasUsd": "1",
+            "extraFee": {"feeAmount": "", "chargeFeeBy": "", "isInBps": false, "feeReceiver": ""},
+            "route": route,
+        }))
+        .unwrap()
+    }
+
+    fn hop(pool: Address, token_in: Address, token_out: Address, amount: &str, exchange: &str) -> serde_json::Value {
+        json!({
+            "pool": pool.to_string(),
+            "tokenIn": token_in.to_string(),
+            "tokenOut": token_out.to_string(),
+            "limitReturnAmount": "0",
+            "swapAmount": amount,
+            "amountOut": "0",
+            "exchange": exchange,
+            "poolLength": 2,
+            "poolType": exchange,
+            "poolExtra": {},
+            "extra": {},
+        })
+    }
+
+    #[test]
+    fn test_plan_route_summary() {
+        let router = UNISWAP_UNIVERSAL_ROUTER;
+        let weth_usdc_v3 = router.v3.pool_address(WETH, USDC, 500).unwrap();
+        let weth_dai_v2 = router.v2.pair_address(WETH, DAI).unwrap();
+        let dai_usdc_v3 = router.v3.pool_address(DAI, USDC, 100).unwrap();
+
+        // ETH in: one split on V3, one through V2 then V3
+        let summary = route_summary(
+            &NATIVE_TOKEN.to_string(),
+            &USDC.to_string(),
+            json!([
+                [hop(weth_usdc_v3, WETH, USDC, "2000000000000000000", "uniswapv3")],
+                [
+                    hop(weth_dai_v2, WETH, DAI, "1000000000000000000", "uniswap"),
+                    hop(dai_usdc_v3, DAI, USDC, "1990000000000000000000", "uniswapv3"),
+                ],
+            ]),
+        );
+        let plan = router
+            .plan_route_summary(&summary, RECIPIENT, U256::from(5_900_000_000u64))
+            .unwrap();
+        assert_eq!(plan.commands(), [0x0b, 0x00, 0x08, 0x00, 0x04]);
+        assert_eq!(plan.value(), U256::from(3) * U256::from(10).pow(U256::from(18)));
+
+        let inputs = plan.inputs();
+        let (_, amount, _, path, payer_is_user) =
+            SwapInput::<sol_data::Bytes>::abi_decode_params(&inputs[1], true).unwrap();
+        assert_eq!(amount, U256::from(2) * U256::from(10).pow(U256::from(18)));
+        assert_eq!(V3Path::decode(&path).unwrap(), V3Path::single(WETH, 500, USDC).unwrap());
+        assert!(!payer_is_user);
+        let (recipient, _, _, path, _) =
+            SwapInput::<sol_data::Array<sol_data::Address>>::abi_decode_params(&inputs[2], true).unwrap();
+        assert_eq!((recipient, path), (ADDRESS_THIS, vec![WETH, DAI]));
+        let (_, amount, _, path, _) = SwapInput::<sol_data::Bytes>::abi_decode_params(&inputs[3], true).unwrap();
+        assert_eq!(amount, CONTRACT_BALANCE);
+        assert_eq!(V3Path::decode(&path).unwrap(), V3Path::single(DAI, 100, USDC).unwrap());
+        assert_eq!(
+            inputs[4][..],
+            (USDC, RECIPIENT, U256::from(5_900_000_000u64)).abi_encode_params()
+        );
+
+        // ERC-20 in, ETH out: consecutive V3 hops share one path, paid by the caller
+        let summary = route_summary(
+            &USDC.to_string(),
+            &NATIVE_TOKEN.to_string(),
+            json!([[
+                hop(dai_usdc_v3, USDC, DAI, "6000000000", "uniswapv3"),
+                hop(
+                    router.v3.pool_address(DAI, WETH, 3000).unwrap(),
+                    DAI,
+                    WETH,
+                    "5990000000000000000000",
+                    "uniswapv3"
+                ),
+            ]]),
+        );
+        let plan = router.plan_route_summary(&summary, RECIPIENT, U256::from(1)).unwrap();
+        assert_eq!(plan.commands(), [0x00, 0x0c]);
+        assert!(plan.value().is_zero());
+        let (_, _, _, path, payer_is_user) =
+            SwapInput::<sol_data::Bytes>::abi_decode_params(&plan.inputs()[0], true).unwrap();
+        assert_eq!(
+            V3Path::decode(&path).unwrap(),
+            V3Path::new(vec![USDC, DAI, WETH], vec![100, 3000]).unwrap()
+        );
+        assert!(payer_is_user);
+
+        let summary = route_summary(
+            &USDC.to_string(),
+            &DAI.to_string(),
+            json!([[hop(RECIPIENT, USDC, DAI, "1", "curve")]]),
+        );
+        assert!(matches!(
+            router.plan_route_summary(&summary, RECIPIENT, U256::ZERO),
+            Err(UniversalRouterError::UnsupportedPool { exchange, .. }) if exchange == "curve"
+        ));
+        let summary = route_summary(
+            &USDC.to_string(),
+            &WETH.to_string(),
+            json!([[hop(dai_usdc_v3, USDC, DAI, "1", "uniswapv3")]]),
+        );
+        assert!(matches!(
+            router.plan_route_summary(&summary, RECIPIENT, U256::ZERO),
+            Err(UniversalRouterError::InvalidRoute(_))
+        ));
+    }
+}
@@ -0,0 +1,500 @@
+use alloy_dyn_abi::TypedData;
+use alloy_primitives::{hex, Bytes, U256};
+use async_trait::async_trait;
+use reqwest::{Client, ClientBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::dexes::aggregator::{
+    parse_address, parse_amount, parse_data, AggregatorError, SwapProvider, SwapQuote, SwapRequest, SwapTransaction,
+};
+use crate::signer::sign::EvmSigner;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ZeroExError {
+    #[error("HTTP request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid status code {status}: {message}")]
+    InvalidStatus {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+    #[error("Invalid API key, it must be a valid header value")]
+    InvalidApiKey,
+    #[error("Invalid Permit2 message: {0}")]
+    InvalidPermit2(String),
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+}
+
+type Result<T> = std::result::Result<T, ZeroExError>;
+
+const BASE_URL: &str = "https://api.0x.org";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllowanceIssue {
+    pub actual: String,
+    pub spender: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BalanceIssue {
+    pub token: String,
+    pub actual: String,
+    pub expected: String,
+}
+
+/// Problems that would make the transaction revert, checked against the taker.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Issues {
+    /// Set if the spender (Permit2 or the AllowanceHolder) can't pull the sell amount.
+    #[serde(default)]
+    pub allowance: Option<AllowanceIssue>,
+    #[serde(default)]
+    pub balance: Option<BalanceIssue>,
+    #[serde(default)]
+    #[serde(rename = "simulationIncomplete")]
+    pub simulation_incomplete: bool,
+    #[serde(default)]
+    #[serde(rename = "invalidSourcesPassed")]
+    pub invalid_sources_passed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transaction {
+    pub to: String,
+    pub data: String,
+    #[serde(default)]
+    pub gas: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "gasPrice")]
+    pub gas_price: String,
+    pub value: String,
+}
+
+/// The message the taker signs for Permit2 to pull the sell token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Permit2 {
+    #[serde(rename = "type")]
+    pub permit_type: String,
+    pub hash: String,
+    pub eip712: TypedData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceResponse {
+    #[serde(rename = "liquidityAvailable")]
+    pub liquidity_available: bool,
+    #[serde(default)]
+    #[serde(rename = "blockNumber")]
+    pub block_number: String,
+    #[serde(default)]
+    #[serde(rename = "buyAmount")]
+    pub buy_amount: String,
+    #[serde(default)]
+    #[serde(rename = "buyToken")]
+    pub buy_token: String,
+    #[serde(default)]
+    #[serde(rename = "sellAmount")]
+    pub sell_amount: String,
+    #[serde(default)]
+    #[serde(rename = "sellToken")]
+    pub sell_token: String,
+    #[serde(default)]
+    #[serde(rename = "minBuyAmount")]
+    pub min_buy_amount: String,
+    #[serde(default)]
+    pub gas: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "gasPrice")]
+    pub gas_price: String,
+    #[serde(default)]
+    #[serde(rename = "totalNetworkFee")]
+    pub total_network_fee: Option<String>,
+    #[serde(default)]
+    pub issues: Issues,
+    #[serde(default)]
+    pub zid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteResponse {
+    #[serde(rename = "liquidityAvailable")]
+    pub liquidity_available: bool,
+    #[serde(default)]
+    #[serde(rename = "blockNumber")]
+    pub block_number: String,
+    #[serde(default)]
+    #[serde(rename = "buyAmount")]
+    pub buy_amount: String,
+    #[serde(default)]
+    #[serde(rename = "buyToken")]
+    pub buy_token: String,
+    #[serde(default)]
+    #[serde(rename = "sellAmount")]
+    pub sell_amount: String,
+    #[serde(default)]
+    #[serde(rename = "sellToken")]
+    pub sell_token: String,
+    #[serde(default)]
+    #[serde(rename = "minBuyAmount")]
+    pub min_buy_amount: String,
+    #[serde(default)]
+    #[serde(rename = "totalNetworkFee")]
+    pub total_network_fee: Option<String>,
+    #[serde(default)]
+    pub issues: Issues,
+    /// Only on Permit2 quotes selling an ERC20.
+    #[serde(default)]
+    pub permit2: Option<Permit2>,
+    #[serde(default)]
+    pub transaction: Transaction,
+    #[serde(default)]
+    pub zid: String,
+}
+
+impl QuoteResponse {
+    /// Calldata of the quote, with the taker's Permit2 signature appended if the quote has a Permit2 message.
+    pub fn signed_data(&self, signer: &EvmSigner) -> Result<Bytes> {
+        let data: Bytes = self
+            .transaction
+            .data
+            .parse()
+            .map_err(|_| ZeroExError::InvalidPermit2("invalid calldata".into()))?;
+        let Some(permit2) = &self.permit2 else {
+            return Ok(data);
+        };
+
+        let hash = permit2
+            .eip712
+            .eip712_signing_hash()
+            .map_err(|e| ZeroExError::InvalidPermit2(e.to_string()))?;
+        if !permit2.hash.eq_ignore_ascii_case(&hash.to_string()) {
+            return Err(ZeroExError::InvalidPermit2(format!(
+                "hash {} does not match the message hash {}",
+                permit2.hash, hash
+            )));
+        }
+        let signature = signer
+            .sign_typed_data(&permit2.eip712)
+            .map_err(|e| ZeroExError::SignatureError(e.to_string()))?;
+        let signature = hex::decode(&signature).map_err(|e| ZeroExError::SignatureError(e.to_string()))?;
+        Ok(append_permit2_signature(&data, &signature))
+    }
+}
+
+/// Appends a Permit2 signature to Settler calldata: its length as a 32 byte word, then the signature.
+pub fn append_permit2_signature(data: &[u8], signature: &[u8]) -> Bytes {
+    let mut signed = Vec::with_capacity(data.len() + 32 + signature.len());
+    signed.extend_from_slice(data);
+    signed.extend_from_slice(&U256::from(signature.len()).to_be_bytes::<32>());
+    signed.extend_from_slice(signature);
+    signed.into()
+}
+
+/// Parameters of a price or quote request.
+#[derive(Debug, Clone)]
+pub struct SwapParams<'a> {
+    pub chain_id: u64,
+    pub sell_token: &'a str,
+    pub buy_token: &'a str,
+    pub sell_amount: &'a str,
+    pub taker: &'a str,
+    /// Defaults to 100, 1%.
+    pub slippage_bps: Option<u32>,
+}
+
+impl SwapParams<'_> {
+    fn query(&self) -> String {
+        let mut query = format!(
+            "chainId={}&sellToken={}&buyToken={}&sellAmount={}&taker={}",
+            self.chain_id, self.sell_token, self.buy_token, self.sell_amount, self.taker
+        );
+        if let Some(slippage_bps) = self.slippage_bps {
+            query.push_str(&format!("&slippageBps={}", slippage_bps));
+        }
+        query
+    }
+}
+
+pub struct ZeroExClient {
+    pub http_client: Client,
+    pub base_url: String,
+    api_key: String,
+}
+
+fn http_client(api_key: &str, timeout: Duration) -> Result<Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("0x-api-key", api_key.parse().map_err(|_| ZeroExError::InvalidApiKey)?);
+    headers.insert("0x-version", "v2".parse().unwrap());
+    Ok(ClientBuilder::new()
+        .timeout(timeout)
+        .default_headers(headers)
+        .build()
+        .expect("Failed to create HTTP client"))
+}
+
+impl ZeroExClient {
+    /// Creates a new 0x Swap API client.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Optional base URL. If None, uses default BASE_URL
+    /// * `api_key` - 0x dashboard key, required by every endpoint
+    pub fn new(base_url: Option<String>, api_key: String) -> Result<Self> {
+        Ok(Self {
+            http_client: http_client(&api_key, Duration::from_secs(10))?,
+            base_url: base_url.unwrap_or(BASE_URL.to_string()),
+            api_key,
+        })
+    }
+
+    /// Sets a custom timeout for the HTTP client
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.http_client = http_client(&self.api_key, timeout)?;
+        Ok(self)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, params: &SwapParams<'_>) -> Result<T> {
+        let url = format!("{}{}?{}", self.base_url, path, params.query());
+        let response = self.http_client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ZeroExError::InvalidStatus {
+                status: response.status(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response.json::<T>().await.map_err(ZeroExError::RequestError)
+    }
+
+    /// Indicative price for a swap through Permit2.
+    pub async fn permit2_price(&self, params: &SwapParams<'_>) -> Result<PriceResponse> {
+        self.get("/swap/permit2/price", params).await
+    }
+
+    /// Firm quote for a swap through Permit2. The taker approves Permit2 once, then signs the quote's
+    /// `permit2` message on every swap, see [`QuoteResponse::signed_data`].
+    pub async fn permit2_quote(&self, params: &SwapParams<'_>) -> Result<QuoteResponse> {
+        self.get("/swap/permit2/quote", params).await
+    }
+
+    /// Indicative price for a swap through the AllowanceHolder.
+    pub async fn allowance_holder_price(&self, params: &SwapParams<'_>) -> Result<PriceResponse> {
+        self.get("/swap/allowance-holder/price", params).await
+    }
+
+    /// Firm quote for a swap through the AllowanceHolder, which the taker approves like a router.
+    pub async fn allowance_holder_quote(&self, params: &SwapParams<'_>) -> Result<QuoteResponse> {
+        self.get("/swap/allowance-holder/quote", params).await
+    }
+}
+
+fn no_liquidity() -> AggregatorError {
+    AggregatorError::InvalidResponse {
+        provider: "0x",
+        message: "no liquidity".into(),
+    }
+}
+
+/// Quotes through the AllowanceHolder, so transactions need no signature.
+#[async_trait]
+impl SwapProvider for ZeroExClient {
+    fn name(&self) -> &'static str {
+        "0x"
+    }
+
+    async fn quote(&self, request: &SwapRequest) -> std::result::Result<SwapQuote, AggregatorError> {
+        let (token_in, token_out) = (request.token_in.to_string(), request.token_out.to_string());
+        let (amount, taker) = (request.amount_in.to_string(), request.sender.to_string());
+        let response = self
+            .allowance_holder_price(&SwapParams {
+                chain_id: request.chain_id,
+                sell_token: &token_in,
+                buy_token: &token_out,
+                sell_amount: &amount,
+                taker: &taker,
+                slippage_bps: Some(request.slippage_bps),
+            })
+            .await?;
+        if !response.liquidity_available {
+            return Err(no_liquidity());
+        }
+        Ok(SwapQuote {
+            provider: self.name(),
+            amount_in: request.amount_in,
+            amount_out: parse_amount(self.name(), &response.buy_amount)?,
+            gas: match &response.gas {
+                Some(gas) => parse_amount(self.name(), gas)?.saturating_to(),
+                None => 0,
+            },
+            route: serde_json::Value::Null,
+        })
+    }
+
+    async fn build(
+        &self,
+        request: &SwapRequest,
+        _quote: &SwapQuote,
+    ) -> std::result::Result<SwapTransaction, AggregatorError> {
+        let (token_in, token_out) = (request.token_in.to_string(), request.token_out.to_string());
+        let (amount, taker) = (request.amount_in.to_string(), request.sender.to_string());
+        let response = self
+            .allowance_holder_quote(&SwapParams {
+                chain_id: request.chain_id,
+                sell_token: &token_in,
+                buy_token: &token_out,
+                sell_amount: &amount,
+                taker: &taker,
+                slippage_bps: Some(request.slippage_bps),
+            })
+            .await?;
+        if !response.liquidity_available {
+            return Err(no_liquidity());
+        }
+        let transaction = response.transaction;
+        Ok(SwapTransaction {
+            provider: self.name(),
+            to: parse_address(self.name(), &transaction.to)?,
+            data: parse_data(self.name(), &transaction.data)?,
+            value: parse_amount(self.name(), &transaction.value)?,
+            gas: match &transaction.gas {
+                Some(gas) => parse_amount(self.name(), gas)?.saturating_to(),
+                None => 0,
+            },
+            amount_out: parse_amount(self.name(), &response.buy_amount)?,
+            min_amount_out: parse_amount(self.name(), &response.min_buy_amount)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dexes::universal_router::PERMIT2;
+    use crate::provider::mock::MockApi;
+    use crate::provider::test_utils::account;
+    use alloy_primitives::{address, Address, PrimitiveSignature};
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19d4a2e9eB0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const SETTLER: Address = address!("0d0E364aa7852291883C162B22D6D81f6355428F");
+
+    async fn mock_api() -> MockApi {
+        MockApi::start(|request| {
+            if request.header("0x-api-key") != Some("test-key") || request.header("0x-version") != Some("v2") {
+                return (
+                    401,
+                    serde_json::json!({ "name": "UNAUTHORIZED", "message": "Invalid API key" }),
+                );
+            }
+            let (status, fixture) = match request.route() {
+                "/swap/permit2/quote" => (200, include_str!("fixtures/zeroex_permit2_quote.json")),
+                "/swap/allowance-holder/price" => (200, include_str!("fixtures/zeroex_allowance_holder_price.json")),
+                "/swap/allowance-holder/quote" => (200, include_str!("fixtures/zeroex_allowance_holder_quote.json")),
+                _ => (404, r#"{"name":"NOT_FOUND","message":"Not found"}"#),
+            };
+            (status, serde_json::from_str(fixture).unwrap())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_permit2_quote() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let taker = signer.address().to_string();
+
+        let api = mock_api().await;
+        let client = ZeroExClient::new(Some(api.url().to_string()), "test-key".to_string()).unwrap();
+        let quote = client
+            .permit2_quote(&SwapParams {
+                chain_id: 1,
+                sell_token: &USDC.to_string(),
+                buy_token: &WETH.to_string(),
+                sell_amount: "1000000000",
+                taker: &taker,
+                slippage_bps: Some(50),
+            })
+            .await
+            .unwrap();
+        assert!(quote.liquidity_available);
+        assert_eq!(quote.buy_amount, "301582129181283456");
+        assert_eq!(
+            quote
+                .issues
+                .allowance
+                .as_ref()
+                .unwrap()
+                .spender
+                .parse::<Address>()
+                .unwrap(),
+            PERMIT2
+        );
+        assert_eq!(quote.transaction.to.parse::<Address>().unwrap(), SETTLER);
+
+        let data = quote.signed_data(&signer).unwrap();
+        let unsigned = parse_data("0x", &quote.transaction.data).unwrap();
+        assert_eq!(&data[..unsigned.len()], &unsigned[..]);
+        assert_eq!(
+            U256::from_be_slice(&data[unsigned.len()..unsigned.len() + 32]),
+            U256::from(65)
+        );
+
+        let permit2 = quote.permit2.as_ref().unwrap();
+        let signature = PrimitiveSignature::try_from(&data[unsigned.len() + 32..]).unwrap();
+        let hash = permit2.eip712.eip712_signing_hash().unwrap();
+        assert_eq!(signature.recover_address_from_prehash(&hash).unwrap(), signer.address());
+
+        let query = &api.requests()[0];
+        assert_eq!(query.query("chainId"), Some("1"));
+        assert_eq!(query.query("slippageBps"), Some("50"));
+        assert_eq!(query.query("taker"), Some(taker.as_str()));
+
+        // a tampered hash is refused rather than signed
+        let mut tampered = quote.clone();
+        tampered.permit2.as_mut().unwrap().hash = format!("0x{}", "00".repeat(32));
+        assert!(matches!(
+            tampered.signed_data(&signer),
+            Err(ZeroExError::InvalidPermit2(_))
+        ));
+
+        // native sells have no Permit2 message
+        let mut native = quote;
+        native.permit2 = None;
+        assert_eq!(native.signed_data(&signer).unwrap(), unsigned);
+    }
+
+    #[tokio::test]
+    async fn test_swap_provider() {
+        let api = mock_api().await;
+        let client = ZeroExClient::new(Some(api.url().to_string()), "test-key".to_string()).unwrap();
+        let sender = address!("d46B96d15ffF9b2B17e9c788086f3159bD0e8355");
+        let request = SwapRequest::new(1, USDC, WETH, U256::from(1_000_000_000), sender).with_slippage(50);
+
+        let quote = SwapProvider::quote(&client, &request).await.unwrap();
+        assert_eq!(quote.amount_out, U256::from(301_590_517_293_410_304u64));
+        assert_eq!(quote.gas, 188_000);
+
+        let transaction = client.build(&request, &quote).await.unwrap();
+        assert_eq!(transaction.to, address!("0000000000001fF3684f28c67538d4D072C22734"));
+        assert_eq!(transaction.value, U256::ZERO);
+        assert_eq!(transaction.gas, 240_000);
+        assert_eq!(transaction.amount_out, U256::from(301_582_129_181_283_456u64));
+        assert_eq!(transaction.min_amount_out, U256::from(300_074_218_535_377_038u64));
+
+        let unauthorized = ZeroExClient::new(Some(api.url().to_string()), "wrong".to_string()).unwrap();
+        assert!(matches!(
+            SwapProvider::quote(&unauthorized, &request).await,
+            Err(AggregatorError::ZeroEx(ZeroExError::InvalidStatus { .. }))
+        ));
+        assert!(matches!(
+            ZeroExClient::new(None, "test-key\n".to_string()),
+            Err(ZeroExError::InvalidApiKey)
+        ));
+    }
+}
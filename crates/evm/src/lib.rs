@@ -1,4 +1,8 @@
 pub mod signer;
 pub mod mev;
 pub mod dexes;
-pub mod abis;
\ No newline at end of file
+pub mod abis;
+pub mod deploy;
+pub mod provider;
+pub mod simulate;
+pub mod tokens;
@@ -346,8 +346,7 @@ mod tests {
             "0xf8a91e850174f35da582d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a764000025a03b59bc434bc3e660969a0d414352dfc0fac09f68ed259b8c2a9a2140aa5fbdcaa00cdcbfc8150ecc0b321b903dca58081915fe97ad625f4ad4d8fdf04cc33c9660".to_string()
         ];
         
-        let mut block = 21541615u64;
-        for i in 0..10 {
+        for (i, block) in (21541615u64..21541625u64).enumerate() {
             println!("Sending bundle {} for block {}", i+1, block);
             
             match flashbot.send_bundle(bundle.clone(), block).await {
@@ -355,7 +354,6 @@ mod tests {
                 Err(e) => println!("Bundle {} error: {:?}", i+1, e),
             }
             
-            block += 1;
             tokio::time::sleep(Duration::from_millis(1000)).await;
         }
     }
@@ -0,0 +1,236 @@
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::{Address, Bytes, B256, U128, U256, U64};
+use alloy_rpc_types::{Block, FeeHistory, Transaction, TransactionReceipt, TransactionRequest};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::provider::transport::{ProviderConfig, ProviderError, Transport};
+use crate::signer::sign::SignedTransaction;
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+/// Typed access to an EVM node over HTTP or WebSocket.
+#[derive(Debug)]
+pub struct EvmProvider {
+    transport: Transport,
+}
+
+impl EvmProvider {
+    /// Connects with the default config, see [`Transport::connect`] for supported URLs.
+    pub async fn connect(url: &str) -> Result<Self> {
+        Self::connect_with_config(url, ProviderConfig::default()).await
+    }
+
+    pub async fn connect_with_config(url: &str, config: ProviderConfig) -> Result<Self> {
+        Ok(Self {
+            transport: Transport::connect(url, config).await?,
+        })
+    }
+
+    /// Sends a raw JSON-RPC request, `params` should serialize to an array (a tuple works).
+    pub async fn request<R: DeserializeOwned>(&self, method: &str, params: impl Serialize) -> Result<R> {
+        let params = serde_json::to_value(params).map_err(|e| ProviderError::RequestError(e.to_string()))?;
+        let result = self.transport.request(method, params).await?;
+        serde_json::from_value(result).map_err(|e| ProviderError::ResponseError(format!("{}: {}", method, e)))
+    }
+
+    pub async fn get_chain_id(&self) -> Result<u64> {
+        Ok(self.request::<U64>("eth_chainId", ()).await?.to())
+    }
+
+    pub async fn get_block_number(&self) -> Result<u64> {
+        Ok(self.request::<U64>("eth_blockNumber", ()).await?.to())
+    }
+
+    /// Nonce of `address`, use [`BlockId::pending`] to include transactions in the mempool.
+    pub async fn get_transaction_count(&self, address: Address, block: BlockId) -> Result<u64> {
+        Ok(self
+            .request::<U64>("eth_getTransactionCount", (address, block))
+            .await?
+            .to())
+    }
+
+    pub async fn get_balance(&self, address: Address, block: BlockId) -> Result<U256> {
+        self.request("eth_getBalance", (address, block)).await
+    }
+
+    pub async fn get_code(&self, address: Address, block: BlockId) -> Result<Bytes> {
+        self.request("eth_getCode", (address, block)).await
+    }
+
+    /// Value of storage `slot` of `address`.
+    pub async fn get_storage_at(&self, address: Address, slot: U256, block: BlockId) -> Result<U256> {
+        self.request("eth_getStorageAt", (address, slot, block)).await
+    }
+
+    pub async fn get_gas_price(&self) -> Result<u128> {
+        Ok(self.request::<U128>("eth_gasPrice", ()).await?.to())
+    }
+
+    pub async fn get_max_priority_fee_per_gas(&self) -> Result<u128> {
+        Ok(self
+            .request::<U128>("eth_maxPriorityFeePerGas", ())
+            .await?
+            .to())
+    }
+
+    pub async fn get_blob_base_fee(&self) -> Result<u128> {
+        Ok(self.request::<U128>("eth_blobBaseFee", ()).await?.to())
+    }
+
+    /// Base fees and priority fee percentiles of the `block_count` blocks up to `newest`.
+    pub async fn get_fee_history(
+        &self,
+        block_count: u64,
+        newest: BlockNumberOrTag,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory> {
+        self.request("eth_feeHistory", (U64::from(block_count), newest, reward_percentiles))
+            .await
+    }
+
+    /// Block header and transaction hashes, `None` if the block doesn't exist yet.
+    pub async fn get_block(&self, block: BlockNumberOrTag) -> Result<Option<Block>> {
+        self.request("eth_getBlockByNumber", (block, false)).await
+    }
+
+    /// Executes a call without creating a transaction, returns the return data.
+    pub async fn call(&self, tx: &TransactionRequest, block: BlockId) -> Result<Bytes> {
+        self.request("eth_call", (tx, block)).await
+    }
+
+    pub async fn estimate_gas(&self, tx: &TransactionRequest) -> Result<u64> {
+        Ok(self.request::<U64>("eth_estimateGas", (tx,)).await?.to())
+    }
+
+    /// Broadcasts an EIP-2718 encoded signed transaction, returns its hash.
+    pub async fn send_raw_transaction(&self, raw: &[u8]) -> Result<B256> {
+        self.request("eth_sendRawTransaction", (Bytes::copy_from_slice(raw),))
+            .await
+    }
+
+    pub async fn send_transaction(&self, tx: &SignedTransaction) -> Result<B256> {
+        self.send_raw_transaction(&tx.raw).await
+    }
+
+    /// A pending or mined transaction, `None` when the node doesn't know it.
+    pub async fn get_transaction_by_hash(&self, hash: B256) -> Result<Option<Transaction>> {
+        self.request("eth_getTransactionByHash", (hash,)).await
+    }
+
+    /// Receipt of a mined transaction, `None` while it is pending or unknown.
+    pub async fn get_transaction_receipt(&self, hash: B256) -> Result<Option<TransactionReceipt>> {
+        self.request("eth_getTransactionReceipt", (hash,)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use alloy_primitives::{address, b256};
+    use serde_json::{json, Value};
+
+    const HASH: B256 = b256!("5e2b7ed5b2d6a2a3b7f0e5a6ff1c4b69a2a9b4f1ae8d3d9b8f7c6e5d4c3b2a19");
+
+    fn receipt_json() -> Value {
+        json!({
+            "transactionHash": HASH,
+            "transactionIndex": "0x0",
+            "blockHash": b256!("00000000000000000000000000000000000000000000000000000000000000aa"),
+            "blockNumber": "0x10",
+            "from": "0x163a5ec5e9c32238d075e2d829fe9fa87451e3b7",
+            "to": "0xec53bf9167f50cdeb3ae105f56099aaab9061f83",
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "type": "0x2",
+            "status": "0x1"
+        })
+    }
+
+    async fn node() -> MockNode {
+        MockNode::start(|method, params| match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_blockNumber" => Ok(json!("0x10")),
+            "eth_getTransactionCount" => Ok(json!("0x5")),
+            "eth_getBalance" => Ok(json!("0xde0b6b3a7640000")),
+            "eth_getCode" => Ok(json!("0x6080")),
+            "eth_gasPrice" => Ok(json!("0x3b9aca00")),
+            "eth_call" => Ok(json!(format!("0x{:064x}", 42))),
+            "eth_estimateGas" => Ok(json!("0x5208")),
+            "eth_sendRawTransaction" if params[0] == "0x02" => Err((-32000, "nonce too low".into())),
+            "eth_sendRawTransaction" => Ok(json!(HASH)),
+            "eth_getTransactionReceipt" if params[0] == json!(HASH) => Ok(receipt_json()),
+            "eth_getTransactionReceipt" => Ok(Value::Null),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await
+    }
+
+    async fn check_typed_calls(provider: &EvmProvider, node: &MockNode) {
+        let owner = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+
+        assert_eq!(provider.get_chain_id().await.unwrap(), 1);
+        assert_eq!(provider.get_block_number().await.unwrap(), 16);
+        assert_eq!(provider.get_transaction_count(owner, BlockId::pending()).await.unwrap(), 5);
+        assert_eq!(node.last_params("eth_getTransactionCount").unwrap(), json!([owner, "pending"]));
+        assert_eq!(
+            provider.get_balance(owner, BlockId::latest()).await.unwrap(),
+            U256::from(10).pow(U256::from(18))
+        );
+        assert_eq!(provider.get_code(owner, BlockId::latest()).await.unwrap(), Bytes::from(vec![0x60, 0x80]));
+        assert_eq!(provider.get_gas_price().await.unwrap(), 1_000_000_000);
+
+        let tx = TransactionRequest::default()
+            .to(address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83"))
+            .input(Bytes::from(vec![0x70, 0xa0, 0x82, 0x31]).into());
+        let output = provider.call(&tx, BlockId::latest()).await.unwrap();
+        assert_eq!(U256::from_be_slice(&output), U256::from(42));
+        assert_eq!(node.last_params("eth_call").unwrap()[0]["input"], json!("0x70a08231"));
+        assert_eq!(provider.estimate_gas(&tx).await.unwrap(), 21_000);
+
+        assert_eq!(provider.send_raw_transaction(&[0x01]).await.unwrap(), HASH);
+        let receipt = provider.get_transaction_receipt(HASH).await.unwrap().unwrap();
+        assert!(receipt.status());
+        assert_eq!(receipt.block_number, Some(16));
+        assert!(provider.get_transaction_receipt(B256::ZERO).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_provider() {
+        let node = node().await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        check_typed_calls(&provider, &node).await;
+    }
+
+    #[tokio::test]
+    async fn test_ws_provider() {
+        let node = node().await;
+        let provider = EvmProvider::connect(node.ws_url()).await.unwrap();
+        check_typed_calls(&provider, &node).await;
+
+        // requests sharing the connection are matched back by id
+        let (chain_id, block_number) = tokio::join!(provider.get_chain_id(), provider.get_block_number());
+        assert_eq!((chain_id.unwrap(), block_number.unwrap()), (1, 16));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_errors() {
+        let node = node().await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let err = provider.send_raw_transaction(&[0x02]).await.unwrap_err();
+        assert!(matches!(err, ProviderError::RpcError { code: -32000, ref message, .. } if message == "nonce too low"));
+        assert!(matches!(
+            provider.request::<Value>("eth_unknown", ()).await,
+            Err(ProviderError::RpcError { code: -32601, .. })
+        ));
+        assert!(matches!(
+            EvmProvider::connect("ftp://localhost").await,
+            Err(ProviderError::RequestError(_))
+        ));
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_rpc_types::FeeHistory;
+use serde::Serialize;
+
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::Transaction;
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum FeeSpeed {
+    Slow,
+    Standard,
+    Fast,
+}
+
+impl FeeSpeed {
+    /// Blocks of maximum base fee increase (12.5% each) the fee cap should survive.
+    fn headroom_blocks(self) -> u32 {
+        match self {
+            Self::Slow => 1,
+            Self::Standard => 3,
+            Self::Fast => 6,
+        }
+    }
+
+    /// Markup applied to `eth_gasPrice` when the chain has no fee market, in percent.
+    fn legacy_markup(self) -> u128 {
+        match self {
+            Self::Slow => 100,
+            Self::Standard => 110,
+            Self::Fast => 125,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeOracleConfig {
+    /// Number of recent blocks sampled with `eth_feeHistory`.
+    pub block_count: u64,
+    /// Priority fee percentiles used for slow, standard and fast.
+    pub reward_percentiles: [f64; 3],
+    /// Floor for the suggested priority fee, in wei.
+    pub min_priority_fee: u128,
+    pub max_priority_fee_cap: Option<u128>,
+    /// Upper bound for both `max_fee_per_gas` and the legacy gas price, in wei.
+    pub max_fee_cap: Option<u128>,
+}
+
+impl Default for FeeOracleConfig {
+    fn default() -> Self {
+        Self {
+            block_count: 20,
+            reward_percentiles: [10.0, 50.0, 90.0],
+            min_priority_fee: 0,
+            max_priority_fee_cap: None,
+            max_fee_cap: None,
+        }
+    }
+}
+
+/// Suggested fees for one speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeeEstimate {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// Price for legacy and EIP-2930 transactions: next base fee plus one block of
+    /// headroom and the priority fee, or the marked up `eth_gasPrice` on legacy chains.
+    pub gas_price: u128,
+}
+
+impl FeeEstimate {
+    /// Sets the fee fields of a transaction, `gas_price` for legacy and EIP-2930,
+    /// the EIP-1559 fee pair for every other type.
+    pub fn apply(&self, tx: &mut Transaction<'_>) {
+        match tx {
+            Transaction::Legacy(tx) => tx.gas_price = self.gas_price,
+            Transaction::Eip2930(tx) => tx.gas_price = self.gas_price,
+            Transaction::Eip1559(tx) => {
+                tx.max_fee_per_gas = self.max_fee_per_gas;
+                tx.max_priority_fee_per_gas = self.max_priority_fee_per_gas;
+            }
+            Transaction::Eip4844(tx) => {
+                tx.max_fee_per_gas = self.max_fee_per_gas;
+                tx.max_priority_fee_per_gas = self.max_priority_fee_per_gas;
+            }
+            Transaction::Eip4844WithSidecar(tx) => {
+                tx.tx.max_fee_per_gas = self.max_fee_per_gas;
+                tx.tx.max_priority_fee_per_gas = self.max_priority_fee_per_gas;
+            }
+            Transaction::Eip7702(tx) => {
+                tx.max_fee_per_gas = self.max_fee_per_gas;
+                tx.max_priority_fee_per_gas = self.max_priority_fee_per_gas;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeSuggestions {
+    /// Base fee of the next block, `None` when the chain has no fee market.
+    pub next_base_fee: Option<u128>,
+    /// Whether base fees went up over the sampled blocks (blocks more than half full on average).
+    pub rising: bool,
+    /// Whether `max_fee_cap` is below the next base fee (or `eth_gasPrice` on chains without a
+    /// fee market): transactions priced from these suggestions won't be included until it drops.
+    pub below_base_fee: bool,
+    pub slow: FeeEstimate,
+    pub standard: FeeEstimate,
+    pub fast: FeeEstimate,
+}
+
+impl FeeSuggestions {
+    pub fn get(&self, speed: FeeSpeed) -> FeeEstimate {
+        match speed {
+            FeeSpeed::Slow => self.slow,
+            FeeSpeed::Standard => self.standard,
+            FeeSpeed::Fast => self.fast,
+        }
+    }
+
+    /// Whether the suggestions come from `eth_gasPrice` rather than `eth_feeHistory`.
+    pub fn is_legacy(&self) -> bool {
+        self.next_base_fee.is_none()
+    }
+}
+
+/// Suggests EIP-1559 fees from recent blocks, with a config per chain id.
+#[derive(Debug, Clone, Default)]
+pub struct FeeOracle {
+    default_config: FeeOracleConfig,
+    chains: HashMap<u64, FeeOracleConfig>,
+}
+
+impl FeeOracle {
+    pub fn new(config: FeeOracleConfig) -> Self {
+        Self {
+            default_config: config,
+            chains: HashMap::new(),
+        }
+    }
+
+    /// Overrides the config for one chain.
+    pub fn with_chain(mut self, chain_id: u64, config: FeeOracleConfig) -> Self {
+        self.chains.insert(chain_id, config);
+        self
+    }
+
+    pub fn config(&self, chain_id: u64) -> &FeeOracleConfig {
+        self.chains.get(&chain_id).unwrap_or(&self.default_config)
+    }
+
+    /// Suggests fees for the chain the provider is connected to.
+    ///
+    /// Falls back to `eth_gasPrice` when the node doesn't support `eth_feeHistory`
+    /// or the chain reports no base fee.
+    pub async fn suggest(&self, provider: &EvmProvider) -> Result<FeeSuggestions> {
+        let config = self.config(provider.get_chain_id().await?);
+
+        let history = provider
+            .get_fee_history(config.block_count, BlockNumberOrTag::Latest, &config.reward_percentiles)
+            .await;
+        match history {
+            Ok(history) if history.base_fee_per_gas.iter().any(|fee| *fee > 0) => {
+                Ok(Self::from_history(config, &history))
+            }
+            Ok(_) | Err(ProviderError::RpcError { .. }) => {
+                let gas_price = provider.get_gas_price().await?;
+                Ok(Self::from_gas_price(config, gas_price))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Suggestion for a single speed.
+    pub async fn estimate(&self, provider: &EvmProvider, speed: FeeSpeed) -> Result<FeeEstimate> {
+        Ok(self.suggest(provider).await?.get(speed))
+    }
+
+    pub fn from_history(config: &FeeOracleConfig, history: &FeeHistory) -> FeeSuggestions {
+        let next_base_fee = history.base_fee_per_gas.last().copied().unwrap_or_default();
+
+        let ratios = &history.gas_used_ratio;
+        let rising = !ratios.is_empty() && ratios.iter().sum::<f64>() / ratios.len() as f64 > 0.5;
+
+        // empty blocks report zero rewards and would drag every percentile down
+        let rewards = history
+            .reward
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .zip(ratios.iter().chain(std::iter::repeat(&1.0)))
+            .filter(|(_, ratio)| **ratio > 0.0)
+            .map(|(reward, _)| reward)
+            .collect::<Vec<_>>();
+
+        let estimate = |speed: FeeSpeed, column: usize| {
+            let samples = rewards
+                .iter()
+                .filter_map(|reward| reward.get(column).copied())
+                .collect::<Vec<_>>();
+            let mut priority_fee = median(samples).max(config.min_priority_fee);
+            if let Some(cap) = config.max_priority_fee_cap {
+                priority_fee = priority_fee.min(cap);
+            }
+
+            let blocks = speed.headroom_blocks() + u32::from(rising);
+            let mut max_fee = project_base_fee(next_base_fee, blocks) + priority_fee;
+            let mut gas_price = project_base_fee(next_base_fee, 1) + priority_fee;
+            if let Some(cap) = config.max_fee_cap {
+                max_fee = max_fee.min(cap);
+                gas_price = gas_price.min(cap);
+            }
+
+            FeeEstimate {
+                max_fee_per_gas: max_fee,
+                max_priority_fee_per_gas: priority_fee.min(max_fee),
+                gas_price,
+            }
+        };
+
+        FeeSuggestions {
+            next_base_fee: Some(next_base_fee),
+            rising,
+            below_base_fee: config.max_fee_cap.is_some_and(|cap| cap < next_base_fee),
+            slow: estimate(FeeSpeed::Slow, 0),
+            standard: estimate(FeeSpeed::Standard, 1),
+            fast: estimate(FeeSpeed::Fast, 2),
+        }
+    }
+
+    pub fn from_gas_price(config: &FeeOracleConfig, gas_price: u128) -> FeeSuggestions {
+        let estimate = |speed: FeeSpeed| {
+            let mut price = gas_price * speed.legacy_markup() / 100;
+            if let Some(cap) = config.max_fee_cap {
+                price = price.min(cap);
+            }
+            // a legacy price pays the whole fee to the block producer
+            FeeEstimate {
+                max_fee_per_gas: price,
+                max_priority_fee_per_gas: price,
+                gas_price: price,
+            }
+        };
+
+        FeeSuggestions {
+            next_base_fee: None,
+            rising: false,
+            below_base_fee: config.max_fee_cap.is_some_and(|cap| cap < gas_price),
+            slow: estimate(FeeSpeed::Slow),
+            standard: estimate(FeeSpeed::Standard),
+            fast: estimate(FeeSpeed::Fast),
+        }
+    }
+}
+
+/// Highest base fee reachable after `blocks` full blocks (+12.5% each).
+fn project_base_fee(base_fee: u128, blocks: u32) -> u128 {
+    base_fee.saturating_mul(9u128.pow(blocks)) / 8u128.pow(blocks)
+}
+
+fn median(mut samples: Vec<u128>) -> u128 {
+    samples.sort_unstable();
+    samples.get(samples.len() / 2).copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use alloy_consensus::{TxEip1559, TxLegacy};
+    use serde_json::json;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn fee_history(gas_used_ratio: [f64; 4]) -> serde_json::Value {
+        json!({
+            "oldestBlock": "0x100",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00", "0x3b9aca00", "0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": gas_used_ratio,
+            "reward": [
+                ["0x5f5e100", "0x3b9aca00", "0xb2d05e00"],
+                ["0xbebc200", "0x59682f00", "0x77359400"],
+                ["0x0", "0x0", "0x0"],
+                ["0x5f5e100", "0x3b9aca00", "0x9502f900"]
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_suggest_from_fee_history() {
+        let node = MockNode::start(|method, _| match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_feeHistory" => Ok(fee_history([0.3, 0.4, 0.0, 0.4])),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let fees = FeeOracle::default().suggest(&provider).await.unwrap();
+        assert_eq!(node.last_params("eth_feeHistory").unwrap(), json!(["0x14", "latest", [10.0, 50.0, 90.0]]));
+        assert_eq!(fees.next_base_fee, Some(GWEI));
+        assert!(!fees.rising);
+
+        // medians of the non-empty blocks, base fee projected 1/3/6 blocks ahead
+        assert_eq!(
+            fees.slow,
+            FeeEstimate {
+                max_fee_per_gas: 1_125_000_000 + 100_000_000,
+                max_priority_fee_per_gas: 100_000_000,
+                gas_price: 1_125_000_000 + 100_000_000,
+            }
+        );
+        assert_eq!(fees.standard.max_priority_fee_per_gas, GWEI);
+        assert_eq!(fees.standard.max_fee_per_gas, 1_423_828_125 + GWEI);
+        assert_eq!(fees.fast.max_priority_fee_per_gas, 2_500_000_000);
+        assert_eq!(fees.fast.max_fee_per_gas, 2_027_286_529 + 2_500_000_000);
+        assert!(!fees.below_base_fee);
+        assert!(fees.slow.max_fee_per_gas < fees.standard.max_fee_per_gas);
+        assert!(fees.standard.max_fee_per_gas < fees.fast.max_fee_per_gas);
+    }
+
+    #[test]
+    fn test_rising_base_fee_and_caps() {
+        let history: FeeHistory = serde_json::from_value(fee_history([0.9, 0.8, 0.0, 1.0])).unwrap();
+        let config = FeeOracleConfig {
+            max_priority_fee_cap: Some(2 * GWEI),
+            max_fee_cap: Some(4 * GWEI),
+            ..Default::default()
+        };
+
+        let fees = FeeOracle::from_history(&config, &history);
+        assert!(fees.rising);
+        // one extra block of headroom: 1.125^2
+        assert_eq!(fees.slow.max_fee_per_gas, 1_265_625_000 + 100_000_000);
+        assert_eq!(fees.fast.max_priority_fee_per_gas, 2 * GWEI);
+        assert_eq!(fees.fast.max_fee_per_gas, 4 * GWEI);
+        assert!(!fees.below_base_fee);
+
+        // a cap under the 1 gwei base fee can't get anything included, say so
+        let config = FeeOracleConfig { max_fee_cap: Some(GWEI / 2), ..Default::default() };
+        let fees = FeeOracle::from_history(&config, &history);
+        assert!(fees.below_base_fee);
+        assert_eq!(fees.standard.max_fee_per_gas, GWEI / 2);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_fallback_and_chain_config() {
+        let node = MockNode::start(|method, _| match method {
+            "eth_chainId" => Ok(json!("0x38")),
+            "eth_gasPrice" => Ok(json!("0xb2d05e00")),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let bsc = FeeOracleConfig { max_fee_cap: Some(3_500_000_000), ..Default::default() };
+        let oracle = FeeOracle::default().with_chain(56, bsc);
+        let fees = oracle.suggest(&provider).await.unwrap();
+        assert!(fees.is_legacy());
+        assert_eq!(fees.slow.gas_price, 3 * GWEI);
+        assert_eq!(fees.standard.gas_price, 3_300_000_000);
+        assert_eq!(fees.fast.gas_price, 3_500_000_000);
+
+        let mut legacy = TxLegacy::default();
+        fees.fast.apply(&mut Transaction::Legacy(&mut legacy));
+        assert_eq!(legacy.gas_price, 3_500_000_000);
+    }
+
+    #[test]
+    fn test_apply_to_eip1559() {
+        let estimate = FeeEstimate { max_fee_per_gas: 30 * GWEI, max_priority_fee_per_gas: 2 * GWEI, gas_price: 20 * GWEI };
+
+        let mut tx = TxEip1559::default();
+        estimate.apply(&mut Transaction::Eip1559(&mut tx));
+        assert_eq!((tx.max_fee_per_gas, tx.max_priority_fee_per_gas), (30 * GWEI, 2 * GWEI));
+    }
+}
@@ -0,0 +1,214 @@
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_rpc_types::TransactionRequest;
+
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::{EvmSigner, SignedTransaction};
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+/// Fee caps are set to this multiple of the current base fee, so the transaction
+/// stays valid through several full blocks of base fee increases.
+pub const BASE_FEE_MULTIPLIER: u128 = 2;
+
+impl EvmProvider {
+    /// Completes a transaction request from the node: chain id, pending nonce of `from`,
+    /// fees and gas limit. Fields already set are kept.
+    ///
+    /// Requests with a `gas_price` or an explicit legacy/EIP-2930 type get a gas price,
+    /// all others get EIP-1559 fees unless the chain has no base fee.
+    pub async fn fill_transaction(&self, mut tx: TransactionRequest) -> Result<TransactionRequest> {
+        let from = tx
+            .from
+            .ok_or_else(|| ProviderError::FillError("missing `from` address".into()))?;
+
+        if tx.chain_id.is_none() {
+            tx.chain_id = Some(self.get_chain_id().await?);
+        }
+        if tx.nonce.is_none() {
+            tx.nonce = Some(self.get_transaction_count(from, BlockId::pending()).await?);
+        }
+        self.fill_fees(&mut tx).await?;
+        if tx.gas.is_none() {
+            tx.gas = Some(self.estimate_gas(&tx).await?);
+        }
+        Ok(tx)
+    }
+
+    async fn fill_fees(&self, tx: &mut TransactionRequest) -> Result<()> {
+        let is_legacy = tx.gas_price.is_some() || matches!(tx.transaction_type, Some(0) | Some(1));
+        if is_legacy {
+            if tx.gas_price.is_none() {
+                tx.gas_price = Some(self.get_gas_price().await?);
+            }
+            return Ok(());
+        }
+
+        if tx.max_fee_per_gas.is_none() || tx.max_priority_fee_per_gas.is_none() {
+            let base_fee = self
+                .get_block(BlockNumberOrTag::Latest)
+                .await?
+                .and_then(|block| block.header.base_fee_per_gas);
+
+            match base_fee {
+                // pre-London chain
+                None if tx.transaction_type.is_none() => tx.gas_price = Some(self.get_gas_price().await?),
+                None => {
+                    return Err(ProviderError::FillError(
+                        "chain has no base fee, can't price a typed transaction".into(),
+                    ))
+                }
+                Some(base_fee) => {
+                    let priority_fee = match tx.max_priority_fee_per_gas {
+                        Some(priority_fee) => priority_fee,
+                        // a tip above the given fee cap gets the transaction rejected
+                        None => {
+                            let suggested = self.get_max_priority_fee_per_gas().await?;
+                            tx.max_fee_per_gas.map_or(suggested, |max_fee| suggested.min(max_fee))
+                        }
+                    };
+                    tx.max_priority_fee_per_gas = Some(priority_fee);
+                    tx.max_fee_per_gas
+                        .get_or_insert(base_fee as u128 * BASE_FEE_MULTIPLIER + priority_fee);
+                }
+            }
+        }
+
+        let has_blobs = tx.sidecar.is_some() || tx.blob_versioned_hashes.is_some();
+        if has_blobs && tx.max_fee_per_blob_gas.is_none() {
+            tx.max_fee_per_blob_gas = Some(self.get_blob_base_fee().await? * BASE_FEE_MULTIPLIER);
+        }
+        Ok(())
+    }
+
+    /// Fills the request for the signer's address with [`EvmProvider::fill_transaction`]
+    /// and signs it with [`EvmSigner::sign_transaction`].
+    pub async fn fill_and_sign(
+        &self,
+        signer: &EvmSigner<'_>,
+        tx: TransactionRequest,
+    ) -> Result<SignedTransaction> {
+        let tx = self.fill_transaction(tx.from(signer.address())).await?;
+        if let Err((tx_type, missing)) = tx.missing_keys() {
+            return Err(ProviderError::FillError(format!(
+                "{} transaction is missing {}",
+                tx_type,
+                missing.join(", ")
+            )));
+        }
+        let typed = tx
+            .build_typed_tx()
+            .map_err(|_| ProviderError::FillError("incomplete transaction".into()))?;
+        Ok(signer.sign_typed_transaction(typed).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use crate::signer::utils::decode_raw_tx;
+    use alloy_network::TransactionBuilder;
+    use alloy_primitives::{address, U256};
+    use alloy_rpc_types::Block;
+    use serde_json::json;
+
+    fn block_json(base_fee: Option<u64>) -> serde_json::Value {
+        let mut block: Block = Block::default();
+        block.header.inner.base_fee_per_gas = base_fee;
+        serde_json::to_value(block).unwrap()
+    }
+
+    async fn node(base_fee: Option<u64>) -> MockNode {
+        MockNode::start(move |method, _| match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_getTransactionCount" => Ok(json!("0x7")),
+            "eth_getBlockByNumber" => Ok(block_json(base_fee)),
+            "eth_maxPriorityFeePerGas" => Ok(json!("0x77359400")),
+            "eth_gasPrice" => Ok(json!("0x4a817c800")),
+            "eth_estimateGas" => Ok(json!("0xb411")),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await
+    }
+
+    fn transfer() -> TransactionRequest {
+        TransactionRequest::default()
+            .to(address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83"))
+            .value(U256::from(1000))
+    }
+
+    #[tokio::test]
+    async fn test_fill_and_sign_eip1559() {
+        let node = node(Some(1_000_000_000)).await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let account = account();
+        let signer = EvmSigner::new(&account);
+
+        let signed = provider.fill_and_sign(&signer, transfer()).await.unwrap();
+        let decoded = decode_raw_tx(&signed.raw_hex()).unwrap();
+        assert_eq!(decoded.from, signer.address());
+        assert_eq!(decoded.tx_type, "EIP-1559");
+        assert_eq!(decoded.chain_id, Some(1));
+        assert_eq!(decoded.nonce, 7);
+        assert_eq!(decoded.gas_limit, 46_097);
+        assert_eq!(decoded.max_priority_fee_per_gas, Some(2_000_000_000));
+        assert_eq!(decoded.max_fee_per_gas, Some(4_000_000_000));
+
+        // the nonce is read from the pending block and gas is estimated for the filled request
+        assert_eq!(node.last_params("eth_getTransactionCount").unwrap()[1], json!("pending"));
+        let estimated = node.last_params("eth_estimateGas").unwrap();
+        assert_eq!(estimated[0]["nonce"], json!("0x7"));
+        assert_eq!(estimated[0]["maxFeePerGas"], json!("0xee6b2800"));
+    }
+
+    #[tokio::test]
+    async fn test_fill_keeps_given_fields() {
+        let node = node(Some(1_000_000_000)).await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let tx = transfer()
+            .from(address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7"))
+            .nonce(3)
+            .gas_limit(21_000)
+            .with_gas_price(5);
+        let filled = provider.fill_transaction(tx).await.unwrap();
+        assert_eq!((filled.nonce, filled.gas, filled.gas_price), (Some(3), Some(21_000), Some(5)));
+        assert!(filled.max_fee_per_gas.is_none());
+        assert_eq!(node.methods(), vec!["eth_chainId"]);
+
+        assert!(matches!(
+            provider.fill_transaction(transfer()).await,
+            Err(ProviderError::FillError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fill_tip_below_fee_cap() {
+        let node = node(Some(500_000_000)).await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        // the node suggests a 2 gwei tip, over the 1 gwei cap
+        let tx = transfer()
+            .from(address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7"))
+            .max_fee_per_gas(1_000_000_000);
+        let filled = provider.fill_transaction(tx).await.unwrap();
+        assert_eq!(filled.max_fee_per_gas, Some(1_000_000_000));
+        assert_eq!(filled.max_priority_fee_per_gas, Some(1_000_000_000));
+    }
+
+    #[tokio::test]
+    async fn test_fill_pre_london() {
+        let node = node(None).await;
+        let provider = EvmProvider::connect(node.ws_url()).await.unwrap();
+        let account = account();
+        let signer = EvmSigner::new(&account);
+
+        let signed = provider.fill_and_sign(&signer, transfer()).await.unwrap();
+        let decoded = decode_raw_tx(&signed.raw_hex()).unwrap();
+        assert_eq!(decoded.tx_type, "Legacy");
+        assert_eq!(decoded.gas_price, Some(20_000_000_000));
+        assert_eq!(decoded.nonce, 7);
+    }
+}
@@ -0,0 +1,276 @@
+//! A local JSON-RPC node and REST API for tests, answering over HTTP and WebSocket from a handler closure.
+
+use std::sync::{Arc, Mutex};
+
+use alloy_primitives::hex;
+use alloy_sol_types::SolCall;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::abis::multicall::IMulticall3;
+
+/// Returns the `result` of a call, or the `(code, message)` of a JSON-RPC error.
+pub(crate) type MockResult = Result<Value, (i64, String)>;
+
+type Handler = Arc<dyn Fn(&str, &Value) -> MockResult + Send + Sync>;
+
+pub(crate) struct MockNode {
+    http_url: String,
+    ws_url: String,
+    calls: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl MockNode {
+    pub(crate) async fn start(handler: impl Fn(&str, &Value) -> MockResult + Send + Sync + 'static) -> Self {
+        let handler: Handler = Arc::new(handler);
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let node = Self {
+            http_url: format!("http://{}", http.local_addr().unwrap()),
+            ws_url: format!("ws://{}", ws.local_addr().unwrap()),
+            calls: calls.clone(),
+        };
+
+        let (http_handler, http_calls) = (handler.clone(), calls.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = http.accept().await {
+                tokio::spawn(serve_http(stream, http_handler.clone(), http_calls.clone()));
+            }
+        });
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = ws.accept().await {
+                tokio::spawn(serve_ws(stream, handler.clone(), calls.clone()));
+            }
+        });
+
+        node
+    }
+
+    pub(crate) fn http_url(&self) -> &str {
+        &self.http_url
+    }
+
+    pub(crate) fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// A node answering Multicall3 `aggregate3` reads, see [`aggregate3`].
+    pub(crate) async fn multicall(
+        handler: impl Fn(&IMulticall3::Call3) -> Option<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        Self::start(move |_, params| aggregate3(params, &handler)).await
+    }
+
+    /// Methods called so far, in order.
+    pub(crate) fn methods(&self) -> Vec<String> {
+        self.calls.lock().unwrap().iter().map(|(method, _)| method.clone()).collect()
+    }
+
+    /// Params of the last call to `method`.
+    pub(crate) fn last_params(&self, method: &str) -> Option<Value> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(m, _)| m == method)
+            .map(|(_, params)| params.clone())
+    }
+}
+
+/// Answers an `eth_call` of Multicall3 `aggregate3` with the output `handler` gives each call,
+/// `None` making that call fail.
+pub(crate) fn aggregate3(params: &Value, handler: impl Fn(&IMulticall3::Call3) -> Option<Vec<u8>>) -> MockResult {
+    let input = hex::decode(params[0]["input"].as_str().unwrap_or_default()).map_err(|e| (-32602, e.to_string()))?;
+    let calls = IMulticall3::aggregate3Call::abi_decode(&input, true)
+        .map_err(|e| (-32602, e.to_string()))?
+        .calls;
+    let results: Vec<_> = calls
+        .iter()
+        .map(|call| {
+            let output = handler(call);
+            IMulticall3::Result {
+                success: output.is_some(),
+                returnData: output.unwrap_or_default().into(),
+            }
+        })
+        .collect();
+    Ok(json!(hex::encode_prefixed(IMulticall3::aggregate3Call::abi_encode_returns(&(results,)))))
+}
+
+fn respond(body: &str, handler: &Handler, calls: &Mutex<Vec<(String, Value)>>) -> String {
+    let request: Value = serde_json::from_str(body).unwrap_or_default();
+    let method = request["method"].as_str().unwrap_or_default();
+    let params = request["params"].clone();
+    calls.lock().unwrap().push((method.to_string(), params.clone()));
+
+    let response = match handler(method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        Err((code, message)) => {
+            json!({ "jsonrpc": "2.0", "id": request["id"], "error": { "code": code, "message": message } })
+        }
+    };
+    response.to_string()
+}
+
+/// Reads one HTTP/1.1 request, returning its request line, headers and body.
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<(String, Vec<(String, String)>, Vec<u8>)> {
+    let mut request_line = String::new();
+    if stream.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+        return None;
+    }
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+            headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    stream.read_exact(&mut body).await.ok()?;
+    Some((request_line.trim_end().to_string(), headers, body))
+}
+
+async fn write_response(stream: &mut BufReader<TcpStream>, status: u16, body: &str) -> bool {
+    let http = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        status,
+        if status < 400 { "OK" } else { "Error" },
+        body.len(),
+        body
+    );
+    stream.get_mut().write_all(http.as_bytes()).await.is_ok()
+}
+
+async fn serve_http(stream: TcpStream, handler: Handler, calls: Arc<Mutex<Vec<(String, Value)>>>) {
+    let mut stream = BufReader::new(stream);
+    while let Some((_, _, body)) = read_request(&mut stream).await {
+        let response = respond(&String::from_utf8_lossy(&body), &handler, &calls);
+        if !write_response(&mut stream, 200, &response).await {
+            return;
+        }
+    }
+}
+
+async fn serve_ws(stream: TcpStream, handler: Handler, calls: Arc<Mutex<Vec<(String, Value)>>>) {
+    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    while let Some(Ok(message)) = socket.next().await {
+        if let Message::Text(body) = message {
+            let response = respond(&body, &handler, &calls);
+            if socket.send(Message::Text(response)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// A request received by a [`MockApi`].
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    pub(crate) method: String,
+    /// Path and query, e.g. `/swap/v6.0/1/quote?src=..`.
+    pub(crate) path: String,
+    /// Header names are lowercased.
+    pub(crate) headers: Vec<(String, String)>,
+    /// JSON body, `Null` if empty.
+    pub(crate) body: Value,
+}
+
+impl MockRequest {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Value of query parameter `name`, not percent-decoded.
+    pub(crate) fn query(&self, name: &str) -> Option<&str> {
+        let (_, query) = self.path.split_once('?')?;
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Path without the query.
+    pub(crate) fn route(&self) -> &str {
+        self.path.split('?').next().unwrap_or_default()
+    }
+}
+
+type ApiHandler = Arc<dyn Fn(&MockRequest) -> (u16, Value) + Send + Sync>;
+
+/// A local REST API for tests, answering each request with the `(status, body)` of a handler closure.
+pub(crate) struct MockApi {
+    url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockApi {
+    pub(crate) async fn start(handler: impl Fn(&MockRequest) -> (u16, Value) + Send + Sync + 'static) -> Self {
+        let handler: ApiHandler = Arc::new(handler);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api = Self {
+            url: format!("http://{}", listener.local_addr().unwrap()),
+            requests: requests.clone(),
+        };
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_api(stream, handler.clone(), requests.clone()));
+            }
+        });
+
+        api
+    }
+
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Requests received so far, in order.
+    pub(crate) fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn serve_api(stream: TcpStream, handler: ApiHandler, requests: Arc<Mutex<Vec<MockRequest>>>) {
+    let mut stream = BufReader::new(stream);
+    while let Some((request_line, headers, body)) = read_request(&mut stream).await {
+        let mut parts = request_line.split_whitespace();
+        let request = MockRequest {
+            method: parts.next().unwrap_or_default().to_string(),
+            path: parts.next().unwrap_or_default().to_string(),
+            headers,
+            body: serde_json::from_slice(&body).unwrap_or_default(),
+        };
+        requests.lock().unwrap().push(request.clone());
+
+        let (status, response) = handler(&request);
+        if !write_response(&mut stream, status, &response.to_string()).await {
+            return;
+        }
+    }
+}
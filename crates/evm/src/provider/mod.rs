@@ -0,0 +1,13 @@
+pub mod client;
+pub mod fees;
+pub mod fill;
+pub mod multicall;
+pub mod nonce;
+pub mod replace;
+pub mod transport;
+pub mod watch;
+
+#[cfg(test)]
+pub(crate) mod mock;
+#[cfg(test)]
+pub(crate) mod test_utils;
@@ -0,0 +1,387 @@
+use std::marker::PhantomData;
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+use futures_util::future::try_join_all;
+
+use crate::abis::decode::DecodedCall;
+use crate::abis::multicall::{IMulticall3, MULTICALL3_ADDRESS};
+use crate::abis::registry::AbiRegistry;
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+/// Calls per `eth_call`, nodes cap the gas and response size of a call.
+pub const DEFAULT_BATCH_SIZE: usize = 200;
+
+/// Calldata bytes per `eth_call`, keeps requests under common body size limits.
+pub const DEFAULT_BATCH_CALLDATA: usize = 128 * 1024;
+
+/// Index of a call in a [`Multicall`], decodes its result to the call's return type.
+#[derive(Debug)]
+pub struct CallHandle<C> {
+    index: usize,
+    call: PhantomData<fn() -> C>,
+}
+
+impl<C> Clone for CallHandle<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for CallHandle<C> {}
+
+impl<C> CallHandle<C> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Why a call in a batch has no typed result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallFailure {
+    /// The call reverted, only possible with `allow_failure`.
+    Reverted(Bytes),
+    /// The call succeeded but returned data of another type.
+    Decode(String),
+    /// No call at this index, the handle belongs to another batch.
+    Missing(usize),
+}
+
+impl CallFailure {
+    /// The decoded revert error (`Error(string)`, `Panic(uint256)` or a known custom error).
+    pub fn revert_error(&self) -> Option<DecodedCall> {
+        match self {
+            Self::Reverted(data) => AbiRegistry::known().decode_error(data),
+            Self::Decode(_) | Self::Missing(_) => None,
+        }
+    }
+}
+
+/// Batches typed calls to many contracts into Multicall3 `aggregate3` reads, or into an
+/// `aggregate3Value` transaction.
+///
+/// ```ignore
+/// let mut multicall = Multicall::new();
+/// let balance = multicall.add(token, IERC20::balanceOfCall { account }, false);
+/// let decimals = multicall.add(token, IERC20::decimalsCall {}, true);
+/// let results = multicall.call(&provider, BlockId::latest()).await?;
+/// let balance = results.get(&balance)?._0;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Multicall {
+    address: Address,
+    calls: Vec<IMulticall3::Call3Value>,
+    batch_size: usize,
+    batch_calldata: usize,
+}
+
+impl Default for Multicall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Multicall {
+    /// A batch sent to the canonical [`MULTICALL3_ADDRESS`].
+    pub fn new() -> Self {
+        Self {
+            address: MULTICALL3_ADDRESS,
+            calls: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_calldata: DEFAULT_BATCH_CALLDATA,
+        }
+    }
+
+    /// For chains where Multicall3 lives at another address.
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Splits reads into `eth_call`s of at most `calls` calls and `calldata` bytes of calldata.
+    pub fn with_batch_limits(mut self, calls: usize, calldata: usize) -> Self {
+        self.batch_size = calls.max(1);
+        self.batch_calldata = calldata;
+        self
+    }
+
+    /// Adds `call` to `target`. Without `allow_failure` a revert fails the whole `eth_call`
+    /// (or transaction) the call ends up in.
+    pub fn add<C: SolCall>(&mut self, target: Address, call: C, allow_failure: bool) -> CallHandle<C> {
+        self.add_with_value(target, call, U256::ZERO, allow_failure)
+    }
+
+    /// Adds a payable call, the value is forwarded by [`Multicall::aggregate3_value`].
+    pub fn add_with_value<C: SolCall>(
+        &mut self,
+        target: Address,
+        call: C,
+        value: U256,
+        allow_failure: bool,
+    ) -> CallHandle<C> {
+        self.calls.push(IMulticall3::Call3Value {
+            target,
+            allowFailure: allow_failure,
+            value,
+            callData: call.abi_encode().into(),
+        });
+        CallHandle {
+            index: self.calls.len() - 1,
+            call: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Sum of the values of all calls, the value of the `aggregate3Value` transaction.
+    pub fn total_value(&self) -> U256 {
+        self.calls.iter().map(|call| call.value).sum()
+    }
+
+    /// Runs every call with `eth_call` at `block`, batches are sent concurrently.
+    /// Calls with a value are sent through `aggregate3Value`.
+    pub async fn call(&self, provider: &EvmProvider, block: BlockId) -> Result<MulticallResults> {
+        let batches = self.batches().into_iter().map(|calls| self.call_batch(provider, calls, block));
+        let results = try_join_all(batches).await?.into_iter().flatten().collect();
+        Ok(MulticallResults { results })
+    }
+
+    /// A single `aggregate3Value` transaction with every call, to fill and sign.
+    /// Writes aren't split, a failing call without `allow_failure` reverts them all.
+    pub fn aggregate3_value(&self) -> TransactionRequest {
+        let input = IMulticall3::aggregate3ValueCall {
+            calls: self.calls.clone(),
+        }
+        .abi_encode();
+        TransactionRequest::default()
+            .to(self.address)
+            .value(self.total_value())
+            .input(Bytes::from(input).into())
+    }
+
+    fn batches(&self) -> Vec<&[IMulticall3::Call3Value]> {
+        let mut batches = Vec::new();
+        let (mut start, mut calldata) = (0, 0);
+        for (index, call) in self.calls.iter().enumerate() {
+            let full = index - start >= self.batch_size || calldata + call.callData.len() > self.batch_calldata;
+            if full && index > start {
+                batches.push(&self.calls[start..index]);
+                (start, calldata) = (index, 0);
+            }
+            calldata += call.callData.len();
+        }
+        if start < self.calls.len() {
+            batches.push(&self.calls[start..]);
+        }
+        batches
+    }
+
+    async fn call_batch(
+        &self,
+        provider: &EvmProvider,
+        calls: &[IMulticall3::Call3Value],
+        block: BlockId,
+    ) -> Result<Vec<IMulticall3::Result>> {
+        let value: U256 = calls.iter().map(|call| call.value).sum();
+        let (input, with_value) = match value.is_zero() {
+            true => {
+                let calls = calls
+                    .iter()
+                    .map(|call| IMulticall3::Call3 {
+                        target: call.target,
+                        allowFailure: call.allowFailure,
+                        callData: call.callData.clone(),
+                    })
+                    .collect();
+                (IMulticall3::aggregate3Call { calls }.abi_encode(), false)
+            }
+            false => {
+                let calls = calls.to_vec();
+                (IMulticall3::aggregate3ValueCall { calls }.abi_encode(), true)
+            }
+        };
+
+        let mut tx = TransactionRequest::default().to(self.address).input(Bytes::from(input).into());
+        if with_value {
+            tx = tx.value(value);
+        }
+        let output = provider.call(&tx, block).await?;
+        // both functions return `Result[]`
+        let results = IMulticall3::aggregate3Call::abi_decode_returns(&output, true)
+            .map_err(|e| ProviderError::ResponseError(format!("aggregate3: {}", e)))?
+            .returnData;
+        if results.len() != calls.len() {
+            return Err(ProviderError::ResponseError(format!(
+                "aggregate3: {} results for {} calls",
+                results.len(),
+                calls.len()
+            )));
+        }
+        Ok(results)
+    }
+}
+
+/// Raw results of a [`Multicall`] read, in the order the calls were added.
+#[derive(Debug, Clone)]
+pub struct MulticallResults {
+    results: Vec<IMulticall3::Result>,
+}
+
+impl MulticallResults {
+    /// Typed result of the call behind `handle`.
+    pub fn get<C: SolCall>(&self, handle: &CallHandle<C>) -> std::result::Result<C::Return, CallFailure> {
+        let (success, data) = self.raw(handle.index).ok_or(CallFailure::Missing(handle.index))?;
+        if !success {
+            return Err(CallFailure::Reverted(data.clone()));
+        }
+        C::abi_decode_returns(data, true).map_err(|e| CallFailure::Decode(format!("{}: {}", C::SIGNATURE, e)))
+    }
+
+    /// Success flag and return (or revert) data of the call at `index`, `None` past the last call.
+    pub fn raw(&self, index: usize) -> Option<(bool, &Bytes)> {
+        let result = self.results.get(index)?;
+        Some((result.success, &result.returnData))
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abis::erc::IERC20;
+    use crate::provider::mock::MockNode;
+    use alloy_primitives::{address, hex};
+    use alloy_sol_types::SolValue;
+    use serde_json::json;
+
+    const TOKEN: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    const BROKEN: Address = address!("00000000000000000000000000000000000000bb");
+
+    /// Runs an `aggregate3` call like the contract would, against a token whose balances are the
+    /// last byte of the owner. Other targets revert with `Error("nope")`.
+    fn aggregate(input: &[u8]) -> Vec<IMulticall3::Result> {
+        let calls = IMulticall3::aggregate3Call::abi_decode(input, true).unwrap().calls;
+        calls
+            .iter()
+            .map(|call| {
+                let output = match (call.target, &call.callData[..4]) {
+                    (TOKEN, selector) if selector == IERC20::balanceOfCall::SELECTOR => {
+                        let owner = IERC20::balanceOfCall::abi_decode(&call.callData, true).unwrap().account;
+                        Some(U256::from(owner[19]).abi_encode())
+                    }
+                    (TOKEN, selector) if selector == IERC20::symbolCall::SELECTOR => Some("USDC".to_string().abi_encode()),
+                    _ => None,
+                };
+                match output {
+                    Some(output) => IMulticall3::Result {
+                        success: true,
+                        returnData: output.into(),
+                    },
+                    None => IMulticall3::Result {
+                        success: false,
+                        returnData: [&hex!("08c379a0")[..], &("nope".to_string(),).abi_encode_params()].concat().into(),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_multicall_reads() {
+        let node = MockNode::start(|method, params| {
+            assert_eq!(method, "eth_call");
+            assert_eq!(params[0]["to"], json!(MULTICALL3_ADDRESS));
+            let input = hex::decode(params[0]["input"].as_str().unwrap()).unwrap();
+            let output = IMulticall3::aggregate3Call::abi_encode_returns(&(aggregate(&input),));
+            Ok(json!(format!("0x{}", hex::encode(output))))
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let mut multicall = Multicall::new().with_batch_limits(2, DEFAULT_BATCH_CALLDATA);
+        let balances: Vec<_> = (1..=3)
+            .map(|i| multicall.add(TOKEN, IERC20::balanceOfCall { account: Address::with_last_byte(i) }, false))
+            .collect();
+        let symbol = multicall.add(TOKEN, IERC20::symbolCall {}, false);
+        let broken = multicall.add(BROKEN, IERC20::decimalsCall {}, true);
+        assert_eq!(multicall.len(), 5);
+
+        let results = multicall.call(&provider, BlockId::latest()).await.unwrap();
+        // 5 calls in batches of 2, results keep the order the calls were added in
+        assert_eq!(node.methods().len(), 3);
+        assert_eq!(results.len(), 5);
+        for (i, handle) in balances.iter().enumerate() {
+            assert_eq!(results.get(handle).unwrap()._0, U256::from(i + 1));
+        }
+        assert_eq!(results.get(&symbol).unwrap()._0, "USDC");
+
+        let failure = results.get(&broken).err().unwrap();
+        assert!(matches!(failure, CallFailure::Reverted(_)));
+        assert_eq!(failure.revert_error().unwrap().params[0].value, json!("nope"));
+        // the raw result decoded as another type
+        let mismatched = CallHandle::<IERC20::decimalsCall> {
+            index: symbol.index(),
+            call: PhantomData,
+        };
+        assert!(matches!(results.get(&mismatched).err(), Some(CallFailure::Decode(_))));
+
+        // a handle from a bigger batch
+        let mut other = Multicall::new();
+        let foreign = (0..6).map(|_| other.add(TOKEN, IERC20::decimalsCall {}, false)).last().unwrap();
+        assert_eq!(results.get(&foreign).err(), Some(CallFailure::Missing(5)));
+        assert_eq!(results.raw(5), None);
+        assert_eq!(results.raw(4).map(|(success, _)| success), Some(false));
+    }
+
+    #[test]
+    fn test_multicall_batches() {
+        let mut multicall = Multicall::new().with_batch_limits(10, 100);
+        for _ in 0..4 {
+            // 36 bytes each
+            multicall.add(TOKEN, IERC20::balanceOfCall { account: BROKEN }, false);
+        }
+        let sizes: Vec<usize> = multicall.batches().iter().map(|batch| batch.len()).collect();
+        assert_eq!(sizes, vec![2, 2]);
+
+        // a call larger than the limit still gets its own batch
+        let mut multicall = Multicall::new().with_batch_limits(10, 10);
+        multicall.add(TOKEN, IERC20::balanceOfCall { account: BROKEN }, false);
+        multicall.add(TOKEN, IERC20::symbolCall {}, false);
+        assert_eq!(multicall.batches().len(), 2);
+        assert!(Multicall::new().batches().is_empty());
+    }
+
+    #[test]
+    fn test_aggregate3_value() {
+        let mut multicall = Multicall::new();
+        multicall.add_with_value(TOKEN, IERC20::transferCall { to: BROKEN, amount: U256::from(1) }, U256::from(10), false);
+        multicall.add_with_value(BROKEN, IERC20::symbolCall {}, U256::from(5), true);
+
+        let tx = multicall.aggregate3_value();
+        assert_eq!(tx.to, Some(MULTICALL3_ADDRESS.into()));
+        assert_eq!(tx.value, Some(U256::from(15)));
+        let call = IMulticall3::aggregate3ValueCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(call.calls.len(), 2);
+        assert_eq!((call.calls[1].target, call.calls[1].allowFailure), (BROKEN, true));
+        let transfer = IERC20::transferCall::abi_decode(&call.calls[0].callData, true).unwrap();
+        assert_eq!(transfer.to, BROKEN);
+    }
+}
@@ -0,0 +1,538 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, B256, U256};
+use alloy_rpc_types::TransactionRequest;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::{EvmSigner, SignedTransaction};
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+/// Gas of a plain ETH transfer, used by the gap-filling and cancelling self-transfers.
+pub(crate) const TRANSFER_GAS: u64 = 21_000;
+
+/// What an `eth_sendRawTransaction` error says about the nonce of the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    /// Rejected before the nonce was looked at, it can be handed out again.
+    NonceFree,
+    /// The node already has this very transaction.
+    AlreadyKnown,
+    /// Another transaction holds the nonce in the pool.
+    NonceTaken,
+    /// The nonce was mined, the local state is behind the node.
+    NonceTooLow,
+    /// Anything else, the transaction may or may not have reached the pool.
+    Unknown,
+}
+
+impl Rejection {
+    /// Classifies the error messages of geth, erigon, nethermind and reth.
+    fn from_message(message: &str) -> Self {
+        let message = message.to_ascii_lowercase();
+        if message.contains("nonce too low") || message.contains("oldnonce") {
+            Self::NonceTooLow
+        } else if message.contains("already known") || message.contains("known transaction") {
+            Self::AlreadyKnown
+        } else if message.contains("replacement") {
+            Self::NonceTaken
+        } else if [
+            "insufficient funds",
+            "intrinsic gas too low",
+            "exceeds block gas limit",
+            "gas limit reached",
+            "fee cap",
+            "max fee per gas less than block base fee",
+            "transaction underpriced",
+            "tip higher than",
+            "oversized data",
+            "invalid sender",
+            "invalid chain id",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+        {
+            Self::NonceFree
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct AccountNonces {
+    /// Next nonce never handed out.
+    next: u64,
+    /// Handed out and not broadcast yet.
+    reserved: BTreeSet<u64>,
+    /// Broadcast and not known to be mined yet.
+    sent: BTreeMap<u64, B256>,
+    /// Handed out but never made it to the node, reused before `next`.
+    released: BTreeSet<u64>,
+}
+
+impl AccountNonces {
+    fn take(&mut self) -> u64 {
+        let nonce = match self.released.pop_first() {
+            Some(nonce) => nonce,
+            None => {
+                self.next += 1;
+                self.next - 1
+            }
+        };
+        self.reserved.insert(nonce);
+        nonce
+    }
+
+    /// Forgets everything below the mined nonce.
+    fn prune(&mut self, latest: u64) {
+        self.reserved.retain(|nonce| *nonce >= latest);
+        self.sent.retain(|nonce, _| *nonce >= latest);
+        self.released.retain(|nonce| *nonce >= latest);
+        self.next = self.next.max(latest);
+    }
+}
+
+/// Hands out nonces to concurrent senders sharing an account.
+///
+/// Each account is synced from its pending nonce on first use, then nonces are reserved
+/// locally without a round trip. Senders report what happened to a nonce with
+/// [`NonceManager::mark_sent`] or [`NonceManager::release`]; released nonces are handed
+/// out again before new ones so the sequence stays contiguous.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    accounts: Mutex<HashMap<Address, AccountNonces>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the next nonce of `address`, the reservation is atomic across tasks.
+    pub async fn reserve(&self, provider: &EvmProvider, address: Address) -> Result<u64> {
+        if let Some(account) = self.accounts.lock().await.get_mut(&address) {
+            return Ok(account.take());
+        }
+
+        // synced without the lock so a slow node doesn't hold up other accounts
+        let pending = provider.get_transaction_count(address, BlockId::pending()).await?;
+        let mut accounts = self.accounts.lock().await;
+        // another task may have synced it meanwhile, its state wins
+        let account = accounts.entry(address).or_insert_with(|| AccountNonces {
+            next: pending,
+            ..Default::default()
+        });
+        Ok(account.take())
+    }
+
+    /// Next nonce that would be reserved, `None` before the account is synced.
+    pub async fn peek(&self, address: Address) -> Option<u64> {
+        let accounts = self.accounts.lock().await;
+        accounts
+            .get(&address)
+            .map(|account| account.released.first().copied().unwrap_or(account.next))
+    }
+
+    /// Records that the transaction using `nonce` was broadcast.
+    pub async fn mark_sent(&self, address: Address, nonce: u64, hash: B256) {
+        if let Some(account) = self.accounts.lock().await.get_mut(&address) {
+            account.reserved.remove(&nonce);
+            account.released.remove(&nonce);
+            account.sent.insert(nonce, hash);
+        }
+    }
+
+    /// Gives back a nonce whose transaction was never broadcast.
+    pub async fn release(&self, address: Address, nonce: u64) {
+        if let Some(account) = self.accounts.lock().await.get_mut(&address) {
+            account.reserved.remove(&nonce);
+            account.sent.remove(&nonce);
+            if nonce < account.next {
+                account.released.insert(nonce);
+            }
+        }
+    }
+
+    /// Forgets the local state of `address`, the next reservation syncs from the node again.
+    pub async fn reset(&self, address: Address) {
+        self.accounts.lock().await.remove(&address);
+    }
+
+    /// Reconciles the local state with the node.
+    ///
+    /// Mined nonces are dropped, nonces used by transactions sent from elsewhere are skipped,
+    /// and when nothing is in flight the counter rewinds to the pending nonce.
+    pub async fn resync(&self, provider: &EvmProvider, address: Address) -> Result<()> {
+        let (latest, pending) = Self::node_nonces(provider, address).await?;
+
+        let mut accounts = self.accounts.lock().await;
+        let account = accounts.entry(address).or_insert_with(|| AccountNonces {
+            next: pending,
+            ..Default::default()
+        });
+        account.prune(latest);
+        account.released.retain(|nonce| *nonce >= pending);
+        if account.reserved.is_empty() && account.sent.is_empty() {
+            account.released.clear();
+            account.next = pending;
+        } else {
+            account.next = account.next.max(pending);
+        }
+        Ok(())
+    }
+
+    /// Nonces between the node's pending nonce and the local counter that will never be
+    /// mined as things stand: released ones, untracked ones, and sent transactions the
+    /// node no longer knows about. They're marked released and returned in order.
+    pub async fn find_gaps(&self, provider: &EvmProvider, address: Address) -> Result<Vec<u64>> {
+        let (latest, pending) = Self::node_nonces(provider, address).await?;
+
+        let sent = {
+            let mut accounts = self.accounts.lock().await;
+            let Some(account) = accounts.get_mut(&address) else {
+                return Ok(vec![]);
+            };
+            account.prune(latest);
+            account.sent.range(pending..).map(|(n, h)| (*n, *h)).collect::<Vec<_>>()
+        };
+
+        // queued transactions above a gap don't count in the pending nonce, look them up one by one;
+        // the raw value is checked so unusual transaction types don't fail deserialization
+        let mut dropped = Vec::new();
+        for (nonce, hash) in sent {
+            let tx: Value = provider.request("eth_getTransactionByHash", (hash,)).await?;
+            if tx.is_null() {
+                dropped.push((nonce, hash));
+            }
+        }
+
+        let mut accounts = self.accounts.lock().await;
+        let Some(account) = accounts.get_mut(&address) else {
+            return Ok(vec![]);
+        };
+        for (nonce, hash) in dropped {
+            // skip nonces another task resent in the meantime
+            if account.sent.get(&nonce) == Some(&hash) {
+                account.sent.remove(&nonce);
+                account.released.insert(nonce);
+            }
+        }
+        for nonce in pending..account.next {
+            if !account.reserved.contains(&nonce) && !account.sent.contains_key(&nonce) {
+                account.released.insert(nonce);
+            }
+        }
+        account.released.retain(|nonce| *nonce >= pending);
+        Ok(account.released.iter().copied().collect())
+    }
+
+    /// Fills every gap found by [`NonceManager::find_gaps`] with a zero-value transfer
+    /// to the signer itself, so the transactions queued behind it can be mined.
+    pub async fn fill_gaps(&self, provider: &EvmProvider, signer: &EvmSigner<'_>) -> Result<Vec<SignedTransaction>> {
+        let address = signer.address();
+        let mut filled = Vec::new();
+
+        for nonce in self.find_gaps(provider, address).await? {
+            if !self.claim(address, nonce).await {
+                continue;
+            }
+            let tx = TransactionRequest::default()
+                .to(address)
+                .value(U256::ZERO)
+                .nonce(nonce)
+                .gas_limit(TRANSFER_GAS);
+            filled.push(self.sign_and_send(provider, signer, nonce, tx).await?);
+        }
+        Ok(filled)
+    }
+
+    /// Reserves a nonce for `tx`, fills the rest from the node, signs and broadcasts it.
+    pub async fn send_transaction(
+        &self,
+        provider: &EvmProvider,
+        signer: &EvmSigner<'_>,
+        tx: TransactionRequest,
+    ) -> Result<SignedTransaction> {
+        let nonce = self.reserve(provider, signer.address()).await?;
+        self.sign_and_send(provider, signer, nonce, tx.nonce(nonce)).await
+    }
+
+    async fn sign_and_send(
+        &self,
+        provider: &EvmProvider,
+        signer: &EvmSigner<'_>,
+        nonce: u64,
+        tx: TransactionRequest,
+    ) -> Result<SignedTransaction> {
+        let address = signer.address();
+        let signed = match provider.fill_and_sign(signer, tx).await {
+            Ok(signed) => signed,
+            Err(e) => {
+                self.release(address, nonce).await;
+                return Err(e);
+            }
+        };
+
+        match provider.send_transaction(&signed).await {
+            Ok(_) => {
+                self.mark_sent(address, nonce, signed.hash).await;
+                Ok(signed)
+            }
+            Err(e) => {
+                let rejection = match &e {
+                    ProviderError::RpcError { message, .. } => Rejection::from_message(message),
+                    _ => Rejection::Unknown,
+                };
+                match rejection {
+                    Rejection::NonceFree => self.release(address, nonce).await,
+                    Rejection::AlreadyKnown => self.mark_sent(address, nonce, signed.hash).await,
+                    Rejection::NonceTaken => self.forget(address, nonce).await,
+                    Rejection::NonceTooLow => {
+                        self.forget(address, nonce).await;
+                        // the send error is what the caller needs, a failed resync is retried on the next one
+                        let _ = self.resync(provider, address).await;
+                    }
+                    // it may have reached the node, find_gaps will tell
+                    Rejection::Unknown => self.mark_sent(address, nonce, signed.hash).await,
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Drops a nonce used on the node by a transaction this manager doesn't track.
+    async fn forget(&self, address: Address, nonce: u64) {
+        if let Some(account) = self.accounts.lock().await.get_mut(&address) {
+            account.reserved.remove(&nonce);
+            account.released.remove(&nonce);
+        }
+    }
+
+    /// Takes a released nonce for this task, `false` if another task already did.
+    async fn claim(&self, address: Address, nonce: u64) -> bool {
+        let mut accounts = self.accounts.lock().await;
+        let Some(account) = accounts.get_mut(&address) else {
+            return false;
+        };
+        if !account.released.remove(&nonce) {
+            return false;
+        }
+        account.reserved.insert(nonce);
+        true
+    }
+
+    async fn node_nonces(provider: &EvmProvider, address: Address) -> Result<(u64, u64)> {
+        let latest = provider.get_transaction_count(address, BlockId::latest()).await?;
+        let pending = provider.get_transaction_count(address, BlockId::pending()).await?;
+        Ok((latest, pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use crate::signer::utils::decode_raw_tx;
+    use alloy_primitives::b256;
+    use alloy_rpc_types::Block;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    const DROPPED: B256 = b256!("00000000000000000000000000000000000000000000000000000000000000dd");
+
+    /// A node whose latest and pending nonces can be moved by the test.
+    async fn node(latest: Arc<AtomicU64>, pending: Arc<AtomicU64>) -> MockNode {
+        MockNode::start(move |method, params| match method {
+            "eth_getTransactionCount" if params[1] == "latest" => {
+                Ok(json!(format!("0x{:x}", latest.load(Ordering::SeqCst))))
+            }
+            "eth_getTransactionCount" => Ok(json!(format!("0x{:x}", pending.load(Ordering::SeqCst)))),
+            "eth_getTransactionByHash" if params[0] == json!(DROPPED) => Ok(Value::Null),
+            "eth_getTransactionByHash" => Ok(json!({ "hash": params[0] })),
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_getBlockByNumber" => {
+                let mut block: Block = Block::default();
+                block.header.inner.base_fee_per_gas = Some(1_000_000_000);
+                Ok(serde_json::to_value(block).unwrap())
+            }
+            "eth_maxPriorityFeePerGas" => Ok(json!("0x3b9aca00")),
+            "eth_sendRawTransaction" => Ok(json!(B256::repeat_byte(0x11))),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reservations() {
+        let node = node(Arc::new(AtomicU64::new(5)), Arc::new(AtomicU64::new(5))).await;
+        let provider = Arc::new(EvmProvider::connect(node.http_url()).await.unwrap());
+        let manager = Arc::new(NonceManager::new());
+        let address = Address::repeat_byte(1);
+
+        let tasks = (0..20)
+            .map(|_| {
+                let (provider, manager) = (provider.clone(), manager.clone());
+                tokio::spawn(async move { manager.reserve(&provider, address).await.unwrap() })
+            })
+            .collect::<Vec<_>>();
+        let mut nonces = Vec::new();
+        for task in tasks {
+            nonces.push(task.await.unwrap());
+        }
+        nonces.sort();
+
+        assert_eq!(nonces, (5..25).collect::<Vec<_>>());
+        assert_eq!(manager.peek(address).await, Some(25));
+        // once synced, every reservation is local
+        let synced = node.methods().len();
+        assert!(node.methods().iter().all(|method| method == "eth_getTransactionCount"));
+        manager.reserve(&provider, address).await.unwrap();
+        assert_eq!(node.methods().len(), synced);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_slow_sync_does_not_block_other_accounts() {
+        let slow = Address::repeat_byte(2);
+        let node = MockNode::start(move |_, params| {
+            if params[0] == json!(slow) {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+            Ok(json!("0x5"))
+        })
+        .await;
+        let provider = Arc::new(EvmProvider::connect(node.http_url()).await.unwrap());
+        let manager = Arc::new(NonceManager::new());
+        let address = Address::repeat_byte(1);
+        manager.reserve(&provider, address).await.unwrap();
+
+        let syncing = {
+            let (provider, manager) = (provider.clone(), manager.clone());
+            tokio::spawn(async move { manager.reserve(&provider, slow).await.unwrap() })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let reserved = tokio::time::timeout(std::time::Duration::from_millis(200), manager.reserve(&provider, address));
+        assert_eq!(reserved.await.unwrap().unwrap(), 6);
+        assert_eq!(syncing.await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_send_rejections() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let address = signer.address();
+
+        let pending = Arc::new(AtomicU64::new(5));
+        let rejection = Arc::new(std::sync::Mutex::new(String::new()));
+        let node = {
+            let (pending, rejection) = (pending.clone(), rejection.clone());
+            MockNode::start(move |method, params| match method {
+                "eth_getTransactionCount" => Ok(json!(format!("0x{:x}", pending.load(Ordering::SeqCst)))),
+                "eth_sendRawTransaction" => Err((-32000, rejection.lock().unwrap().clone())),
+                "eth_chainId" => Ok(json!("0x1")),
+                "eth_getBlockByNumber" => {
+                    let mut block: Block = Block::default();
+                    block.header.inner.base_fee_per_gas = Some(1_000_000_000);
+                    Ok(serde_json::to_value(block).unwrap())
+                }
+                "eth_maxPriorityFeePerGas" => Ok(json!("0x3b9aca00")),
+                _ => Err((-32601, format!("method not found: {method} {params}"))),
+            })
+            .await
+        };
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let manager = NonceManager::new();
+        let transfer = || TransactionRequest::default().to(address).gas_limit(TRANSFER_GAS);
+        let reject = |message: &str| *rejection.lock().unwrap() = message.to_string();
+
+        // rejected before the nonce was used, it's handed out again
+        reject("insufficient funds for gas * price + value");
+        assert!(manager.send_transaction(&provider, &signer, transfer()).await.is_err());
+        assert_eq!(manager.peek(address).await, Some(5));
+
+        // the nonce is used on the node, it's never handed out again
+        for message in ["already known", "replacement transaction underpriced", "execution timeout"] {
+            reject(message);
+            let nonce = manager.peek(address).await.unwrap();
+            assert!(manager.send_transaction(&provider, &signer, transfer()).await.is_err());
+            assert_eq!(manager.peek(address).await, Some(nonce + 1), "{message}");
+        }
+
+        // mined elsewhere, the manager catches up with the node
+        reject("nonce too low: next nonce 12, tx nonce 8");
+        pending.store(12, Ordering::SeqCst);
+        assert!(manager.send_transaction(&provider, &signer, transfer()).await.is_err());
+        assert_eq!(manager.peek(address).await, Some(12));
+    }
+
+    #[tokio::test]
+    async fn test_release_and_resync() {
+        let latest = Arc::new(AtomicU64::new(5));
+        let pending = Arc::new(AtomicU64::new(5));
+        let node = node(latest.clone(), pending.clone()).await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let manager = NonceManager::new();
+        let address = Address::repeat_byte(1);
+
+        for expected in 5..8 {
+            assert_eq!(manager.reserve(&provider, address).await.unwrap(), expected);
+        }
+        manager.release(address, 6).await;
+        assert_eq!(manager.reserve(&provider, address).await.unwrap(), 6);
+        assert_eq!(manager.reserve(&provider, address).await.unwrap(), 8);
+
+        // transactions sent from another process moved the account ahead
+        for nonce in 5..9 {
+            manager.mark_sent(address, nonce, B256::repeat_byte(nonce as u8)).await;
+        }
+        latest.store(9, Ordering::SeqCst);
+        pending.store(12, Ordering::SeqCst);
+        manager.resync(&provider, address).await.unwrap();
+        assert_eq!(manager.reserve(&provider, address).await.unwrap(), 12);
+
+        // nothing in flight, the counter follows the node back down
+        manager.release(address, 12).await;
+        pending.store(11, Ordering::SeqCst);
+        latest.store(11, Ordering::SeqCst);
+        manager.resync(&provider, address).await.unwrap();
+        assert_eq!(manager.peek(address).await, Some(11));
+    }
+
+    #[tokio::test]
+    async fn test_find_and_fill_gaps() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let address = signer.address();
+
+        let latest = Arc::new(AtomicU64::new(5));
+        let pending = Arc::new(AtomicU64::new(5));
+        let node = node(latest, pending.clone()).await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let manager = NonceManager::new();
+
+        for nonce in 5..9 {
+            assert_eq!(manager.reserve(&provider, address).await.unwrap(), nonce);
+        }
+        manager.mark_sent(address, 5, B256::repeat_byte(5)).await;
+        manager.mark_sent(address, 6, DROPPED).await;
+        manager.mark_sent(address, 8, B256::repeat_byte(8)).await;
+        // 5 is pending, 6 was dropped, 7 is still being signed and 8 is queued behind the gap
+        pending.store(6, Ordering::SeqCst);
+        assert_eq!(manager.find_gaps(&provider, address).await.unwrap(), vec![6]);
+
+        let filled = manager.fill_gaps(&provider, &signer).await.unwrap();
+        assert_eq!(filled.len(), 1);
+        let decoded = decode_raw_tx(&filled[0].raw_hex()).unwrap();
+        assert_eq!((decoded.nonce, decoded.to, decoded.value), (6, Some(address), U256::ZERO));
+        assert_eq!(decoded.gas_limit, TRANSFER_GAS);
+
+        assert!(manager.find_gaps(&provider, address).await.unwrap().is_empty());
+        assert_eq!(manager.peek(address).await, Some(9));
+    }
+}
@@ -0,0 +1,365 @@
+use alloy_consensus::{Transaction as _, TxEip1559, TxEnvelope, TxLegacy, TypedTransaction};
+use alloy_primitives::{Address, TxKind, B256, U256};
+
+use crate::provider::client::EvmProvider;
+use crate::provider::fees::FeeEstimate;
+use crate::provider::nonce::TRANSFER_GAS;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::{EvmSigner, SignedTransaction};
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+/// Fee increase a node requires before a transaction replaces a pending one with the same nonce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceBump {
+    /// 10%, the geth default, also used by Nethermind, Besu and Reth.
+    Geth,
+    /// 12.5%, required by OpenEthereum, enough for every 10% client too.
+    #[default]
+    Parity,
+}
+
+impl PriceBump {
+    /// Lowest fee a node accepts to replace a transaction paying `fee`.
+    pub fn min_fee(self, fee: u128) -> u128 {
+        let permille = match self {
+            Self::Geth => 100,
+            Self::Parity => 125,
+        };
+        fee.saturating_add(fee.saturating_mul(permille).div_ceil(1000))
+    }
+
+    fn gas_price(self, gas_price: u128, market: Option<&FeeEstimate>) -> u128 {
+        self.min_fee(gas_price).max(market.map_or(0, |fees| fees.gas_price))
+    }
+
+    /// Bumped `(max_fee_per_gas, max_priority_fee_per_gas)`.
+    fn eip1559_fees(self, max_fee: u128, priority_fee: u128, market: Option<&FeeEstimate>) -> (u128, u128) {
+        let priority_fee = self
+            .min_fee(priority_fee)
+            .max(market.map_or(0, |fees| fees.max_priority_fee_per_gas));
+        let max_fee = self
+            .min_fee(max_fee)
+            .max(market.map_or(0, |fees| fees.max_fee_per_gas))
+            .max(priority_fee);
+        (max_fee, priority_fee)
+    }
+}
+
+/// The pending transaction to replace.
+#[derive(Debug, Clone)]
+pub enum ReplaceTarget {
+    /// Looked up with `eth_getTransactionByHash`, it must still be pending.
+    Hash(B256),
+    Signed(Box<TxEnvelope>),
+}
+
+impl From<B256> for ReplaceTarget {
+    fn from(hash: B256) -> Self {
+        Self::Hash(hash)
+    }
+}
+
+impl From<TxEnvelope> for ReplaceTarget {
+    fn from(tx: TxEnvelope) -> Self {
+        Self::Signed(Box::new(tx))
+    }
+}
+
+impl From<&SignedTransaction> for ReplaceTarget {
+    fn from(tx: &SignedTransaction) -> Self {
+        Self::Signed(Box::new(tx.envelope.clone()))
+    }
+}
+
+fn ensure_replaceable(original: &TxEnvelope) -> Result<()> {
+    match original.is_eip4844() {
+        true => Err(ProviderError::ReplacementError(
+            "blob transactions can't be replaced without their sidecar".into(),
+        )),
+        false => Ok(()),
+    }
+}
+
+/// `original` with its fees bumped, or raised to `market` when that is higher.
+///
+/// Legacy and EIP-2930 transactions bump the gas price, the others both EIP-1559 fees
+/// (nodes reject a replacement unless both go up). Blob transactions aren't supported.
+pub fn speed_up_tx(original: &TxEnvelope, bump: PriceBump, market: Option<&FeeEstimate>) -> Result<TypedTransaction> {
+    ensure_replaceable(original)?;
+    let mut tx = TypedTransaction::from(original.clone());
+    match &mut tx {
+        TypedTransaction::Legacy(tx) => tx.gas_price = bump.gas_price(tx.gas_price, market),
+        TypedTransaction::Eip2930(tx) => tx.gas_price = bump.gas_price(tx.gas_price, market),
+        TypedTransaction::Eip1559(tx) => {
+            (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) =
+                bump.eip1559_fees(tx.max_fee_per_gas, tx.max_priority_fee_per_gas, market)
+        }
+        TypedTransaction::Eip7702(tx) => {
+            (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) =
+                bump.eip1559_fees(tx.max_fee_per_gas, tx.max_priority_fee_per_gas, market)
+        }
+        TypedTransaction::Eip4844(_) => unreachable!("checked by ensure_replaceable"),
+    }
+    Ok(tx)
+}
+
+/// A zero-value transfer from `from` to itself with the nonce of `original`, priced like
+/// [`speed_up_tx`]. Legacy and EIP-2930 originals get a legacy cancel, the others EIP-1559.
+pub fn cancel_tx(
+    original: &TxEnvelope,
+    from: Address,
+    bump: PriceBump,
+    market: Option<&FeeEstimate>,
+) -> Result<TypedTransaction> {
+    ensure_replaceable(original)?;
+    let tx = match original.max_priority_fee_per_gas() {
+        None => TypedTransaction::Legacy(TxLegacy {
+            chain_id: original.chain_id(),
+            nonce: original.nonce(),
+            gas_price: bump.gas_price(original.max_fee_per_gas(), market),
+            gas_limit: TRANSFER_GAS,
+            to: TxKind::Call(from),
+            value: U256::ZERO,
+            ..Default::default()
+        }),
+        Some(priority_fee) => {
+            let (max_fee_per_gas, max_priority_fee_per_gas) =
+                bump.eip1559_fees(original.max_fee_per_gas(), priority_fee, market);
+            TypedTransaction::Eip1559(TxEip1559 {
+                chain_id: original.chain_id().unwrap_or(1),
+                nonce: original.nonce(),
+                gas_limit: TRANSFER_GAS,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                to: TxKind::Call(from),
+                value: U256::ZERO,
+                ..Default::default()
+            })
+        }
+    };
+    Ok(tx)
+}
+
+impl EvmProvider {
+    /// Signs a replacement of `target` that pays more gas, see [`speed_up_tx`].
+    ///
+    /// Pass suggested fees from [`crate::provider::fees::FeeOracle`] as `market` to catch up
+    /// with the current base fee, not just beat the original. Nothing is broadcast.
+    pub async fn speed_up_transaction(
+        &self,
+        signer: &EvmSigner<'_>,
+        target: impl Into<ReplaceTarget>,
+        bump: PriceBump,
+        market: Option<&FeeEstimate>,
+    ) -> Result<SignedTransaction> {
+        let original = self.replaceable(signer, target.into()).await?;
+        let tx = speed_up_tx(&original, bump, market)?;
+        Ok(signer.sign_typed_transaction(tx).await?)
+    }
+
+    /// Signs a zero-value self-transfer taking the nonce of `target`, see [`cancel_tx`].
+    /// Nothing is broadcast.
+    pub async fn cancel_transaction(
+        &self,
+        signer: &EvmSigner<'_>,
+        target: impl Into<ReplaceTarget>,
+        bump: PriceBump,
+        market: Option<&FeeEstimate>,
+    ) -> Result<SignedTransaction> {
+        let original = self.replaceable(signer, target.into()).await?;
+        let tx = cancel_tx(&original, signer.address(), bump, market)?;
+        Ok(signer.sign_typed_transaction(tx).await?)
+    }
+
+    /// The transaction behind `target`, checked to be pending and sent by `signer`.
+    async fn replaceable(&self, signer: &EvmSigner<'_>, target: ReplaceTarget) -> Result<TxEnvelope> {
+        let (from, original) = match target {
+            ReplaceTarget::Signed(tx) => {
+                let from = tx
+                    .recover_signer()
+                    .map_err(|e| ProviderError::ReplacementError(e.to_string()))?;
+                (from, *tx)
+            }
+            ReplaceTarget::Hash(hash) => {
+                let tx = self
+                    .get_transaction_by_hash(hash)
+                    .await?
+                    .ok_or_else(|| ProviderError::ReplacementError(format!("transaction {} not found", hash)))?;
+                if tx.block_number.is_some() {
+                    return Err(ProviderError::ReplacementError(format!(
+                        "transaction {} is already mined",
+                        hash
+                    )));
+                }
+                (tx.from, tx.inner)
+            }
+        };
+
+        if from != signer.address() {
+            return Err(ProviderError::ReplacementError(format!(
+                "transaction was sent by {}, not {}",
+                from,
+                signer.address()
+            )));
+        }
+        Ok(original)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use crate::signer::account::EvmAccount;
+    use crate::signer::sign::Transaction;
+    use alloy_primitives::{address, Bytes};
+    use alloy_rpc_types::Transaction as RpcTransaction;
+    use serde_json::{json, Value};
+
+    const GWEI: u128 = 1_000_000_000;
+
+    async fn eip1559(signer: &EvmSigner<'_>) -> SignedTransaction {
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 9,
+            gas_limit: 60_000,
+            max_fee_per_gas: 10 * GWEI,
+            max_priority_fee_per_gas: GWEI,
+            to: TxKind::Call(address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83")),
+            value: U256::from(1000),
+            input: Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb]),
+            ..Default::default()
+        };
+        signer.sign_transaction(Transaction::Eip1559(&mut tx)).await.unwrap()
+    }
+
+    #[test]
+    fn test_price_bump() {
+        assert_eq!(PriceBump::Geth.min_fee(100), 110);
+        assert_eq!(PriceBump::Parity.min_fee(100), 113);
+        assert_eq!(PriceBump::Parity.min_fee(8 * GWEI), 9 * GWEI);
+        assert_eq!(PriceBump::Geth.min_fee(1), 2);
+        assert_eq!(PriceBump::Geth.min_fee(u128::MAX), u128::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_speed_up_and_cancel() {
+        let node = MockNode::start(|_, _| Err((-32601, "method not found".into()))).await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let original = eip1559(&signer).await;
+
+        let replacement = provider
+            .speed_up_transaction(&signer, &original, PriceBump::Geth, None)
+            .await
+            .unwrap();
+        let TxEnvelope::Eip1559(tx) = &replacement.envelope else {
+            panic!("expected an EIP-1559 replacement");
+        };
+        let tx = tx.tx();
+        assert_eq!((tx.nonce, tx.gas_limit, tx.value), (9, 60_000, U256::from(1000)));
+        assert_eq!(tx.input, Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb]));
+        assert_eq!((tx.max_fee_per_gas, tx.max_priority_fee_per_gas), (11 * GWEI, 1_100_000_000));
+
+        // market fees above the bump win, the fee cap never drops below the tip
+        let market = FeeEstimate {
+            max_fee_per_gas: 5 * GWEI,
+            max_priority_fee_per_gas: 12 * GWEI,
+            gas_price: 0,
+        };
+        let tx = speed_up_tx(&original.envelope, PriceBump::Parity, Some(&market)).unwrap();
+        assert_eq!((tx.max_fee_per_gas(), tx.max_priority_fee_per_gas()), (12 * GWEI, Some(12 * GWEI)));
+
+        let cancel = provider
+            .cancel_transaction(&signer, &original, PriceBump::Parity, None)
+            .await
+            .unwrap();
+        let tx = &cancel.envelope;
+        assert_eq!(tx.nonce(), 9);
+        assert_eq!(tx.to(), Some(signer.address()));
+        assert_eq!((tx.value(), tx.gas_limit()), (U256::ZERO, TRANSFER_GAS));
+        assert!(tx.input().is_empty());
+        assert_eq!(tx.max_fee_per_gas(), 11_250_000_000);
+
+        // signed targets don't need the node
+        assert!(node.methods().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_legacy() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let mut tx = TxLegacy {
+            chain_id: Some(56),
+            nonce: 3,
+            gas_price: 20 * GWEI,
+            gas_limit: 100_000,
+            to: TxKind::Call(address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83")),
+            value: U256::from(1000),
+            ..Default::default()
+        };
+        let original = signer.sign_transaction(Transaction::Legacy(&mut tx)).await.unwrap();
+
+        let cancel = cancel_tx(&original.envelope, signer.address(), PriceBump::Parity, None).unwrap();
+        let TypedTransaction::Legacy(cancel) = cancel else {
+            panic!("expected a legacy cancel");
+        };
+        assert_eq!((cancel.chain_id, cancel.nonce, cancel.gas_price), (Some(56), 3, 22_500_000_000));
+        assert_eq!(cancel.to, TxKind::Call(signer.address()));
+    }
+
+    #[tokio::test]
+    async fn test_replace_by_hash() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let original = eip1559(&signer).await;
+        let pending = serde_json::to_value(RpcTransaction {
+            inner: original.envelope.clone(),
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            effective_gas_price: None,
+            from: original.from,
+        })
+        .unwrap();
+        let mut mined = pending.clone();
+        mined["blockNumber"] = json!("0x10");
+        mined["blockHash"] = json!(B256::repeat_byte(0xaa));
+        mined["transactionIndex"] = json!("0x0");
+
+        let pending_hash = original.hash;
+        let node = MockNode::start(move |method, params| match method {
+            "eth_getTransactionByHash" if params[0] == json!(pending_hash) => Ok(pending.clone()),
+            "eth_getTransactionByHash" if params[0] == json!(B256::ZERO) => Ok(mined.clone()),
+            "eth_getTransactionByHash" => Ok(Value::Null),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let replacement = provider
+            .speed_up_transaction(&signer, original.hash, PriceBump::Parity, None)
+            .await
+            .unwrap();
+        assert_eq!(replacement.envelope.nonce(), 9);
+        assert_ne!(replacement.hash, original.hash);
+
+        for hash in [B256::ZERO, B256::repeat_byte(1)] {
+            assert!(matches!(
+                provider.cancel_transaction(&signer, hash, PriceBump::Parity, None).await,
+                Err(ProviderError::ReplacementError(_))
+            ));
+        }
+
+        let other = EvmAccount::from_private_key_hex(&format!("{:064x}", 7)).unwrap();
+        assert!(matches!(
+            provider
+                .cancel_transaction(&EvmSigner::new(&other), original.hash, PriceBump::Parity, None)
+                .await,
+            Err(ProviderError::ReplacementError(_))
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+//! Fixtures shared by the tests of the provider, DEX and token modules.
+
+use alloy_primitives::U256;
+
+use crate::signer::account::EvmAccount;
+
+/// The account the signing tests use.
+pub(crate) fn account() -> EvmAccount {
+    EvmAccount::from_private_key_hex("c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d").unwrap()
+}
+
+/// `amount` whole units of an 18 decimals token.
+pub(crate) fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::from(10).pow(U256::from(18))
+}
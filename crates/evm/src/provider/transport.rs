@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::signer::sign::EvmSignerError;
+
+#[derive(Error, Debug)]
+pub enum ProviderError {
+    #[error("Request error: {0}")]
+    RequestError(String),
+    #[error("RPC error {code}: {message}")]
+    RpcError {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    #[error("Response error: {0}")]
+    ResponseError(String),
+    #[error("Timeout error: {0}")]
+    TimeoutError(String),
+    #[error("Fill error: {0}")]
+    FillError(String),
+    #[error("Replacement error: {0}")]
+    ReplacementError(String),
+    #[error("Signer error: {0}")]
+    SignerError(#[from] EvmSignerError),
+}
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub timeout: Duration,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRpcRequest<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: Value,
+}
+
+impl<'a> JsonRpcRequest<'a> {
+    fn new(id: u64, method: &'a str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            method,
+            // `null` params are rejected by some nodes
+            params: if params.is_null() { Value::Array(vec![]) } else { params },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    #[serde(default)]
+    id: Value,
+    #[serde(default)]
+    result: Value,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl JsonRpcResponse {
+    fn into_result(self) -> Result<Value> {
+        match self.error {
+            Some(error) => Err(ProviderError::RpcError {
+                code: error.code,
+                message: error.message,
+                data: error.data,
+            }),
+            None => Ok(self.result),
+        }
+    }
+}
+
+/// Connection to a node, picked from the URL scheme by [`Transport::connect`].
+#[derive(Debug)]
+pub enum Transport {
+    Http(HttpTransport),
+    Ws(WsTransport),
+}
+
+impl Transport {
+    /// Connects over HTTP for `http(s)://` URLs and WebSocket for `ws(s)://` URLs.
+    pub async fn connect(url: &str, config: ProviderConfig) -> Result<Self> {
+        if url.starts_with("ws://") || url.starts_with("wss://") {
+            Ok(Self::Ws(WsTransport::connect(url, config).await?))
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            Ok(Self::Http(HttpTransport::new(url, config)?))
+        } else {
+            Err(ProviderError::RequestError(format!("Unsupported RPC URL: {}", url)))
+        }
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        match self {
+            Self::Http(transport) => transport.request(method, params).await,
+            Self::Ws(transport) => transport.request(method, params).await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct HttpTransport {
+    client: Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, config: ProviderConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| ProviderError::RequestError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&JsonRpcRequest::new(id, method, params))
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    ProviderError::TimeoutError(e.to_string())
+                } else {
+                    ProviderError::RequestError(e.to_string())
+                }
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::RequestError(format!("HTTP {}: {}", status, body)));
+        }
+
+        response
+            .json::<JsonRpcResponse>()
+            .await
+            .map_err(|e| ProviderError::ResponseError(e.to_string()))?
+            .into_result()
+    }
+}
+
+/// Requests waiting for a response by id, `None` once the connection is closed.
+type PendingRequests = Arc<Mutex<Option<HashMap<u64, oneshot::Sender<Result<Value>>>>>>;
+
+fn connection_closed() -> ProviderError {
+    ProviderError::RequestError("WebSocket connection closed".into())
+}
+
+/// JSON-RPC over a WebSocket, requests are multiplexed on one connection and matched by id.
+#[derive(Debug)]
+pub struct WsTransport {
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    timeout: Duration,
+}
+
+impl WsTransport {
+    pub async fn connect(url: &str, config: ProviderConfig) -> Result<Self> {
+        let (stream, _) = tokio::time::timeout(config.timeout, tokio_tungstenite::connect_async(url))
+            .await
+            .map_err(|_| ProviderError::TimeoutError(format!("connecting to {}", url)))?
+            .map_err(|e| ProviderError::RequestError(e.to_string()))?;
+        let (mut sink, mut source) = stream.split();
+
+        let (outgoing, mut messages) = mpsc::unbounded_channel::<Message>();
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+            // the transport was dropped
+            let _ = sink.close().await;
+        });
+
+        let pending = PendingRequests::new(Mutex::new(Some(HashMap::new())));
+        let responses = pending.clone();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = source.next().await {
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+                let Ok(response) = serde_json::from_str::<JsonRpcResponse>(&text) else {
+                    continue;
+                };
+                let sender = response
+                    .id
+                    .as_u64()
+                    .and_then(|id| responses.lock().unwrap().as_mut()?.remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(response.into_result());
+                }
+            }
+
+            // fail what's in flight now rather than at the timeout, and anything sent later
+            let pending = responses.lock().unwrap().take().unwrap_or_default();
+            for (_, sender) in pending {
+                let _ = sender.send(Err(connection_closed()));
+            }
+        });
+
+        Ok(Self {
+            outgoing,
+            pending,
+            next_id: AtomicU64::new(1),
+            timeout: config.timeout,
+        })
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = serde_json::to_string(&JsonRpcRequest::new(id, method, params))
+            .map_err(|e| ProviderError::RequestError(e.to_string()))?;
+
+        let (sender, receiver) = oneshot::channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(id, sender),
+            None => return Err(connection_closed()),
+        };
+        if self.outgoing.send(Message::Text(request)).is_err() {
+            self.remove_pending(id);
+            return Err(connection_closed());
+        }
+
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(connection_closed()),
+            Err(_) => {
+                self.remove_pending(id);
+                Err(ProviderError::TimeoutError(format!("{} timed out", method)))
+            }
+        }
+    }
+
+    fn remove_pending(&self, id: u64) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_ws_close_fails_requests() {
+        // a node that closes the connection on the first request instead of answering
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = socket.next().await {
+                if let Message::Text(_) = message {
+                    let _ = socket.close(None).await;
+                }
+            }
+        });
+
+        let config = ProviderConfig {
+            timeout: Duration::from_secs(30),
+        };
+        let transport = WsTransport::connect(&url, config).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), transport.request("eth_chainId", json!([])))
+            .await
+            .expect("pending request failed at the close, not the timeout");
+        assert!(matches!(closed, Err(ProviderError::RequestError(_))));
+
+        let after = tokio::time::timeout(Duration::from_secs(5), transport.request("eth_chainId", json!([])))
+            .await
+            .expect("request after the close failed immediately");
+        assert!(matches!(after, Err(ProviderError::RequestError(_))));
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy_consensus::Transaction as _;
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, B256};
+use alloy_rpc_types::TransactionReceipt;
+use futures_util::{stream, Stream};
+
+use crate::abis::decode::DecodedLog;
+use crate::abis::registry::AbiRegistry;
+use crate::abis::signatures::SignatureDb;
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+use crate::signer::sign::SignedTransaction;
+
+type Result<T> = std::result::Result<T, ProviderError>;
+
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// Blocks on top of and including the inclusion block before a transaction is confirmed.
+    pub confirmations: u64,
+    pub poll_interval: Duration,
+    /// Polls a transaction may be unknown to the node before it's reported dropped.
+    pub drop_after: u32,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            confirmations: 1,
+            poll_interval: Duration::from_secs(2),
+            drop_after: 30,
+        }
+    }
+}
+
+/// A confirmed transaction's receipt with its logs decoded where the event is known.
+#[derive(Debug, Clone)]
+pub struct ConfirmedTransaction {
+    pub receipt: TransactionReceipt,
+    pub confirmations: u64,
+    /// One entry per receipt log, in order.
+    pub logs: Vec<Option<DecodedLog>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TxStatus {
+    /// Known to the node and not in a block yet.
+    Pending,
+    Included { block_number: u64, block_hash: B256 },
+    /// Reached the confirmation depth, the last event of the transaction.
+    Confirmed(Box<ConfirmedTransaction>),
+    /// The block that included the transaction left the canonical chain.
+    Reorged { block_number: u64, block_hash: B256 },
+    /// Unknown to the node while its nonce is still unused, the last event of the transaction.
+    Dropped,
+    /// Another transaction with the same nonce was mined, the last event of the transaction.
+    Replaced { nonce: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct TxEvent {
+    pub hash: B256,
+    pub status: TxStatus,
+}
+
+/// A transaction to watch. Replacements are only detected when the sender and nonce
+/// are known, from the signed transaction or from the node while it was pending.
+#[derive(Debug, Clone)]
+pub struct WatchedTx {
+    pub hash: B256,
+    pub sender: Option<(Address, u64)>,
+}
+
+impl From<B256> for WatchedTx {
+    fn from(hash: B256) -> Self {
+        Self { hash, sender: None }
+    }
+}
+
+impl From<&SignedTransaction> for WatchedTx {
+    fn from(tx: &SignedTransaction) -> Self {
+        Self {
+            hash: tx.hash,
+            sender: Some((tx.from, tx.envelope.nonce())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Seen {
+    Unknown,
+    Pending,
+    Included(u64, B256),
+}
+
+struct Tracked {
+    tx: WatchedTx,
+    seen: Seen,
+    missing_polls: u32,
+    done: bool,
+}
+
+/// Polls receipts of broadcast transactions and reports their status changes.
+#[derive(Debug, Clone)]
+pub struct ReceiptWatcher {
+    provider: Arc<EvmProvider>,
+    config: WatchConfig,
+    registry: Option<Arc<AbiRegistry>>,
+}
+
+impl ReceiptWatcher {
+    pub fn new(provider: Arc<EvmProvider>) -> Self {
+        Self {
+            provider,
+            config: WatchConfig::default(),
+            registry: None,
+        }
+    }
+
+    pub fn with_config(mut self, config: WatchConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Decodes receipt logs with `registry` instead of [`AbiRegistry::known`], events it
+    /// doesn't know are still guessed from [`SignatureDb::embedded`].
+    pub fn with_registry(mut self, registry: Arc<AbiRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Streams status changes of `txs` until every one is confirmed, dropped or replaced.
+    ///
+    /// The first poll happens right away, then every `poll_interval`. A failed poll yields
+    /// the error and is retried on the next interval.
+    pub fn watch<T: Into<WatchedTx>>(
+        &self,
+        txs: impl IntoIterator<Item = T>,
+    ) -> impl Stream<Item = Result<TxEvent>> + Send + 'static {
+        let tracked: Vec<Tracked> = txs
+            .into_iter()
+            .map(|tx| Tracked {
+                tx: tx.into(),
+                seen: Seen::Unknown,
+                missing_polls: 0,
+                done: false,
+            })
+            .collect();
+        let state = (self.clone(), tracked, VecDeque::new(), true);
+
+        stream::unfold(state, |(watcher, mut tracked, mut queue, first)| async move {
+            let mut first = first;
+            loop {
+                if let Some(event) = queue.pop_front() {
+                    return Some((event, (watcher, tracked, queue, first)));
+                }
+                if tracked.iter().all(|tx| tx.done) {
+                    return None;
+                }
+                if !first {
+                    tokio::time::sleep(watcher.config.poll_interval).await;
+                }
+                first = false;
+                if let Err(e) = watcher.poll(&mut tracked, &mut queue).await {
+                    queue.push_back(Err(e));
+                }
+            }
+        })
+    }
+
+    async fn poll(&self, tracked: &mut [Tracked], events: &mut VecDeque<Result<TxEvent>>) -> Result<()> {
+        let head = self.provider.get_block_number().await?;
+
+        for entry in tracked.iter_mut().filter(|tx| !tx.done) {
+            let hash = entry.tx.hash;
+            let mut emit = |status| events.push_back(Ok(TxEvent { hash, status }));
+            let receipt = self.provider.get_transaction_receipt(hash).await?;
+            let included = receipt
+                .as_ref()
+                .and_then(|receipt| Some((receipt.block_number?, receipt.block_hash?)));
+
+            // a receipt from another block, or none at all, means the old block was reorged out
+            if let Seen::Included(block_number, block_hash) = entry.seen {
+                if included != Some((block_number, block_hash)) {
+                    emit(TxStatus::Reorged { block_number, block_hash });
+                    entry.seen = Seen::Unknown;
+                }
+            }
+
+            if let (Some(receipt), Some((block_number, block_hash))) = (receipt, included) {
+                entry.missing_polls = 0;
+                if entry.seen != Seen::Included(block_number, block_hash) {
+                    emit(TxStatus::Included { block_number, block_hash });
+                    entry.seen = Seen::Included(block_number, block_hash);
+                }
+                let confirmations = (head + 1).saturating_sub(block_number);
+                if confirmations >= self.config.confirmations {
+                    let logs = self.decode_logs(&receipt);
+                    emit(TxStatus::Confirmed(Box::new(ConfirmedTransaction {
+                        receipt,
+                        confirmations,
+                        logs,
+                    })));
+                    entry.done = true;
+                }
+                continue;
+            }
+
+            if let Some(tx) = self.provider.get_transaction_by_hash(hash).await? {
+                entry.missing_polls = 0;
+                entry.tx.sender.get_or_insert((tx.from, tx.inner.nonce()));
+                if entry.seen != Seen::Pending {
+                    emit(TxStatus::Pending);
+                    entry.seen = Seen::Pending;
+                }
+                continue;
+            }
+
+            if let Some((from, nonce)) = entry.tx.sender {
+                let mined = self.provider.get_transaction_count(from, BlockId::latest()).await?;
+                if mined > nonce {
+                    emit(TxStatus::Replaced { nonce });
+                    entry.done = true;
+                    continue;
+                }
+            }
+            entry.missing_polls += 1;
+            if entry.missing_polls >= self.config.drop_after {
+                emit(TxStatus::Dropped);
+                entry.done = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_logs(&self, receipt: &TransactionReceipt) -> Vec<Option<DecodedLog>> {
+        let registry = self.registry.as_deref().unwrap_or_else(|| AbiRegistry::known());
+        receipt
+            .inner
+            .logs()
+            .iter()
+            .map(|log| {
+                registry
+                    .decode_log(&log.inner.data)
+                    .or_else(|| SignatureDb::embedded().guess_log(&log.inner.data).into_iter().next())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use alloy_primitives::{address, b256, U256};
+    use alloy_sol_types::SolValue;
+    use futures_util::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const HASH: B256 = b256!("5e2b7ed5b2d6a2a3b7f0e5a6ff1c4b69a2a9b4f1ae8d3d9b8f7c6e5d4c3b2a19");
+    const TRANSFER: B256 = b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+    fn config() -> WatchConfig {
+        WatchConfig {
+            confirmations: 2,
+            poll_interval: Duration::from_millis(5),
+            drop_after: 2,
+        }
+    }
+
+    fn receipt_json(block_number: u64, block_hash: B256) -> Value {
+        let from = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+        let token = address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83");
+        json!({
+            "transactionHash": HASH,
+            "transactionIndex": "0x0",
+            "blockHash": block_hash,
+            "blockNumber": format!("0x{:x}", block_number),
+            "from": from,
+            "to": token,
+            "cumulativeGasUsed": "0xb411",
+            "gasUsed": "0xb411",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": [{
+                "address": token,
+                "topics": [TRANSFER, B256::left_padding_from(from.as_slice()), B256::left_padding_from(token.as_slice())],
+                "data": format!("0x{}", alloy_primitives::hex::encode(U256::from(1000).abi_encode())),
+                "blockHash": block_hash,
+                "blockNumber": format!("0x{:x}", block_number),
+                "transactionHash": HASH,
+                "transactionIndex": "0x0",
+                "logIndex": "0x0",
+                "removed": false
+            }],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "type": "0x2",
+            "status": "0x1"
+        })
+    }
+
+    fn pending_json() -> Value {
+        json!({
+            "type": "0x2",
+            "chainId": "0x1",
+            "nonce": "0x5",
+            "gas": "0xb411",
+            "maxFeePerGas": "0x77359400",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "to": "0xec53bf9167f50cdeb3ae105f56099aaab9061f83",
+            "value": "0x0",
+            "accessList": [],
+            "input": "0x",
+            "r": "0x1",
+            "s": "0x1",
+            "yParity": "0x0",
+            "v": "0x0",
+            "hash": HASH,
+            "blockHash": null,
+            "blockNumber": null,
+            "transactionIndex": null,
+            "from": "0x163a5ec5e9c32238d075e2d829fe9fa87451e3b7"
+        })
+    }
+
+    #[tokio::test]
+    async fn test_watch_reorg_and_confirm() {
+        let head = Arc::new(AtomicU64::new(0));
+        let node_head = head.clone();
+        let (first, second) = (B256::repeat_byte(0xa1), B256::repeat_byte(0xb2));
+
+        // pending at head 1, mined in block 2, reorged out at head 3, mined again in block 4
+        let node = MockNode::start(move |method, _| {
+            let head = node_head.load(Ordering::SeqCst);
+            match method {
+                "eth_blockNumber" => Ok(json!(format!("0x{:x}", node_head.fetch_add(1, Ordering::SeqCst) + 1))),
+                "eth_getTransactionReceipt" if head == 2 => Ok(receipt_json(2, first)),
+                "eth_getTransactionReceipt" if head >= 4 => Ok(receipt_json(4, second)),
+                "eth_getTransactionReceipt" => Ok(Value::Null),
+                "eth_getTransactionByHash" => Ok(pending_json()),
+                _ => Err((-32601, "method not found".into())),
+            }
+        })
+        .await;
+        let provider = Arc::new(EvmProvider::connect(node.http_url()).await.unwrap());
+        let watcher = ReceiptWatcher::new(provider).with_config(config());
+
+        let events: Vec<TxEvent> = watcher.watch([HASH]).map(|event| event.unwrap()).collect().await;
+        let statuses: Vec<String> = events
+            .iter()
+            .map(|event| match &event.status {
+                TxStatus::Included { block_number, .. } => format!("included {}", block_number),
+                TxStatus::Reorged { block_number, .. } => format!("reorged {}", block_number),
+                TxStatus::Confirmed(confirmed) => format!("confirmed {}", confirmed.confirmations),
+                status => format!("{:?}", status).to_lowercase(),
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            ["pending", "included 2", "reorged 2", "pending", "included 4", "confirmed 2"]
+        );
+        assert!(events.iter().all(|event| event.hash == HASH));
+
+        let TxStatus::Confirmed(confirmed) = &events[5].status else {
+            panic!("expected a confirmation");
+        };
+        assert_eq!(confirmed.receipt.block_hash, Some(second));
+        let transfer = confirmed.logs[0].as_ref().unwrap();
+        assert_eq!(transfer.event, "Transfer");
+        assert_eq!(transfer.params[2].value, json!("1000"));
+        assert_eq!(head.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_watch_dropped_and_replaced() {
+        let replaced = B256::repeat_byte(0x01);
+        let node = MockNode::start(|method, _| match method {
+            "eth_blockNumber" => Ok(json!("0x10")),
+            "eth_getTransactionReceipt" | "eth_getTransactionByHash" => Ok(Value::Null),
+            "eth_getTransactionCount" => Ok(json!("0x6")),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await;
+        let provider = Arc::new(EvmProvider::connect(node.http_url()).await.unwrap());
+        let watcher = ReceiptWatcher::new(provider).with_config(config());
+
+        let txs = vec![
+            WatchedTx {
+                hash: replaced,
+                sender: Some((address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7"), 5)),
+            },
+            WatchedTx::from(HASH),
+        ];
+        let events: Vec<TxEvent> = watcher.watch(txs).map(|event| event.unwrap()).collect().await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].hash, replaced);
+        assert!(matches!(events[0].status, TxStatus::Replaced { nonce: 5 }));
+        assert_eq!(events[1].hash, HASH);
+        assert!(matches!(events[1].status, TxStatus::Dropped));
+        // dropped after `drop_after` polls
+        assert_eq!(node.methods().iter().filter(|m| *m == "eth_blockNumber").count(), 2);
+    }
+}
@@ -1,6 +1,4 @@
 use alloy_signer_local::{coins_bip39::English, MnemonicBuilder, LocalSigner, LocalSignerError, PrivateKeySigner};
-use alloy_signer::Signer;
-use alloy_primitives::hex;
 use k256::ecdsa::SigningKey;
 use thiserror::Error;
 
@@ -50,11 +48,9 @@ impl EvmAccount {
         private_key_hex: &str
     ) -> Result<Self, EvmAccountError> {
         // if has 0x prefix, remove it
-        let private_key_hex = if private_key_hex.starts_with("0x") {
-            &private_key_hex[2..]
-        } else {
-            private_key_hex
-        };
+        let private_key_hex = private_key_hex
+            .strip_prefix("0x")
+            .unwrap_or(private_key_hex);
 
         let signer: PrivateKeySigner = private_key_hex.parse().unwrap();
         
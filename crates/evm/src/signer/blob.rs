@@ -0,0 +1,160 @@
+use alloy_consensus::TxEip4844WithSidecar;
+use alloy_eips::eip4844::{
+    builder::{SidecarBuilder, SidecarCoder, SimpleCoder},
+    env_settings::EnvKzgSettings,
+    Blob, BlobTransactionSidecar, BYTES_PER_BLOB, DATA_GAS_PER_BLOB, MAX_DATA_GAS_PER_BLOCK,
+};
+use alloy_primitives::B256;
+use thiserror::Error;
+
+/// Maximum number of blobs a single transaction may carry (Cancun).
+pub const MAX_BLOBS_PER_TX: usize = (MAX_DATA_GAS_PER_BLOCK / DATA_GAS_PER_BLOB) as usize;
+
+#[derive(Error, Debug)]
+pub enum BlobError {
+    #[error("No data to pack into blobs")]
+    EmptyData,
+    #[error("Too many blobs: {0}, at most {MAX_BLOBS_PER_TX} are allowed per transaction")]
+    TooManyBlobs(usize),
+    #[error("KZG error: {0}")]
+    KzgError(String),
+    #[error("Blob validation error: {0}")]
+    ValidationError(String),
+}
+
+/// Packs arbitrary data into EIP-4844 blobs and commits to them.
+///
+/// Data is packed with the simple coder (31 usable bytes per field element,
+/// length prefixed), so it can be recovered with [`BlobBuilder::decode`].
+/// Commitments and proofs are computed with the mainnet trusted setup that
+/// ships with the crate, no network access is needed.
+pub struct BlobBuilder {
+    inner: SidecarBuilder<SimpleCoder>,
+    settings: EnvKzgSettings,
+}
+
+impl Default for BlobBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlobBuilder {
+    pub fn new() -> Self {
+        Self {
+            inner: SidecarBuilder::new(),
+            settings: EnvKzgSettings::Default,
+        }
+    }
+
+    pub fn from_data(data: &[u8]) -> Self {
+        Self {
+            inner: SidecarBuilder::from_slice(data),
+            settings: EnvKzgSettings::Default,
+        }
+    }
+
+    /// Uses a custom trusted setup instead of the bundled mainnet one.
+    pub fn with_settings(mut self, settings: EnvKzgSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Appends a piece of data, each piece is decoded back separately.
+    pub fn ingest(&mut self, data: &[u8]) -> &mut Self {
+        self.inner.ingest(data);
+        self
+    }
+
+    /// Number of blobs currently needed to hold the ingested data.
+    pub fn blob_count(&self) -> usize {
+        self.inner.len().div_ceil(BYTES_PER_BLOB)
+    }
+
+    /// Computes KZG commitments and proofs for the packed blobs.
+    pub fn build(self) -> Result<BlobTransactionSidecar, BlobError> {
+        match self.blob_count() {
+            0 => return Err(BlobError::EmptyData),
+            n if n > MAX_BLOBS_PER_TX => return Err(BlobError::TooManyBlobs(n)),
+            _ => {}
+        }
+
+        self.inner
+            .build_with_settings(self.settings.get())
+            .map_err(|e| BlobError::KzgError(e.to_string()))
+    }
+
+    /// Recovers the data pieces packed by [`BlobBuilder::ingest`].
+    pub fn decode(blobs: &[Blob]) -> Option<Vec<Vec<u8>>> {
+        SimpleCoder::default().decode_all(blobs)
+    }
+}
+
+/// Versioned hashes (`0x01 || sha256(commitment)[1..]`) of every blob in the sidecar.
+pub fn versioned_hashes(sidecar: &BlobTransactionSidecar) -> Vec<B256> {
+    sidecar.versioned_hashes().collect()
+}
+
+/// Checks the sidecar proofs and that it matches the hashes committed in the transaction.
+pub fn validate_sidecar(tx: &TxEip4844WithSidecar) -> Result<(), BlobError> {
+    tx.validate_blob(EnvKzgSettings::Default.get())
+        .map_err(|e| BlobError::ValidationError(e.to_string()))
+}
+
+/// Size in bytes of the raw blob payload carried by the sidecar.
+pub fn sidecar_data_size(sidecar: &BlobTransactionSidecar) -> usize {
+    sidecar.blobs.len() * BYTES_PER_BLOB
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_decode() {
+        let mut builder = BlobBuilder::new();
+        builder.ingest(b"hello").ingest(b"blobs");
+        assert_eq!(builder.blob_count(), 1);
+
+        let sidecar = builder.build().unwrap();
+        assert_eq!(sidecar.blobs.len(), 1);
+        assert_eq!(sidecar.commitments.len(), 1);
+        assert_eq!(sidecar.proofs.len(), 1);
+
+        let decoded = BlobBuilder::decode(&sidecar.blobs).unwrap();
+        assert_eq!(decoded, vec![b"hello".to_vec(), b"blobs".to_vec()]);
+    }
+
+    #[test]
+    fn test_versioned_hashes() {
+        let sidecar = BlobBuilder::from_data(b"versioned hash").build().unwrap();
+        let hashes = versioned_hashes(&sidecar);
+
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(hashes[0][0], 0x01);
+        assert_eq!(
+            hashes[0],
+            alloy_eips::eip4844::kzg_to_versioned_hash(sidecar.commitments[0].as_slice())
+        );
+    }
+
+    #[test]
+    fn test_multiple_blobs() {
+        let data = vec![0xabu8; BYTES_PER_BLOB + 1];
+        let builder = BlobBuilder::from_data(&data);
+        assert_eq!(builder.blob_count(), 2);
+
+        let sidecar = builder.build().unwrap();
+        assert_eq!(sidecar_data_size(&sidecar), 2 * BYTES_PER_BLOB);
+        assert_eq!(BlobBuilder::decode(&sidecar.blobs).unwrap(), vec![data]);
+    }
+
+    #[test]
+    fn test_empty_and_oversized_data() {
+        assert!(matches!(BlobBuilder::new().build(), Err(BlobError::EmptyData)));
+
+        let data = vec![1u8; BYTES_PER_BLOB * MAX_BLOBS_PER_TX];
+        let result = BlobBuilder::from_data(&data).build();
+        assert!(matches!(result, Err(BlobError::TooManyBlobs(n)) if n > MAX_BLOBS_PER_TX));
+    }
+}
@@ -1,3 +1,4 @@
 pub mod account;
+pub mod blob;
 pub mod sign;
 pub mod utils;
\ No newline at end of file
@@ -1,12 +1,16 @@
 use std::str::FromStr;
 
 use crate::signer::account::EvmAccount;
-use alloy_consensus::{TxEip1559, TxEip2930, TxEip4844, TxEip7702, TxLegacy};
+use crate::signer::blob;
+use alloy_consensus::{
+    SignableTransaction, TxEip1559, TxEip2930, TxEip4844, TxEip4844Variant, TxEip4844WithSidecar,
+    TxEip7702, TxEnvelope, TxLegacy, TxType, TypedTransaction,
+};
 use alloy_dyn_abi::eip712::TypedData;
-use alloy_network::{EthereumWallet, TransactionBuilder};
-use alloy_primitives::Address;
-use alloy_primitives::{hex, PrimitiveSignature, TxKind};
-use alloy_rlp::Encodable;
+use alloy_eips::eip2718::Encodable2718;
+use alloy_network::{EthereumWallet, TransactionBuilder, TxSignerSync};
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_primitives::{hex, PrimitiveSignature};
 use alloy_rpc_types::TransactionRequest;
 use alloy_signer::SignerSync;
 use alloy_sol_types::SolStruct;
@@ -19,6 +23,7 @@ pub enum Transaction<'a> {
     Eip1559(&'a mut TxEip1559),
     Eip2930(&'a mut TxEip2930),
     Eip4844(&'a mut TxEip4844),
+    Eip4844WithSidecar(&'a mut TxEip4844WithSidecar),
     Eip7702(&'a mut TxEip7702),
 }
 
@@ -28,6 +33,56 @@ pub enum EvmSignerError {
     SignatureError(String),
     #[error("Invalid address format: {0}")]
     InvalidAddress(String),
+    #[error("Blob error: {0}")]
+    BlobError(#[from] blob::BlobError),
+}
+
+/// A signed transaction with everything needed to broadcast and track it.
+#[derive(Debug, Clone)]
+pub struct SignedTransaction {
+    /// EIP-2718 encoding of the envelope, the payload of `eth_sendRawTransaction`. An EIP-4844
+    /// transaction signed without its sidecar is in the consensus form, which nodes won't
+    /// accept for broadcast; sign those with the blobs to get [`SignedBlobTransaction`].
+    pub raw: Bytes,
+    pub hash: B256,
+    pub from: Address,
+    pub signature: PrimitiveSignature,
+    pub envelope: TxEnvelope,
+}
+
+impl SignedTransaction {
+    pub fn from_envelope(envelope: TxEnvelope) -> Result<Self, EvmSignerError> {
+        let from = envelope
+            .recover_signer()
+            .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?;
+
+        Ok(Self {
+            raw: envelope.encoded_2718().into(),
+            hash: *envelope.tx_hash(),
+            from,
+            signature: *envelope.signature(),
+            envelope,
+        })
+    }
+
+    /// `0x` prefixed hex of [`SignedTransaction::raw`].
+    pub fn raw_hex(&self) -> String {
+        format!("0x{}", hex::encode(&self.raw))
+    }
+
+    pub fn tx_type(&self) -> TxType {
+        self.envelope.tx_type()
+    }
+}
+
+/// A signed EIP-4844 transaction in both of its encodings.
+#[derive(Debug, Clone)]
+pub struct SignedBlobTransaction {
+    /// Network form, `0x03 || rlp([tx_payload_body, blobs, commitments, proofs])`.
+    pub transaction: SignedTransaction,
+    /// `0x03 || rlp(tx_payload_body)`, the form included in blocks.
+    pub consensus_encoding: Bytes,
+    pub blob_versioned_hashes: Vec<B256>,
 }
 
 pub struct EvmSigner<'a> {
@@ -50,11 +105,20 @@ impl<'a> EvmSigner<'a> {
         Self { account }
     }
 
+    pub fn address(&self) -> Address {
+        self.account.signer.address()
+    }
+
     pub fn sign_eip191(&self, message: String) -> Result<String, EvmSignerError> {
+        self.sign_eip191_bytes(message.as_bytes())
+    }
+
+    /// Signs raw bytes with the EIP-191 prefix, as `eth_sign` does for a 32 byte digest.
+    pub fn sign_eip191_bytes(&self, message: &[u8]) -> Result<String, EvmSignerError> {
         let signature = self
             .account
             .signer
-            .sign_message_sync(message.as_bytes())
+            .sign_message_sync(message)
             .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?;
         Ok(format!("0x{}", hex::encode(signature.as_bytes())))
     }
@@ -64,12 +128,8 @@ impl<'a> EvmSigner<'a> {
         signature: &str,
     ) -> Result<Address, EvmSignerError> {
         // if signature starts with 0x, remove the 0x prefix
-        let signature_bytes = if signature.starts_with("0x") {
-            hex::decode(&signature[2..])
-        } else {
-            hex::decode(signature)
-        }
-        .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?;
+        let signature_bytes = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+            .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?;
 
         let signature = PrimitiveSignature::try_from(signature_bytes.as_slice())
             .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?;
@@ -101,6 +161,16 @@ impl<'a> EvmSigner<'a> {
         Ok(format!("0x{}", hex::encode(signature.as_bytes())))
     }
 
+    /// Signs EIP-712 typed data given as JSON, e.g. as returned by an API.
+    pub fn sign_typed_data(&self, typed_data: &TypedData) -> Result<String, EvmSignerError> {
+        let signature = self
+            .account
+            .signer
+            .sign_dynamic_typed_data_sync(typed_data)
+            .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?;
+        Ok(format!("0x{}", hex::encode(signature.as_bytes())))
+    }
+
     pub fn recover_eip712_address<T: SolStruct + Serialize>(
         domain: alloy_dyn_abi::Eip712Domain,
         data: &T,
@@ -139,69 +209,126 @@ impl<'a> EvmSigner<'a> {
             .map_err(|_| EvmSignerError::InvalidAddress("Invalid address format".into()))
     }
 
-    pub async fn sign_transaction(&self, tx: Transaction<'_>) -> Result<String, EvmSignerError> {
+    pub async fn sign_transaction(
+        &self,
+        tx: Transaction<'_>,
+    ) -> Result<SignedTransaction, EvmSignerError> {
         let signer = self.account.signer.clone();
         let wallet = EthereumWallet::from(signer);
-        match tx {
-            Transaction::Legacy(tx) => {
-                let to_address = match tx.to {
-                    TxKind::Call(addr) => addr,
-                    TxKind::Create => Address::ZERO,
-                };
-
-                let tx_envelope = TransactionRequest::default()
-                    .with_to(to_address)
-                    .with_nonce(tx.nonce)
-                    .with_chain_id(tx.chain_id.unwrap_or(1))
-                    .with_value(tx.value)
-                    .with_gas_limit(tx.gas_limit)
-                    .with_gas_price(tx.gas_price)
-                    .with_input(tx.input.clone())
-                    .build(&wallet)
-                    .await
-                    .unwrap();
+        let tx_envelope = match tx {
+            Transaction::Legacy(tx) => TransactionRequest::default()
+                .with_kind(tx.to)
+                .with_nonce(tx.nonce)
+                .with_chain_id(tx.chain_id.unwrap_or(1))
+                .with_value(tx.value)
+                .with_gas_limit(tx.gas_limit)
+                .with_gas_price(tx.gas_price)
+                .with_input(tx.input.clone())
+                .build(&wallet)
+                .await
+                .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?,
+            Transaction::Eip1559(tx) => TransactionRequest::default()
+                .with_kind(tx.to)
+                .with_nonce(tx.nonce)
+                .with_chain_id(tx.chain_id)
+                .with_value(tx.value)
+                .with_gas_limit(tx.gas_limit)
+                .with_max_priority_fee_per_gas(tx.max_priority_fee_per_gas)
+                .with_max_fee_per_gas(tx.max_fee_per_gas)
+                .with_input(tx.input.clone())
+                .with_access_list(tx.access_list.clone())
+                .build(&wallet)
+                .await
+                .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?,
+            Transaction::Eip2930(tx) => TransactionRequest::default()
+                .with_kind(tx.to)
+                .with_nonce(tx.nonce)
+                .with_chain_id(tx.chain_id)
+                .with_value(tx.value)
+                .with_gas_limit(tx.gas_limit)
+                .with_gas_price(tx.gas_price)
+                .with_input(tx.input.clone())
+                .with_access_list(tx.access_list.clone())
+                .build(&wallet)
+                .await
+                .map_err(|e| EvmSignerError::SignatureError(e.to_string()))?,
+            Transaction::Eip4844(tx) => {
+                let signature = self.sign_hash_of(tx)?;
+                TxEnvelope::from(tx.clone().into_signed(signature))
+            }
+            Transaction::Eip4844WithSidecar(tx) => {
+                return Ok(self.sign_blob_transaction(tx)?.transaction);
+            }
+            Transaction::Eip7702(tx) => {
+                let signature = self.sign_hash_of(tx)?;
+                TxEnvelope::from(tx.clone().into_signed(signature))
+            }
+        };
 
-                let mut raw_data = Vec::new();
-                tx_envelope.encode(&mut raw_data);
-                Ok(format!("0x{}", hex::encode(raw_data)))
+        SignedTransaction::from_envelope(tx_envelope)
+    }
+
+    /// Same as [`EvmSigner::sign_transaction`] for an owned transaction of any type.
+    pub async fn sign_typed_transaction(
+        &self,
+        tx: TypedTransaction,
+    ) -> Result<SignedTransaction, EvmSignerError> {
+        match tx {
+            TypedTransaction::Legacy(mut tx) => self.sign_transaction(Transaction::Legacy(&mut tx)).await,
+            TypedTransaction::Eip2930(mut tx) => self.sign_transaction(Transaction::Eip2930(&mut tx)).await,
+            TypedTransaction::Eip1559(mut tx) => self.sign_transaction(Transaction::Eip1559(&mut tx)).await,
+            TypedTransaction::Eip4844(TxEip4844Variant::TxEip4844(mut tx)) => {
+                self.sign_transaction(Transaction::Eip4844(&mut tx)).await
             }
-            Transaction::Eip1559(tx) => {
-                let to_address = match tx.to {
-                    TxKind::Call(addr) => addr,
-                    TxKind::Create => Address::ZERO,
-                };
-
-                let tx_envelope = TransactionRequest::default()
-                    .with_to(to_address)
-                    .with_nonce(tx.nonce)
-                    .with_chain_id(tx.chain_id)
-                    .with_value(tx.value)
-                    .with_gas_limit(tx.gas_limit)
-                    .with_max_priority_fee_per_gas(tx.max_priority_fee_per_gas)
-                    .with_max_fee_per_gas(tx.max_fee_per_gas)
-                    .with_input(tx.input.clone())
-                    .with_access_list(tx.access_list.clone()).build(&wallet).await.unwrap();
-
-                let mut raw_data = Vec::new();
-                tx_envelope.encode(&mut raw_data);
-                // remove the first two bytes, rlp encoded length and type flag
-                if raw_data.len() > 2 {
-                    raw_data = raw_data[2..].to_vec();
-                }
-                Ok(format!("0x{}", hex::encode(raw_data)))
+            TypedTransaction::Eip4844(TxEip4844Variant::TxEip4844WithSidecar(mut tx)) => {
+                self.sign_transaction(Transaction::Eip4844WithSidecar(&mut tx))
+                    .await
             }
-            _ => Err(EvmSignerError::SignatureError(
-                "Unsupported transaction type".into(),
-            )),
+            TypedTransaction::Eip7702(mut tx) => self.sign_transaction(Transaction::Eip7702(&mut tx)).await,
         }
     }
+
+    /// Signs a blob transaction, filling `blob_versioned_hashes` from the sidecar
+    /// when empty, and returns both the network and consensus encodings.
+    pub fn sign_blob_transaction(
+        &self,
+        tx: &mut TxEip4844WithSidecar,
+    ) -> Result<SignedBlobTransaction, EvmSignerError> {
+        if tx.tx.blob_versioned_hashes.is_empty() {
+            tx.tx.blob_versioned_hashes = blob::versioned_hashes(&tx.sidecar);
+        }
+        blob::validate_sidecar(tx)?;
+
+        let signature = self.sign_hash_of(&mut tx.tx)?;
+        let consensus = TxEnvelope::from(tx.tx.clone().into_signed(signature));
+
+        Ok(SignedBlobTransaction {
+            transaction: SignedTransaction::from_envelope(TxEnvelope::from(
+                tx.clone().into_signed(signature),
+            ))?,
+            consensus_encoding: consensus.encoded_2718().into(),
+            blob_versioned_hashes: tx.tx.blob_versioned_hashes.clone(),
+        })
+    }
+
+    fn sign_hash_of<T: SignableTransaction<PrimitiveSignature>>(
+        &self,
+        tx: &mut T,
+    ) -> Result<PrimitiveSignature, EvmSignerError> {
+        self.account
+            .signer
+            .sign_transaction_sync(tx)
+            .map_err(|e| EvmSignerError::SignatureError(e.to_string()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use alloy_primitives::{Address, Bytes, U256};
+    use crate::abis::erc::IERC20::approveCall;
+    use alloy_eips::eip2718::Decodable2718;
+    use alloy_primitives::{Address, Bytes, TxKind, U256};
     use alloy_sol_types::{sol, SolCall};
     use serde::Serialize;
 
@@ -241,10 +368,6 @@ mod tests {
         }
     }
 
-    sol! {
-        function approve(address spender, uint256 amount) external returns (bool);
-    }
-
     #[test]
     fn test_sign_and_recover() {
         let account = EvmAccount::from_private_key_hex(
@@ -434,13 +557,16 @@ mod tests {
             chain_id: Some(1),
         };
 
-        let raw_tx = signer
+        let signed_tx = signer
             .sign_transaction(Transaction::Legacy(&mut legacy_tx))
             .await
             .unwrap();
 
-        println!("Signed approve transaction: {}", raw_tx);
-        assert!(raw_tx.starts_with("0x"));
+        println!("Signed approve transaction: {}", signed_tx.raw_hex());
+        assert!(signed_tx.raw_hex().starts_with("0x"));
+        assert_eq!(signed_tx.tx_type(), TxType::Legacy);
+        assert_eq!(signed_tx.from, account.signer.address());
+        assert_eq!(signed_tx.hash, alloy_primitives::keccak256(&signed_tx.raw));
     }
 
     #[tokio::test]
@@ -461,7 +587,7 @@ mod tests {
         let mut eip1559_tx: TxEip1559 = TxEip1559 {
             nonce: 1u64,
             max_fee_per_gas: 13_500_000_000u128,
-            max_priority_fee_per_gas: 13_500_000_00u128,
+            max_priority_fee_per_gas: 1_350_000_000u128,
             gas_limit: 54_250u64,
             to: TxKind::Call(token_address),
             value: U256::ZERO,
@@ -470,12 +596,208 @@ mod tests {
             access_list: vec![].into(),
         };
 
-        let raw_tx = signer
+        let signed_tx = signer
             .sign_transaction(Transaction::Eip1559(&mut eip1559_tx))
             .await
             .unwrap();
 
-        println!("Signed approve transaction: {}", raw_tx);
-        assert!(raw_tx.starts_with("0x"));
+        println!("Signed approve transaction: {}", signed_tx.raw_hex());
+        assert!(signed_tx.raw_hex().starts_with("0x02"));
+        assert_eq!(signed_tx.tx_type(), TxType::Eip1559);
+        assert_eq!(signed_tx.from, account.signer.address());
+        assert_eq!(signed_tx.hash, alloy_primitives::keccak256(&signed_tx.raw));
+
+        let decoded = TxEnvelope::decode_2718(&mut signed_tx.raw.as_ref()).unwrap();
+        assert_eq!(decoded, signed_tx.envelope);
+    }
+
+    #[tokio::test]
+    async fn test_eip4844_blob_tx() {
+        let account = EvmAccount::from_private_key_hex(
+            "c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d",
+        )
+        .unwrap();
+        let signer = EvmSigner::new(&account);
+
+        let sidecar = blob::BlobBuilder::from_data(b"web3ium blob data")
+            .build()
+            .unwrap();
+        let mut blob_tx = TxEip4844WithSidecar::from_tx_and_sidecar(
+            TxEip4844 {
+                chain_id: 1,
+                nonce: 2,
+                gas_limit: 21_000,
+                max_fee_per_gas: 20_000_000_000u128,
+                max_priority_fee_per_gas: 1_000_000_000u128,
+                to: Address::from_str("0x163a5ec5e9c32238d075e2d829fe9fa87451e3b7").unwrap(),
+                max_fee_per_blob_gas: 1_000_000_000u128,
+                ..Default::default()
+            },
+            sidecar,
+        );
+
+        let signed = signer.sign_blob_transaction(&mut blob_tx).unwrap();
+        assert_eq!(signed.blob_versioned_hashes.len(), 1);
+        let network_encoding = &signed.transaction.raw;
+        assert_eq!(network_encoding[0], 0x03);
+        assert_eq!(signed.consensus_encoding[0], 0x03);
+        assert!(network_encoding.len() > signed.consensus_encoding.len());
+
+        let network = TxEnvelope::decode_2718(&mut network_encoding.as_ref()).unwrap();
+        let consensus = TxEnvelope::decode_2718(&mut signed.consensus_encoding.as_ref()).unwrap();
+        assert_eq!(*network.tx_hash(), signed.transaction.hash);
+        assert_eq!(*consensus.tx_hash(), signed.transaction.hash);
+        assert_eq!(network.recover_signer().unwrap(), account.signer.address());
+
+        let signed_tx = signer
+            .sign_transaction(Transaction::Eip4844WithSidecar(&mut blob_tx))
+            .await
+            .unwrap();
+        assert_eq!(&signed_tx.raw, network_encoding);
+
+        let signed_tx = signer
+            .sign_transaction(Transaction::Eip4844(&mut blob_tx.tx))
+            .await
+            .unwrap();
+        assert_eq!(signed_tx.raw, signed.consensus_encoding);
+        assert_eq!(signed_tx.hash, signed.transaction.hash);
+    }
+
+    #[test]
+    fn test_eip4844_mismatched_sidecar() {
+        let account = EvmAccount::from_private_key_hex(
+            "c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d",
+        )
+        .unwrap();
+        let signer = EvmSigner::new(&account);
+
+        let sidecar = blob::BlobBuilder::from_data(b"first").build().unwrap();
+        let other = blob::BlobBuilder::from_data(b"second").build().unwrap();
+        let mut blob_tx = TxEip4844WithSidecar::from_tx_and_sidecar(
+            TxEip4844 {
+                chain_id: 1,
+                blob_versioned_hashes: blob::versioned_hashes(&other),
+                ..Default::default()
+            },
+            sidecar,
+        );
+
+        let result = signer.sign_blob_transaction(&mut blob_tx);
+        assert!(matches!(result, Err(EvmSignerError::BlobError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_contract_creation_tx() {
+        let account = EvmAccount::from_private_key_hex(
+            "c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d",
+        )
+        .unwrap();
+        let signer = EvmSigner::new(&account);
+        let init_code = Bytes::from_str("0x6080604052348015600f57600080fd5b50").unwrap();
+
+        let mut legacy_tx = TxLegacy {
+            nonce: 7,
+            gas_price: 13_500_000_000u128,
+            gas_limit: 500_000,
+            to: TxKind::Create,
+            input: init_code.clone(),
+            chain_id: Some(1),
+            ..Default::default()
+        };
+        let mut eip2930_tx = TxEip2930 {
+            chain_id: 1,
+            nonce: 7,
+            gas_price: 13_500_000_000u128,
+            gas_limit: 500_000,
+            to: TxKind::Create,
+            input: init_code.clone(),
+            ..Default::default()
+        };
+        let mut eip1559_tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 7,
+            gas_limit: 500_000,
+            max_fee_per_gas: 13_500_000_000u128,
+            max_priority_fee_per_gas: 1_000_000_000u128,
+            to: TxKind::Create,
+            input: init_code.clone(),
+            ..Default::default()
+        };
+
+        let signed_txs = vec![
+            signer.sign_transaction(Transaction::Legacy(&mut legacy_tx)).await.unwrap(),
+            signer.sign_transaction(Transaction::Eip2930(&mut eip2930_tx)).await.unwrap(),
+            signer.sign_transaction(Transaction::Eip1559(&mut eip1559_tx)).await.unwrap(),
+        ];
+
+        let expected = crate::deploy::address::create_address(account.signer.address(), 7);
+        for signed_tx in signed_txs {
+            let envelope = TxEnvelope::decode_2718(&mut signed_tx.raw.as_ref()).unwrap();
+            assert_eq!(alloy_consensus::Transaction::kind(&envelope), TxKind::Create);
+            assert_eq!(alloy_consensus::Transaction::input(&envelope), &init_code);
+
+            let sender = envelope.recover_signer().unwrap();
+            assert_eq!(sender, account.signer.address());
+            assert_eq!(sender.create(alloy_consensus::Transaction::nonce(&envelope)), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_eip1559_large_calldata_tx() {
+        let account = EvmAccount::from_private_key_hex(
+            "c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d",
+        )
+        .unwrap();
+        let signer = EvmSigner::new(&account);
+
+        // long enough for a multi-byte RLP length prefix
+        let mut eip1559_tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 3,
+            gas_limit: 1_000_000,
+            max_fee_per_gas: 13_500_000_000u128,
+            max_priority_fee_per_gas: 1_000_000_000u128,
+            to: TxKind::Call(Address::repeat_byte(0x42)),
+            input: vec![0xab; 70_000].into(),
+            ..Default::default()
+        };
+
+        let signed_tx = signer
+            .sign_transaction(Transaction::Eip1559(&mut eip1559_tx))
+            .await
+            .unwrap();
+
+        assert_eq!(signed_tx.raw[0], 0x02);
+        let decoded = TxEnvelope::decode_2718(&mut signed_tx.raw.as_ref()).unwrap();
+        assert_eq!(*decoded.tx_hash(), signed_tx.hash);
+        assert_eq!(decoded.recover_signer().unwrap(), account.signer.address());
+    }
+
+    #[tokio::test]
+    async fn test_eip7702_tx() {
+        let account = EvmAccount::from_private_key_hex(
+            "c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d",
+        )
+        .unwrap();
+        let signer = EvmSigner::new(&account);
+
+        let mut eip7702_tx = TxEip7702 {
+            chain_id: 1,
+            nonce: 4,
+            gas_limit: 100_000,
+            max_fee_per_gas: 13_500_000_000u128,
+            max_priority_fee_per_gas: 1_000_000_000u128,
+            to: account.signer.address(),
+            ..Default::default()
+        };
+
+        let signed_tx = signer
+            .sign_transaction(Transaction::Eip7702(&mut eip7702_tx))
+            .await
+            .unwrap();
+
+        assert_eq!(signed_tx.tx_type(), TxType::Eip7702);
+        assert_eq!(signed_tx.raw[0], 0x04);
+        assert_eq!(signed_tx.from, account.signer.address());
     }
 }
@@ -1,40 +1,237 @@
-use alloy_consensus::TxEnvelope;
-use alloy_primitives::hex;
-use alloy_rlp::Decodable;
-
-pub fn decode_raw_tx(_tx: &str) -> Result<TxEnvelope, Box<dyn std::error::Error>> {
-    let raw_tx = hex::decode(_tx).unwrap();
-    let res = TxEnvelope::decode(&mut raw_tx.as_slice()).unwrap();
-    Ok(res)
+use alloy_consensus::{Transaction, TxEnvelope};
+use alloy_eips::eip2718::Decodable2718;
+use alloy_primitives::{hex, Address, Bytes, B256, U256};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::abis::{decode::DecodedCall, registry::AbiRegistry, signatures::SignatureDb};
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("Invalid hex: {0}")]
+    InvalidHex(String),
+    #[error("Invalid transaction encoding: {0}")]
+    InvalidTransaction(String),
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+}
+
+/// A signed transaction decoded for display.
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedTransaction {
+    pub hash: B256,
+    pub tx_type: String,
+    pub from: Address,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    pub nonce: u64,
+    pub chain_id: Option<u64>,
+    pub value: U256,
+    pub gas_limit: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_blob_gas: Option<u128>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blob_versioned_hashes: Vec<B256>,
+    pub input: Bytes,
+    /// Calldata decoded against the ABIs in [`crate::abis`].
+    pub call: Option<DecodedCall>,
+    /// Best-effort guesses from the signature database when no ABI matches, most plausible first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<DecodedCall>,
+}
+
+/// Decodes a raw signed transaction of any type, with or without `0x` prefix.
+pub fn decode_raw_tx(raw_tx: &str) -> Result<DecodedTransaction, DecodeError> {
+    decode_raw_tx_with(raw_tx, AbiRegistry::known())
+}
+
+/// Same as [`decode_raw_tx`] with calldata decoded against a custom registry.
+pub fn decode_raw_tx_with(raw_tx: &str, registry: &AbiRegistry) -> Result<DecodedTransaction, DecodeError> {
+    let envelope = decode_raw_envelope(raw_tx)?;
+    describe_envelope(&envelope, registry, SignatureDb::embedded())
+}
+
+/// Decodes a raw signed transaction into its EIP-2718 envelope.
+///
+/// Blob transactions are accepted in both network (with sidecar) and consensus form.
+pub fn decode_raw_envelope(raw_tx: &str) -> Result<TxEnvelope, DecodeError> {
+    let raw_tx = hex::decode(raw_tx).map_err(|e| DecodeError::InvalidHex(e.to_string()))?;
+    let mut buf = raw_tx.as_slice();
+    let envelope = TxEnvelope::decode_2718(&mut buf)
+        .map_err(|e| DecodeError::InvalidTransaction(e.to_string()))?;
+    if !buf.is_empty() {
+        return Err(DecodeError::InvalidTransaction(format!(
+            "{} trailing bytes",
+            buf.len()
+        )));
+    }
+    Ok(envelope)
+}
+
+/// Describes a signed envelope, calldata unknown to `registry` is guessed from `signatures`.
+pub fn describe_envelope(
+    envelope: &TxEnvelope,
+    registry: &AbiRegistry,
+    signatures: &SignatureDb,
+) -> Result<DecodedTransaction, DecodeError> {
+    let from = envelope
+        .recover_signer()
+        .map_err(|e| DecodeError::SignatureError(e.to_string()))?;
+    let is_dynamic_fee = envelope.is_dynamic_fee();
+    let call = registry.decode_call(envelope.input());
+    let candidates = match call {
+        Some(_) => vec![],
+        None => signatures.guess_call(envelope.input()),
+    };
+
+    Ok(DecodedTransaction {
+        hash: *envelope.tx_hash(),
+        tx_type: envelope.tx_type().to_string(),
+        from,
+        to: envelope.to(),
+        nonce: envelope.nonce(),
+        chain_id: envelope.chain_id(),
+        value: envelope.value(),
+        gas_limit: envelope.gas_limit(),
+        gas_price: envelope.gas_price(),
+        max_fee_per_gas: is_dynamic_fee.then(|| envelope.max_fee_per_gas()),
+        max_priority_fee_per_gas: envelope.max_priority_fee_per_gas(),
+        max_fee_per_blob_gas: envelope.max_fee_per_blob_gas(),
+        blob_versioned_hashes: envelope
+            .blob_versioned_hashes()
+            .map(<[B256]>::to_vec)
+            .unwrap_or_default(),
+        input: envelope.input().clone(),
+        call,
+        candidates,
+    })
 }
 
 #[cfg(test)]
 mod tests {
    use super::*;
+   use std::str::FromStr;
 
    #[test]
    fn test_decode_legacy_tx() {
        // Legacy transaction raw data
        let legacy_tx = "0xf8a91e85032c9797e982d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a764000025a0437a7c1077dd8fb77c434756f486346c564556e0ea65e59428643b91b7184632a070df9c281661b23f4e7547015a9382c9a8c8e23393733eb9550b6630528a4005";
-       
+
        let tx = decode_raw_tx(legacy_tx).unwrap();
-       println!("Legacy transaction decoded: {:#?}", tx);
+       println!("Legacy transaction decoded: {}", serde_json::to_string_pretty(&tx).unwrap());
+
+       assert_eq!(tx.tx_type, "Legacy");
+       assert_eq!(tx.nonce, 30);
+       assert_eq!(tx.to, Some(Address::from_str("0xec53bf9167f50cdeb3ae105f56099aaab9061f83").unwrap()));
+       assert_eq!(tx.hash, alloy_primitives::keccak256(hex::decode(legacy_tx).unwrap()));
+
+       let call = tx.call.unwrap();
+       assert_eq!(call.contract, "ERC20");
+       assert_eq!(call.function, "approve");
+       assert_eq!(call.params[1].value, serde_json::json!("1000000000000000000"));
    }
 
    #[test]
    fn test_decode_eip1559_tx() {
        // EIP-1559 transaction raw data
        let eip1559_tx = "0x02f8b001018450775d80850324a9a70082d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a7640000c001a098421643be02def45744834741859d065b20dfe814001dcc54f521626281a5e0a03fe4c9d2cb0a473865efe0ebee2cf5288aaa54dedf5093430a88ac5c167e5d90";
-       
+
        let tx = decode_raw_tx(eip1559_tx).unwrap();
-       println!("EIP-1559 transaction decoded: {:#?}", tx);
+       println!("EIP-1559 transaction decoded: {}", serde_json::to_string_pretty(&tx).unwrap());
+
+       assert_eq!(tx.tx_type, "EIP-1559");
+       assert_eq!(tx.chain_id, Some(1));
+       assert_eq!(tx.max_fee_per_gas, Some(13_500_000_000));
+       assert!(tx.gas_price.is_none());
+       assert_eq!(tx.call.unwrap().signature, "approve(address,uint256)");
    }
 
    #[test]
    fn test_decode_with_0x_prefix() {
+       let eip1559_tx = "0x02f8b001018450775d80850324a9a70082d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a7640000c001a098421643be02def45744834741859d065b20dfe814001dcc54f521626281a5e0a03fe4c9d2cb0a473865efe0ebee2cf5288aaa54dedf5093430a88ac5c167e5d90";
+
+       let tx = decode_raw_tx(eip1559_tx).unwrap();
+       assert_eq!(tx.from, Address::from_str("0x3d49c6ad083b51f18b1d93faeb78123e592f9061").unwrap());
+       assert_eq!(tx.to, Some(Address::from_str("0xec53bf9167f50cdeb3ae105f56099aaab9061f83").unwrap()));
+       assert_eq!(tx.call.unwrap().signature, "approve(address,uint256)");
+   }
+
+   #[test]
+   fn test_decode_prefix_is_optional() {
+       let eip1559_tx = "02f8b001018450775d80850324a9a70082d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a7640000c001a098421643be02def45744834741859d065b20dfe814001dcc54f521626281a5e0a03fe4c9d2cb0a473865efe0ebee2cf5288aaa54dedf5093430a88ac5c167e5d90";
+
+       let without_prefix = decode_raw_tx(eip1559_tx).unwrap();
+       let with_prefix = decode_raw_tx(&format!("0x{}", eip1559_tx)).unwrap();
+       assert_eq!(without_prefix.hash, with_prefix.hash);
+       assert_eq!(without_prefix.from, with_prefix.from);
+   }
+
+   #[test]
+   fn test_decode_unsigned_tx() {
+       // unsigned legacy transaction (r = s = 0), the sender can't be recovered
        let legacy_tx = "0xf8691e850324a9a70082d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a7640000018080";
-       
-       let tx = decode_raw_tx(legacy_tx).unwrap();
-       println!("Legacy transaction with 0x prefix decoded: {:#?}", tx);
+
+       assert!(decode_raw_tx(legacy_tx).is_err());
+       assert!(matches!(decode_raw_tx("0xzz"), Err(DecodeError::InvalidHex(_))));
+       assert!(matches!(decode_raw_tx("0x02c0"), Err(DecodeError::InvalidTransaction(_))));
    }
-}
\ No newline at end of file
+
+   #[test]
+   fn test_decode_with_registry() {
+       let eip1559_tx = "0x02f8b001018450775d80850324a9a70082d3ea94ec53bf9167f50cdeb3ae105f56099aaab9061f8380b844095ea7b3000000000000000000000000163a5ec5e9c32238d075e2d829fe9fa87451e3b70000000000000000000000000000000000000000000000000de0b6b3a7640000c001a098421643be02def45744834741859d065b20dfe814001dcc54f521626281a5e0a03fe4c9d2cb0a473865efe0ebee2cf5288aaa54dedf5093430a88ac5c167e5d90";
+
+       let mut registry = AbiRegistry::new();
+       registry
+           .add_signatures("Token", ["approve(address operator, uint256 allowance) returns (bool)"])
+           .unwrap();
+
+       let tx = decode_raw_tx_with(eip1559_tx, &registry).unwrap();
+       let call = tx.call.unwrap();
+       assert_eq!(call.contract, "Token");
+       assert_eq!(call.params[0].name, "operator");
+       assert!(tx.candidates.is_empty());
+
+       // without an ABI the selector is looked up in the signature database
+       let tx = decode_raw_tx_with(eip1559_tx, &AbiRegistry::new()).unwrap();
+       assert!(tx.call.is_none());
+       assert_eq!(tx.candidates[0].signature, "approve(address,uint256)");
+       assert_eq!(tx.candidates[0].params[1].value, serde_json::json!("1000000000000000000"));
+   }
+
+   #[tokio::test]
+   async fn test_decode_signed_blob_tx() {
+       use crate::signer::{account::EvmAccount, blob::BlobBuilder, sign::EvmSigner};
+       use alloy_consensus::{TxEip4844, TxEip4844WithSidecar};
+
+       let account = EvmAccount::from_private_key_hex(
+           "c277f46a9cab407af9ac3cdf517b33f1d6e3615faf4a52a57ecc7b7d187a075d",
+       )
+       .unwrap();
+       let signer = EvmSigner::new(&account);
+       let mut blob_tx = TxEip4844WithSidecar::from_tx_and_sidecar(
+           TxEip4844 {
+               chain_id: 1,
+               to: Address::repeat_byte(0x11),
+               max_fee_per_blob_gas: 1,
+               ..Default::default()
+           },
+           BlobBuilder::from_data(b"decode me").build().unwrap(),
+       );
+       let signed = signer.sign_blob_transaction(&mut blob_tx).unwrap();
+
+       let network = decode_raw_tx(&signed.transaction.raw_hex()).unwrap();
+       let consensus = decode_raw_tx(&hex::encode(&signed.consensus_encoding)).unwrap();
+       assert_eq!(network.hash, signed.transaction.hash);
+       assert_eq!(consensus.hash, signed.transaction.hash);
+       assert_eq!(network.from, account.signer.address());
+       assert_eq!(network.blob_versioned_hashes, signed.blob_versioned_hashes);
+       assert!(network.call.is_none());
+       assert!(network.candidates.is_empty());
+   }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use revm::db::CacheDB;
+use revm::primitives::{AccountInfo, Bytecode};
+use revm::DatabaseRef;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+
+/// Backing state of a simulation: an RPC node read lazily at a fixed block, or nothing
+/// at all when every account comes from a [`StateSnapshot`].
+///
+/// RPC reads block on the runtime, the EVM has to run on a blocking thread
+/// (`tokio::task::spawn_blocking`) as [`crate::simulate::simulator::Simulator`] does.
+#[derive(Debug, Clone, Default)]
+pub struct ForkDb {
+    remote: Option<Remote>,
+}
+
+#[derive(Debug, Clone)]
+struct Remote {
+    provider: Arc<EvmProvider>,
+    block: BlockId,
+    handle: Handle,
+}
+
+impl ForkDb {
+    /// A database without remote state, unknown accounts are empty.
+    pub fn offline() -> Self {
+        Self::default()
+    }
+
+    /// Reads state from `provider` at `block`, must be created inside a tokio runtime.
+    pub fn remote(provider: Arc<EvmProvider>, block: BlockId) -> Self {
+        Self {
+            remote: Some(Remote {
+                provider,
+                block,
+                handle: Handle::current(),
+            }),
+        }
+    }
+
+    pub fn block(&self) -> Option<BlockId> {
+        self.remote.as_ref().map(|remote| remote.block)
+    }
+
+    fn block_on<F: Future>(remote: &Remote, future: F) -> F::Output {
+        remote.handle.block_on(future)
+    }
+}
+
+impl DatabaseRef for ForkDb {
+    type Error = ProviderError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let Some(remote) = &self.remote else {
+            return Ok(None);
+        };
+        let (balance, nonce, code) = Self::block_on(remote, async {
+            tokio::try_join!(
+                remote.provider.get_balance(address, remote.block),
+                remote.provider.get_transaction_count(address, remote.block),
+                remote.provider.get_code(address, remote.block),
+            )
+        })?;
+        let code = Bytecode::new_raw(code);
+        Ok(Some(AccountInfo::new(balance, nonce, code.hash_slow(), code)))
+    }
+
+    fn code_by_hash_ref(&self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // code is always returned with the account, so the cache never misses here
+        Ok(Bytecode::default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let Some(remote) = &self.remote else {
+            return Ok(U256::ZERO);
+        };
+        Self::block_on(remote, remote.provider.get_storage_at(address, index, remote.block))
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let Some(remote) = &self.remote else {
+            return Ok(keccak256(number.to_string()));
+        };
+        let block = Self::block_on(remote, remote.provider.get_block(BlockNumberOrTag::Number(number)))?;
+        Ok(block.map(|block| block.header.hash).unwrap_or_default())
+    }
+}
+
+/// Account state injected into a simulation, serialized as the usual `balance`,
+/// `nonce`, `code` and `storage` JSON fields.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountSnapshot {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Bytes,
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// A set of accounts overriding (or replacing, when offline) the forked state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StateSnapshot {
+    pub accounts: BTreeMap<Address, AccountSnapshot>,
+}
+
+impl StateSnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_account(mut self, address: Address, account: AccountSnapshot) -> Self {
+        self.accounts.insert(address, account);
+        self
+    }
+
+    /// Writes the accounts into `db`, replacing their storage entirely.
+    pub fn apply<DB: DatabaseRef>(&self, db: &mut CacheDB<DB>) -> Result<(), DB::Error> {
+        for (address, account) in &self.accounts {
+            let code = Bytecode::new_raw(account.code.clone());
+            let info = AccountInfo::new(account.balance, account.nonce, code.hash_slow(), code);
+            db.insert_account_info(*address, info);
+            let storage = account.storage.iter().map(|(slot, value)| (*slot, *value)).collect();
+            db.replace_account_storage(*address, storage)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn test_snapshot_json() {
+        let json = r#"{
+            "0x163a5ec5e9c32238d075e2d829fe9fa87451e3b7": { "balance": "0xde0b6b3a7640000", "nonce": 3 },
+            "0xec53bf9167f50cdeb3ae105f56099aaab9061f83": { "code": "0x6001", "storage": { "0x0": "0x2a" } }
+        }"#;
+        let snapshot: StateSnapshot = serde_json::from_str(json).unwrap();
+        let owner = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+        let contract = address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83");
+        assert_eq!(snapshot.accounts[&owner].nonce, 3);
+        assert_eq!(snapshot.accounts[&contract].storage[&U256::ZERO], U256::from(42));
+
+        let mut db = CacheDB::new(ForkDb::offline());
+        snapshot.apply(&mut db).unwrap();
+        let info = db.basic_ref(owner).unwrap().unwrap();
+        assert_eq!((info.balance, info.nonce), (U256::from(10).pow(U256::from(18)), 3));
+        assert_eq!(db.storage_ref(contract, U256::ZERO).unwrap(), U256::from(42));
+        assert_eq!(db.storage_ref(contract, U256::from(1)).unwrap(), U256::ZERO);
+        assert!(db.basic_ref(Address::ZERO).unwrap().is_none());
+    }
+}
@@ -0,0 +1,18 @@
+pub mod fork;
+pub mod simulator;
+
+use thiserror::Error;
+
+use crate::provider::transport::ProviderError;
+
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+    #[error("Execution error: {0}")]
+    ExecutionError(String),
+}
+
+type Result<T> = std::result::Result<T, SimulationError>;
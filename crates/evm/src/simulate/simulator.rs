@@ -0,0 +1,619 @@
+use std::sync::{Arc, Mutex};
+
+use alloy_consensus::TxEnvelope;
+use alloy_eips::eip1559::BaseFeeParams;
+use alloy_eips::eip7840::BlobParams;
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::{hex, Address, Bytes, Log, TxKind, U256};
+use alloy_rpc_types::TransactionRequest;
+use revm::db::CacheDB;
+use revm::primitives::{
+    AuthorizationList, BlockEnv, EVMError, Env, EvmState, ExecutionResult, ResultAndState, SpecId, TxEnv,
+};
+use revm::{DatabaseCommit, DatabaseRef, Evm};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::abis::registry::AbiRegistry;
+use crate::provider::client::EvmProvider;
+use crate::signer::sign::{SignedTransaction, Transaction};
+use crate::simulate::fork::{ForkDb, StateSnapshot};
+use crate::simulate::{Result, SimulationError};
+
+/// Block gas limit used by offline simulators.
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+/// Seconds between a forked block and the block simulated on top of it (mainnet slot time).
+pub const BLOCK_TIME: u64 = 12;
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub spec_id: SpecId,
+    /// Lets senders without enough ether for value and gas execute anyway.
+    pub disable_balance_check: bool,
+    /// Accepts gas prices below the block base fee.
+    pub disable_base_fee: bool,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            spec_id: SpecId::CANCUN,
+            disable_balance_check: false,
+            disable_base_fee: false,
+        }
+    }
+}
+
+/// A transaction to simulate: an unsigned request (`from` is required) or a signed envelope.
+///
+/// Requests without any fee field run like `eth_call`, at a zero gas price and without
+/// the base fee check, and without a nonce they skip the nonce check.
+#[derive(Debug, Clone)]
+pub enum SimTransaction {
+    Request(TransactionRequest),
+    Signed(TxEnvelope),
+}
+
+impl From<TransactionRequest> for SimTransaction {
+    fn from(tx: TransactionRequest) -> Self {
+        Self::Request(tx)
+    }
+}
+
+impl From<TxEnvelope> for SimTransaction {
+    fn from(tx: TxEnvelope) -> Self {
+        Self::Signed(tx)
+    }
+}
+
+impl From<&SignedTransaction> for SimTransaction {
+    fn from(tx: &SignedTransaction) -> Self {
+        Self::Request(TransactionRequest::from_transaction_with_sender(tx.envelope.clone(), tx.from))
+    }
+}
+
+impl SimTransaction {
+    /// A transaction about to be signed with [`crate::signer::sign::EvmSigner`] by `from`.
+    pub fn unsigned(from: Address, tx: &Transaction<'_>) -> Self {
+        let request = match tx {
+            Transaction::Legacy(tx) => TransactionRequest::from_transaction_with_sender((**tx).clone(), from),
+            Transaction::Eip1559(tx) => TransactionRequest::from_transaction_with_sender((**tx).clone(), from),
+            Transaction::Eip2930(tx) => TransactionRequest::from_transaction_with_sender((**tx).clone(), from),
+            Transaction::Eip4844(tx) => TransactionRequest::from_transaction_with_sender((**tx).clone(), from),
+            Transaction::Eip4844WithSidecar(tx) => TransactionRequest::from_transaction_with_sender((**tx).clone(), from),
+            Transaction::Eip7702(tx) => TransactionRequest::from_transaction_with_sender((**tx).clone(), from),
+        };
+        Self::Request(request)
+    }
+
+    fn into_request(self) -> Result<TransactionRequest> {
+        match self {
+            Self::Request(tx) => Ok(tx),
+            Self::Signed(tx) => {
+                let from = tx
+                    .recover_signer()
+                    .map_err(|e| SimulationError::InvalidTransaction(e.to_string()))?;
+                Ok(TransactionRequest::from_transaction_with_sender(tx, from))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceChange {
+    pub address: Address,
+    pub before: U256,
+    pub after: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageChange {
+    pub address: Address,
+    pub slot: U256,
+    pub before: U256,
+    pub after: U256,
+}
+
+/// Outcome of a simulated transaction. Balance and storage changes are sorted by
+/// address (and slot) and include the fees paid to the coinbase.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationResult {
+    pub success: bool,
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    /// Return data, or revert data when the transaction reverted.
+    pub output: Bytes,
+    /// Decoded `Error(string)`, `Panic(uint256)` or known custom error of a revert.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    /// Why the EVM stopped, e.g. out of gas, when it halted without a revert.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub halt_reason: Option<String>,
+    pub logs: Vec<Log>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_address: Option<Address>,
+    pub balance_changes: Vec<BalanceChange>,
+    pub storage_changes: Vec<StorageChange>,
+}
+
+impl SimulationResult {
+    fn new(result: ExecutionResult, balance_changes: Vec<BalanceChange>, storage_changes: Vec<StorageChange>) -> Self {
+        let mut simulation = Self {
+            success: result.is_success(),
+            gas_used: result.gas_used(),
+            gas_refunded: 0,
+            output: Bytes::new(),
+            revert_reason: None,
+            halt_reason: None,
+            logs: Vec::new(),
+            created_address: None,
+            balance_changes,
+            storage_changes,
+        };
+        match result {
+            ExecutionResult::Success {
+                gas_refunded,
+                logs,
+                output,
+                ..
+            } => {
+                simulation.gas_refunded = gas_refunded;
+                simulation.created_address = output.address().copied();
+                simulation.output = output.into_data();
+                simulation.logs = logs;
+            }
+            ExecutionResult::Revert { output, .. } => {
+                simulation.revert_reason = Some(revert_reason(&output));
+                simulation.output = output;
+            }
+            ExecutionResult::Halt { reason, .. } => simulation.halt_reason = Some(format!("{:?}", reason)),
+        }
+        simulation
+    }
+}
+
+fn revert_reason(output: &[u8]) -> String {
+    let Some(error) = AbiRegistry::known().decode_error(output) else {
+        return match output.is_empty() {
+            true => "execution reverted".to_string(),
+            false => format!("0x{}", hex::encode(output)),
+        };
+    };
+    let args: Vec<String> = error
+        .params
+        .iter()
+        .map(|param| match &param.value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        })
+        .collect();
+    match error.function.as_str() {
+        "Error" => args.join(", "),
+        _ => format!("{}({})", error.function, args.join(", ")),
+    }
+}
+
+enum Mode {
+    /// Runs on the shared state without keeping the changes.
+    Call,
+    /// Runs on the shared state and keeps the changes.
+    Commit,
+    /// Runs in sequence on a copy of the shared state.
+    Bundle,
+}
+
+/// Executes transactions locally in revm, against state forked from a node at a fixed
+/// block or against an injected [`StateSnapshot`].
+///
+/// Remote state is fetched on first use and cached for the lifetime of the simulator.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    db: Arc<Mutex<CacheDB<ForkDb>>>,
+    chain_id: u64,
+    block: BlockEnv,
+    config: SimulationConfig,
+}
+
+impl Simulator {
+    /// Forks the state after `block` from `provider`. Transactions run in the block after it:
+    /// number, base fee and blob gas follow from its header, the timestamp is [`BLOCK_TIME`]
+    /// later. Use [`Simulator::with_block_env`] for chains with other block times.
+    pub async fn fork(provider: Arc<EvmProvider>, block: BlockNumberOrTag) -> Result<Self> {
+        let (chain_id, block) = tokio::try_join!(provider.get_chain_id(), provider.get_block(block))?;
+        let block = block.ok_or_else(|| SimulationError::ExecutionError("block not found".into()))?;
+        let header = &block.header;
+
+        let mut env = BlockEnv {
+            number: U256::from(header.number + 1),
+            coinbase: header.beneficiary,
+            timestamp: U256::from(header.timestamp + BLOCK_TIME),
+            gas_limit: U256::from(header.gas_limit),
+            basefee: U256::from(header.next_block_base_fee(BaseFeeParams::ethereum()).unwrap_or_default()),
+            difficulty: header.difficulty,
+            prevrandao: Some(header.mix_hash),
+            blob_excess_gas_and_price: None,
+        };
+        // pre-Cancun headers have no blob gas, the default spec still needs a blob price
+        let excess_blob_gas = header.next_block_excess_blob_gas(BlobParams::cancun()).unwrap_or_default();
+        env.set_blob_excess_gas_and_price(excess_blob_gas, false);
+
+        let db = ForkDb::remote(provider, BlockId::number(header.number));
+        Ok(Self::new(db, chain_id, env))
+    }
+
+    /// A simulator without a node, holding only the accounts of `snapshot`.
+    pub fn offline(snapshot: &StateSnapshot, chain_id: u64) -> Self {
+        let block = BlockEnv {
+            gas_limit: U256::from(DEFAULT_GAS_LIMIT),
+            ..Default::default()
+        };
+        let simulator = Self::new(ForkDb::offline(), chain_id, block);
+        simulator
+            .override_state(snapshot)
+            .expect("offline overrides never read through");
+        simulator
+    }
+
+    fn new(db: ForkDb, chain_id: u64, block: BlockEnv) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(CacheDB::new(db))),
+            chain_id,
+            block,
+            config: SimulationConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: SimulationConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_block_env(mut self, block: BlockEnv) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn block_env(&self) -> &BlockEnv {
+        &self.block
+    }
+
+    /// Replaces the given accounts, e.g. to fund a sender or stub a contract on a fork.
+    pub fn override_state(&self, snapshot: &StateSnapshot) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        snapshot.apply(&mut db)?;
+        Ok(())
+    }
+
+    /// Runs `tx` without changing the simulator state.
+    pub async fn simulate(&self, tx: impl Into<SimTransaction>) -> Result<SimulationResult> {
+        let mut results = self.run(vec![tx.into()], Mode::Call).await?;
+        Ok(results.remove(0))
+    }
+
+    /// Runs `tx` and keeps its changes, so later transactions see them.
+    pub async fn execute(&self, tx: impl Into<SimTransaction>) -> Result<SimulationResult> {
+        let mut results = self.run(vec![tx.into()], Mode::Commit).await?;
+        Ok(results.remove(0))
+    }
+
+    /// Runs the transactions in order, each on top of the previous ones, without
+    /// changing the simulator state. Fails on the first invalid transaction.
+    pub async fn simulate_bundle(&self, txs: Vec<SimTransaction>) -> Result<Vec<SimulationResult>> {
+        self.run(txs, Mode::Bundle).await
+    }
+
+    async fn run(&self, txs: Vec<SimTransaction>, mode: Mode) -> Result<Vec<SimulationResult>> {
+        let envs = txs
+            .into_iter()
+            .map(|tx| self.env(tx))
+            .collect::<Result<Vec<_>>>()?;
+        let spec_id = self.config.spec_id;
+        let db = self.db.clone();
+
+        // remote reads block on the runtime, so the EVM can't run on an async worker
+        tokio::task::spawn_blocking(move || {
+            let mut shared = db.lock().unwrap();
+            match mode {
+                Mode::Call => envs.into_iter().map(|env| transact(&mut shared, env, spec_id, false)).collect(),
+                Mode::Commit => envs.into_iter().map(|env| transact(&mut shared, env, spec_id, true)).collect(),
+                Mode::Bundle => {
+                    let mut db = shared.clone();
+                    drop(shared);
+                    envs.into_iter().map(|env| transact(&mut db, env, spec_id, true)).collect()
+                }
+            }
+        })
+        .await
+        .map_err(|e| SimulationError::ExecutionError(e.to_string()))?
+    }
+
+    fn env(&self, tx: SimTransaction) -> Result<Box<Env>> {
+        let tx = tx.into_request()?;
+        let caller = tx
+            .from
+            .ok_or_else(|| SimulationError::InvalidTransaction("missing `from` address".into()))?;
+        let priced = tx.gas_price.is_some() || tx.max_fee_per_gas.is_some();
+
+        let mut env = Env::default();
+        env.cfg.chain_id = self.chain_id;
+        env.cfg.disable_balance_check = self.config.disable_balance_check;
+        env.cfg.disable_base_fee = self.config.disable_base_fee || !priced;
+        env.block = self.block.clone();
+        env.tx = TxEnv {
+            caller,
+            gas_limit: tx.gas.unwrap_or_else(|| self.block.gas_limit.saturating_to()),
+            gas_price: U256::from(tx.gas_price.or(tx.max_fee_per_gas).unwrap_or_default()),
+            transact_to: tx.to.unwrap_or(TxKind::Create),
+            value: tx.value.unwrap_or_default(),
+            data: tx.input.input().cloned().unwrap_or_default(),
+            nonce: tx.nonce,
+            chain_id: tx.chain_id,
+            access_list: tx.access_list.map(|list| list.0).unwrap_or_default(),
+            gas_priority_fee: tx.max_priority_fee_per_gas.map(U256::from),
+            blob_hashes: tx.blob_versioned_hashes.unwrap_or_default(),
+            max_fee_per_blob_gas: tx.max_fee_per_blob_gas.map(U256::from),
+            authorization_list: tx.authorization_list.map(AuthorizationList::Signed),
+        };
+        Ok(Box::new(env))
+    }
+}
+
+fn transact(db: &mut CacheDB<ForkDb>, env: Box<Env>, spec_id: SpecId, commit: bool) -> Result<SimulationResult> {
+    let ResultAndState { result, state } = Evm::builder()
+        .with_db(&mut *db)
+        .with_env(env)
+        .with_spec_id(spec_id)
+        .build()
+        .transact()
+        .map_err(|e| match e {
+            EVMError::Transaction(e) => SimulationError::InvalidTransaction(e.to_string()),
+            EVMError::Database(e) => SimulationError::ProviderError(e),
+            e => SimulationError::ExecutionError(e.to_string()),
+        })?;
+
+    let (balance_changes, storage_changes) = state_changes(db, &state)?;
+    if commit {
+        db.commit(state);
+    }
+    Ok(SimulationResult::new(result, balance_changes, storage_changes))
+}
+
+fn state_changes(db: &CacheDB<ForkDb>, state: &EvmState) -> Result<(Vec<BalanceChange>, Vec<StorageChange>)> {
+    let mut balances = Vec::new();
+    let mut storage = Vec::new();
+    for (address, account) in state.iter().filter(|(_, account)| account.is_touched()) {
+        // accounts touched by the EVM are cached, so this doesn't reach the node
+        let before = db.basic_ref(*address)?.map(|info| info.balance).unwrap_or_default();
+        if before != account.info.balance {
+            balances.push(BalanceChange {
+                address: *address,
+                before,
+                after: account.info.balance,
+            });
+        }
+        storage.extend(
+            account
+                .storage
+                .iter()
+                .filter(|(_, slot)| slot.is_changed())
+                .map(|(index, slot)| StorageChange {
+                    address: *address,
+                    slot: *index,
+                    before: slot.original_value(),
+                    after: slot.present_value(),
+                }),
+        );
+    }
+    balances.sort_by_key(|change| change.address);
+    storage.sort_by_key(|change| (change.address, change.slot));
+    Ok((balances, storage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use crate::signer::sign::EvmSigner;
+    use crate::simulate::fork::AccountSnapshot;
+    use alloy_consensus::TxEip1559;
+    use alloy_primitives::{address, b256, hex, B256};
+    use alloy_rpc_types::Block;
+    use alloy_sol_types::SolValue;
+    use serde_json::json;
+
+    const CALLER: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+    const STORE: Address = address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83");
+    const REVERTER: Address = address!("00000000000000000000000000000000000000aa");
+    const TOPIC: B256 = b256!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+
+    const ETHER: u128 = 1_000_000_000_000_000_000;
+
+    /// Stores the first calldata word in slot 0 and emits `LOG1(TOPIC)`.
+    fn store_code() -> Bytes {
+        [&hex!("6000356000557f")[..], TOPIC.as_slice(), &hex!("60006000a100")].concat().into()
+    }
+
+    /// Reverts with `Error("nope")` copied from the end of its code.
+    fn revert_code() -> Bytes {
+        let reason = [&hex!("08c379a0")[..], &("nope",).abi_encode_params()].concat();
+        [&hex!("6064600c60003960646000fd")[..], &reason].concat().into()
+    }
+
+    fn call(to: Address, word: u64) -> TransactionRequest {
+        TransactionRequest::default()
+            .from(CALLER)
+            .to(to)
+            .input(U256::from(word).to_be_bytes_vec().into())
+    }
+
+    fn simulator() -> Simulator {
+        let snapshot = StateSnapshot::new()
+            .with_account(
+                CALLER,
+                AccountSnapshot {
+                    balance: U256::from(ETHER),
+                    ..Default::default()
+                },
+            )
+            .with_account(
+                STORE,
+                AccountSnapshot {
+                    code: store_code(),
+                    ..Default::default()
+                },
+            )
+            .with_account(
+                REVERTER,
+                AccountSnapshot {
+                    code: revert_code(),
+                    ..Default::default()
+                },
+            );
+        Simulator::offline(&snapshot, 1)
+    }
+
+    #[tokio::test]
+    async fn test_simulate_call() {
+        let simulator = simulator();
+
+        let result = simulator.simulate(call(STORE, 7)).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.logs.len(), 1);
+        assert_eq!(result.logs[0].address, STORE);
+        assert_eq!(result.logs[0].topics(), &[TOPIC]);
+        assert_eq!(
+            result.storage_changes,
+            vec![StorageChange {
+                address: STORE,
+                slot: U256::ZERO,
+                before: U256::ZERO,
+                after: U256::from(7),
+            }]
+        );
+        // unpriced calls pay no fees
+        assert!(result.balance_changes.is_empty());
+
+        let result = simulator.simulate(call(REVERTER, 0)).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.revert_reason.as_deref(), Some("nope"));
+        assert!(result.storage_changes.is_empty());
+
+        let result = simulator.simulate(call(STORE, 7).gas_limit(21_500)).await.unwrap();
+        assert_eq!(result.halt_reason.as_deref(), Some("OutOfGas(Basic)"));
+        assert!(matches!(
+            simulator.simulate(TransactionRequest::default().to(STORE)).await,
+            Err(SimulationError::InvalidTransaction(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_bundle() {
+        let simulator = simulator();
+
+        let results = simulator
+            .simulate_bundle(vec![call(STORE, 7).into(), call(STORE, 9).into()])
+            .await
+            .unwrap();
+        assert_eq!(results[1].storage_changes[0].before, U256::from(7));
+        assert_eq!(results[1].storage_changes[0].after, U256::from(9));
+
+        // neither the bundle nor a simulation changed the state, an execution does
+        let result = simulator.simulate(call(STORE, 1)).await.unwrap();
+        assert_eq!(result.storage_changes[0].before, U256::ZERO);
+        simulator.execute(call(STORE, 3)).await.unwrap();
+        let result = simulator.simulate(call(STORE, 1)).await.unwrap();
+        assert_eq!(result.storage_changes[0].before, U256::from(3));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_signed() {
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let simulator = simulator();
+        simulator
+            .override_state(&StateSnapshot::new().with_account(
+                signer.address(),
+                AccountSnapshot {
+                    balance: U256::from(ETHER),
+                    ..Default::default()
+                },
+            ))
+            .unwrap();
+
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 0,
+            gas_limit: 100_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(STORE),
+            value: U256::from(1000),
+            input: U256::from(5).to_be_bytes_vec().into(),
+            ..Default::default()
+        };
+        let unsigned = simulator
+            .simulate(SimTransaction::unsigned(signer.address(), &Transaction::Eip1559(&mut tx)))
+            .await
+            .unwrap();
+        let signed = signer.sign_transaction(Transaction::Eip1559(&mut tx)).await.unwrap();
+        let result = simulator.execute(&signed).await.unwrap();
+        assert_eq!(result.gas_used, unsigned.gas_used);
+
+        let fee = U256::from(result.gas_used) * U256::from(1_000_000_000u64);
+        let sender = result
+            .balance_changes
+            .iter()
+            .find(|change| change.address == signer.address())
+            .unwrap();
+        assert_eq!(sender.before - sender.after, fee + U256::from(1000));
+        assert!(result.balance_changes.iter().any(|change| change.address == STORE));
+
+        // the nonce was used by the execution
+        assert!(matches!(
+            simulator.execute(signed.envelope.clone()).await,
+            Err(SimulationError::InvalidTransaction(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fork() {
+        // returns storage slot 0
+        let reader = hex!("60005460005260206000f3");
+        let node = MockNode::start(move |method, params| match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_getBlockByNumber" => {
+                let mut block: Block = Block::default();
+                block.header.inner.number = 16;
+                block.header.inner.gas_limit = 30_000_000;
+                block.header.inner.base_fee_per_gas = Some(1_000_000_000);
+                Ok(serde_json::to_value(block).unwrap())
+            }
+            "eth_getBalance" => Ok(json!("0xde0b6b3a7640000")),
+            "eth_getTransactionCount" => Ok(json!("0x0")),
+            "eth_getCode" if params[0] == json!(STORE) => Ok(json!(Bytes::from(reader))),
+            "eth_getCode" => Ok(json!("0x")),
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", 42))),
+            _ => Err((-32601, "method not found".into())),
+        })
+        .await;
+        let provider = Arc::new(EvmProvider::connect(node.http_url()).await.unwrap());
+        let simulator = Simulator::fork(provider, BlockNumberOrTag::Latest).await.unwrap();
+        // transactions run in the next block, an empty parent lowers the base fee by 1/8
+        let env = simulator.block_env();
+        assert_eq!(env.number, U256::from(17));
+        assert_eq!(env.timestamp, U256::from(BLOCK_TIME));
+        assert_eq!(env.basefee, U256::from(875_000_000));
+
+        let result = simulator.simulate(call(STORE, 0)).await.unwrap();
+        assert_eq!(U256::from_be_slice(&result.output), U256::from(42));
+        // state is read at the forked block number
+        assert_eq!(node.last_params("eth_getStorageAt").unwrap(), json!([STORE, "0x0", "0x10"]));
+
+        // cached state isn't fetched again
+        let fetched = node.methods().len();
+        simulator.simulate(call(STORE, 0)).await.unwrap();
+        assert_eq!(node.methods().len(), fetched);
+    }
+}
@@ -0,0 +1,178 @@
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+
+use crate::abis::erc::IERC1155;
+use crate::provider::client::EvmProvider;
+use crate::tokens::nft::{substitute_id, MetadataResolver, NftMetadata};
+use crate::tokens::{call, request, Result};
+
+pub fn safe_transfer_from_calldata(from: Address, to: Address, id: U256, value: U256, data: Bytes) -> Bytes {
+    IERC1155::safeTransferFromCall {
+        from,
+        to,
+        id,
+        value,
+        data,
+    }
+    .abi_encode()
+    .into()
+}
+
+/// Transfers `(id, value)` pairs in one call.
+pub fn safe_batch_transfer_from_calldata(from: Address, to: Address, amounts: &[(U256, U256)], data: Bytes) -> Bytes {
+    let (ids, values) = amounts.iter().copied().unzip();
+    IERC1155::safeBatchTransferFromCall {
+        from,
+        to,
+        ids,
+        values,
+        data,
+    }
+    .abi_encode()
+    .into()
+}
+
+pub fn set_approval_for_all_calldata(operator: Address, approved: bool) -> Bytes {
+    IERC1155::setApprovalForAllCall { operator, approved }.abi_encode().into()
+}
+
+/// An ERC-1155 multi-token contract, read through an [`EvmProvider`] at the latest block.
+///
+/// Write methods only build the transaction request, fill and sign it with
+/// [`EvmProvider::fill_and_sign`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc1155 {
+    pub address: Address,
+}
+
+impl Erc1155 {
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    pub async fn balance_of(&self, provider: &EvmProvider, account: Address, id: U256) -> Result<U256> {
+        Ok(call(provider, self.address, IERC1155::balanceOfCall { account, id }).await?._0)
+    }
+
+    /// Balances of `(account, id)` pairs, in order.
+    pub async fn balance_of_batch(&self, provider: &EvmProvider, pairs: &[(Address, U256)]) -> Result<Vec<U256>> {
+        let (accounts, ids) = pairs.iter().copied().unzip();
+        Ok(call(provider, self.address, IERC1155::balanceOfBatchCall { accounts, ids })
+            .await?
+            ._0)
+    }
+
+    pub async fn is_approved_for_all(&self, provider: &EvmProvider, account: Address, operator: Address) -> Result<bool> {
+        Ok(call(provider, self.address, IERC1155::isApprovedForAllCall { account, operator })
+            .await?
+            ._0)
+    }
+
+    /// The `uri` of `id` as returned by the contract, possibly an `{id}` template.
+    pub async fn uri(&self, provider: &EvmProvider, id: U256) -> Result<String> {
+        Ok(call(provider, self.address, IERC1155::uriCall { id }).await?._0)
+    }
+
+    /// The `uri` of `id` with `{id}` substituted, see [`substitute_id`].
+    pub async fn token_uri(&self, provider: &EvmProvider, id: U256) -> Result<String> {
+        Ok(substitute_id(&self.uri(provider, id).await?, id))
+    }
+
+    /// Reads the URI of `id` and resolves the metadata behind it.
+    pub async fn metadata(&self, provider: &EvmProvider, resolver: &MetadataResolver, id: U256) -> Result<NftMetadata> {
+        resolver.fetch(&self.token_uri(provider, id).await?).await
+    }
+
+    pub fn safe_transfer_from(&self, from: Address, to: Address, id: U256, value: U256, data: Bytes) -> TransactionRequest {
+        request(self.address, safe_transfer_from_calldata(from, to, id, value, data))
+    }
+
+    pub fn safe_batch_transfer_from(
+        &self,
+        from: Address,
+        to: Address,
+        amounts: &[(U256, U256)],
+        data: Bytes,
+    ) -> TransactionRequest {
+        request(self.address, safe_batch_transfer_from_calldata(from, to, amounts, data))
+    }
+
+    pub fn set_approval_for_all(&self, operator: Address, approved: bool) -> TransactionRequest {
+        request(self.address, set_approval_for_all_calldata(operator, approved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use crate::signer::sign::{EvmSigner, Transaction};
+    use crate::signer::utils::decode_raw_tx;
+    use alloy_consensus::TxLegacy;
+    use alloy_primitives::{address, hex, TxKind};
+    use alloy_sol_types::SolValue;
+    use serde_json::json;
+
+    const CONTRACT: Address = address!("76be3b62873462d2142405439777e971754e8e77");
+    const OWNER: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+    const RECIPIENT: Address = address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83");
+
+    #[tokio::test]
+    async fn test_erc1155_reads() {
+        let node = MockNode::start(|method, params| {
+            let input = params[0]["input"].as_str().unwrap_or_default();
+            let output = match (method, input.get(2..10).unwrap_or_default()) {
+                ("eth_call", "00fdd58e") => U256::from(5).abi_encode(),
+                ("eth_call", "4e1273f4") => vec![U256::from(5), U256::ZERO].abi_encode(),
+                ("eth_call", "0e89341c") => "ipfs://QmCollection/{id}.json".to_string().abi_encode(),
+                _ => return Err((-32601, "method not found".into())),
+            };
+            Ok(json!(format!("0x{}", hex::encode(output))))
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let contract = Erc1155::new(CONTRACT);
+
+        assert_eq!(contract.balance_of(&provider, OWNER, U256::from(10)).await.unwrap(), U256::from(5));
+        let balances = contract
+            .balance_of_batch(&provider, &[(OWNER, U256::from(10)), (RECIPIENT, U256::from(10))])
+            .await
+            .unwrap();
+        assert_eq!(balances, vec![U256::from(5), U256::ZERO]);
+
+        let uri = contract.token_uri(&provider, U256::from(10)).await.unwrap();
+        assert_eq!(uri, format!("ipfs://QmCollection/{:064x}.json", 10));
+        assert_eq!(
+            MetadataResolver::new().http_url(&uri),
+            format!("https://ipfs.io/ipfs/QmCollection/{:064x}.json", 10)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_erc1155_batch_transfer() {
+        let contract = Erc1155::new(CONTRACT);
+        let amounts = [(U256::from(1), U256::from(10)), (U256::from(2), U256::from(20))];
+        let tx = contract.safe_batch_transfer_from(OWNER, RECIPIENT, &amounts, Bytes::new());
+
+        let call = IERC1155::safeBatchTransferFromCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(call.ids, vec![U256::from(1), U256::from(2)]);
+        assert_eq!(call.values, vec![U256::from(10), U256::from(20)]);
+
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let mut tx = TxLegacy {
+            chain_id: Some(137),
+            gas_price: 30_000_000_000,
+            gas_limit: 120_000,
+            to: TxKind::Call(CONTRACT),
+            input: tx.input.into_input().unwrap(),
+            ..Default::default()
+        };
+        let signed = signer.sign_transaction(Transaction::Legacy(&mut tx)).await.unwrap();
+        let call = decode_raw_tx(&signed.raw_hex()).unwrap().call.unwrap();
+        assert_eq!(call.contract, "ERC1155");
+        assert_eq!(call.function, "safeBatchTransferFrom");
+    }
+}
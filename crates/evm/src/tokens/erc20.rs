@@ -0,0 +1,214 @@
+use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::{SolCall, SolValue};
+use serde::Serialize;
+
+use crate::abis::erc::IERC20;
+use crate::provider::client::EvmProvider;
+use crate::tokens::units::{format_units, parse_units};
+use crate::tokens::{call, call_raw, request, Result, TokenError};
+
+pub fn transfer_calldata(to: Address, amount: U256) -> Bytes {
+    IERC20::transferCall { to, amount }.abi_encode().into()
+}
+
+pub fn transfer_from_calldata(from: Address, to: Address, amount: U256) -> Bytes {
+    IERC20::transferFromCall { from, to, amount }.abi_encode().into()
+}
+
+pub fn approve_calldata(spender: Address, amount: U256) -> Bytes {
+    IERC20::approveCall { spender, amount }.abi_encode().into()
+}
+
+/// Only for tokens implementing the OpenZeppelin extension, plain EIP-20 tokens revert.
+pub fn increase_allowance_calldata(spender: Address, added_value: U256) -> Bytes {
+    IERC20::increaseAllowanceCall {
+        spender,
+        addedValue: added_value,
+    }
+    .abi_encode()
+    .into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenMetadata {
+    pub address: Address,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl TokenMetadata {
+    /// Parses a decimal amount of this token into base units, see [`parse_units`].
+    pub fn parse_amount(&self, amount: &str) -> Result<U256> {
+        parse_units(amount, self.decimals)
+    }
+
+    /// Formats base units of this token as a decimal amount, see [`format_units`].
+    pub fn format_amount(&self, amount: U256) -> Result<String> {
+        format_units(amount, self.decimals)
+    }
+}
+
+/// An ERC-20 token contract, read through an [`EvmProvider`] at the latest block.
+///
+/// Write methods only build the transaction request, fill and sign it with
+/// [`EvmProvider::fill_and_sign`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc20 {
+    pub address: Address,
+}
+
+impl Erc20 {
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    pub async fn balance_of(&self, provider: &EvmProvider, owner: Address) -> Result<U256> {
+        Ok(call(provider, self.address, IERC20::balanceOfCall { account: owner }).await?._0)
+    }
+
+    pub async fn allowance(&self, provider: &EvmProvider, owner: Address, spender: Address) -> Result<U256> {
+        Ok(call(provider, self.address, IERC20::allowanceCall { owner, spender }).await?._0)
+    }
+
+    pub async fn total_supply(&self, provider: &EvmProvider) -> Result<U256> {
+        Ok(call(provider, self.address, IERC20::totalSupplyCall {}).await?._0)
+    }
+
+    pub async fn decimals(&self, provider: &EvmProvider) -> Result<u8> {
+        Ok(call(provider, self.address, IERC20::decimalsCall {}).await?._0)
+    }
+
+    /// Token name, from a `string` or a legacy `bytes32` return value.
+    pub async fn name(&self, provider: &EvmProvider) -> Result<String> {
+        self.text(provider, IERC20::nameCall {}.abi_encode()).await
+    }
+
+    /// Token symbol, from a `string` or a legacy `bytes32` return value.
+    pub async fn symbol(&self, provider: &EvmProvider) -> Result<String> {
+        self.text(provider, IERC20::symbolCall {}.abi_encode()).await
+    }
+
+    pub async fn metadata(&self, provider: &EvmProvider) -> Result<TokenMetadata> {
+        let (name, symbol, decimals) =
+            tokio::try_join!(self.name(provider), self.symbol(provider), self.decimals(provider))?;
+        Ok(TokenMetadata {
+            address: self.address,
+            name,
+            symbol,
+            decimals,
+        })
+    }
+
+    pub fn transfer(&self, to: Address, amount: U256) -> TransactionRequest {
+        request(self.address, transfer_calldata(to, amount))
+    }
+
+    pub fn transfer_from(&self, from: Address, to: Address, amount: U256) -> TransactionRequest {
+        request(self.address, transfer_from_calldata(from, to, amount))
+    }
+
+    pub fn approve(&self, spender: Address, amount: U256) -> TransactionRequest {
+        request(self.address, approve_calldata(spender, amount))
+    }
+
+    /// See [`increase_allowance_calldata`].
+    pub fn increase_allowance(&self, spender: Address, added_value: U256) -> TransactionRequest {
+        request(self.address, increase_allowance_calldata(spender, added_value))
+    }
+
+    /// Early tokens (e.g. MKR) return `bytes32` text padded with zeros instead of a `string`.
+    async fn text(&self, provider: &EvmProvider, input: Vec<u8>) -> Result<String> {
+        let output = call_raw(provider, self.address, input).await?;
+        if let Ok(text) = String::abi_decode(&output, true) {
+            return Ok(text);
+        }
+        let text = B256::abi_decode(&output, true)
+            .map_err(|e| TokenError::DecodeError(format!("text of {}: {}", self.address, e)))?;
+        let end = text.iter().position(|b| *b == 0).unwrap_or(text.len());
+        Ok(String::from_utf8_lossy(&text[..end]).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use alloy_primitives::{address, hex};
+    use serde_json::{json, Value};
+
+    const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    const MKR: Address = address!("9f8f72aa9304c8b593d555f12ef6589cc3a579a2");
+    const OWNER: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+    const SPENDER: Address = address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83");
+
+    fn encoded(value: impl SolValue) -> Value {
+        json!(format!("0x{}", hex::encode(value.abi_encode())))
+    }
+
+    async fn node() -> MockNode {
+        MockNode::start(|method, params| {
+            if method != "eth_call" {
+                return Err((-32601, "method not found".into()));
+            }
+            let to: Address = serde_json::from_value(params[0]["to"].clone()).unwrap();
+            let input = params[0]["input"].as_str().unwrap_or_default();
+            let selector = &input[2..10];
+            let result = match (to, selector) {
+                (_, "70a08231") => encoded(U256::from(1_500_000)),
+                (_, "dd62ed3e") if input.contains(&hex::encode(SPENDER)) => encoded(U256::MAX),
+                (_, "313ce567") => encoded(U256::from(if to == USDC { 6 } else { 18 })),
+                (USDC, "06fdde03") => encoded("USD Coin".to_string()),
+                (USDC, "95d89b41") => encoded("USDC".to_string()),
+                (MKR, "06fdde03") => encoded(B256::right_padding_from(b"Maker")),
+                (MKR, "95d89b41") => encoded(B256::right_padding_from(b"MKR")),
+                _ => json!("0x"),
+            };
+            Ok(result)
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_erc20_reads() {
+        let node = node().await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+
+        let usdc = Erc20::new(USDC);
+        let metadata = usdc.metadata(&provider).await.unwrap();
+        assert_eq!((metadata.name.as_str(), metadata.symbol.as_str(), metadata.decimals), ("USD Coin", "USDC", 6));
+        let balance = usdc.balance_of(&provider, OWNER).await.unwrap();
+        assert_eq!(metadata.format_amount(balance).unwrap(), "1.5");
+        assert_eq!(usdc.allowance(&provider, OWNER, SPENDER).await.unwrap(), U256::MAX);
+        assert_eq!(node.last_params("eth_call").unwrap()[1], json!("latest"));
+
+        let mkr = Erc20::new(MKR).metadata(&provider).await.unwrap();
+        assert_eq!((mkr.name.as_str(), mkr.symbol.as_str(), mkr.decimals), ("Maker", "MKR", 18));
+
+        // an address without code returns no data
+        assert!(matches!(
+            usdc.allowance(&provider, OWNER, OWNER).await,
+            Err(TokenError::DecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_erc20_calldata() {
+        let token = Erc20::new(USDC);
+        let amount = parse_units("2.5", 6).unwrap();
+
+        let tx = token.transfer(SPENDER, amount);
+        assert_eq!(tx.to, Some(USDC.into()));
+        let call = IERC20::transferCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!((call.to, call.amount), (SPENDER, U256::from(2_500_000)));
+
+        let call = IERC20::approveCall::abi_decode(&approve_calldata(SPENDER, U256::MAX), true).unwrap();
+        assert_eq!((call.spender, call.amount), (SPENDER, U256::MAX));
+
+        let data = increase_allowance_calldata(SPENDER, amount);
+        assert_eq!(&data[..4], &hex!("39509351"));
+        let data = transfer_from_calldata(OWNER, SPENDER, amount);
+        assert_eq!(&data[..4], &hex!("23b872dd"));
+    }
+}
@@ -0,0 +1,206 @@
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+
+use crate::abis::erc::IERC721;
+use crate::provider::client::EvmProvider;
+use crate::tokens::nft::{MetadataResolver, NftMetadata};
+use crate::tokens::{call, request, Result};
+
+/// `safeTransferFrom(address,address,uint256)`, or the overload with `data` when it isn't empty.
+pub fn safe_transfer_from_calldata(from: Address, to: Address, token_id: U256, data: Bytes) -> Bytes {
+    match data.is_empty() {
+        true => IERC721::safeTransferFrom_1Call {
+            from,
+            to,
+            tokenId: token_id,
+        }
+        .abi_encode()
+        .into(),
+        false => IERC721::safeTransferFrom_0Call {
+            from,
+            to,
+            tokenId: token_id,
+            data,
+        }
+        .abi_encode()
+        .into(),
+    }
+}
+
+pub fn transfer_from_calldata(from: Address, to: Address, token_id: U256) -> Bytes {
+    IERC721::transferFromCall {
+        from,
+        to,
+        tokenId: token_id,
+    }
+    .abi_encode()
+    .into()
+}
+
+pub fn approve_calldata(to: Address, token_id: U256) -> Bytes {
+    IERC721::approveCall { to, tokenId: token_id }.abi_encode().into()
+}
+
+pub fn set_approval_for_all_calldata(operator: Address, approved: bool) -> Bytes {
+    IERC721::setApprovalForAllCall { operator, approved }.abi_encode().into()
+}
+
+/// An ERC-721 collection, read through an [`EvmProvider`] at the latest block.
+///
+/// Write methods only build the transaction request, fill and sign it with
+/// [`EvmProvider::fill_and_sign`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc721 {
+    pub address: Address,
+}
+
+impl Erc721 {
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    pub async fn owner_of(&self, provider: &EvmProvider, token_id: U256) -> Result<Address> {
+        Ok(call(provider, self.address, IERC721::ownerOfCall { tokenId: token_id }).await?._0)
+    }
+
+    pub async fn balance_of(&self, provider: &EvmProvider, owner: Address) -> Result<U256> {
+        Ok(call(provider, self.address, IERC721::balanceOfCall { owner }).await?._0)
+    }
+
+    pub async fn get_approved(&self, provider: &EvmProvider, token_id: U256) -> Result<Address> {
+        Ok(call(provider, self.address, IERC721::getApprovedCall { tokenId: token_id }).await?._0)
+    }
+
+    pub async fn is_approved_for_all(&self, provider: &EvmProvider, owner: Address, operator: Address) -> Result<bool> {
+        Ok(call(provider, self.address, IERC721::isApprovedForAllCall { owner, operator })
+            .await?
+            ._0)
+    }
+
+    pub async fn token_uri(&self, provider: &EvmProvider, token_id: U256) -> Result<String> {
+        Ok(call(provider, self.address, IERC721::tokenURICall { tokenId: token_id }).await?._0)
+    }
+
+    /// Reads `tokenURI` and resolves the metadata behind it.
+    pub async fn metadata(
+        &self,
+        provider: &EvmProvider,
+        resolver: &MetadataResolver,
+        token_id: U256,
+    ) -> Result<NftMetadata> {
+        resolver.fetch(&self.token_uri(provider, token_id).await?).await
+    }
+
+    pub fn safe_transfer_from(&self, from: Address, to: Address, token_id: U256) -> TransactionRequest {
+        request(self.address, safe_transfer_from_calldata(from, to, token_id, Bytes::new()))
+    }
+
+    /// Passes `data` to `onERC721Received` of a contract recipient.
+    pub fn safe_transfer_from_with_data(&self, from: Address, to: Address, token_id: U256, data: Bytes) -> TransactionRequest {
+        request(self.address, safe_transfer_from_calldata(from, to, token_id, data))
+    }
+
+    /// One `safeTransferFrom` per token, ERC-721 has no batch transfer. Nonces are left
+    /// to the caller, e.g. [`crate::provider::nonce::NonceManager::send_transaction`].
+    pub fn safe_batch_transfer_from(&self, from: Address, to: Address, token_ids: &[U256]) -> Vec<TransactionRequest> {
+        token_ids
+            .iter()
+            .map(|token_id| self.safe_transfer_from(from, to, *token_id))
+            .collect()
+    }
+
+    pub fn transfer_from(&self, from: Address, to: Address, token_id: U256) -> TransactionRequest {
+        request(self.address, transfer_from_calldata(from, to, token_id))
+    }
+
+    pub fn approve(&self, to: Address, token_id: U256) -> TransactionRequest {
+        request(self.address, approve_calldata(to, token_id))
+    }
+
+    pub fn set_approval_for_all(&self, operator: Address, approved: bool) -> TransactionRequest {
+        request(self.address, set_approval_for_all_calldata(operator, approved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::mock::MockNode;
+    use crate::provider::test_utils::account;
+    use crate::signer::sign::{EvmSigner, Transaction};
+    use crate::signer::utils::decode_raw_tx;
+    use alloy_consensus::TxEip1559;
+    use alloy_primitives::{address, hex, TxKind};
+    use alloy_sol_types::SolValue;
+    use serde_json::json;
+
+    const COLLECTION: Address = address!("bc4ca0eda7647a8ab7c2061c2e118a18a936f13d");
+    const OWNER: Address = address!("163a5ec5e9c32238d075e2d829fe9fa87451e3b7");
+    const RECIPIENT: Address = address!("ec53bf9167f50cdeb3ae105f56099aaab9061f83");
+
+    #[tokio::test]
+    async fn test_erc721_reads() {
+        let node = MockNode::start(|method, params| {
+            let input = params[0]["input"].as_str().unwrap_or_default();
+            let output = match (method, input.get(2..10).unwrap_or_default()) {
+                ("eth_call", "6352211e") => OWNER.abi_encode(),
+                ("eth_call", "70a08231") => U256::from(3).abi_encode(),
+                ("eth_call", "e985e9c5") => true.abi_encode(),
+                // data: URI holding {"name":"Ape #7"}
+                ("eth_call", "c87b56dd") => "data:application/json;base64,eyJuYW1lIjoiQXBlICM3In0=".to_string().abi_encode(),
+                _ => return Err((-32601, "method not found".into())),
+            };
+            Ok(json!(format!("0x{}", hex::encode(output))))
+        })
+        .await;
+        let provider = EvmProvider::connect(node.http_url()).await.unwrap();
+        let collection = Erc721::new(COLLECTION);
+
+        assert_eq!(collection.owner_of(&provider, U256::from(7)).await.unwrap(), OWNER);
+        assert_eq!(collection.balance_of(&provider, OWNER).await.unwrap(), U256::from(3));
+        assert!(collection.is_approved_for_all(&provider, OWNER, RECIPIENT).await.unwrap());
+
+        let metadata = collection
+            .metadata(&provider, &MetadataResolver::new(), U256::from(7))
+            .await
+            .unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Ape #7"));
+        let call = IERC721::tokenURICall::abi_decode(
+            &hex::decode(node.last_params("eth_call").unwrap()[0]["input"].as_str().unwrap()).unwrap(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(call.tokenId, U256::from(7));
+    }
+
+    #[tokio::test]
+    async fn test_erc721_transfers() {
+        let collection = Erc721::new(COLLECTION);
+
+        let txs = collection.safe_batch_transfer_from(OWNER, RECIPIENT, &[U256::from(1), U256::from(2)]);
+        assert_eq!(txs.len(), 2);
+        let call = IERC721::safeTransferFrom_1Call::abi_decode(txs[1].input.input().unwrap(), true).unwrap();
+        assert_eq!((call.from, call.to, call.tokenId), (OWNER, RECIPIENT, U256::from(2)));
+
+        let data = safe_transfer_from_calldata(OWNER, RECIPIENT, U256::from(1), Bytes::from(vec![1, 2]));
+        let call = IERC721::safeTransferFrom_0Call::abi_decode(&data, true).unwrap();
+        assert_eq!(call.data, Bytes::from(vec![1, 2]));
+
+        // the built calldata signs like any other transaction
+        let account = account();
+        let signer = EvmSigner::new(&account);
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            gas_limit: 80_000,
+            max_fee_per_gas: 20_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(COLLECTION),
+            input: collection.set_approval_for_all(RECIPIENT, true).input.into_input().unwrap(),
+            ..Default::default()
+        };
+        let signed = signer.sign_transaction(Transaction::Eip1559(&mut tx)).await.unwrap();
+        let decoded = decode_raw_tx(&signed.raw_hex()).unwrap();
+        assert_eq!(decoded.call.unwrap().function, "setApprovalForAll");
+    }
+}
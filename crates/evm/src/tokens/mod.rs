@@ -0,0 +1,46 @@
+pub mod erc1155;
+pub mod erc20;
+pub mod erc721;
+pub mod nft;
+pub mod units;
+
+use alloy_eips::BlockId;
+use alloy_primitives::{Address, Bytes};
+use alloy_rpc_types::TransactionRequest;
+use alloy_sol_types::SolCall;
+use thiserror::Error;
+
+use crate::provider::client::EvmProvider;
+use crate::provider::transport::ProviderError;
+
+#[derive(Error, Debug)]
+pub enum TokenError {
+    #[error("Provider error: {0}")]
+    ProviderError(#[from] ProviderError),
+    #[error("Decode error: {0}")]
+    DecodeError(String),
+    #[error("Units error: {0}")]
+    UnitsError(String),
+    #[error("Metadata error: {0}")]
+    MetadataError(String),
+}
+
+type Result<T> = std::result::Result<T, TokenError>;
+
+/// A call to `contract`, ready to be filled and signed.
+fn request(contract: Address, input: Bytes) -> TransactionRequest {
+    TransactionRequest::default().to(contract).input(input.into())
+}
+
+async fn call_raw(provider: &EvmProvider, contract: Address, input: Vec<u8>) -> Result<Bytes> {
+    Ok(provider
+        .call(&request(contract, input.into()), BlockId::latest())
+        .await?)
+}
+
+/// Calls a view function of `contract` at the latest block.
+async fn call<C: SolCall>(provider: &EvmProvider, contract: Address, call: C) -> Result<C::Return> {
+    let output = call_raw(provider, contract, call.abi_encode()).await?;
+    C::abi_decode_returns(&output, true)
+        .map_err(|e| TokenError::DecodeError(format!("{} of {}: {}", C::SIGNATURE, contract, e)))
+}
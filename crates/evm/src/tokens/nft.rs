@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use alloy_primitives::U256;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::tokens::{Result, TokenError};
+
+const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+const ARWEAVE_GATEWAY: &str = "https://arweave.net/";
+
+/// Replaces `{id}` in an ERC-1155 URI with the token id as 64 lowercase hex digits,
+/// without a `0x` prefix, as the standard requires.
+pub fn substitute_id(uri: &str, id: U256) -> String {
+    uri.replace("{id}", &format!("{:064x}", id))
+}
+
+/// Decodes a `data:` URI (RFC 2397), returns its media type and payload.
+pub fn decode_data_uri(uri: &str) -> Result<(String, Vec<u8>)> {
+    let invalid = |reason: &str| TokenError::MetadataError(format!("invalid data URI: {}", reason));
+    let rest = uri.strip_prefix("data:").ok_or_else(|| invalid("missing `data:` scheme"))?;
+    let (header, payload) = rest.split_once(',').ok_or_else(|| invalid("missing `,`"))?;
+
+    let (media_type, is_base64) = match header.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (header, false),
+    };
+    let media_type = match media_type {
+        "" => "text/plain;charset=US-ASCII".to_string(),
+        media_type => media_type.to_string(),
+    };
+
+    let payload = match is_base64 {
+        true => base64::engine::general_purpose::STANDARD
+            .decode(percent_decode_str(payload).collect::<Vec<u8>>())
+            .map_err(|e| invalid(&e.to_string()))?,
+        false => percent_decode_str(payload).collect(),
+    };
+    Ok((media_type, payload))
+}
+
+/// Token metadata in the common ERC-721/ERC-1155 JSON schema, other fields are kept in `extra`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NftMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Fetches token metadata from `data:`, `http(s)://`, `ipfs://` and `ar://` URIs.
+#[derive(Debug, Clone)]
+pub struct MetadataResolver {
+    client: Client,
+    ipfs_gateway: String,
+}
+
+impl Default for MetadataResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetadataResolver {
+    pub fn new() -> Self {
+        Self::with_timeout(Duration::from_secs(30))
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("Failed to create HTTP client"),
+            ipfs_gateway: IPFS_GATEWAY.to_string(),
+        }
+    }
+
+    /// Gateway prefix for `ipfs://` URIs, e.g. `https://cloudflare-ipfs.com/ipfs/`.
+    pub fn with_ipfs_gateway(mut self, gateway: &str) -> Self {
+        self.ipfs_gateway = match gateway.ends_with('/') {
+            true => gateway.to_string(),
+            false => format!("{}/", gateway),
+        };
+        self
+    }
+
+    /// HTTP URL serving `uri`, gateway URLs for IPFS and Arweave. Other URIs are returned as is.
+    pub fn http_url(&self, uri: &str) -> String {
+        if let Some(path) = uri.strip_prefix("ipfs://") {
+            let path = path.strip_prefix("ipfs/").unwrap_or(path);
+            return format!("{}{}", self.ipfs_gateway, path);
+        }
+        if let Some(path) = uri.strip_prefix("ar://") {
+            return format!("{}{}", ARWEAVE_GATEWAY, path);
+        }
+        uri.to_string()
+    }
+
+    /// Reads the metadata JSON behind a `tokenURI` or a substituted `uri`.
+    pub async fn fetch(&self, uri: &str) -> Result<NftMetadata> {
+        let uri = uri.trim();
+        if uri.starts_with("data:") {
+            let (_, payload) = decode_data_uri(uri)?;
+            return serde_json::from_slice(&payload).map_err(|e| TokenError::MetadataError(e.to_string()));
+        }
+        // some collections return the JSON itself instead of a URI
+        if uri.starts_with('{') {
+            return serde_json::from_str(uri).map_err(|e| TokenError::MetadataError(e.to_string()));
+        }
+
+        let url = self.http_url(uri);
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(TokenError::MetadataError(format!("unsupported URI `{}`", uri)));
+        }
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| TokenError::MetadataError(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(TokenError::MetadataError(format!("{} returned {}", url, status)));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| TokenError::MetadataError(format!("{}: {}", url, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_substitute_id() {
+        assert_eq!(
+            substitute_id("https://token-cdn-domain/{id}.json", U256::from(314592)),
+            "https://token-cdn-domain/000000000000000000000000000000000000000000000000000000000004cce0.json"
+        );
+        assert_eq!(substitute_id("ipfs://Qm/1.json", U256::from(1)), "ipfs://Qm/1.json");
+    }
+
+    #[test]
+    fn test_decode_data_uri() {
+        let (media_type, payload) = decode_data_uri("data:application/json;base64,eyJuYW1lIjoiQSJ9").unwrap();
+        assert_eq!((media_type.as_str(), payload.as_slice()), ("application/json", &br#"{"name":"A"}"#[..]));
+
+        let (media_type, payload) = decode_data_uri("data:application/json;utf8,%7B%22name%22%3A%22B%22%7D").unwrap();
+        assert_eq!((media_type.as_str(), payload.as_slice()), ("application/json;utf8", &br#"{"name":"B"}"#[..]));
+
+        let (media_type, payload) = decode_data_uri("data:,hello%20world").unwrap();
+        assert_eq!((media_type.as_str(), payload.as_slice()), ("text/plain;charset=US-ASCII", &b"hello world"[..]));
+
+        assert!(decode_data_uri("https://example.com").is_err());
+        assert!(decode_data_uri("data:application/json;base64").is_err());
+        assert!(decode_data_uri("data:application/json;base64,!!!").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_metadata() {
+        let resolver = MetadataResolver::new().with_ipfs_gateway("https://gateway.example/ipfs");
+        assert_eq!(resolver.http_url("ipfs://QmHash/1.json"), "https://gateway.example/ipfs/QmHash/1.json");
+        assert_eq!(resolver.http_url("ipfs://ipfs/QmHash"), "https://gateway.example/ipfs/QmHash");
+        assert_eq!(resolver.http_url("ar://tx-id"), "https://arweave.net/tx-id");
+
+        let json = json!({
+            "name": "Token #1",
+            "image": "ipfs://QmImage",
+            "attributes": [{ "trait_type": "Background", "value": "Blue" }],
+            "animation_url": "ipfs://QmAnimation"
+        });
+        let uri = format!(
+            "data:application/json;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(json.to_string())
+        );
+        let metadata = resolver.fetch(&uri).await.unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Token #1"));
+        assert_eq!(metadata.attributes.len(), 1);
+        assert_eq!(metadata.extra["animation_url"], json!("ipfs://QmAnimation"));
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), json);
+
+        assert!(matches!(
+            resolver.fetch("ftp://example.com/1.json").await,
+            Err(TokenError::MetadataError(_))
+        ));
+    }
+}
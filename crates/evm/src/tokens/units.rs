@@ -0,0 +1,111 @@
+use alloy_primitives::U256;
+
+use crate::tokens::{Result, TokenError};
+
+/// Most decimals a token amount can have, `10^77` is the largest power of ten in a `U256`.
+pub const MAX_DECIMALS: u8 = 77;
+
+fn scale(decimals: u8) -> Result<U256> {
+    if decimals > MAX_DECIMALS {
+        return Err(TokenError::UnitsError(format!(
+            "{} decimals, at most {} are supported",
+            decimals, MAX_DECIMALS
+        )));
+    }
+    Ok(U256::from(10).pow(U256::from(decimals)))
+}
+
+/// Parses a decimal string such as `"1.5"` into base units, e.g. `1500000` for 6 decimals.
+///
+/// Exact: fails rather than rounds when the value has more significant fraction digits
+/// than `decimals`, and on signs, exponents, separators or overflow.
+pub fn parse_units(value: &str, decimals: u8) -> Result<U256> {
+    let invalid = || TokenError::UnitsError(format!("invalid amount `{}`", value));
+    let scale = scale(decimals)?;
+
+    let (integer, fraction) = value.trim().split_once('.').unwrap_or((value.trim(), ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(TokenError::UnitsError(format!(
+            "`{}` has more than {} decimals",
+            value, decimals
+        )));
+    }
+
+    let overflow = || TokenError::UnitsError(format!("`{}` overflows a uint256", value));
+    let integer = match integer {
+        "" => U256::ZERO,
+        digits => U256::from_str_radix(digits, 10).map_err(|_| overflow())?,
+    };
+    let fraction = match fraction {
+        "" => U256::ZERO,
+        digits => {
+            let padding = U256::from(10).pow(U256::from(decimals as usize - digits.len()));
+            U256::from_str_radix(digits, 10).map_err(|_| invalid())? * padding
+        }
+    };
+    integer
+        .checked_mul(scale)
+        .and_then(|integer| integer.checked_add(fraction))
+        .ok_or_else(overflow)
+}
+
+/// Formats base units as a decimal string without trailing zeros, `"1.5"` or `"2"`.
+/// [`parse_units`] reads the output back to the same value.
+pub fn format_units(value: U256, decimals: u8) -> Result<String> {
+    let scale = scale(decimals)?;
+    let (integer, fraction) = (value / scale, value % scale);
+    if fraction.is_zero() {
+        return Ok(integer.to_string());
+    }
+    let fraction = format!("{:0>width$}", fraction.to_string(), width = decimals as usize);
+    Ok(format!("{}.{}", integer, fraction.trim_end_matches('0')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_units() {
+        assert_eq!(parse_units("1.5", 6).unwrap(), U256::from(1_500_000));
+        assert_eq!(parse_units("1", 18).unwrap(), U256::from(10).pow(U256::from(18)));
+        assert_eq!(parse_units(".25", 2).unwrap(), U256::from(25));
+        assert_eq!(parse_units("3.", 2).unwrap(), U256::from(300));
+        assert_eq!(parse_units("0.100", 1).unwrap(), U256::from(1));
+        assert_eq!(parse_units("42", 0).unwrap(), U256::from(42));
+        assert_eq!(parse_units(&U256::MAX.to_string(), 0).unwrap(), U256::MAX);
+
+        for invalid in ["", ".", "-1", "+1", "1e18", "1,000", "1.2.3", "0x10", "1 000"] {
+            assert!(matches!(parse_units(invalid, 18), Err(TokenError::UnitsError(_))), "{}", invalid);
+        }
+        // never rounds
+        assert!(parse_units("1.0000001", 6).is_err());
+        assert!(parse_units("1", 78).is_err());
+        assert!(parse_units(&U256::MAX.to_string(), 1).is_err());
+    }
+
+    #[test]
+    fn test_format_units_round_trip() {
+        assert_eq!(format_units(U256::from(1_500_000), 6).unwrap(), "1.5");
+        assert_eq!(format_units(U256::from(2_000_000), 6).unwrap(), "2");
+        assert_eq!(format_units(U256::from(1), 18).unwrap(), "0.000000000000000001");
+        assert_eq!(format_units(U256::ZERO, 18).unwrap(), "0");
+        assert_eq!(format_units(U256::from(7), 0).unwrap(), "7");
+
+        let values = [U256::ZERO, U256::from(1), U256::from(123_456_789), U256::MAX, U256::MAX - U256::from(1)];
+        for decimals in [0, 1, 6, 8, 18, 24, MAX_DECIMALS] {
+            for value in values {
+                let formatted = format_units(value, decimals).unwrap();
+                assert_eq!(parse_units(&formatted, decimals).unwrap(), value, "{}", formatted);
+            }
+        }
+    }
+}